@@ -0,0 +1,125 @@
+use axum::http::header::CONTENT_DISPOSITION;
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Bson, Document};
+use serde_json::Value;
+
+use crate::audit::{AuditAction, AuditEntry};
+use crate::database::ERASED_FIELDS;
+use crate::error::AppError;
+use crate::repository::Repositories;
+
+/// Everything held on a single customer, as relaxed extended JSON
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct DataSubjectExport {
+    pub customer_id: String,
+    pub exported_at: DateTime<Utc>,
+    /// The stored customer document
+    pub customer: Value,
+    /// The audit trail of the customer
+    pub audit: Vec<Value>,
+    /// The delivery log of the reminders sent to the customer
+    pub notifications: Vec<Value>,
+    /// The appointments of the customer
+    pub appointments: Vec<Value>
+}
+
+impl IntoResponse for DataSubjectExport {
+    fn into_response(self) -> axum::response::Response {
+        let content_disposition = format!("attachment; filename=\"{}.json\"", self.customer_id);
+
+        (StatusCode::OK, [(CONTENT_DISPOSITION, content_disposition)], Json(self)).into_response()
+    }
+}
+
+/// The outcome of erasing the personal data of a customer
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ErasureReport {
+    pub customer_id: String,
+    pub erased_fields: Vec<String>,
+    pub anonymized_notifications: u64,
+    pub anonymized_appointments: u64
+}
+
+impl IntoResponse for ErasureReport {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+fn to_json(document: Document) -> Value {
+    Bson::Document(document).into_relaxed_extjson()
+}
+
+/// Collect everything held on the customer with `customer_id`
+///
+/// The export itself is recorded in the audit trail, after the trail was collected.
+pub async fn export_customer_data(
+    repositories: &Repositories,
+    customer_id: &str,
+    exported_by: &str
+) -> Result<DataSubjectExport, AppError> {
+    let customer =
+        repositories.customers.get_raw_customer(customer_id).await?.ok_or_else(|| {
+            AppError::NotFound(format!("No customer with customer_id={customer_id}"))
+        })?;
+
+    let audit = repositories.audit.for_customer(customer_id).await?;
+    let notifications = repositories.notifications.for_customer(customer_id).await?;
+    let appointments = repositories.appointments.for_customer(customer_id).await?;
+
+    repositories
+        .audit
+        .record(AuditEntry::new(
+            customer_id.to_owned(),
+            AuditAction::Export,
+            exported_by.to_owned(),
+            Document::new()
+        ))
+        .await?;
+
+    Ok(DataSubjectExport {
+        customer_id: customer_id.to_owned(),
+        exported_at: Utc::now(),
+        customer: to_json(customer),
+        audit: audit.into_iter().map(to_json).collect(),
+        notifications: notifications.into_iter().map(to_json).collect(),
+        appointments: appointments.into_iter().map(to_json).collect()
+    })
+}
+
+/// Anonymize the personal data of the customer with `customer_id`
+///
+/// Covers the customer document, the delivery log of it's reminders and it's appointments.
+/// The appliance inspection records are kept. The erasure is recorded in the audit trail.
+pub async fn erase_customer_data(
+    repositories: &Repositories,
+    customer_id: &str,
+    erased_by: &str
+) -> Result<ErasureReport, AppError> {
+    repositories.customers.erase_customer(customer_id, erased_by).await?;
+    let anonymized_notifications =
+        repositories.notifications.erase_for_customer(customer_id).await?;
+    let anonymized_appointments = repositories.appointments.erase_for_customer(customer_id).await?;
+
+    repositories
+        .audit
+        .record(AuditEntry::new(
+            customer_id.to_owned(),
+            AuditAction::Erase,
+            erased_by.to_owned(),
+            doc! {
+                "fields": ERASED_FIELDS.to_vec(),
+                "anonymized_notifications": anonymized_notifications as i64,
+                "anonymized_appointments": anonymized_appointments as i64
+            }
+        ))
+        .await?;
+
+    Ok(ErasureReport {
+        customer_id: customer_id.to_owned(),
+        erased_fields: ERASED_FIELDS.iter().map(ToString::to_string).collect(),
+        anonymized_notifications,
+        anonymized_appointments
+    })
+}
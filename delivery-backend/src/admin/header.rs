@@ -3,15 +3,40 @@ use axum::{
     http::{request::Parts, HeaderName, HeaderValue},
     RequestPartsExt, TypedHeader
 };
-use std::iter;
+use std::{env, iter};
 
+use crate::auth::jwt::AuthError;
 use crate::error::AppError;
 
 static X_ADMIN_HEADER: HeaderName = HeaderName::from_static("x-admin-secret");
 
+/// The `x-admin-secret` header
+///
+/// As an extractor, it only accepts requests where the header matches
+/// the `ADMIN_SECRET` environment variable. If `ADMIN_SECRET` isn't set, every request is rejected.
 #[derive(Debug)]
 pub struct AdminHeader(HeaderValue);
 
+impl AdminHeader {
+    /// Check the header against the `ADMIN_SECRET` environment variable
+    fn is_valid(&self) -> bool {
+        match env::var("ADMIN_SECRET") {
+            Ok(secret) if !secret.is_empty() => {
+                constant_time_eq(self.0.as_bytes(), secret.as_bytes())
+            }
+            _ => {
+                tracing::error!("ADMIN_SECRET not set, rejecting admin request");
+                false
+            }
+        }
+    }
+}
+
+/// Compare two byte slices in time that only depends on their length
+fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
 impl Header for AdminHeader {
     fn name() -> &'static HeaderName {
         &X_ADMIN_HEADER
@@ -41,6 +66,10 @@ where
         let TypedHeader(admin_header) =
             parts.extract::<TypedHeader<Self>>().await.map_err(AppError::from)?;
 
+        if !admin_header.is_valid() {
+            return Err(AppError::AuthError(AuthError::WrongCredentials));
+        }
+
         Ok(admin_header)
     }
 }
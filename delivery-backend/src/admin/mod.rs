@@ -1 +1,2 @@
+pub mod gdpr;
 pub mod header;
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderName;
+use axum::routing::{get, post};
+use axum::Router;
+use tower::ServiceBuilder;
+use tower_http::compression::CompressionLayer;
+use tower_http::propagate_header::PropagateHeaderLayer;
+use tower_http::request_id::{MakeRequestUuid, SetRequestIdLayer};
+use tower_http::sensitive_headers::SetSensitiveRequestHeadersLayer;
+use tower_http::trace::TraceLayer;
+
+use crate::database::setup_database;
+use crate::error::AppError;
+use crate::jobs::{spawn_purge_job, spawn_reminder_job, PurgeConfig, ReminderConfig};
+use crate::notification::Notifier;
+use crate::routers;
+use crate::state::{setup_app_state, AppState};
+
+/// App setup
+///
+/// Connects to the database at `mongo_url`, initializes the app state, spawns the background jobs
+/// and builds the root router with [`app_router`]
+#[tracing::instrument]
+pub async fn setup_app(mongo_url: &str) -> Result<Router, AppError> {
+    let database = setup_database(mongo_url).await?;
+    let app_state = setup_app_state(&database)?;
+    spawn_purge_job(Arc::clone(&database), PurgeConfig::from_env());
+    spawn_reminder_job(database, Notifier::from_env(), ReminderConfig::from_env());
+    tracing::info!("Application setup ok");
+
+    Ok(app_router(app_state))
+}
+
+/// The root router, with every route and the middlewares, over `app_state`
+///
+/// Doesn't start anything in the background, so tests can build it over their own [`AppState`].
+pub fn app_router(app_state: AppState) -> Router {
+    let x_request_id_header = HeaderName::from_static("x-request-id");
+
+    let middleware_stack = ServiceBuilder::new()
+        .layer(SetSensitiveRequestHeadersLayer::new(std::iter::once(AUTHORIZATION)))
+        .layer(TraceLayer::new_for_http())
+        .layer(CompressionLayer::new())
+        .layer(SetRequestIdLayer::new(x_request_id_header.clone(), MakeRequestUuid))
+        .layer(PropagateHeaderLayer::new(x_request_id_header));
+
+    Router::<AppState>::new()
+        .route("/search", post(routers::customer_search))
+        .route("/history", get(routers::customer_history))
+        .route("/route", post(routers::plan_route))
+        .nest("/customer", routers::customer_router())
+        .nest("/appointment", routers::appointment_router())
+        .nest("/auth", routers::auth_router())
+        .nest("/calendar", routers::calendar_router())
+        .nest("/admin", routers::admin_router())
+        .route_layer(middleware_stack)
+        .with_state(app_state)
+}
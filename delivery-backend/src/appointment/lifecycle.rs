@@ -0,0 +1,100 @@
+use mongodb::bson::{self, doc, oid::ObjectId};
+
+use crate::error::AppError;
+use crate::repository::{Repositories, UserRepository};
+use crate::responses::InsertOneResultResponse;
+
+use super::{AppointmentIn, AppointmentOut, CompletionRequest, RescheduleRequest};
+
+/// Schedule a new appointment
+///
+/// Both the customer and the technician have to exist.
+/// Fails with [`AppError::Conflict`] if the technician is already booked for that slot.
+pub async fn schedule_appointment(
+    repositories: &Repositories,
+    appointment: AppointmentIn,
+    created_by: &str
+) -> Result<InsertOneResultResponse, AppError> {
+    repositories.customers.get_customer(&appointment.customer_id).await?;
+    check_technician(repositories.users.as_ref(), &appointment.technician).await?;
+
+    repositories.appointments.create_appointment(appointment, created_by).await
+}
+
+/// Move a scheduled appointment to another slot, and maybe another technician
+///
+/// Fails with [`AppError::Conflict`] if the technician is already booked for the new slot.
+pub async fn reschedule_appointment(
+    repositories: &Repositories,
+    id: ObjectId,
+    request: RescheduleRequest
+) -> Result<AppointmentOut, AppError> {
+    if let Some(technician) = &request.technician {
+        check_technician(repositories.users.as_ref(), technician).await?;
+    }
+
+    repositories.appointments.reschedule_appointment(id, request).await
+}
+
+/// Technicians are users, so `technician` has to be the `username` of one
+async fn check_technician(users: &dyn UserRepository, technician: &str) -> Result<(), AppError> {
+    match users.get_user(technician).await? {
+        Some(_) => Ok(()),
+        None => {
+            Err(AppError::UnprocessableEntity(format!("No technician with username={technician}")))
+        }
+    }
+}
+
+/// Complete a scheduled appointment
+///
+/// The intervention is recorded on the appliance of the customer,
+/// which also moves it's `expiration_date` to the next check.
+/// If the customer can't be updated, the appointment stays scheduled.
+pub async fn complete_appointment(
+    repositories: &Repositories,
+    id: ObjectId,
+    request: CompletionRequest,
+    completed_by: &str
+) -> Result<AppointmentOut, AppError> {
+    let appointment = repositories.appointments.get_appointment(id).await?;
+
+    let date = request.date.map_or(appointment.start, |date| date.into());
+    let expiration_date = request.expiration_date.into();
+
+    if expiration_date < date {
+        return Err(AppError::UnprocessableEntity(
+            "expiration_date must not be before date".into()
+        ));
+    }
+
+    let completion = doc! {
+        "operation_performed": request.operation_performed.to_string(),
+        "date": bson::DateTime::from_chrono(date),
+        "expiration_date": bson::DateTime::from_chrono(expiration_date),
+        "observations": &request.observations,
+        "at": bson::DateTime::now(),
+        "by": completed_by
+    };
+
+    let completed = repositories.appointments.mark_completed(id, completion).await?;
+
+    let recorded = repositories
+        .customers
+        .record_intervention(
+            &completed.customer_id,
+            request.operation_performed,
+            date,
+            expiration_date,
+            request.observations
+        )
+        .await;
+
+    if let Err(err) = recorded {
+        tracing::error!("Failed to record the intervention of appointment {id}: {err}");
+        repositories.appointments.revert_completion(id).await?;
+        return Err(err);
+    }
+
+    Ok(completed)
+}
@@ -0,0 +1,7 @@
+mod lifecycle;
+mod record;
+mod request;
+
+pub use lifecycle::{complete_appointment, reschedule_appointment, schedule_appointment};
+pub use record::{AppointmentList, AppointmentOut, AppointmentStatus, Cancellation, Completion};
+pub use request::{AppointmentIn, CancelRequest, CompletionRequest, RescheduleRequest};
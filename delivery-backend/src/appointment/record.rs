@@ -0,0 +1,102 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::serde_helpers::serialize_object_id_as_hex_string;
+use mongodb::bson::{self, Document};
+
+use crate::customer::{deserialize_chrono_from_bson_datetime, OperationPerformed};
+use crate::error::AppError;
+
+/// Where an appointment is in it's life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppointmentStatus {
+    /// Planned, the visit hasn't happened yet
+    Scheduled,
+    /// The visit happened and the intervention was recorded on the customer
+    Completed,
+    /// The visit won't happen
+    Cancelled
+}
+
+impl std::fmt::Display for AppointmentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            AppointmentStatus::Scheduled => write!(f, "scheduled"),
+            AppointmentStatus::Completed => write!(f, "completed"),
+            AppointmentStatus::Cancelled => write!(f, "cancelled")
+        }
+    }
+}
+
+/// Who cancelled an appointment, when and why
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Cancellation {
+    pub reason: Option<String>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub at: DateTime<Utc>,
+    pub by: String
+}
+
+/// The intervention recorded when an appointment was completed
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Completion {
+    pub operation_performed: OperationPerformed,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub date: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub expiration_date: DateTime<Utc>,
+    pub observations: Option<String>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub at: DateTime<Utc>,
+    pub by: String
+}
+
+/// An appointment as it's returned to the client
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct AppointmentOut {
+    #[serde(serialize_with = "serialize_object_id_as_hex_string", rename = "_id")]
+    pub id: ObjectId,
+    pub customer_id: String,
+    pub technician: String,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub start: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub end: DateTime<Utc>,
+    pub status: AppointmentStatus,
+    pub notes: Option<String>,
+    pub created_by: String,
+    /// Incremented every time the appointment changes
+    #[serde(default)]
+    pub sequence: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancellation: Option<Cancellation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion: Option<Completion>
+}
+
+impl TryFrom<Document> for AppointmentOut {
+    type Error = AppError;
+
+    fn try_from(value: Document) -> Result<Self, Self::Error> {
+        bson::from_document(value).map_err(AppError::from)
+    }
+}
+
+impl IntoResponse for AppointmentOut {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+/// A list of [`AppointmentOut`]s
+#[derive(Debug, Default, serde::Serialize)]
+pub struct AppointmentList {
+    pub appointments: Vec<AppointmentOut>
+}
+
+impl IntoResponse for AppointmentList {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
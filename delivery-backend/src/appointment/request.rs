@@ -0,0 +1,101 @@
+use chrono::{DateTime, Duration, FixedOffset};
+use mongodb::bson::{self, doc, Document};
+use validator::{Validate, ValidationError};
+
+use crate::customer::OperationPerformed;
+
+/// The longest an appointment can take
+const MAX_DURATION_HOURS: i64 = 12;
+
+/// A slot has to end after it starts, and can't take longer than a working day
+fn validate_slot(
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>
+) -> Result<(), ValidationError> {
+    if end <= start {
+        let mut error = ValidationError::new("end");
+        error.message = Some("end must be after start".into());
+        return Err(error);
+    }
+
+    if end - start > Duration::hours(MAX_DURATION_HOURS) {
+        let mut error = ValidationError::new("end");
+        error.message = Some(
+            format!("an appointment can't take longer than {MAX_DURATION_HOURS} hours").into()
+        );
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// A new visit of a technician to a customer
+///
+/// `technician` is the `username` of the technician's user.
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+#[validate(schema(function = "validate_appointment_slot"))]
+pub struct AppointmentIn {
+    #[validate(length(min = 1, message = "customer_id must not be empty"))]
+    pub customer_id: String,
+    #[validate(length(min = 1, message = "technician must not be empty"))]
+    pub technician: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    #[validate(length(max = 1000, message = "notes must be at most 1000 characters"))]
+    pub notes: Option<String>
+}
+
+fn validate_appointment_slot(appointment: &AppointmentIn) -> Result<(), ValidationError> {
+    validate_slot(appointment.start, appointment.end)
+}
+
+impl AppointmentIn {
+    /// Convert [`Self`] into a MongoDB [`Document`] of a scheduled appointment
+    pub fn into_document(self, created_by: &str) -> Document {
+        doc! {
+            "customer_id": self.customer_id,
+            "technician": self.technician,
+            "start": bson::DateTime::from_chrono(self.start),
+            "end": bson::DateTime::from_chrono(self.end),
+            "status": "scheduled",
+            "notes": self.notes,
+            "created_by": created_by,
+            "created_at": bson::DateTime::now(),
+            "sequence": 0i64
+        }
+    }
+}
+
+/// Moves an appointment to a new slot, optionally with another technician
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+#[validate(schema(function = "validate_reschedule_slot"))]
+pub struct RescheduleRequest {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    #[validate(length(min = 1, message = "technician must not be empty"))]
+    pub technician: Option<String>
+}
+
+fn validate_reschedule_slot(request: &RescheduleRequest) -> Result<(), ValidationError> {
+    validate_slot(request.start, request.end)
+}
+
+/// Why an appointment was cancelled
+#[derive(Debug, Default, Validate, serde::Serialize, serde::Deserialize)]
+pub struct CancelRequest {
+    #[validate(length(max = 1000, message = "reason must be at most 1000 characters"))]
+    pub reason: Option<String>
+}
+
+/// The intervention done during an appointment
+///
+/// `date` is when the check was performed, the start of the appointment by default.
+/// `expiration_date` is when the next check is due.
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+pub struct CompletionRequest {
+    pub operation_performed: OperationPerformed,
+    pub date: Option<DateTime<FixedOffset>>,
+    pub expiration_date: DateTime<FixedOffset>,
+    #[validate(length(max = 1000, message = "observations must be at most 1000 characters"))]
+    pub observations: Option<String>
+}
@@ -0,0 +1,40 @@
+use mongodb::bson::{self, Document};
+
+/// What was done to the data of a customer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    /// Everything held on the customer was exported
+    Export,
+    /// The personal data of the customer was anonymized
+    Erase
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            AuditAction::Export => write!(f, "export"),
+            AuditAction::Erase => write!(f, "erase")
+        }
+    }
+}
+
+/// An entry of the `audit` collection
+///
+/// Records who did what to the data of a customer, and when.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    pub customer_id: String,
+    pub action: AuditAction,
+    pub by: String,
+    pub at: bson::DateTime,
+    /// Anything else worth knowing about the action, like the fields that were erased
+    pub details: Document
+}
+
+impl AuditEntry {
+    /// Creates a new [`AuditEntry`] of an action done right now.
+    pub fn new(customer_id: String, action: AuditAction, by: String, details: Document) -> Self {
+        Self { customer_id, action, by, at: bson::DateTime::now(), details }
+    }
+}
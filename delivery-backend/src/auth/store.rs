@@ -11,7 +11,7 @@ pub struct Store {
 
 impl Store {
     /// Get a [`RefreshToken`] by `key` from the [`Store`]
-    pub fn get(&self, key: &str) -> Option<Ref<String, RefreshToken>> {
+    pub fn get(&self, key: &str) -> Option<Ref<'_, String, RefreshToken>> {
         self.inner.get(key)
     }
 
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::appointment::{AppointmentOut, AppointmentStatus};
+use crate::customer::DeliveryCustomerOut;
+use crate::error::AppError;
+use crate::local_time::local_date;
+use crate::query::{AppointmentQuery, ExpirationStatus, ExpiredCustomersQuery};
+use crate::repository::{CustomerRepository, Repositories};
+
+use super::{Calendar, Event, EventTime};
+
+/// Appointments that ended more than this many days ago are left out of the feed
+const PAST_APPOINTMENT_DAYS: i64 = 30;
+
+/// Expiration dates this many days in the past and in the future are part of the feed
+const EXPIRATION_DAYS: (i64, i64) = (90, 365);
+
+/// The line describing a customer in an event
+fn describe_customer(customer: &DeliveryCustomerOut) -> String {
+    let appliance = &customer.appliance;
+    let mut lines = vec![
+        format!("{} ({})", customer.name, customer.customer_id),
+        format!("{} {} {}", appliance.typ, appliance.manufacturer, appliance.model),
+    ];
+
+    lines.extend(customer.phones.iter().map(|phone| phone.number.clone()));
+    lines.extend(customer.email.clone());
+
+    lines.join("\n")
+}
+
+fn appointment_event(appointment: AppointmentOut, customer: Option<&DeliveryCustomerOut>) -> Event {
+    let summary = match customer {
+        Some(customer) => format!("Visit: {}", customer.name),
+        None => format!("Visit: {}", appointment.customer_id)
+    };
+
+    let mut description = customer.map(describe_customer).unwrap_or_default();
+    if let Some(notes) = &appointment.notes {
+        description = format!("{description}\n\n{notes}");
+    }
+
+    Event {
+        uid: format!("appointment-{}@delivery-srl", appointment.id),
+        sequence: appointment.sequence,
+        start: EventTime::DateTime(appointment.start),
+        end: EventTime::DateTime(appointment.end),
+        summary,
+        description: Some(description.trim().to_owned()).filter(|text| !text.is_empty()),
+        location: customer.map(|customer| customer.address.to_string()),
+        cancelled: appointment.status == AppointmentStatus::Cancelled
+    }
+}
+
+/// The calendar of the visits of the technician with `username`
+///
+/// Cancelled visits stay in the feed as cancelled events, so calendar apps remove them.
+pub async fn technician_calendar(
+    repositories: &Repositories,
+    username: &str,
+    now: DateTime<Utc>
+) -> Result<Calendar, AppError> {
+    let query = AppointmentQuery {
+        technician: Some(username.to_owned()),
+        from: Some((now - Duration::days(PAST_APPOINTMENT_DAYS)).into()),
+        ..Default::default()
+    };
+
+    let appointments = repositories.appointments.list_appointments(query).await?.appointments;
+
+    let customer_ids =
+        appointments.iter().map(|appointment| appointment.customer_id.clone()).collect::<Vec<_>>();
+
+    let customers = repositories
+        .customers
+        .get_customers(&customer_ids)
+        .await?
+        .into_iter()
+        .map(|customer| (customer.customer_id.clone(), customer))
+        .collect::<HashMap<_, _>>();
+
+    let events = appointments
+        .into_iter()
+        .map(|appointment| {
+            let customer = customers.get(&appointment.customer_id);
+            appointment_event(appointment, customer)
+        })
+        .collect();
+
+    Ok(Calendar { name: format!("Visits of {username}"), events })
+}
+
+fn expiration_event(customer: DeliveryCustomerOut) -> Event {
+    let date = local_date(customer.appliance.expiration_date);
+
+    Event {
+        uid: format!("expiration-{}@delivery-srl", customer.id),
+        sequence: customer.version,
+        start: EventTime::Date(date),
+        end: EventTime::Date(date.succ_opt().unwrap_or(date)),
+        summary: format!("{} expires: {}", customer.appliance.operation_performed, customer.name),
+        description: Some(describe_customer(&customer)),
+        location: Some(customer.address.to_string()),
+        cancelled: false
+    }
+}
+
+/// The office wide calendar of the appliance `expiration_date`s, as all day events
+///
+/// Every active customer has a single event, which moves when the check is performed again.
+pub async fn expiration_calendar(
+    customers: &dyn CustomerRepository,
+    now: DateTime<Utc>
+) -> Result<Calendar, AppError> {
+    let (past_days, future_days) = EXPIRATION_DAYS;
+    let mut events = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let query = ExpiredCustomersQuery {
+            status: ExpirationStatus::All,
+            start_date: Some((now - Duration::days(past_days)).into()),
+            end_date: Some((now + Duration::days(future_days)).into()),
+            limit: Some(500),
+            cursor: cursor.take(),
+            ..Default::default()
+        };
+
+        let page = customers.expired_customers(query).await?;
+        cursor = page.next_cursor().map(ToOwned::to_owned);
+
+        events.extend(page.into_iter().filter(|customer| customer.active).map(expiration_event));
+
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(Calendar { name: "Appliance check expirations".into(), events })
+}
@@ -0,0 +1,148 @@
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::{http::StatusCode, response::IntoResponse};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Lines longer than this many octets are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// When an [`Event`] starts or ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTime {
+    /// An exact point in time, written in UTC
+    DateTime(DateTime<Utc>),
+    /// A whole day, for all day events
+    Date(NaiveDate)
+}
+
+impl EventTime {
+    /// The value and parameters of a `DTSTART` or `DTEND` property
+    fn property(&self, name: &str) -> String {
+        match self {
+            EventTime::DateTime(datetime) => {
+                format!("{name}:{}", datetime.format("%Y%m%dT%H%M%SZ"))
+            }
+            EventTime::Date(date) => format!("{name};VALUE=DATE:{}", date.format("%Y%m%d"))
+        }
+    }
+}
+
+/// A `VEVENT` of a [`Calendar`]
+///
+/// The `uid` has to stay the same for as long as the event exists, so calendar apps
+/// replace the event when it changes instead of adding a new one.
+/// `sequence` has to grow with every change.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub uid: String,
+    pub sequence: i64,
+    pub start: EventTime,
+    pub end: EventTime,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub cancelled: bool
+}
+
+/// An RFC 5545 `VCALENDAR`
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    pub name: String,
+    pub events: Vec<Event>
+}
+
+/// Escape the characters that have a special meaning in a `TEXT` value
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+/// Append `line` to `output`, folded into lines of at most [`MAX_LINE_OCTETS`] octets
+///
+/// Continuation lines start with a space, lines are never split inside a character.
+fn push_line(output: &mut String, line: &str) {
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_OCTETS {
+            output.push_str("\r\n ");
+            length = 1;
+        }
+
+        output.push(c);
+        length += c.len_utf8();
+    }
+
+    output.push_str("\r\n");
+}
+
+impl Calendar {
+    /// Render [`Self`] as an iCalendar object
+    ///
+    /// `now` is the `DTSTAMP` of every event.
+    pub fn render(&self, now: DateTime<Utc>) -> String {
+        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut output = String::new();
+
+        push_line(&mut output, "BEGIN:VCALENDAR");
+        push_line(&mut output, "VERSION:2.0");
+        push_line(&mut output, "PRODID:-//Delivery SRL//delivery-backend//RO");
+        push_line(&mut output, "CALSCALE:GREGORIAN");
+        push_line(&mut output, "METHOD:PUBLISH");
+        push_line(&mut output, &format!("X-WR-CALNAME:{}", escape_text(&self.name)));
+
+        for event in &self.events {
+            push_line(&mut output, "BEGIN:VEVENT");
+            push_line(&mut output, &format!("UID:{}", event.uid));
+            push_line(&mut output, &format!("DTSTAMP:{stamp}"));
+            push_line(&mut output, &format!("SEQUENCE:{}", event.sequence));
+            push_line(&mut output, &event.start.property("DTSTART"));
+            push_line(&mut output, &event.end.property("DTEND"));
+            push_line(&mut output, &format!("SUMMARY:{}", escape_text(&event.summary)));
+
+            if let Some(description) = &event.description {
+                push_line(&mut output, &format!("DESCRIPTION:{}", escape_text(description)));
+            }
+
+            if let Some(location) = &event.location {
+                push_line(&mut output, &format!("LOCATION:{}", escape_text(location)));
+            }
+
+            let status = if event.cancelled { "CANCELLED" } else { "CONFIRMED" };
+            push_line(&mut output, &format!("STATUS:{status}"));
+
+            // All day events shouldn't block the day in the calendar
+            if matches!(event.start, EventTime::Date(_)) {
+                push_line(&mut output, "TRANSP:TRANSPARENT");
+            }
+
+            push_line(&mut output, "END:VEVENT");
+        }
+
+        push_line(&mut output, "END:VCALENDAR");
+
+        output
+    }
+}
+
+impl IntoResponse for Calendar {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::OK,
+            [(CONTENT_TYPE, "text/calendar; charset=utf-8"), (CACHE_CONTROL, "no-cache")],
+            self.render(Utc::now())
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,7 @@
+mod feed;
+mod ics;
+mod token;
+
+pub use feed::{expiration_calendar, technician_calendar};
+pub use ics::{Calendar, Event, EventTime};
+pub use token::{generate_calendar_token, hash_calendar_token, CalendarToken};
@@ -0,0 +1,47 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Length of a calendar token
+const TOKEN_LENGTH: usize = 40;
+
+/// Generate a new random calendar token
+///
+/// Calendar apps can't send an `Authorization` header,
+/// so feeds are protected by a token in their url instead.
+pub fn generate_calendar_token() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(TOKEN_LENGTH).map(char::from).collect()
+}
+
+/// The hash of a calendar token, which is what's stored on the user
+pub fn hash_calendar_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// A newly generated calendar token, with the urls of the feeds it opens
+///
+/// The token is only ever shown once, generating a new one revokes the old one.
+#[derive(Debug, serde::Serialize)]
+pub struct CalendarToken {
+    pub token: String,
+    pub feed: String,
+    pub expirations_feed: String
+}
+
+impl CalendarToken {
+    /// Creates a new [`CalendarToken`] of the user with `username`.
+    pub fn new(username: &str, token: String) -> Self {
+        Self {
+            feed: format!("/calendar/{username}.ics?token={token}"),
+            expirations_feed: format!("/calendar/office/expirations.ics?token={token}"),
+            token
+        }
+    }
+}
+
+impl IntoResponse for CalendarToken {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::CREATED, Json(self)).into_response()
+    }
+}
@@ -0,0 +1,84 @@
+/// Widths of the printable ASCII characters of Helvetica, in 1/1000 of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584
+];
+
+/// Widths of the printable ASCII characters of Helvetica-Bold, in 1/1000 of the font size
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584
+];
+
+/// The fonts certificates are written with
+///
+/// Both are standard PDF fonts, which every viewer has, so nothing is embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold
+}
+
+impl Font {
+    /// The name of the font resource in the page
+    pub fn resource_name(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2"
+        }
+    }
+
+    /// The name of the standard font
+    pub fn base_font(&self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold"
+        }
+    }
+
+    /// The width of `text` written at `size`, in points
+    ///
+    /// Letters with diacritics are as wide as the letter without them.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        let widths = match self {
+            Font::Regular => &HELVETICA_WIDTHS,
+            Font::Bold => &HELVETICA_BOLD_WIDTHS
+        };
+
+        let total: u32 = text
+            .chars()
+            .map(|c| {
+                let base = match c {
+                    'ă' | 'â' | 'á' | 'à' | 'ä' => 'a',
+                    'Ă' | 'Â' | 'Á' | 'À' | 'Ä' => 'A',
+                    'î' | 'í' | 'ì' | 'ï' => 'i',
+                    'Î' | 'Í' | 'Ì' | 'Ï' => 'I',
+                    'ș' | 'ş' => 's',
+                    'Ș' | 'Ş' => 'S',
+                    'ț' | 'ţ' => 't',
+                    'Ț' | 'Ţ' => 'T',
+                    'é' | 'è' | 'ë' => 'e',
+                    'É' | 'È' | 'Ë' => 'E',
+                    'ó' | 'ò' | 'ö' => 'o',
+                    'Ó' | 'Ò' | 'Ö' => 'O',
+                    'ú' | 'ù' | 'ü' => 'u',
+                    'Ú' | 'Ù' | 'Ü' => 'U',
+                    c => c
+                };
+
+                match base {
+                    ' '..='~' => u32::from(widths[base as usize - 32]),
+                    _ => 556
+                }
+            })
+            .sum();
+
+        total as f32 * size / 1000.0
+    }
+}
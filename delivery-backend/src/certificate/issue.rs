@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+
+use crate::customer::{Certificate, DeliveryCustomerOut, OperationPerformed};
+use crate::error::AppError;
+use crate::local_time::local_date;
+use crate::repository::{CounterRepository, CustomerRepository};
+
+use super::{render_certificate, CertificateTemplate};
+
+/// Name of the counter certificate numbers are taken from
+const CERTIFICATE_COUNTER: &str = "certificate";
+
+/// The operations a customer gets a certificate for
+const CERTIFIED_OPERATIONS: [OperationPerformed; 3] =
+    [OperationPerformed::VTP, OperationPerformed::RGAZ, OperationPerformed::VGAZ];
+
+/// A rendered inspection certificate
+pub struct CertificatePdf {
+    number: i64,
+    pdf: Vec<u8>
+}
+
+impl IntoResponse for CertificatePdf {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::OK,
+            [
+                (CONTENT_TYPE, "application/pdf".to_owned()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("inline; filename=\"certificate-{}.pdf\"", self.number)
+                )
+            ],
+            self.pdf
+        )
+            .into_response()
+    }
+}
+
+/// A certificate, as it's returned by [`issue_certificate`]
+pub struct IssuedCertificate {
+    certificate: Certificate,
+    /// Whether it was issued by this request, rather than before
+    issued: bool
+}
+
+impl IntoResponse for IssuedCertificate {
+    fn into_response(self) -> axum::response::Response {
+        let status = if self.issued { StatusCode::CREATED } else { StatusCode::OK };
+
+        (status, Json(self.certificate)).into_response()
+    }
+}
+
+/// A date the way it's written on a certificate, in Romanian local time
+fn certificate_date(date: DateTime<Utc>) -> String {
+    local_date(date).format("%d.%m.%Y").to_string()
+}
+
+/// The values of the placeholders of a [`CertificateTemplate`]
+pub fn certificate_fields(
+    certificate: &Certificate,
+    customer: &DeliveryCustomerOut
+) -> HashMap<&'static str, String> {
+    let appliance = &customer.appliance;
+    let phones = customer.phones.iter().map(|phone| phone.number.as_str()).collect::<Vec<_>>();
+
+    HashMap::from([
+        ("certificate_number", certificate.number.to_string()),
+        ("issued_at", certificate_date(certificate.issued_at)),
+        ("customer_id", customer.customer_id.clone()),
+        ("name", customer.name.clone()),
+        ("address", customer.address.to_string()),
+        ("county", customer.address.county.clone()),
+        ("locality", customer.address.locality.clone()),
+        ("phone", phones.join(", ")),
+        ("email", customer.email.clone().unwrap_or_default()),
+        ("manufacturer", appliance.manufacturer.clone()),
+        ("model", appliance.model.clone()),
+        ("type", appliance.typ.clone()),
+        ("year_of_manufacture", appliance.year_of_manufacture.clone()),
+        ("number", appliance.number.clone()),
+        ("warranty", certificate_date(appliance.warranty)),
+        ("operation_performed", appliance.operation_performed.to_string()),
+        ("date", certificate_date(appliance.date)),
+        ("expiration_date", certificate_date(appliance.expiration_date)),
+        ("observations", appliance.observations.clone().unwrap_or_default())
+    ])
+}
+
+/// The certificate of the last visit of `customer`, if it's one that gets a certificate
+///
+/// Fails with [`AppError::UnprocessableEntity`] for any other operation.
+fn current_certificate(customer: &DeliveryCustomerOut) -> Result<Option<&Certificate>, AppError> {
+    let appliance = &customer.appliance;
+
+    if !CERTIFIED_OPERATIONS.contains(&appliance.operation_performed) {
+        return Err(AppError::UnprocessableEntity(format!(
+            "No certificate is issued for {}",
+            appliance.operation_performed
+        )));
+    }
+
+    Ok(customer.certificate.as_ref().filter(|certificate| certificate.is_for(appliance.date)))
+}
+
+/// Issue the inspection certificate of the last visit of a customer
+///
+/// The first time a certificate is issued for a visit, it gets the next number
+/// of the `certificate` counter, which is stored on the customer. Issuing it again
+/// returns the same certificate, until the appliance is checked again.
+/// If another request issued the certificate of the visit in the meantime, that one is returned
+/// and the number taken by this one is skipped.
+#[tracing::instrument(skip(customers, counters))]
+pub async fn issue_certificate(
+    customers: &dyn CustomerRepository,
+    counters: &dyn CounterRepository,
+    customer_id: &str
+) -> Result<IssuedCertificate, AppError> {
+    let customer = customers.get_customer(customer_id).await?;
+
+    if let Some(certificate) = current_certificate(&customer)? {
+        return Ok(IssuedCertificate { certificate: certificate.clone(), issued: false });
+    }
+
+    let number = counters.next(CERTIFICATE_COUNTER).await?;
+    let certificate = Certificate::new(number, customer.appliance.date);
+
+    if customers.set_certificate(customer_id, &certificate).await? {
+        tracing::info!("Issued certificate {number} to customer_id={customer_id}");
+
+        return Ok(IssuedCertificate { certificate, issued: true });
+    }
+
+    let customer = customers.get_customer(customer_id).await?;
+
+    match current_certificate(&customer)? {
+        Some(certificate) => {
+            Ok(IssuedCertificate { certificate: certificate.clone(), issued: false })
+        }
+        None => Err(AppError::Conflict(format!(
+            "The last visit of customer_id={customer_id} changed while it's certificate was issued"
+        )))
+    }
+}
+
+/// Render the inspection certificate issued for the last visit of a customer
+///
+/// Fails with [`AppError::NotFound`] if it wasn't issued yet, see [`issue_certificate`].
+#[tracing::instrument(skip(customers, template))]
+pub async fn certificate_pdf(
+    customers: &dyn CustomerRepository,
+    template: &CertificateTemplate,
+    customer_id: &str
+) -> Result<CertificatePdf, AppError> {
+    let customer = customers.get_customer(customer_id).await?;
+
+    let Some(certificate) = current_certificate(&customer)? else {
+        return Err(AppError::NotFound(format!(
+            "No certificate was issued for the last visit of customer_id={customer_id}"
+        )));
+    };
+
+    let fields = certificate_fields(certificate, &customer);
+    let pdf = render_certificate(template, &fields, &format!("Certificat {}", certificate.number));
+
+    Ok(CertificatePdf { number: certificate.number, pdf })
+}
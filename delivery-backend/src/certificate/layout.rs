@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use super::pdf::{Page, PdfDocument, PAGE_HEIGHT, PAGE_WIDTH};
+use super::{CertificateTemplate, Font, LineStyle};
+
+/// Space left empty around the edges of the page, about 2cm
+const MARGIN: f32 = 56.0;
+
+/// Height of a [`LineStyle::Blank`] line
+const BLANK_HEIGHT: f32 = 8.0;
+
+/// Space taken by a [`LineStyle::Rule`], the line is drawn in the middle of it
+const RULE_HEIGHT: f32 = 14.0;
+
+/// Distance between the baselines of two lines of text, relative to the font size
+const LINE_SPACING: f32 = 1.4;
+
+/// Font, size and whether lines of `style` are centered
+fn text_style(style: LineStyle) -> (Font, f32, bool) {
+    match style {
+        LineStyle::Title => (Font::Bold, 18.0, true),
+        LineStyle::Subtitle => (Font::Bold, 12.0, true),
+        LineStyle::Heading => (Font::Bold, 12.0, false),
+        _ => (Font::Regular, 11.0, false)
+    }
+}
+
+/// Break `text` into lines no wider than `width`
+///
+/// Lines that already fit are kept as they are, the others are broken between words.
+/// A single word wider than `width` gets a line of it's own.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        if font.text_width(paragraph, size) <= width {
+            lines.push(paragraph.to_owned());
+            continue;
+        }
+
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let candidate =
+                if line.is_empty() { word.to_owned() } else { format!("{line} {word}") };
+
+            if font.text_width(&candidate, size) > width && !line.is_empty() {
+                lines.push(std::mem::replace(&mut line, word.to_owned()));
+            } else {
+                line = candidate;
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Lay out `template` on A4 pages, with the placeholders replaced by `fields`
+///
+/// A new page is started whenever the next line doesn't fit on the current one.
+pub fn render_certificate(
+    template: &CertificateTemplate,
+    fields: &HashMap<&'static str, String>,
+    title: &str
+) -> Vec<u8> {
+    let width = PAGE_WIDTH - 2.0 * MARGIN;
+    let top = PAGE_HEIGHT - MARGIN;
+
+    let mut document = PdfDocument::new(title);
+    let mut page = Page::default();
+    let mut y = top;
+
+    for line in template.lines() {
+        match line.style {
+            LineStyle::Blank => y -= BLANK_HEIGHT,
+            LineStyle::Rule => {
+                if y - RULE_HEIGHT < MARGIN {
+                    document.push_page(std::mem::take(&mut page));
+                    y = top;
+                }
+
+                y -= RULE_HEIGHT;
+                let middle = y + RULE_HEIGHT / 2.0;
+                page.line((MARGIN, middle), (PAGE_WIDTH - MARGIN, middle), 0.75);
+            }
+            style => {
+                let (font, size, centered) = text_style(style);
+
+                for text in wrap(&line.render(fields), font, size, width) {
+                    if y - size * LINE_SPACING < MARGIN {
+                        document.push_page(std::mem::take(&mut page));
+                        y = top;
+                    }
+
+                    y -= size * LINE_SPACING;
+                    let x = if centered {
+                        (PAGE_WIDTH - font.text_width(&text, size)) / 2.0
+                    } else {
+                        MARGIN
+                    };
+
+                    page.text(x.max(MARGIN), y, font, size, &text);
+                }
+            }
+        }
+    }
+
+    document.push_page(page);
+    document.render()
+}
@@ -0,0 +1,13 @@
+mod font;
+mod issue;
+mod layout;
+mod pdf;
+mod template;
+
+pub use font::Font;
+pub use issue::{
+    certificate_fields, certificate_pdf, issue_certificate, CertificatePdf, IssuedCertificate
+};
+pub use layout::render_certificate;
+pub use pdf::{Page, PdfDocument, PAGE_HEIGHT, PAGE_WIDTH};
+pub use template::{CertificateTemplate, LineStyle, TemplateLine, CERTIFICATE_FIELDS};
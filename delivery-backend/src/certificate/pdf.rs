@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use super::Font;
+
+/// Width of an A4 page, in points
+pub const PAGE_WIDTH: f32 = 595.28;
+
+/// Height of an A4 page, in points
+pub const PAGE_HEIGHT: f32 = 841.89;
+
+/// Codes the standard `WinAnsiEncoding` leaves unused, given to the Romanian letters it lacks
+///
+/// Written as the `Differences` array of the font encoding.
+const ROMANIAN_DIFFERENCES: &str =
+    "[127 /tcommaaccent 129 /Abreve 141 /abreve 143 /Scommaaccent 144 /scommaaccent 157 /Tcommaaccent]";
+
+/// Encode `c` as a single `WinAnsiEncoding` byte, with the [`ROMANIAN_DIFFERENCES`]
+///
+/// The cedilla forms of `ș` and `ț` are written with a comma, characters that can't
+/// be encoded become `?`.
+fn encode_char(c: char) -> u8 {
+    match c {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+        'ț' | 'ţ' => 127,
+        'Ă' => 129,
+        'ă' => 141,
+        'Ș' | 'Ş' => 143,
+        'ș' | 'ş' => 144,
+        'Ț' | 'Ţ' => 157,
+        '€' => 128,
+        '„' => 132,
+        '…' => 133,
+        '‘' => 145,
+        '’' => 146,
+        '“' => 147,
+        '”' => 148,
+        '•' => 149,
+        '–' => 150,
+        '—' => 151,
+        _ => b'?'
+    }
+}
+
+/// Write `text` as a PDF string literal
+///
+/// Bytes outside of printable ASCII are written as octal escapes.
+fn string_literal(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('(');
+
+    for byte in text.chars().map(encode_char) {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                literal.push('\\');
+                literal.push(byte as char);
+            }
+            b' '..=b'~' => literal.push(byte as char),
+            _ => {
+                let _ = write!(literal, "\\{byte:03o}");
+            }
+        }
+    }
+
+    literal.push(')');
+    literal
+}
+
+/// A page of a [`PdfDocument`], holding it's content stream
+///
+/// Coordinates are in points, from the bottom left corner of the page.
+#[derive(Debug, Default)]
+pub struct Page {
+    content: String
+}
+
+impl Page {
+    /// Write `text` on a single line, starting at `x` and `y`
+    pub fn text(&mut self, x: f32, y: f32, font: Font, size: f32, text: &str) {
+        let _ = writeln!(
+            self.content,
+            "BT /{} {size:.1} Tf {x:.2} {y:.2} Td {} Tj ET",
+            font.resource_name(),
+            string_literal(text)
+        );
+    }
+
+    /// Draw a straight line from `from` to `to`
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32) {
+        let _ = writeln!(
+            self.content,
+            "{width:.2} w {:.2} {:.2} m {:.2} {:.2} l S",
+            from.0, from.1, to.0, to.1
+        );
+    }
+}
+
+/// A minimal PDF document, made of A4 [`Page`]s written with the standard fonts
+#[derive(Debug, Default)]
+pub struct PdfDocument {
+    title: String,
+    pages: Vec<Page>
+}
+
+impl PdfDocument {
+    /// Creates a new, empty [`PdfDocument`].
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), pages: Vec::new() }
+    }
+
+    /// Add a page at the end of the document
+    pub fn push_page(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    /// Write the document as PDF 1.4
+    ///
+    /// The catalog, page tree, fonts, encoding and info dictionary come first,
+    /// followed by every page and it's content stream.
+    pub fn render(&self) -> Vec<u8> {
+        let first_page = 7;
+        let kids = (0..self.pages.len())
+            .map(|index| format!("{} 0 R", first_page + 2 * index))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            format!("<< /Type /Pages /Kids [{kids}] /Count {} >>", self.pages.len()),
+            font_object(Font::Regular),
+            font_object(Font::Bold),
+            format!(
+                "<< /Type /Encoding /BaseEncoding /WinAnsiEncoding /Differences {ROMANIAN_DIFFERENCES} >>"
+            ),
+            format!(
+                "<< /Title {} /Producer (delivery-backend) /CreationDate (D:{}Z) >>",
+                string_literal(&self.title),
+                chrono::Utc::now().format("%Y%m%d%H%M%S")
+            ),
+        ];
+
+        for (index, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                first_page + 2 * index + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.content.len(),
+                page.content
+            ));
+        }
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            let _ = write!(pdf, "{} 0 obj\n{object}\nendobj\n", index + 1);
+        }
+
+        let xref = pdf.len();
+        let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+
+        for offset in offsets {
+            let _ = writeln!(pdf, "{offset:010} 00000 n ");
+        }
+
+        let _ = write!(
+            pdf,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 6 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+
+        pdf.into_bytes()
+    }
+}
+
+/// The dictionary of a standard `font`, with the Romanian encoding
+fn font_object(font: Font) -> String {
+    format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding 5 0 R >>", font.base_font())
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::env;
+
+use crate::error::AppError;
+
+/// The template used when `CERTIFICATE_TEMPLATE` isn't set
+const DEFAULT_TEMPLATE: &str = include_str!("template.txt");
+
+/// The placeholders a [`CertificateTemplate`] can use, written as `{name}`
+pub const CERTIFICATE_FIELDS: [&str; 19] = [
+    "certificate_number",
+    "issued_at",
+    "customer_id",
+    "name",
+    "address",
+    "county",
+    "locality",
+    "phone",
+    "email",
+    "manufacturer",
+    "model",
+    "type",
+    "year_of_manufacture",
+    "number",
+    "warranty",
+    "operation_performed",
+    "date",
+    "expiration_date",
+    "observations"
+];
+
+/// How a [`TemplateLine`] is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineStyle {
+    /// `# ...`, large, bold and centered
+    Title,
+    /// `## ...`, bold and centered
+    Subtitle,
+    /// `### ...`, bold
+    Heading,
+    /// Any other line, wrapped if it doesn't fit the page
+    Text,
+    /// `---`, a horizontal line across the page
+    Rule,
+    /// An empty line, a small vertical gap
+    Blank
+}
+
+/// A piece of a [`TemplateLine`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(&'static str)
+}
+
+/// A line of a [`CertificateTemplate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateLine {
+    pub style: LineStyle,
+    segments: Vec<Segment>
+}
+
+impl TemplateLine {
+    /// Parse a line of a template
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim_end();
+
+        let (style, text) = if line.trim().is_empty() {
+            (LineStyle::Blank, "")
+        } else if line.trim() == "---" {
+            (LineStyle::Rule, "")
+        } else if let Some(text) = line.strip_prefix("### ") {
+            (LineStyle::Heading, text)
+        } else if let Some(text) = line.strip_prefix("## ") {
+            (LineStyle::Subtitle, text)
+        } else if let Some(text) = line.strip_prefix("# ") {
+            (LineStyle::Title, text)
+        } else {
+            (LineStyle::Text, line)
+        };
+
+        Ok(Self { style, segments: parse_segments(text)? })
+    }
+
+    /// The text of the line, with the placeholders replaced by `fields`
+    ///
+    /// Placeholders missing from `fields` are left empty.
+    pub fn render(&self, fields: &HashMap<&'static str, String>) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.as_str(),
+                Segment::Field(field) => fields.get(field).map_or("", String::as_str)
+            })
+            .collect()
+    }
+}
+
+/// Split `text` into literals and placeholders
+///
+/// `{{` and `}}` are written as literal braces.
+fn parse_segments(text: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                literal.push(c);
+            }
+            ('{', _) => {
+                let mut name = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("unclosed placeholder {{{name}"))
+                    }
+                }
+
+                let field = CERTIFICATE_FIELDS
+                    .into_iter()
+                    .find(|field| *field == name.trim())
+                    .ok_or_else(|| format!("unknown placeholder {{{name}}}"))?;
+
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Field(field));
+            }
+            ('}', _) => return Err("unmatched }".to_owned()),
+            (c, _) => literal.push(c)
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    Ok(segments)
+}
+
+/// The layout and wording of an inspection certificate
+///
+/// A template is plain text, one [`TemplateLine`] per line. Lines starting with `#`, `##`
+/// and `###` are titles, subtitles and headings, `---` draws a line across the page.
+/// `{name}` placeholders are replaced with the [`CERTIFICATE_FIELDS`] of the customer.
+#[derive(Debug, Clone)]
+pub struct CertificateTemplate {
+    lines: Vec<TemplateLine>
+}
+
+impl CertificateTemplate {
+    /// Parse a [`CertificateTemplate`]
+    ///
+    /// Unknown placeholders are rejected, so a typo doesn't leave a blank on every certificate.
+    pub fn parse(template: &str) -> Result<Self, AppError> {
+        let lines = template
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                TemplateLine::parse(line).map_err(|err| {
+                    AppError::CertificateError(format!("line {} of template: {err}", index + 1))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { lines })
+    }
+
+    /// Load the template from the file in `CERTIFICATE_TEMPLATE`
+    ///
+    /// Falls back to the built in Romanian template if it isn't set.
+    pub fn from_env() -> Result<Self, AppError> {
+        match env::var("CERTIFICATE_TEMPLATE") {
+            Ok(path) => {
+                let template = std::fs::read_to_string(&path).map_err(|err| {
+                    AppError::CertificateError(format!("Failed to read {path}: {err}"))
+                })?;
+                tracing::info!("Using certificate template {path}");
+                Self::parse(&template)
+            }
+            Err(_) => {
+                tracing::info!("CERTIFICATE_TEMPLATE not specified, using default");
+                Ok(Self::default())
+            }
+        }
+    }
+
+    /// The lines of the template
+    pub fn lines(&self) -> &[TemplateLine] {
+        &self.lines
+    }
+}
+
+impl Default for CertificateTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TEMPLATE).expect("default certificate template is valid")
+    }
+}
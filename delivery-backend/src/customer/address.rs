@@ -1,43 +1,127 @@
-use mongodb::bson::{bson, Document};
+use validator::{Validate, ValidationError};
+
+use crate::locality::{self, normalize_county, normalize_locality};
 
 /// Represents the [`Address`] of a [`DeliveryCustomer`]
-#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+///
+/// `county` is stored as the canonical county code (`CJ`, `B`, ..).
+/// When deserialized, counties written as names are converted to their code
+/// and known localities get their canonical spelling.
+#[derive(Debug, Default, Validate, serde::Deserialize, serde::Serialize)]
+#[serde(from = "RawAddress")]
+#[validate(schema(function = "validate_address"))]
 pub struct Address {
     pub county: String,
+    pub locality: String,
+    pub postal_code: Option<String>,
     pub street: String,
     pub number: String,
+    pub building: Option<String>,
+    pub staircase: Option<String>,
+    pub apartment: Option<String>,
     pub additional: String
 }
 
-impl Address {
-    /// Creates a new [`Address`].
-    pub fn new(county: String, street: String, number: String, additional: String) -> Self {
+/// An [`Address`] as it's written by clients, before normalization
+#[derive(serde::Deserialize)]
+struct RawAddress {
+    county: String,
+    #[serde(default)]
+    locality: String,
+    #[serde(default)]
+    postal_code: Option<String>,
+    street: String,
+    number: String,
+    #[serde(default)]
+    building: Option<String>,
+    #[serde(default)]
+    staircase: Option<String>,
+    #[serde(default)]
+    apartment: Option<String>,
+    #[serde(default)]
+    additional: String
+}
+
+impl From<RawAddress> for Address {
+    fn from(value: RawAddress) -> Self {
+        let county = match normalize_county(&value.county) {
+            Some(code) => code.to_owned(),
+            None => value.county.trim().to_owned()
+        };
+
+        let locality = match normalize_locality(&county, &value.locality) {
+            Some(locality) => locality.to_owned(),
+            None => value.locality.trim().to_owned()
+        };
+
+        let trimmed = |value: Option<String>| {
+            value.map(|value| value.trim().to_owned()).filter(|value| !value.is_empty())
+        };
+
         Self {
             county,
-            street,
-            number,
-            additional
+            locality,
+            postal_code: trimmed(value.postal_code),
+            street: value.street.trim().to_owned(),
+            number: value.number.trim().to_owned(),
+            building: trimmed(value.building),
+            staircase: trimmed(value.staircase),
+            apartment: trimmed(value.apartment),
+            additional: value.additional.trim().to_owned()
+        }
+    }
+}
+
+/// The county has to be known and the postal code, if there is one, has to belong to it
+fn validate_address(address: &Address) -> Result<(), ValidationError> {
+    if locality::county(&address.county).is_none() {
+        let mut error = ValidationError::new("county");
+        error.message = Some("county must be a Romanian county code or name".into());
+        return Err(error);
+    }
+
+    if let Some(postal_code) = &address.postal_code {
+        if !locality::is_valid_postal_code(&address.county, postal_code) {
+            let mut error = ValidationError::new("postal_code");
+            error.message = Some("postal_code must be a postal code of the county".into());
+            return Err(error);
         }
     }
 
-    /// Convert [`Self`] into a [`Document`]
-    pub fn into_document(self) -> Document {
-        Document::from_iter(self.into_iter().map(|(key, value)| (key, bson!(value))))
+    Ok(())
+}
+
+impl Address {
+    /// Creates a new [`Address`].
+    pub fn new(county: String, street: String, number: String, additional: String) -> Self {
+        Self { county, street, number, additional, ..Default::default() }
     }
 }
 
-impl IntoIterator for Address {
-    type Item = (String, String);
+/// Written the way it's on an envelope, e.g. `Strada Lunga 12, bl. A, ap. 3, Cluj-Napoca, Cluj 400001`
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.street, self.number)?;
 
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+        let parts =
+            [("bl. ", &self.building), ("sc. ", &self.staircase), ("ap. ", &self.apartment)];
+        for (prefix, part) in parts {
+            if let Some(part) = part {
+                write!(f, ", {prefix}{part}")?;
+            }
+        }
+
+        if !self.locality.is_empty() {
+            write!(f, ", {}", self.locality)?;
+        }
+
+        let county = locality::county(&self.county).map_or(self.county.as_str(), |c| c.name);
+        write!(f, ", {county}")?;
+
+        if let Some(postal_code) = &self.postal_code {
+            write!(f, " {postal_code}")?;
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        vec![
-            ("county".into(), self.county),
-            ("street".into(), self.street),
-            ("number".into(), self.number),
-            ("additional".into(), self.additional),
-        ]
-        .into_iter()
+        Ok(())
     }
 }
@@ -1,8 +1,7 @@
-use chrono::{DateTime, Utc};
-use mongodb::bson::{self, Document};
-use serde::{Deserialize, Deserializer};
-
-use crate::appliance_field::ApplianceField;
+use chrono::{DateTime, TimeZone, Utc};
+use mongodb::bson;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use validator::{Validate, ValidationError};
 
 use super::OperationPerformed;
 
@@ -11,54 +10,38 @@ use super::OperationPerformed;
 /// We don't know or care about the appliance (but they're mostly water heaters).
 /// They have some operation performed on them for the [`DeliveryCustomerIn`], which we know from
 /// the [`OperationPerformed`] field.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+///
+/// It's serialized the way it's stored: dates as BSON datetimes and
+/// missing `observations` as an empty string.
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+#[validate(schema(function = "validate_appliance_dates"))]
 pub struct ApplianceIn {
     pub manufacturer: String,
     pub year_of_manufacture: String,
     pub model: String,
     #[serde(rename = "type")]
     pub typ: String,
+    #[serde(serialize_with = "serialize_chrono_as_bson_datetime")]
     pub warranty: DateTime<chrono::FixedOffset>,
     pub operation_performed: OperationPerformed,
     pub number: String,
+    #[serde(serialize_with = "serialize_chrono_as_bson_datetime")]
     pub date: DateTime<chrono::FixedOffset>,
+    #[serde(serialize_with = "serialize_chrono_as_bson_datetime")]
     pub expiration_date: DateTime<chrono::FixedOffset>,
+    #[serde(serialize_with = "serialize_none_as_empty")]
     pub observations: Option<String>
 }
 
-impl ApplianceIn {
-    /// Convert [`Self`] into a MongoDB [`Document`]
-    pub fn into_document(self) -> Document {
-        Document::from_iter(self.into_iter().map(|(key, value)| (key, value.into())))
+/// The check can't expire before it was performed
+fn validate_appliance_dates(appliance: &ApplianceIn) -> Result<(), ValidationError> {
+    if appliance.expiration_date < appliance.date {
+        let mut error = ValidationError::new("expiration_date");
+        error.message = Some("expiration_date must not be before date".into());
+        return Err(error);
     }
-}
 
-impl IntoIterator for ApplianceIn {
-    type Item = (String, ApplianceField);
-
-    type IntoIter = std::vec::IntoIter<Self::Item>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        vec![
-            ("manufacturer".into(), self.manufacturer.into()),
-            (
-                "year_of_manufacture".into(),
-                self.year_of_manufacture.into()
-            ),
-            ("model".into(), self.model.into()),
-            ("type".into(), self.typ.into()),
-            ("warranty".into(), self.warranty.into()),
-            (
-                "operation_performed".into(),
-                self.operation_performed.into()
-            ),
-            ("number".into(), self.number.into()),
-            ("date".into(), self.date.into()),
-            ("expiration_date".into(), self.expiration_date.into()),
-            ("observations".into(), self.observations.into()),
-        ]
-        .into_iter()
-    }
+    Ok(())
 }
 
 /// Represents some kind of `Appliance`
@@ -94,3 +77,23 @@ where
     let datetime = bson::DateTime::deserialize(deserializer)?;
     Ok(datetime.to_chrono())
 }
+
+/// Serializes a [`chrono::DateTime`] as a [`mongodb::bson::DateTime`].
+pub fn serialize_chrono_as_bson_datetime<S, Tz>(
+    datetime: &DateTime<Tz>,
+    serializer: S
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    Tz: TimeZone
+{
+    bson::DateTime::from_chrono(datetime.clone()).serialize(serializer)
+}
+
+/// Serializes a missing string as an empty one, the way older customers have it stored.
+pub fn serialize_none_as_empty<S>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer
+{
+    value.as_deref().unwrap_or_default().serialize(serializer)
+}
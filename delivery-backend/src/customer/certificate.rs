@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{self, doc, Document};
+
+use super::appliance::deserialize_chrono_from_bson_datetime;
+
+/// The inspection certificate handed to a customer after a visit
+///
+/// `date` is the `appliance.date` of the visit it was issued for. Once the appliance
+/// is checked again, the next certificate gets a new `number`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Certificate {
+    pub number: i64,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub date: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub issued_at: DateTime<Utc>
+}
+
+impl Certificate {
+    /// Creates a new [`Certificate`], issued now.
+    pub fn new(number: i64, date: DateTime<Utc>) -> Self {
+        Self { number, date, issued_at: Utc::now() }
+    }
+
+    /// Returns `true` if the certificate was issued for the visit on `date`
+    pub fn is_for(&self, date: DateTime<Utc>) -> bool {
+        self.date == date
+    }
+
+    /// Convert a [`Certificate`] into the document stored on the customer
+    pub fn to_document(&self) -> Document {
+        doc! {
+            "number": self.number,
+            "date": bson::DateTime::from_chrono(self.date),
+            "issued_at": bson::DateTime::from_chrono(self.issued_at)
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Deserializer};
+use validator::{Validate, ValidationError};
+
+use super::{deserialize_chrono_from_bson_datetime, serialize_chrono_as_bson_datetime};
+
+/// Numbers written without an international prefix are assumed to be Romanian
+const DEFAULT_COUNTRY_CODE: &str = "40";
+
+/// Normalize a phone number to E.164, e.g. `0712 345 678` to `+40712345678`
+///
+/// Spaces, dashes, dots and parentheses are dropped, a `00` prefix becomes `+`
+/// and a leading `0` is replaced by the [`DEFAULT_COUNTRY_CODE`].
+/// The national trunk `0` after a country code, written as `+44 (0) 20 ..` or, for Romanian
+/// numbers, as `+40 0712 ..`, is dropped too.
+/// Returns [`None`] if the result isn't a valid E.164 number.
+pub fn normalize_phone(phone: &str) -> Option<String> {
+    let mut compact: String = phone.chars().filter(|c| !" -.".contains(*c)).collect();
+
+    // `(0)` marks the trunk prefix, which isn't dialed from abroad
+    if compact.starts_with('+') || compact.starts_with("00") {
+        compact = compact.replacen("(0)", "", 1);
+    }
+
+    compact.retain(|c| !"()".contains(c));
+
+    let mut international = if let Some(rest) = compact.strip_prefix('+') {
+        rest.to_owned()
+    } else if let Some(rest) = compact.strip_prefix("00") {
+        rest.to_owned()
+    } else if let Some(rest) = compact.strip_prefix('0') {
+        format!("{DEFAULT_COUNTRY_CODE}{rest}")
+    } else {
+        compact
+    };
+
+    // Romanian numbers never start with `0` after the country code, that's the trunk prefix
+    if international.starts_with(&format!("{DEFAULT_COUNTRY_CODE}0")) {
+        international.remove(DEFAULT_COUNTRY_CODE.len());
+    }
+
+    let is_e164 = (7..=15).contains(&international.len())
+        && international.chars().all(|c| c.is_ascii_digit())
+        && !international.starts_with('0');
+
+    is_e164.then(|| format!("+{international}"))
+}
+
+/// A phone number must be in E.164 format, which is what [`normalize_phone`] produces
+pub fn validate_phone(phone: &str) -> Result<(), ValidationError> {
+    if normalize_phone(phone).as_deref() != Some(phone) {
+        let mut error = ValidationError::new("phone");
+        error.message = Some("phone must be a valid phone number".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Deserializes a phone number, normalized with [`normalize_phone`]
+///
+/// Numbers that can't be normalized are kept as they are, so validation can report them.
+fn deserialize_phone<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let phone = String::deserialize(deserializer)?;
+    Ok(normalize_phone(&phone).unwrap_or(phone))
+}
+
+/// A labelled phone number of a customer, like `mobile` or `home`
+#[derive(Debug, Clone, PartialEq, Eq, Validate, serde::Serialize, serde::Deserialize)]
+pub struct PhoneNumber {
+    #[validate(length(min = 1, max = 30, message = "label must be 1 to 30 characters"))]
+    pub label: String,
+    #[serde(deserialize_with = "deserialize_phone")]
+    #[validate(custom = "validate_phone")]
+    pub number: String
+}
+
+/// How a customer prefers to be contacted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactChannel {
+    Phone,
+    Sms,
+    Email,
+    Post
+}
+
+impl std::fmt::Display for ContactChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ContactChannel::Phone => write!(f, "phone"),
+            ContactChannel::Sms => write!(f, "sms"),
+            ContactChannel::Email => write!(f, "email"),
+            ContactChannel::Post => write!(f, "post")
+        }
+    }
+}
+
+/// Where a consent was collected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentSource {
+    /// Signed paper form
+    Paper,
+    /// Given over the phone
+    Phone,
+    /// Given by email
+    Email,
+    /// Given on the website
+    Web,
+    /// Given in person, at the office or on site
+    InPerson
+}
+
+impl std::fmt::Display for ConsentSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ConsentSource::Paper => write!(f, "paper"),
+            ConsentSource::Phone => write!(f, "phone"),
+            ConsentSource::Email => write!(f, "email"),
+            ConsentSource::Web => write!(f, "web"),
+            ConsentSource::InPerson => write!(f, "in_person")
+        }
+    }
+}
+
+fn now() -> DateTime<FixedOffset> {
+    Utc::now().into()
+}
+
+/// A consent that's going IN to the database
+///
+/// `at` defaults to the current time, for consents collected right now.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConsentRecordIn {
+    pub granted: bool,
+    #[serde(default = "now", serialize_with = "serialize_chrono_as_bson_datetime")]
+    pub at: DateTime<FixedOffset>,
+    pub source: ConsentSource
+}
+
+/// The GDPR consents of a customer that are going IN to the database
+///
+/// A missing consent means it was never asked for.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ConsentIn {
+    /// Consent to receive offers and news
+    pub marketing: Option<ConsentRecordIn>,
+    /// Consent to receive reminders about appliance checks
+    pub notifications: Option<ConsentRecordIn>
+}
+
+/// A consent that's returned to the client
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConsentRecordOut {
+    pub granted: bool,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub at: DateTime<Utc>,
+    pub source: ConsentSource
+}
+
+/// The GDPR consents of a customer that are returned to the client
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ConsentOut {
+    pub marketing: Option<ConsentRecordOut>,
+    pub notifications: Option<ConsentRecordOut>
+}
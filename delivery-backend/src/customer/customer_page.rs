@@ -0,0 +1,20 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+
+use super::delivery_customer::DeliveryCustomerOut;
+
+/// A single page of a list of [`DeliveryCustomerOut`]s
+///
+/// `next_cursor` is [`None`] on the last page, `total` is the number of customers
+/// matching the filters across all pages.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct CustomerPage {
+    pub customers: Vec<DeliveryCustomerOut>,
+    pub next_cursor: Option<String>,
+    pub total: u64
+}
+
+impl IntoResponse for CustomerPage {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
@@ -1,13 +1,17 @@
+use axum::headers::ETag;
 use mongodb::bson::oid::ObjectId;
 use mongodb::bson::serde_helpers::serialize_object_id_as_hex_string;
-use mongodb::bson::{self, Document};
-use mongodb::error::Error as MongoError;
+use mongodb::bson::{self, doc, Bson, Document};
+use validator::Validate;
 
 use crate::error::AppError;
+use crate::query::version_etag;
 
-use super::{appliance::ApplianceOut, Address, ApplianceIn};
+use super::{appliance::ApplianceOut, Address, ApplianceIn, Certificate, Reminder};
+use super::{validate_location, GeoPoint};
+use super::{ConsentIn, ConsentOut, ContactChannel, PhoneNumber};
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum CustomerStatus {
     Active,
@@ -44,19 +48,41 @@ impl std::str::FromStr for CustomerStatus {
 ///
 /// * The field with `ObjectId` has to be named `_id`.
 /// * The `appliance` field has datetimes from `chrono`
-/// that don't play well with `BSON`.
+///   that don't play well with `BSON`, so they're serialized as BSON datetimes.
+///
+/// It serializes to exactly the stored document, so the customer collection
+/// can be typed with it for inserts.
 ///
 /// Customers get the appliances checked for certain things.
 /// That name of the operation is carried by the `OperationPerformed` enum.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
 pub struct DeliveryCustomerIn {
     #[serde(rename = "_id")]
     pub id: ObjectId,
+    #[validate(length(min = 1, message = "customer_id must not be empty"))]
     pub customer_id: String,
+    #[validate(length(min = 1, message = "name must not be empty"))]
     pub name: String,
     pub active: bool,
+    #[validate]
     pub address: Address,
-    pub appliance: ApplianceIn
+    #[validate]
+    pub appliance: ApplianceIn,
+    #[serde(default)]
+    #[validate]
+    pub phones: Vec<PhoneNumber>,
+    #[serde(default)]
+    #[validate(email(message = "email must be a valid email address"))]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub preferred_channel: Option<ContactChannel>,
+    #[serde(default)]
+    pub consent: ConsentIn,
+    #[serde(default)]
+    #[validate(custom = "validate_location")]
+    pub location: Option<GeoPoint>,
+    #[serde(default, skip_deserializing)]
+    pub version: i64
 }
 
 impl DeliveryCustomerIn {
@@ -74,26 +100,59 @@ impl DeliveryCustomerIn {
             name,
             active,
             address,
-            appliance
+            appliance,
+            phones: Vec::new(),
+            email: None,
+            preferred_channel: None,
+            consent: ConsentIn::default(),
+            location: None,
+            version: 0
         }
     }
 
-    /// Convert a [`DeliveryCustomerIn`] into a MongoDB [`Document`]
-    /// for update operations
-    pub fn into_update_document(self) -> Document {
-        let address_document = self.address.into_document();
-        let appliance_document = self.appliance.into_document();
-        let mut inner_document = Document::default();
-        let mut document = Document::default();
+    /// Convert a [`DeliveryCustomerIn`] into the MongoDB [`Document`] of a new customer
+    ///
+    /// This is the document the typed customer collection inserts.
+    pub fn into_document(self) -> Result<Document, AppError> {
+        Ok(bson::to_document(&self)?)
+    }
 
-        inner_document.insert("name", self.name);
-        inner_document.insert("active", self.active);
-        inner_document.insert("address", address_document);
-        inner_document.insert("appliance", appliance_document);
+    /// Convert a [`DeliveryCustomerIn`] into a MongoDB [`Document`] that overwrites
+    /// every field a client is allowed to change
+    ///
+    /// The `_id`, `customer_id` and `version` of the stored document are left untouched.
+    pub fn into_replacement_document(self) -> Result<Document, AppError> {
+        let mut fields = self.into_document()?;
 
-        document.insert("$set", inner_document);
+        for key in ["_id", "customer_id", "version"] {
+            fields.remove(key);
+        }
+
+        Ok(doc! { "$set": fields })
+    }
+
+    /// Convert a [`DeliveryCustomerIn`] into a MongoDB [`Document`] that only overwrites
+    /// the fields at `paths`
+    ///
+    /// Nested fields are addressed with dotted paths, like `address.street`,
+    /// the same way a [`PartialDeliveryCustomer`](crate::query::PartialDeliveryCustomer) does.
+    /// Every update increments the `version` of the document.
+    pub fn into_update_document(self, paths: &[&str]) -> Result<Document, AppError> {
+        let fields = self.into_document()?;
+        let mut set_document = Document::new();
+
+        for &path in paths {
+            let value = match path.split_once('.') {
+                Some((parent, field)) => {
+                    fields.get_document(parent).ok().and_then(|nested| nested.get(field))
+                }
+                None => fields.get(path)
+            };
+
+            set_document.insert(path, value.cloned().unwrap_or(Bson::Null));
+        }
 
-        document
+        Ok(doc! { "$set": set_document, "$inc": { "version": 1 } })
     }
 }
 
@@ -110,24 +169,39 @@ pub struct DeliveryCustomerOut {
     pub name: String,
     pub active: bool,
     pub address: Address,
-    pub appliance: ApplianceOut
+    pub appliance: ApplianceOut,
+    #[serde(default)]
+    pub phones: Vec<PhoneNumber>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub preferred_channel: Option<ContactChannel>,
+    #[serde(default)]
+    pub consent: ConsentOut,
+    #[serde(default)]
+    pub location: Option<GeoPoint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder: Option<Reminder>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<Certificate>,
+    #[serde(default)]
+    pub version: i64
 }
 
-impl TryFrom<Document> for DeliveryCustomerOut {
-    type Error = AppError;
-
-    fn try_from(value: Document) -> Result<Self, Self::Error> {
-        bson::from_document(value).map_err(AppError::from)
+impl DeliveryCustomerOut {
+    /// Returns the `ETag` of this [`DeliveryCustomerOut`]
+    ///
+    /// The tag is derived from the `version` of the document,
+    /// which is incremented by every update.
+    pub fn etag(&self) -> ETag {
+        version_etag(self.version)
     }
 }
 
-impl TryFrom<Result<Document, MongoError>> for DeliveryCustomerOut {
+impl TryFrom<Document> for DeliveryCustomerOut {
     type Error = AppError;
 
-    fn try_from(value: Result<Document, MongoError>) -> Result<Self, Self::Error> {
-        match value {
-            Ok(d) => bson::from_document(d).map_err(AppError::from),
-            Err(e) => Err(e.into())
-        }
+    fn try_from(value: Document) -> Result<Self, Self::Error> {
+        bson::from_document(value).map_err(AppError::from)
     }
 }
@@ -2,19 +2,43 @@ use axum::{http::StatusCode, response::IntoResponse, Json};
 
 use super::delivery_customer::DeliveryCustomerOut;
 
+/// A list of [`DeliveryCustomerOut`]s
+///
+/// If the list is paginated, `has_more` tells whether there is a next page,
+/// which can be fetched with `next_cursor`.
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
-#[serde(transparent)]
-pub struct DeliveryCustomerList(Vec<DeliveryCustomerOut>);
+pub struct DeliveryCustomerList {
+    customers: Vec<DeliveryCustomerOut>,
+    has_more: bool,
+    next_cursor: Option<String>
+}
 
 impl DeliveryCustomerList {
     /// Return the number of [`DeliveryCustomerOut`]s held
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.customers.len()
     }
 
     /// Returns `true` if the underlying vector contains no elements
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.customers.is_empty()
+    }
+
+    /// Returns the [`DeliveryCustomerOut`]s held
+    pub fn customers(&self) -> &[DeliveryCustomerOut] {
+        &self.customers
+    }
+
+    /// Returns the cursor of the next page, if there is one
+    pub fn next_cursor(&self) -> Option<&str> {
+        self.next_cursor.as_deref()
+    }
+
+    /// Attach the cursor of the next page to this [`DeliveryCustomerList`]
+    pub fn with_next_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.has_more = next_cursor.is_some();
+        self.next_cursor = next_cursor;
+        self
     }
 }
 
@@ -26,6 +50,16 @@ impl IntoResponse for DeliveryCustomerList {
 
 impl From<Vec<DeliveryCustomerOut>> for DeliveryCustomerList {
     fn from(value: Vec<DeliveryCustomerOut>) -> Self {
-        Self(value)
+        Self { customers: value, has_more: false, next_cursor: None }
+    }
+}
+
+impl IntoIterator for DeliveryCustomerList {
+    type Item = DeliveryCustomerOut;
+
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.customers.into_iter()
     }
 }
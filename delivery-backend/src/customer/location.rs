@@ -0,0 +1,49 @@
+use mongodb::bson::{doc, Document};
+use validator::ValidationError;
+
+/// A GeoJSON `Point`, e.g. `{"type": "Point", "coordinates": [23.59, 46.77]}`
+///
+/// Like in GeoJSON, the coordinates are longitude first, then latitude.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename = "Point")]
+pub struct GeoPoint {
+    pub coordinates: [f64; 2]
+}
+
+impl GeoPoint {
+    /// Creates a new [`GeoPoint`].
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self { coordinates: [longitude, latitude] }
+    }
+
+    /// Returns the latitude of this [`GeoPoint`].
+    pub fn latitude(&self) -> f64 {
+        self.coordinates[1]
+    }
+
+    /// Returns the longitude of this [`GeoPoint`].
+    pub fn longitude(&self) -> f64 {
+        self.coordinates[0]
+    }
+
+    /// Returns `true` if the coordinates are within the valid range
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude()) && (-180.0..=180.0).contains(&self.longitude())
+    }
+
+    /// Convert [`Self`] into a GeoJSON [`Document`]
+    pub fn into_document(self) -> Document {
+        doc! { "type": "Point", "coordinates": [self.longitude(), self.latitude()] }
+    }
+}
+
+/// The latitude has to be between -90 and 90, the longitude between -180 and 180
+pub fn validate_location(location: &GeoPoint) -> Result<(), ValidationError> {
+    if !location.is_valid() {
+        let mut error = ValidationError::new("location");
+        error.message = Some("location must have a valid latitude and longitude".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
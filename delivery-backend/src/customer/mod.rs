@@ -1,11 +1,30 @@
 mod address;
 mod appliance;
+mod certificate;
+mod contact;
+mod customer_page;
 mod delivery_customer;
 mod expired_customer;
+mod location;
+mod nearby;
 mod operation_performed;
+mod reminder;
+mod worklist;
 
 pub use address::Address;
-pub use appliance::ApplianceIn;
+pub use appliance::{
+    deserialize_chrono_from_bson_datetime, serialize_chrono_as_bson_datetime, ApplianceIn
+};
+pub use certificate::Certificate;
+pub use contact::{
+    normalize_phone, validate_phone, ConsentIn, ConsentOut, ConsentRecordIn, ConsentRecordOut,
+    ConsentSource, ContactChannel, PhoneNumber
+};
+pub use customer_page::CustomerPage;
 pub use delivery_customer::{CustomerStatus, DeliveryCustomerIn, DeliveryCustomerOut};
 pub use expired_customer::DeliveryCustomerList;
+pub use location::{validate_location, GeoPoint};
+pub use nearby::{NearbyCustomer, NearbyCustomerList};
 pub use operation_performed::OperationPerformed;
+pub use reminder::{ContactOutcome, ContactRecord, Reminder};
+pub use worklist::{ReminderWorklist, WorklistGroup};
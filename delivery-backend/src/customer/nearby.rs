@@ -0,0 +1,43 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use mongodb::bson::Document;
+
+use crate::error::AppError;
+
+use super::{DeliveryCustomerOut, GeoPoint};
+
+/// A [`DeliveryCustomerOut`] with it's distance from the center of the search
+#[derive(Debug, serde::Serialize)]
+pub struct NearbyCustomer {
+    #[serde(flatten)]
+    pub customer: DeliveryCustomerOut,
+    /// Distance in metres
+    pub distance: f64
+}
+
+impl TryFrom<Document> for NearbyCustomer {
+    type Error = AppError;
+
+    fn try_from(mut value: Document) -> Result<Self, Self::Error> {
+        let distance = value
+            .remove("distance")
+            .and_then(|distance| distance.as_f64())
+            .ok_or_else(|| AppError::UnprocessableEntity("Missing distance".into()))?;
+
+        Ok(Self { customer: value.try_into()?, distance })
+    }
+}
+
+/// The customers around a point, closest first
+#[derive(Debug, serde::Serialize)]
+pub struct NearbyCustomerList {
+    pub center: GeoPoint,
+    /// Radius of the search in metres
+    pub radius: u32,
+    pub customers: Vec<NearbyCustomer>
+}
+
+impl IntoResponse for NearbyCustomerList {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{self, doc, Document};
+use validator::Validate;
+
+use super::appliance::deserialize_chrono_from_bson_datetime;
+
+/// The outcome of calling a customer about an upcoming appliance check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactOutcome {
+    /// The customer was reached, but nothing was agreed yet
+    Contacted,
+    /// The check was scheduled
+    Scheduled,
+    /// The customer doesn't want the check done by us
+    Declined
+}
+
+impl std::fmt::Display for ContactOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ContactOutcome::Contacted => write!(f, "contacted"),
+            ContactOutcome::Scheduled => write!(f, "scheduled"),
+            ContactOutcome::Declined => write!(f, "declined")
+        }
+    }
+}
+
+/// What the office sends after calling a customer
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+pub struct ContactRecord {
+    pub outcome: ContactOutcome,
+    #[validate(length(max = 1000, message = "note must be at most 1000 characters"))]
+    pub note: Option<String>
+}
+
+impl ContactRecord {
+    /// Convert a [`ContactRecord`] into the [`Reminder`] document stored on the customer
+    ///
+    /// `for_expiration` is the `appliance.expiration_date` the customer was contacted about,
+    /// so the customer shows up on the worklist again once the next check is due.
+    pub fn into_reminder_document(self, for_expiration: DateTime<Utc>, by: &str) -> Document {
+        doc! {
+            "outcome": self.outcome.to_string(),
+            "note": self.note,
+            "for_expiration": bson::DateTime::from_chrono(for_expiration),
+            "at": bson::DateTime::now(),
+            "by": by
+        }
+    }
+}
+
+/// The last time a customer was contacted about an appliance check
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Reminder {
+    pub outcome: ContactOutcome,
+    pub note: Option<String>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub for_expiration: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_chrono_from_bson_datetime")]
+    pub at: DateTime<Utc>,
+    pub by: String
+}
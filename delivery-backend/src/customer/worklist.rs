@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use crate::locality;
+
+use super::delivery_customer::DeliveryCustomerOut;
+
+/// Customers of a single county whose appliance check expires in the same ISO week
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct WorklistGroup {
+    /// The county code
+    pub county: String,
+    pub county_name: Option<String>,
+    pub iso_year: i32,
+    pub iso_week: u32,
+    /// The Monday of the week
+    pub week_start: Option<NaiveDate>,
+    pub customers: Vec<DeliveryCustomerOut>
+}
+
+/// The customers the office has to call, grouped by county and week
+///
+/// Groups are ordered by county, then week. Customers keep the order they were collected in.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ReminderWorklist {
+    pub groups: Vec<WorklistGroup>,
+    pub total: usize
+}
+
+impl FromIterator<DeliveryCustomerOut> for ReminderWorklist {
+    fn from_iter<T: IntoIterator<Item = DeliveryCustomerOut>>(iter: T) -> Self {
+        let mut groups: BTreeMap<(String, i32, u32), Vec<DeliveryCustomerOut>> = BTreeMap::new();
+        let mut total = 0;
+
+        for customer in iter {
+            let week = customer.appliance.expiration_date.iso_week();
+            let key = (customer.address.county.clone(), week.year(), week.week());
+
+            groups.entry(key).or_default().push(customer);
+            total += 1;
+        }
+
+        let groups = groups
+            .into_iter()
+            .map(|((county, iso_year, iso_week), customers)| WorklistGroup {
+                county_name: locality::county(&county).map(|county| county.name.to_owned()),
+                county,
+                iso_year,
+                iso_week,
+                week_start: NaiveDate::from_isoywd_opt(iso_year, iso_week, Weekday::Mon),
+                customers
+            })
+            .collect();
+
+        Self { groups, total }
+    }
+}
+
+impl IntoResponse for ReminderWorklist {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
@@ -0,0 +1,414 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::TryStreamExt;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions},
+    Client as MongoClient, Collection as MongoCollection
+};
+
+use crate::appointment::{
+    AppointmentIn, AppointmentList, AppointmentOut, AppointmentStatus, CancelRequest,
+    RescheduleRequest
+};
+use crate::error::AppError;
+use crate::query::AppointmentQuery;
+use crate::responses::InsertOneResultResponse;
+
+use super::is_duplicate_key;
+
+/// How long a technician's schedule is locked at most, so a crashed request doesn't block it
+const SCHEDULE_LOCK_TTL_SECONDS: i64 = 30;
+
+/// How long to wait for another request to finish changing a technician's schedule
+const SCHEDULE_LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// The [`AppointmentCollection`] holds a reference to the MongoClient
+/// and does operations on the `appointment` collection
+#[derive(Debug)]
+pub struct AppointmentCollection {
+    client: Arc<MongoClient>
+}
+
+impl AppointmentCollection {
+    /// Creates a new [`AppointmentCollection`].
+    pub fn new(client: Arc<MongoClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get the `appointment` collection
+    #[tracing::instrument(skip(self))]
+    fn appointment_collection(&self) -> MongoCollection<Document> {
+        Arc::clone(&self.client).database("delivery_database").collection("appointment")
+    }
+
+    /// Fetch an appointment by it's `_id`
+    #[tracing::instrument(skip(self))]
+    pub async fn get_appointment(&self, id: ObjectId) -> Result<AppointmentOut, AppError> {
+        match self.appointment_collection().find_one(doc! { "_id": id }, None).await? {
+            Some(appointment) => appointment.try_into(),
+            None => Err(AppError::NotFound(format!("No appointment with _id={id}")))
+        }
+    }
+
+    /// Fetch the appointments matching an [`AppointmentQuery`], ordered by their start
+    #[tracing::instrument(skip(self))]
+    pub async fn list_appointments(
+        &self,
+        query: AppointmentQuery
+    ) -> Result<AppointmentList, AppError> {
+        let mut cursor = self
+            .appointment_collection()
+            .find(
+                query.as_filter(),
+                FindOptions::builder().sort(doc! { "start": 1, "_id": 1 }).build()
+            )
+            .await?;
+
+        let mut appointments = Vec::new();
+
+        while cursor.advance().await? {
+            appointments.push(cursor.deserialize_current()?.try_into()?);
+        }
+
+        tracing::info!("Found {} appointments", appointments.len());
+
+        Ok(AppointmentList { appointments })
+    }
+
+    /// Get the `schedule_lock` collection, which holds a lock document per technician
+    #[tracing::instrument(skip(self))]
+    fn schedule_lock_collection(&self) -> MongoCollection<Document> {
+        Arc::clone(&self.client).database("delivery_database").collection("schedule_lock")
+    }
+
+    /// Lock the schedule of `technician`, returning the owner the lock is held by
+    ///
+    /// Checking for a conflict and booking the slot aren't atomic, so both happen while
+    /// the schedule is locked. If another request holds the lock, it's waited for up to
+    /// [`SCHEDULE_LOCK_WAIT`], after which this fails with [`AppError::Conflict`].
+    #[tracing::instrument(skip(self))]
+    async fn lock_schedule(&self, technician: &str) -> Result<ObjectId, AppError> {
+        let owner = ObjectId::new();
+        let started = Instant::now();
+
+        loop {
+            let now = Utc::now();
+            let expires_at = now + chrono::Duration::seconds(SCHEDULE_LOCK_TTL_SECONDS);
+
+            // A lock that's still held doesn't match, so the upsert runs into it's `_id`
+            let result = self
+                .schedule_lock_collection()
+                .update_one(
+                    doc! { "_id": technician, "expires_at": { "$lt": now } },
+                    doc! { "$set": { "owner": owner, "expires_at": expires_at } },
+                    UpdateOptions::builder().upsert(true).build()
+                )
+                .await;
+
+            match result {
+                Ok(_) => return Ok(owner),
+                Err(error)
+                    if is_duplicate_key(&error) && started.elapsed() < SCHEDULE_LOCK_WAIT =>
+                {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                Err(error) if is_duplicate_key(&error) => {
+                    return Err(AppError::Conflict(format!(
+                        "The schedule of {technician} is being changed, try again"
+                    )))
+                }
+                Err(error) => return Err(error.into())
+            }
+        }
+    }
+
+    /// Release the lock on the schedule of `technician`, if it's still held by `owner`
+    #[tracing::instrument(skip(self))]
+    async fn unlock_schedule(&self, technician: &str, owner: ObjectId) -> Result<(), AppError> {
+        self.schedule_lock_collection()
+            .delete_one(doc! { "_id": technician, "owner": owner }, None)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fail with [`AppError::Conflict`] if `technician` already has a scheduled appointment
+    /// overlapping `start` and `end`
+    ///
+    /// The appointment with the `_id` of `except` is ignored, so it doesn't conflict with itself
+    /// when it's rescheduled.
+    #[tracing::instrument(skip(self))]
+    async fn check_conflict(
+        &self,
+        technician: &str,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        except: Option<ObjectId>
+    ) -> Result<(), AppError> {
+        let mut filter = doc! {
+            "technician": technician,
+            "status": AppointmentStatus::Scheduled.to_string(),
+            "start": { "$lt": end },
+            "end": { "$gt": start }
+        };
+
+        if let Some(except) = except {
+            filter.insert("_id", doc! { "$ne": except });
+        }
+
+        match self.appointment_collection().find_one(filter, None).await? {
+            Some(conflict) => {
+                let conflict = AppointmentOut::try_from(conflict)?;
+                Err(AppError::Conflict(format!(
+                    "{technician} already has appointment {} from {} to {}",
+                    conflict.id, conflict.start, conflict.end
+                )))
+            }
+            None => Ok(())
+        }
+    }
+
+    /// Schedule a new appointment
+    ///
+    /// Fails with [`AppError::Conflict`] if the technician is already booked for that slot.
+    /// The technician's schedule is locked meanwhile, so concurrent bookings can't overlap.
+    #[tracing::instrument(skip(self))]
+    pub async fn create_appointment(
+        &self,
+        appointment: AppointmentIn,
+        created_by: &str
+    ) -> Result<InsertOneResultResponse, AppError> {
+        let technician = appointment.technician.clone();
+        let owner = self.lock_schedule(&technician).await?;
+
+        let created = self.insert_unless_conflicting(appointment, created_by).await;
+        self.unlock_schedule(&technician, owner).await?;
+
+        created
+    }
+
+    /// Insert `appointment`, unless it conflicts with another one of the technician
+    async fn insert_unless_conflicting(
+        &self,
+        appointment: AppointmentIn,
+        created_by: &str
+    ) -> Result<InsertOneResultResponse, AppError> {
+        self.check_conflict(&appointment.technician, appointment.start, appointment.end, None)
+            .await?;
+
+        Ok(self
+            .appointment_collection()
+            .insert_one(appointment.into_document(created_by), None)
+            .await?
+            .into())
+    }
+
+    /// Apply `update` to the scheduled appointment with a matching `_id`
+    ///
+    /// Completed and cancelled appointments can't be changed anymore,
+    /// updating them fails with [`AppError::UnprocessableEntity`].
+    /// So does an appointment whose `sequence` isn't `sequence` anymore, if it's given,
+    /// since it was changed after it was read.
+    /// Every update increments the `sequence` of the appointment.
+    #[tracing::instrument(skip(self))]
+    async fn update_scheduled(
+        &self,
+        id: ObjectId,
+        sequence: Option<i64>,
+        mut update: Document
+    ) -> Result<AppointmentOut, AppError> {
+        update.insert("$inc", doc! { "sequence": 1 });
+
+        let mut filter = doc! { "_id": id, "status": AppointmentStatus::Scheduled.to_string() };
+        if let Some(sequence) = sequence {
+            filter.insert("sequence", sequence);
+        }
+
+        let updated = self
+            .appointment_collection()
+            .find_one_and_update(
+                filter,
+                update,
+                FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build()
+            )
+            .await?;
+
+        match updated {
+            Some(appointment) => appointment.try_into(),
+            None => {
+                let current = self.get_appointment(id).await?;
+
+                if current.status == AppointmentStatus::Scheduled {
+                    return Err(AppError::Conflict(format!(
+                        "Appointment with _id={id} was changed meanwhile, try again"
+                    )));
+                }
+
+                Err(AppError::UnprocessableEntity(format!(
+                    "Appointment with _id={id} is {}",
+                    current.status
+                )))
+            }
+        }
+    }
+
+    /// Move a scheduled appointment to another slot, and maybe another technician
+    ///
+    /// Fails with [`AppError::Conflict`] if the technician is already booked for the new slot,
+    /// or if the appointment was changed after it was read.
+    /// Like a new booking, this happens while the technician's schedule is locked.
+    #[tracing::instrument(skip(self))]
+    pub async fn reschedule_appointment(
+        &self,
+        id: ObjectId,
+        request: RescheduleRequest
+    ) -> Result<AppointmentOut, AppError> {
+        let current = self.get_appointment(id).await?;
+        let technician = request.technician.unwrap_or(current.technician);
+        let owner = self.lock_schedule(&technician).await?;
+
+        let rescheduled = self
+            .move_unless_conflicting(id, current.sequence, &technician, request.start, request.end)
+            .await;
+        self.unlock_schedule(&technician, owner).await?;
+
+        rescheduled
+    }
+
+    /// Move the appointment to `technician` from `start` to `end`,
+    /// unless it conflicts with another one of the technician
+    async fn move_unless_conflicting(
+        &self,
+        id: ObjectId,
+        sequence: i64,
+        technician: &str,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>
+    ) -> Result<AppointmentOut, AppError> {
+        self.check_conflict(technician, start, end, Some(id)).await?;
+
+        self.update_scheduled(
+            id,
+            Some(sequence),
+            doc! {
+                "$set": {
+                    "technician": technician,
+                    "start": bson::DateTime::from_chrono(start),
+                    "end": bson::DateTime::from_chrono(end)
+                }
+            }
+        )
+        .await
+    }
+
+    /// Cancel a scheduled appointment
+    #[tracing::instrument(skip(self))]
+    pub async fn cancel_appointment(
+        &self,
+        id: ObjectId,
+        request: CancelRequest,
+        cancelled_by: &str
+    ) -> Result<AppointmentOut, AppError> {
+        self.update_scheduled(
+            id,
+            None,
+            doc! {
+                "$set": {
+                    "status": AppointmentStatus::Cancelled.to_string(),
+                    "cancellation": {
+                        "reason": request.reason,
+                        "at": bson::DateTime::now(),
+                        "by": cancelled_by
+                    }
+                }
+            }
+        )
+        .await
+    }
+
+    /// Mark a scheduled appointment as completed, with the intervention done during it
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_completed(
+        &self,
+        id: ObjectId,
+        completion: Document
+    ) -> Result<AppointmentOut, AppError> {
+        self.update_scheduled(
+            id,
+            None,
+            doc! {
+                "$set": {
+                    "status": AppointmentStatus::Completed.to_string(),
+                    "completion": completion
+                }
+            }
+        )
+        .await
+    }
+
+    /// Undo [`AppointmentCollection::mark_completed`]
+    ///
+    /// Used when the intervention couldn't be recorded on the customer.
+    #[tracing::instrument(skip(self))]
+    pub async fn revert_completion(&self, id: ObjectId) -> Result<(), AppError> {
+        self.appointment_collection()
+            .update_one(
+                doc! { "_id": id, "status": AppointmentStatus::Completed.to_string() },
+                doc! {
+                    "$set": { "status": AppointmentStatus::Scheduled.to_string() },
+                    "$unset": { "completion": "" },
+                    "$inc": { "sequence": 1 }
+                },
+                None
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the stored appointments of the customer with `customer_id`, oldest first
+    #[tracing::instrument(skip(self))]
+    pub async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        let cursor = self
+            .appointment_collection()
+            .find(
+                doc! { "customer_id": customer_id },
+                FindOptions::builder().sort(doc! { "start": 1 }).build()
+            )
+            .await?;
+
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Drop the free text of the appointments of the customer with `customer_id`
+    ///
+    /// The notes and cancellation reasons can hold personal data,
+    /// the slots and the recorded interventions are kept.
+    /// Returns the number of anonymized appointments.
+    #[tracing::instrument(skip(self))]
+    pub async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError> {
+        let collection = self.appointment_collection();
+
+        collection
+            .update_many(
+                doc! { "customer_id": customer_id, "cancellation": { "$exists": true } },
+                doc! { "$set": { "cancellation.reason": null } },
+                None
+            )
+            .await?;
+
+        let update_result = collection
+            .update_many(
+                doc! { "customer_id": customer_id },
+                doc! { "$set": { "notes": null } },
+                None
+            )
+            .await?;
+
+        Ok(update_result.modified_count)
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::FindOptions,
+    Client as MongoClient, Collection as MongoCollection
+};
+
+use crate::audit::AuditEntry;
+use crate::error::AppError;
+
+/// The [`AuditCollection`] holds a reference to the MongoClient
+/// and does operations on the `audit` collection
+#[derive(Debug)]
+pub struct AuditCollection {
+    client: Arc<MongoClient>
+}
+
+impl AuditCollection {
+    /// Creates a new [`AuditCollection`].
+    pub fn new(client: Arc<MongoClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get the `audit` collection
+    #[tracing::instrument(skip(self))]
+    fn audit_collection(&self) -> MongoCollection<AuditEntry> {
+        Arc::clone(&self.client).database("delivery_database").collection("audit")
+    }
+
+    /// Append an [`AuditEntry`] to the audit trail
+    #[tracing::instrument(skip(self))]
+    pub async fn record(&self, entry: AuditEntry) -> Result<(), AppError> {
+        self.audit_collection().insert_one(entry, None).await?;
+
+        Ok(())
+    }
+
+    /// Fetch the audit trail of the customer with `customer_id`, oldest first
+    #[tracing::instrument(skip(self))]
+    pub async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        let cursor = self
+            .audit_collection()
+            .clone_with_type::<Document>()
+            .find(
+                doc! { "customer_id": customer_id },
+                FindOptions::builder().sort(doc! { "at": 1 }).build()
+            )
+            .await?;
+
+        Ok(cursor.try_collect().await?)
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use mongodb::{
+    bson::doc,
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    Client as MongoClient, Collection as MongoCollection
+};
+
+use crate::error::AppError;
+
+/// A named sequence
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Counter {
+    #[serde(rename = "_id")]
+    name: String,
+    value: i64
+}
+
+/// The [`CounterCollection`] holds a reference to the MongoClient
+/// and does operations on the `counter` collection, which holds named sequences
+#[derive(Debug)]
+pub struct CounterCollection {
+    client: Arc<MongoClient>
+}
+
+impl CounterCollection {
+    /// Creates a new [`CounterCollection`].
+    pub fn new(client: Arc<MongoClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get the `counter` collection
+    #[tracing::instrument(skip(self))]
+    fn counter_collection(&self) -> MongoCollection<Counter> {
+        Arc::clone(&self.client).database("delivery_database").collection("counter")
+    }
+
+    /// Increment the counter called `name` and return it's new value
+    ///
+    /// Counters start at 1 and the increment is atomic,
+    /// so no two callers ever get the same value.
+    #[tracing::instrument(skip(self))]
+    pub async fn next(&self, name: &str) -> Result<i64, AppError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let counter = self
+            .counter_collection()
+            .find_one_and_update(doc! { "_id": name }, doc! { "$inc": { "value": 1_i64 } }, options)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("No counter named {name}")))?;
+
+        Ok(counter.value)
+    }
+}
@@ -1,17 +1,96 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
 use mongodb::{
-    bson::{doc, Document},
-    options::FindOptions,
-    Client as MongoClient, Collection as MongoCollection, Database as MongoDatabase
+    bson::{self, doc, oid::ObjectId, Document},
+    options::{FindOptions, IndexOptions, InsertManyOptions},
+    results::UpdateResult,
+    Client as MongoClient, Collection as MongoCollection, Cursor, Database as MongoDatabase,
+    IndexModel
 };
 
-use crate::customer::{DeliveryCustomerIn, DeliveryCustomerList};
-use crate::database::customer_list::try_customer_list;
+use crate::customer::{
+    Certificate, ContactRecord, CustomerPage, DeliveryCustomerIn, DeliveryCustomerList,
+    DeliveryCustomerOut, GeoPoint, NearbyCustomer, NearbyCustomerList, OperationPerformed,
+    ReminderWorklist
+};
+use crate::database::customer_list::{try_customer_list, try_customer_page};
 use crate::error::AppError;
-use crate::query::{ExpiredCustomersQuery, PartialDeliveryCustomer, SearchQuery};
+use crate::locality::normalize_county;
+use crate::query::{
+    CustomerListQuery, CustomerPatch, ExpiredCustomersQuery, NearQuery, PartialDeliveryCustomer,
+    SearchQuery, VersionPrecondition, WorklistQuery, PATCH_ATTEMPTS
+};
 use crate::responses::{DeleteResultResponse, InsertOneResultResponse, UpdateResultResponse};
 
+use super::is_duplicate_key;
+
+/// What the personal fields of an erased customer are replaced with
+const ERASED: &str = "ERASED";
+
+/// The personal fields that are anonymized when a customer is erased
+pub const ERASED_FIELDS: [&str; 15] = [
+    "name",
+    "address.postal_code",
+    "address.street",
+    "address.number",
+    "address.building",
+    "address.staircase",
+    "address.apartment",
+    "address.additional",
+    "phones",
+    "email",
+    "preferred_channel",
+    "consent",
+    "location",
+    "reminder.note",
+    "active"
+];
+
+/// The most customers written by a single bulk command
+const BULK_WRITE_BATCH_SIZE: usize = 1000;
+
+/// What [`CustomerCollection::upsert_customers`] wrote
+#[derive(Debug, Default)]
+pub struct UpsertedCustomers {
+    pub inserted: u64,
+    pub updated: u64,
+    /// The `customer_id` and the error of every customer that couldn't be written
+    pub failed: Vec<(String, String)>
+}
+
+/// The update that anonymizes the personal data of a customer, see [`ERASED_FIELDS`]
+pub fn erasure_update(erased_by: &str) -> Document {
+    doc! {
+        "$set": {
+            "name": ERASED,
+            "active": false,
+            "address.postal_code": null,
+            "address.street": ERASED,
+            "address.number": "",
+            "address.building": null,
+            "address.staircase": null,
+            "address.apartment": null,
+            "address.additional": "",
+            "phones": [],
+            "email": null,
+            "preferred_channel": null,
+            "consent": { "marketing": null, "notifications": null },
+            "location": null,
+            "erased": { "at": bson::DateTime::now(), "by": erased_by }
+        },
+        "$unset": { "reminder.note": "" },
+        "$inc": { "version": 1 }
+    }
+}
+
+/// Matches customers that haven't been soft deleted
+fn not_deleted() -> Document {
+    doc! { "deleted": { "$exists": false } }
+}
+
+#[derive(Debug)]
 pub struct CustomerCollection {
     client: Arc<MongoClient>
 }
@@ -34,20 +113,252 @@ impl CustomerCollection {
         self.get_database().collection("customer")
     }
 
+    /// Returns the `customer` collection, typed for inserting new customers.
+    ///
+    /// [`DeliveryCustomerIn`] serializes to exactly the stored document.
+    #[tracing::instrument(skip(self))]
+    fn customer_in_collection(&self) -> MongoCollection<DeliveryCustomerIn> {
+        self.customer_collection().clone_with_type()
+    }
+
+    /// Returns the `customer` collection, typed for reading customers.
+    #[tracing::instrument(skip(self))]
+    fn customer_out_collection(&self) -> MongoCollection<DeliveryCustomerOut> {
+        self.customer_collection().clone_with_type()
+    }
+
+    /// Fetch a single [`DeliveryCustomerOut`] matching `filter`
+    ///
+    /// Returns [`AppError::NotFound`] if there is no such customer.
+    /// Soft deleted customers are never returned.
+    #[tracing::instrument(skip(self))]
+    async fn find_customer(&self, mut filter: Document) -> Result<DeliveryCustomerOut, AppError> {
+        filter.extend(not_deleted());
+
+        match self.customer_out_collection().find_one(filter.clone(), None).await? {
+            Some(customer) => Ok(customer),
+            None => Err(AppError::NotFound(format!("No customer matching {filter}")))
+        }
+    }
+
+    /// Fetch a [`DeliveryCustomerOut`] by it's `customer_id`
+    #[tracing::instrument(skip(self))]
+    pub async fn get_customer(&self, customer_id: &str) -> Result<DeliveryCustomerOut, AppError> {
+        self.find_customer(doc! { "customer_id": customer_id }).await
+    }
+
+    /// Fetch a [`DeliveryCustomerOut`] by it's `ObjectId`
+    #[tracing::instrument(skip(self))]
+    pub async fn get_customer_by_oid(
+        &self,
+        oid: ObjectId
+    ) -> Result<DeliveryCustomerOut, AppError> {
+        self.find_customer(doc! { "_id": oid }).await
+    }
+
+    /// Fetch the customers with any of the `customer_ids`
+    ///
+    /// Ids without a matching customer are skipped.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_customers(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<DeliveryCustomerList, AppError> {
+        let mut filter = doc! { "customer_id": { "$in": customer_ids } };
+        filter.extend(not_deleted());
+
+        let cursor = self.customer_out_collection().find(filter, None).await?;
+
+        try_customer_list(cursor).await
+    }
+
     /// Commit a [`DeliveryCustomerIn`] to the database
     ///
-    /// Note that there will be no checks made whether the customer already exists.
-    /// The [`DeliveryCustomerIn`] will be inserted as is.
+    /// A customer whose `customer_id` or `_id` is already taken is rejected
+    /// with [`AppError::Conflict`] by the unique indexes.
+    /// The [`DeliveryCustomerIn`] will be inserted as is, through the typed collection,
+    /// so the stored document always has it's shape. It used to be inserted as a `$set`
+    /// update document, see [`CustomerCollection::unwrap_set_documents`].
     #[tracing::instrument(skip(self))]
     pub async fn insert_customer(
         &self,
         customer: DeliveryCustomerIn
     ) -> Result<InsertOneResultResponse, AppError> {
-        Ok(self
-            .customer_collection()
-            .insert_one(customer.into_update_document(), None)
-            .await?
-            .into())
+        let customer_id = customer.customer_id.clone();
+
+        match self.customer_in_collection().insert_one(customer, None).await {
+            Ok(insert_result) => Ok(insert_result.into()),
+            Err(error) if is_duplicate_key(&error) => Err(AppError::Conflict(format!(
+                "A customer with customer_id={customer_id} or the same _id already exists"
+            ))),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// Find which of the `customer_ids` are already taken
+    ///
+    /// Maps every taken `customer_id` to whether that customer was soft deleted.
+    #[tracing::instrument(skip(self, customer_ids))]
+    pub async fn existing_customer_ids(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<HashMap<String, bool>, AppError> {
+        let mut existing = HashMap::new();
+
+        for chunk in customer_ids.chunks(BULK_WRITE_BATCH_SIZE) {
+            let mut cursor = self
+                .customer_collection()
+                .find(
+                    doc! { "customer_id": { "$in": chunk } },
+                    FindOptions::builder()
+                        .projection(doc! { "customer_id": 1, "deleted": 1 })
+                        .build()
+                )
+                .await?;
+
+            while cursor.advance().await? {
+                let document = cursor.deserialize_current()?;
+                if let Ok(customer_id) = document.get_str("customer_id") {
+                    existing.insert(customer_id.to_owned(), document.contains_key("deleted"));
+                }
+            }
+        }
+
+        Ok(existing)
+    }
+
+    /// Insert many new customers at once, returning how many were inserted
+    ///
+    /// Like [`CustomerCollection::insert_customer`], there are no checks made
+    /// whether the customers already exist.
+    #[tracing::instrument(skip(self, customers))]
+    pub async fn insert_customers(
+        &self,
+        customers: Vec<DeliveryCustomerIn>
+    ) -> Result<u64, AppError> {
+        if customers.is_empty() {
+            return Ok(0);
+        }
+
+        let insert_result = self
+            .customer_in_collection()
+            .insert_many(customers, InsertManyOptions::builder().ordered(false).build())
+            .await?;
+
+        Ok(insert_result.inserted_ids.len() as u64)
+    }
+
+    /// Insert new customers and update existing ones, matched by `customer_id`
+    ///
+    /// New customers are upserted with `$setOnInsert`, so one that was inserted meanwhile
+    /// is left as it is. Existing customers only get the fields at `paths` overwritten,
+    /// see [`DeliveryCustomerIn::into_update_document`]. Soft deleted customers are never updated.
+    ///
+    /// The writes are sent as `update` commands of up to [`BULK_WRITE_BATCH_SIZE`] statements,
+    /// which aren't atomic. Customers that fail to be written are reported in
+    /// [`UpsertedCustomers::failed`], every other one is still written.
+    #[tracing::instrument(skip(self, new, existing))]
+    pub async fn upsert_customers(
+        &self,
+        new: Vec<DeliveryCustomerIn>,
+        existing: Vec<DeliveryCustomerIn>,
+        paths: &[&str]
+    ) -> Result<UpsertedCustomers, AppError> {
+        let inserts = new.into_iter().map(|customer| {
+            let customer_id = customer.customer_id.clone();
+            let statement = doc! {
+                "q": { "customer_id": &customer_id },
+                "u": { "$setOnInsert": customer.into_document()? },
+                "upsert": true
+            };
+
+            Ok((customer_id, statement))
+        });
+
+        let updates = existing.into_iter().map(|customer| {
+            let customer_id = customer.customer_id.clone();
+            let mut filter = doc! { "customer_id": &customer_id };
+            filter.extend(not_deleted());
+
+            Ok((customer_id, doc! { "q": filter, "u": customer.into_update_document(paths)? }))
+        });
+
+        let mut upserted = UpsertedCustomers::default();
+        let mut statements = inserts.chain(updates).peekable();
+
+        while statements.peek().is_some() {
+            let (customer_ids, updates): (Vec<_>, Vec<_>) = statements
+                .by_ref()
+                .take(BULK_WRITE_BATCH_SIZE)
+                .collect::<Result<Vec<_>, AppError>>()?
+                .into_iter()
+                .unzip();
+
+            let response = self
+                .get_database()
+                .run_command(
+                    doc! { "update": "customer", "updates": updates, "ordered": false },
+                    None
+                )
+                .await?;
+
+            upserted.inserted += response.get_array("upserted").map_or(0, Vec::len) as u64;
+            upserted.updated += match response.get("nModified") {
+                Some(bson::Bson::Int32(count)) => *count as u64,
+                Some(bson::Bson::Int64(count)) => *count as u64,
+                _ => 0
+            };
+
+            for error in response.get_array("writeErrors").into_iter().flatten() {
+                let Some(error) = error.as_document() else {
+                    continue;
+                };
+                let index =
+                    error.get_i32("index").ok().and_then(|index| usize::try_from(index).ok());
+                let Some(customer_id) = index.and_then(|index| customer_ids.get(index)) else {
+                    continue;
+                };
+
+                upserted.failed.push((
+                    customer_id.clone(),
+                    error.get_str("errmsg").unwrap_or("Unknown write error").to_owned()
+                ));
+            }
+        }
+
+        Ok(upserted)
+    }
+
+    /// Apply `update` to the customer with a matching `customer_id`
+    ///
+    /// Soft deleted customers are never updated.
+    /// Every update increments the `version` of the document.
+    /// If a [`VersionPrecondition`] is given and no document at one of the expected versions
+    /// matches, the update is rejected with [`AppError::PreconditionFailed`].
+    #[tracing::instrument(skip(self))]
+    async fn update_versioned(
+        &self,
+        customer_id: &str,
+        mut update: Document,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResult, AppError> {
+        let mut filter = doc! { "customer_id": customer_id };
+        filter.extend(not_deleted());
+        if let Some(precondition) = &precondition {
+            precondition.apply_to(&mut filter);
+        }
+
+        update.insert("$inc", doc! { "version": 1 });
+
+        let update_result = self.customer_collection().update_one(filter, update, None).await?;
+
+        if precondition.is_some() && update_result.matched_count == 0 {
+            return Err(AppError::PreconditionFailed(format!(
+                "Customer with customer_id={customer_id} is not at the expected version"
+            )));
+        }
+
+        Ok(update_result)
     }
 
     /// Update a [`DeliveryCustomer`] in the database
@@ -58,51 +369,119 @@ impl CustomerCollection {
     #[tracing::instrument(skip(self))]
     pub async fn update_customer(
         &self,
-        customer: PartialDeliveryCustomer
+        customer: PartialDeliveryCustomer,
+        precondition: Option<VersionPrecondition>
     ) -> Result<UpdateResultResponse, AppError> {
-        let update_result_response = self
-            .customer_collection()
-            .update_one(
-                doc! { "customer_id": &customer.customer_id },
-                customer.into_update_document_no_none(),
-                None
-            )
+        let customer_id = customer.customer_id.clone();
+
+        Ok(self
+            .update_versioned(&customer_id, customer.into_update_document_no_none()?, precondition)
             .await?
-            .into();
+            .into())
+    }
 
-        Ok(update_result_response)
+    /// Apply a [`CustomerPatch`] to a [`DeliveryCustomer`]
+    ///
+    /// The patch is applied to the current state of the customer, validated, then written back
+    /// only if nobody else has updated the customer in the meantime. If somebody did and the
+    /// client sent no precondition, the patch is applied again to the newer version.
+    /// Returns the customer as it is after the update.
+    #[tracing::instrument(skip(self))]
+    pub async fn patch_customer(
+        &self,
+        customer_id: &str,
+        patch: CustomerPatch,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<DeliveryCustomerOut, AppError> {
+        for _ in 0..PATCH_ATTEMPTS {
+            let current = self.get_customer(customer_id).await?;
+
+            if precondition.as_ref().is_some_and(|p| !p.is_satisfied_by(current.version)) {
+                return Err(AppError::PreconditionFailed(format!(
+                    "Customer with customer_id={customer_id} is not at the expected version"
+                )));
+            }
+
+            let patched = patch.apply(&current)?;
+
+            // The patch was applied to `current`, so it may only replace that version
+            let updated = self
+                .update_versioned(
+                    customer_id,
+                    patched.into_replacement_document()?,
+                    Some(VersionPrecondition::OneOf(vec![current.version]))
+                )
+                .await;
+
+            match updated {
+                Ok(_) => return self.get_customer(customer_id).await,
+                // Without a precondition from the client, apply the patch to the newer version
+                Err(AppError::PreconditionFailed(_)) if precondition.is_none() => continue,
+                Err(err) => return Err(err)
+            }
+        }
+
+        Err(AppError::Conflict(format!(
+            "Customer with customer_id={customer_id} kept changing while it was patched"
+        )))
     }
 
     /// Activate a [`DeliveryCustomer`]
     #[tracing::instrument(skip(self))]
     pub async fn activate_customer(
         &self,
-        customer_id: String
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
     ) -> Result<UpdateResultResponse, AppError> {
-        let update_result_response = self
-            .customer_collection()
-            .update_one(
-                doc! { "customer_id": customer_id },
-                doc! { "$set": { "active": true } },
-                None
-            )
+        Ok(self
+            .update_versioned(&customer_id, doc! { "$set": { "active": true } }, precondition)
             .await?
-            .into();
-
-        Ok(update_result_response)
+            .into())
     }
 
     /// Deactivate a [`DeliveryCustomer`]
     #[tracing::instrument(skip(self))]
     pub async fn deactivate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        Ok(self
+            .update_versioned(&customer_id, doc! { "$set": { "active": false } }, precondition)
+            .await?
+            .into())
+    }
+
+    /// Delete a [`DeliveryCustomer`]
+    ///
+    /// The customer is only marked as deleted, along with who deleted it and when.
+    /// It's data is kept until [`CustomerCollection::purge_deleted_customers`] removes it.
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_customer(
+        &self,
+        customer_id: String,
+        deleted_by: &str
+    ) -> Result<DeleteResultResponse, AppError> {
+        let update = doc! {
+            "$set": { "deleted": { "at": bson::DateTime::now(), "by": deleted_by } }
+        };
+
+        let update_result = self.update_versioned(&customer_id, update, None).await?;
+
+        Ok(DeleteResultResponse::new(update_result.matched_count))
+    }
+
+    /// Restore a soft deleted [`DeliveryCustomer`]
+    #[tracing::instrument(skip(self))]
+    pub async fn restore_customer(
         &self,
         customer_id: String
     ) -> Result<UpdateResultResponse, AppError> {
         let update_result_response = self
             .customer_collection()
             .update_one(
-                doc! { "customer_id": customer_id },
-                doc! { "$set": { "active": false } },
+                doc! { "customer_id": customer_id, "deleted": { "$exists": true } },
+                doc! { "$unset": { "deleted": "" }, "$inc": { "version": 1 } },
                 None
             )
             .await?
@@ -111,36 +490,269 @@ impl CustomerCollection {
         Ok(update_result_response)
     }
 
-    /// Delete a [`DeliveryCustomer`]
+    /// Permanently remove customers that were soft deleted before `deleted_before`
     #[tracing::instrument(skip(self))]
-    pub async fn delete_customer(
+    pub async fn purge_deleted_customers(
         &self,
-        customer_id: String
+        deleted_before: DateTime<Utc>
     ) -> Result<DeleteResultResponse, AppError> {
         let delete_result_response = self
             .customer_collection()
-            .delete_one(doc! {"customer_id": customer_id}, None)
+            .delete_many(doc! { "deleted.at": { "$lt": deleted_before } }, None)
             .await?
             .into();
 
         Ok(delete_result_response)
     }
 
+    /// Fetch the stored document of a customer as it is, even if it was soft deleted
+    #[tracing::instrument(skip(self))]
+    pub async fn get_raw_customer(&self, customer_id: &str) -> Result<Option<Document>, AppError> {
+        Ok(self.customer_collection().find_one(doc! { "customer_id": customer_id }, None).await?)
+    }
+
+    /// Repair the customers that were inserted as a `$set` update document
+    ///
+    /// [`CustomerCollection::insert_customer`] used to store `{ "$set": { .. } }` without
+    /// a `customer_id`, so those customers could neither be found nor deserialized.
+    /// Their fields are moved back to the top level of the document, in place.
+    /// The `customer_id` was never stored, so it's set to the hex string of the `_id`,
+    /// which lets the customer be found and corrected. Returns the `_id`s of the customers
+    /// this call repaired.
+    #[tracing::instrument(skip(self))]
+    pub async fn unwrap_set_documents(&self) -> Result<Vec<ObjectId>, AppError> {
+        let filter = doc! { "customer_id": { "$exists": false } };
+        let mut cursor = self.customer_collection().find(filter, None).await?;
+        let mut repaired = Vec::new();
+
+        while cursor.advance().await? {
+            let document = cursor.deserialize_current()?;
+            let Ok(id) = document.get_object_id("_id") else {
+                continue;
+            };
+            let Ok(fields) = document.get_document("$set") else {
+                continue;
+            };
+
+            let mut replacement = doc! { "_id": id, "customer_id": id.to_hex() };
+            replacement.extend(fields.clone());
+
+            // Another instance may have repaired it meanwhile, so only count what this one did
+            let replace_result = self
+                .customer_collection()
+                .replace_one(
+                    doc! { "_id": id, "customer_id": { "$exists": false } },
+                    replacement,
+                    None
+                )
+                .await?;
+
+            if replace_result.modified_count == 1 {
+                repaired.push(id);
+            }
+        }
+
+        Ok(repaired)
+    }
+
+    /// Give every customer without a `version` the version of a new customer
+    ///
+    /// Customers stored before documents were versioned are read as `v0` and matched as such
+    /// by a [`VersionPrecondition`]. Afterwards every stored customer has the shape of a new one.
+    /// Returns the number of customers that got a `version`.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_missing_versions(&self) -> Result<u64, AppError> {
+        let update_result = self
+            .customer_collection()
+            .update_many(
+                doc! { "customer_id": { "$exists": true }, "version": { "$exists": false } },
+                doc! { "$set": { "version": 0_i64 } },
+                None
+            )
+            .await?;
+
+        Ok(update_result.modified_count)
+    }
+
+    /// Replace the county of every customer by it's canonical code
+    ///
+    /// Customers stored before counties were normalized have the county as it was written,
+    /// like `cluj` or `Cluj`, which [`normalize_county`] maps to `CJ`. Counties that aren't known
+    /// are left as they are. Returns the number of customers whose county was changed.
+    #[tracing::instrument(skip(self))]
+    pub async fn normalize_counties(&self) -> Result<u64, AppError> {
+        let filter = doc! { "address.county": { "$type": "string" } };
+        let mut cursor = self.customer_collection().find(filter, None).await?;
+        let mut normalized = 0;
+
+        while cursor.advance().await? {
+            let document = cursor.deserialize_current()?;
+            let Ok(id) = document.get_object_id("_id") else {
+                continue;
+            };
+            let Ok(address) = document.get_document("address") else {
+                continue;
+            };
+            let Ok(county) = address.get_str("county") else {
+                continue;
+            };
+            let Some(code) = normalize_county(county).filter(|&code| code != county) else {
+                continue;
+            };
+
+            let update_result = self
+                .customer_collection()
+                .update_one(
+                    doc! { "_id": id, "address.county": county },
+                    doc! { "$set": { "address.county": code }, "$inc": { "version": 1 } },
+                    None
+                )
+                .await?;
+
+            normalized += update_result.modified_count;
+        }
+
+        Ok(normalized)
+    }
+
+    /// The `customer_id`s that more than one stored customer has, in order
+    ///
+    /// Soft deleted customers count too, they keep their `customer_id` until they're purged.
+    #[tracing::instrument(skip(self))]
+    pub async fn duplicate_customer_ids(&self) -> Result<Vec<String>, AppError> {
+        let pipeline = [
+            doc! { "$match": { "customer_id": { "$type": "string" } } },
+            doc! { "$group": { "_id": "$customer_id", "count": { "$sum": 1 } } },
+            doc! { "$match": { "count": { "$gt": 1 } } },
+            doc! { "$sort": { "_id": 1 } }
+        ];
+        let mut cursor = self.customer_collection().aggregate(pipeline, None).await?;
+        let mut duplicates = Vec::new();
+
+        while cursor.advance().await? {
+            if let Ok(customer_id) = cursor.deserialize_current()?.get_str("_id") {
+                duplicates.push(customer_id.to_owned());
+            }
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Create the unique index on `customer_id`, which imports upsert by
+    ///
+    /// Fails if customers share a `customer_id`, see [`CustomerCollection::duplicate_customer_ids`].
+    #[tracing::instrument(skip(self))]
+    pub async fn create_customer_id_index(&self) -> Result<(), AppError> {
+        // Customers stored as `$set` documents have no `customer_id` until they're migrated
+        let options = IndexOptions::builder()
+            .unique(true)
+            .partial_filter_expression(doc! { "customer_id": { "$type": "string" } })
+            .build();
+        let index = IndexModel::builder().keys(doc! { "customer_id": 1 }).options(options).build();
+
+        self.customer_collection().create_index(index, None).await?;
+
+        Ok(())
+    }
+
+    /// Anonymize the personal data of a customer
+    ///
+    /// The name, street address and contact details are dropped, along with any note
+    /// taken while contacting the customer. The county and locality are kept, so are
+    /// the appliance inspection records, since we're required to retain them.
+    /// Soft deleted customers are erased too.
+    #[tracing::instrument(skip(self))]
+    pub async fn erase_customer(
+        &self,
+        customer_id: &str,
+        erased_by: &str
+    ) -> Result<UpdateResultResponse, AppError> {
+        let update = erasure_update(erased_by);
+
+        let update_result = self
+            .customer_collection()
+            .update_one(doc! { "customer_id": customer_id }, update, None)
+            .await?;
+
+        if update_result.matched_count == 0 {
+            return Err(AppError::NotFound(format!("No customer with customer_id={customer_id}")));
+        }
+
+        Ok(update_result.into())
+    }
+
+    /// Fetch a page of customers
+    ///
+    /// A [`CustomerListQuery`] contains the filters, the order and the cursor of the page.
+    /// One more customer than the page holds is fetched, to know whether there is a next page.
+    #[tracing::instrument(skip(self))]
+    pub async fn list_customers(&self, query: CustomerListQuery) -> Result<CustomerPage, AppError> {
+        let sort = query.sort_spec()?;
+        let limit = query.limit() as usize;
+
+        let mut filter = query.as_filter();
+        filter.extend(not_deleted());
+
+        let total = self.customer_collection().count_documents(filter.clone(), None).await?;
+
+        let page_filter = match query.page_cursor()? {
+            Some(cursor) => doc! { "$and": [filter, cursor.keyset_filter(&sort)?] },
+            None => filter
+        };
+
+        let options =
+            FindOptions::builder().sort(sort.as_document()).limit(limit as i64 + 1).build();
+
+        let cursor = self.customer_collection().find(page_filter, options).await?;
+        let (customers, next_cursor) = try_customer_page(cursor, limit, &sort).await?;
+
+        tracing::info!("Found {} customers out of {}", customers.len(), total);
+
+        Ok(CustomerPage { customers, next_cursor, total })
+    }
+
+    /// Open a cursor over every customer of a list, to export them
+    ///
+    /// Same filters and order as [`CustomerCollection::list_customers`], but not paged.
+    #[tracing::instrument(skip(self))]
+    pub async fn export_customers(
+        &self,
+        query: CustomerListQuery
+    ) -> Result<Cursor<DeliveryCustomerOut>, AppError> {
+        let mut filter = query.as_filter();
+        filter.extend(not_deleted());
+
+        let options = FindOptions::builder().sort(query.sort_spec()?.as_document()).build();
+
+        Ok(self.customer_out_collection().find(filter, options).await?)
+    }
+
     /// Fetch expired customers
     ///
     /// An [`ExpiredCustomersQuery`] contains the possible query parameters.
+    /// Customers are ordered by `appliance.expiration_date`, then `_id`,
+    /// and the list carries the cursor of the next page, if there is one.
     #[tracing::instrument(skip(self))]
     pub async fn expired_customers(
         &self,
         time_range: ExpiredCustomersQuery
     ) -> Result<DeliveryCustomerList, AppError> {
-        let cursor =
-            self.customer_collection().aggregate(time_range.as_aggregation(), None).await?;
+        let mut pipeline = vec![doc! { "$match": not_deleted() }];
+        pipeline.extend(time_range.as_aggregation()?);
 
-        let customer_list = try_customer_list(cursor).await;
+        let cursor = self.customer_collection().aggregate(pipeline, None).await?;
 
-        match customer_list {
-            Ok(customer_list) => {
+        let customer_page = try_customer_page(
+            cursor,
+            time_range.limit() as usize,
+            &ExpiredCustomersQuery::sort_spec()
+        )
+        .await;
+
+        match customer_page {
+            Ok((customers, next_cursor)) => {
+                let customer_list =
+                    DeliveryCustomerList::from(customers).with_next_cursor(next_cursor);
                 tracing::info!("Found {} expired customers", &customer_list.len());
                 Ok(customer_list)
             }
@@ -151,22 +763,195 @@ impl CustomerCollection {
         }
     }
 
+    /// Open a cursor over every expired customer, to export them
+    ///
+    /// Same filters and order as [`CustomerCollection::expired_customers`], but not paged.
+    #[tracing::instrument(skip(self))]
+    pub async fn export_expired_customers(
+        &self,
+        time_range: ExpiredCustomersQuery
+    ) -> Result<Cursor<DeliveryCustomerOut>, AppError> {
+        let mut pipeline = vec![doc! { "$match": not_deleted() }];
+        pipeline.extend(time_range.as_export_aggregation());
+
+        Ok(self.customer_collection().aggregate(pipeline, None).await?.with_type())
+    }
+
+    /// Fetch the customers around a point
+    ///
+    /// A [`NearQuery`] contains the center, the radius and the expiration filters.
+    /// Customers without a `location` are never part of the result.
+    #[tracing::instrument(skip(self))]
+    pub async fn customers_near(&self, query: NearQuery) -> Result<NearbyCustomerList, AppError> {
+        let pipeline = query.as_aggregation(not_deleted())?;
+
+        let mut cursor = self.customer_collection().aggregate(pipeline, None).await?;
+        let mut customers = Vec::new();
+
+        while cursor.advance().await? {
+            customers.push(NearbyCustomer::try_from(cursor.deserialize_current()?)?);
+        }
+
+        tracing::info!("Found {} customers within {}m", customers.len(), query.radius());
+
+        Ok(NearbyCustomerList { center: query.center()?, radius: query.radius(), customers })
+    }
+
+    /// Store the coordinates of a customer's address
+    #[tracing::instrument(skip(self))]
+    pub async fn set_location(
+        &self,
+        customer_id: &str,
+        location: GeoPoint
+    ) -> Result<UpdateResultResponse, AppError> {
+        let update_result = self
+            .update_versioned(
+                customer_id,
+                doc! { "$set": { "location": location.into_document() } },
+                None
+            )
+            .await?;
+
+        if update_result.matched_count == 0 {
+            return Err(AppError::NotFound(format!("No customer with customer_id={customer_id}")));
+        }
+
+        Ok(update_result.into())
+    }
+
+    /// Store the [`Certificate`] issued for the current visit of a customer
+    ///
+    /// The update only goes through if the visit is still the one the certificate is for
+    /// and no certificate was stored for it yet, so neither a visit that was overwritten
+    /// in the meantime nor one that was already certified gets it.
+    /// Other changes to the customer don't matter. Returns `false` if it wasn't stored.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_certificate(
+        &self,
+        customer_id: &str,
+        certificate: &Certificate
+    ) -> Result<bool, AppError> {
+        let date = bson::DateTime::from_chrono(certificate.date);
+        let mut filter = doc! {
+            "customer_id": customer_id,
+            "appliance.date": date,
+            "certificate.date": { "$ne": date }
+        };
+        filter.extend(not_deleted());
+
+        let update = doc! {
+            "$set": { "certificate": certificate.to_document() },
+            "$inc": { "version": 1 }
+        };
+        let update_result = self.customer_collection().update_one(filter, update, None).await?;
+
+        Ok(update_result.matched_count == 1)
+    }
+
+    /// Fetch the customers to call about their upcoming appliance check
+    ///
+    /// Inactive customers and the ones already contacted about their current
+    /// `expiration_date` are left out. See [`WorklistQuery`].
+    #[tracing::instrument(skip(self))]
+    pub async fn reminder_worklist(
+        &self,
+        query: WorklistQuery
+    ) -> Result<ReminderWorklist, AppError> {
+        let mut filter = query.as_filter();
+        filter.extend(not_deleted());
+
+        let cursor = self
+            .customer_out_collection()
+            .find(filter, FindOptions::builder().sort(WorklistQuery::sort()).build())
+            .await?;
+
+        let worklist: ReminderWorklist = try_customer_list(cursor).await?.into_iter().collect();
+
+        tracing::info!(
+            "Found {} customers to contact in {} groups",
+            worklist.total,
+            worklist.groups.len()
+        );
+
+        Ok(worklist)
+    }
+
+    /// Record the outcome of contacting a customer about their upcoming appliance check
+    ///
+    /// The outcome is tied to the current `appliance.expiration_date` of the customer,
+    /// which takes them off the worklist until their next check is due.
+    #[tracing::instrument(skip(self))]
+    pub async fn record_contact(
+        &self,
+        customer_id: &str,
+        contact: ContactRecord,
+        contacted_by: &str
+    ) -> Result<UpdateResultResponse, AppError> {
+        let current = self.get_customer(customer_id).await?;
+
+        let reminder =
+            contact.into_reminder_document(current.appliance.expiration_date, contacted_by);
+
+        Ok(self
+            .update_versioned(
+                customer_id,
+                doc! { "$set": { "reminder": reminder } },
+                Some(VersionPrecondition::OneOf(vec![current.version]))
+            )
+            .await?
+            .into())
+    }
+
+    /// Record an intervention on the appliance of a customer
+    ///
+    /// Overwrites the operation performed, it's date and the `expiration_date` of the appliance.
+    /// The `observations` are only overwritten if there are new ones.
+    #[tracing::instrument(skip(self))]
+    pub async fn record_intervention(
+        &self,
+        customer_id: &str,
+        operation_performed: OperationPerformed,
+        date: DateTime<Utc>,
+        expiration_date: DateTime<Utc>,
+        observations: Option<String>
+    ) -> Result<UpdateResultResponse, AppError> {
+        let mut fields = doc! {
+            "appliance.operation_performed": operation_performed.to_string(),
+            "appliance.date": bson::DateTime::from_chrono(date),
+            "appliance.expiration_date": bson::DateTime::from_chrono(expiration_date)
+        };
+
+        if let Some(observations) = observations {
+            fields.insert("appliance.observations", observations);
+        }
+
+        let update_result =
+            self.update_versioned(customer_id, doc! { "$set": fields }, None).await?;
+
+        if update_result.matched_count == 0 {
+            return Err(AppError::NotFound(format!("No customer with customer_id={customer_id}")));
+        }
+
+        Ok(update_result.into())
+    }
+
     /// Use `MongoDB` full-text search
     ///
     /// Search by `query`, a space delimited string.
     /// All of them are `Option`s, but filtering is done to only search by fields
     /// that actually contain a value.
+    /// Phone numbers and email addresses are matched against the contact details instead.
     #[tracing::instrument(skip(self))]
     pub async fn search_customers(
         &self,
         search: SearchQuery
     ) -> Result<DeliveryCustomerList, AppError> {
+        let mut filter = search.as_filter();
+        filter.extend(not_deleted());
+
         let cursor = self
-            .customer_collection()
-            .find(
-                doc! { "$text": { "$search": &search.query } },
-                FindOptions::builder().sort(doc! { "score": { "$meta": "textScore" } }).build()
-            )
+            .customer_out_collection()
+            .find(filter, FindOptions::builder().sort(search.sort()).build())
             .await?;
 
         let customer_list = try_customer_list(cursor).await;
@@ -182,4 +967,21 @@ impl CustomerCollection {
             }
         }
     }
+
+    /// Open a cursor over every result of a search, to export them
+    ///
+    /// Same filters and order as [`CustomerCollection::search_customers`].
+    #[tracing::instrument(skip(self))]
+    pub async fn export_search(
+        &self,
+        search: SearchQuery
+    ) -> Result<Cursor<DeliveryCustomerOut>, AppError> {
+        let mut filter = search.as_filter();
+        filter.extend(not_deleted());
+
+        Ok(self
+            .customer_out_collection()
+            .find(filter, FindOptions::builder().sort(search.sort()).build())
+            .await?)
+    }
 }
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::UpdateOptions,
+    Client as MongoClient, Collection as MongoCollection
+};
+
+use crate::error::AppError;
+
+use super::is_duplicate_key;
+
+/// The `_id` of the lock document
+const LOCK_ID: &str = "lock";
+
+/// How long a lock is held without being renewed, so a crashed instance doesn't block
+/// migrations forever
+const LOCK_TTL_MINUTES: i64 = 10;
+
+/// A migration that was applied to the database
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct AppliedMigration {
+    #[serde(rename = "_id")]
+    name: String,
+    description: String,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    applied_at: DateTime<Utc>,
+    modified: i64
+}
+
+/// The [`MigrationCollection`] holds a reference to the MongoClient
+/// and does operations on the `_migrations` collection
+///
+/// Every applied migration is recorded by it's name. The same collection holds the lock
+/// document, so only one instance migrates at a time.
+#[derive(Debug)]
+pub struct MigrationCollection {
+    client: Arc<MongoClient>
+}
+
+impl MigrationCollection {
+    /// Creates a new [`MigrationCollection`].
+    pub fn new(client: Arc<MongoClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get the `_migrations` collection
+    #[tracing::instrument(skip(self))]
+    fn migration_collection(&self) -> MongoCollection<AppliedMigration> {
+        Arc::clone(&self.client).database("delivery_database").collection("_migrations")
+    }
+
+    /// The names of the migrations that were already applied
+    #[tracing::instrument(skip(self))]
+    pub async fn applied(&self) -> Result<HashSet<String>, AppError> {
+        let filter = doc! { "_id": { "$ne": LOCK_ID } };
+        let applied: Vec<AppliedMigration> =
+            self.migration_collection().find(filter, None).await?.try_collect().await?;
+
+        Ok(applied.into_iter().map(|migration| migration.name).collect())
+    }
+
+    /// Record that the migration called `name` was applied, changing `modified` documents
+    #[tracing::instrument(skip(self))]
+    pub async fn record(
+        &self,
+        name: &str,
+        description: &str,
+        modified: u64
+    ) -> Result<(), AppError> {
+        let applied = AppliedMigration {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            applied_at: Utc::now(),
+            modified: modified as i64
+        };
+
+        self.migration_collection().insert_one(applied, None).await?;
+
+        Ok(())
+    }
+
+    /// Take the lock for `owner`
+    ///
+    /// The lock is taken if there is none, or if the one there has expired.
+    /// Returns [`AppError::Conflict`] if another instance holds it.
+    #[tracing::instrument(skip(self))]
+    pub async fn lock(&self, owner: &str) -> Result<(), AppError> {
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(LOCK_TTL_MINUTES);
+
+        // A lock that's still held doesn't match, so the upsert runs into it's `_id`
+        let result = self
+            .migration_collection()
+            .clone_with_type::<Document>()
+            .update_one(
+                doc! { "_id": LOCK_ID, "expires_at": { "$lt": now } },
+                doc! { "$set": { "owner": owner, "acquired_at": now, "expires_at": expires_at } },
+                UpdateOptions::builder().upsert(true).build()
+            )
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) if is_duplicate_key(&error) => {
+                Err(AppError::Conflict("Migrations are run by another instance".into()))
+            }
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// Extend the lock held by `owner` by another [`LOCK_TTL_MINUTES`]
+    ///
+    /// Returns `false` if `owner` doesn't hold the lock anymore.
+    #[tracing::instrument(skip(self))]
+    pub async fn renew(&self, owner: &str) -> Result<bool, AppError> {
+        let expires_at = Utc::now() + Duration::minutes(LOCK_TTL_MINUTES);
+
+        let result = self
+            .migration_collection()
+            .update_one(
+                doc! { "_id": LOCK_ID, "owner": owner },
+                doc! { "$set": { "expires_at": expires_at } },
+                None
+            )
+            .await?;
+
+        Ok(result.matched_count == 1)
+    }
+
+    /// Release the lock, if it's still held by `owner`
+    #[tracing::instrument(skip(self))]
+    pub async fn unlock(&self, owner: &str) -> Result<(), AppError> {
+        self.migration_collection()
+            .delete_one(doc! { "_id": LOCK_ID, "owner": owner }, None)
+            .await?;
+
+        Ok(())
+    }
+}
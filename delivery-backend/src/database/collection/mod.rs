@@ -1,5 +1,25 @@
+use mongodb::error::{Error as MongoError, ErrorKind, WriteError, WriteFailure};
+
+mod appointment;
+mod audit;
+mod counter;
 mod customer;
+mod migration;
+mod notification;
 mod user;
 
-pub use customer::CustomerCollection;
-pub use user::UserCollection;
+pub use appointment::AppointmentCollection;
+pub use audit::AuditCollection;
+pub use counter::CounterCollection;
+pub use customer::{erasure_update, CustomerCollection, UpsertedCustomers, ERASED_FIELDS};
+pub use migration::MigrationCollection;
+pub use notification::NotificationCollection;
+pub use user::{DeliveryUserOut, UserCollection};
+
+/// Returns `true` if `error` is a write that ran into a unique index
+fn is_duplicate_key(error: &MongoError) -> bool {
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(WriteError { code: 11000, .. }))
+    )
+}
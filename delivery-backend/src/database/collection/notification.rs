@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions},
+    Client as MongoClient, Collection as MongoCollection
+};
+
+use crate::error::AppError;
+use crate::notification::{DeliveryStatus, NotificationLog, OutgoingMessage};
+
+/// The [`NotificationCollection`] holds a reference to the MongoClient
+/// and does operations on the `notification` collection, the delivery log of reminders
+#[derive(Debug)]
+pub struct NotificationCollection {
+    client: Arc<MongoClient>
+}
+
+impl NotificationCollection {
+    /// Creates a new [`NotificationCollection`].
+    pub fn new(client: Arc<MongoClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get the `notification` collection
+    #[tracing::instrument(skip(self))]
+    fn notification_collection(&self) -> MongoCollection<NotificationLog> {
+        Arc::clone(&self.client).database("delivery_database").collection("notification")
+    }
+
+    /// Queue `message` to be sent to the customer with `customer_id`
+    ///
+    /// Returns `false` if the same reminder was already queued,
+    /// in which case the existing entry is left as it is.
+    #[tracing::instrument(skip(self))]
+    pub async fn queue(
+        &self,
+        customer_id: &str,
+        message: OutgoingMessage,
+        for_expiration: DateTime<Utc>,
+        offset_days: u32,
+        now: DateTime<Utc>
+    ) -> Result<bool, AppError> {
+        let filter = doc! {
+            "customer_id": customer_id,
+            "channel": message.channel.to_string(),
+            "for_expiration": bson::DateTime::from_chrono(for_expiration),
+            "offset_days": offset_days
+        };
+
+        let update = doc! {
+            "$setOnInsert": {
+                "to": message.to,
+                "subject": message.subject,
+                "body": message.body,
+                "status": DeliveryStatus::Pending.to_string(),
+                "attempts": 0,
+                "last_error": null,
+                "next_attempt_at": bson::DateTime::from_chrono(now),
+                "created_at": bson::DateTime::from_chrono(now),
+                "sent_at": null
+            }
+        };
+
+        let update_result = self
+            .notification_collection()
+            .clone_with_type::<Document>()
+            .update_one(filter, update, UpdateOptions::builder().upsert(true).build())
+            .await?;
+
+        Ok(update_result.upserted_id.is_some())
+    }
+
+    /// Take the next notification that is due for delivery
+    ///
+    /// The notification is leased until `now + lease`, so concurrent runs of the
+    /// reminder job don't deliver it twice. Notifications that already failed
+    /// `max_attempts` times are never returned.
+    #[tracing::instrument(skip(self))]
+    pub async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        max_attempts: u32,
+        lease: chrono::Duration
+    ) -> Result<Option<NotificationLog>, AppError> {
+        let filter = doc! {
+            "status": {
+                "$in": [DeliveryStatus::Pending.to_string(), DeliveryStatus::Failed.to_string()]
+            },
+            "attempts": { "$lt": max_attempts },
+            "next_attempt_at": { "$lte": bson::DateTime::from_chrono(now) }
+        };
+
+        let update =
+            doc! { "$set": { "next_attempt_at": bson::DateTime::from_chrono(now + lease) } };
+
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "next_attempt_at": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+
+        Ok(self.notification_collection().find_one_and_update(filter, update, options).await?)
+    }
+
+    /// Record a successful delivery
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_sent(&self, id: ObjectId, now: DateTime<Utc>) -> Result<(), AppError> {
+        let update = doc! {
+            "$set": {
+                "status": DeliveryStatus::Sent.to_string(),
+                "sent_at": bson::DateTime::from_chrono(now),
+                "last_error": null
+            },
+            "$inc": { "attempts": 1 }
+        };
+
+        self.notification_collection().update_one(doc! { "_id": id }, update, None).await?;
+
+        Ok(())
+    }
+
+    /// Record a failed delivery, to be retried at `next_attempt_at`
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_failed(
+        &self,
+        id: ObjectId,
+        error: String,
+        next_attempt_at: DateTime<Utc>
+    ) -> Result<(), AppError> {
+        let update = doc! {
+            "$set": {
+                "status": DeliveryStatus::Failed.to_string(),
+                "last_error": error,
+                "next_attempt_at": bson::DateTime::from_chrono(next_attempt_at)
+            },
+            "$inc": { "attempts": 1 }
+        };
+
+        self.notification_collection().update_one(doc! { "_id": id }, update, None).await?;
+
+        Ok(())
+    }
+
+    /// Fetch the delivery log of the customer with `customer_id`, oldest first
+    #[tracing::instrument(skip(self))]
+    pub async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        let cursor = self
+            .notification_collection()
+            .clone_with_type::<Document>()
+            .find(
+                doc! { "customer_id": customer_id },
+                FindOptions::builder().sort(doc! { "created_at": 1 }).build()
+            )
+            .await?;
+
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Remove the personal data from the delivery log of the customer with `customer_id`
+    ///
+    /// Recipients and message contents are dropped, notifications that weren't delivered
+    /// yet are cancelled. Returns the number of anonymized entries.
+    #[tracing::instrument(skip(self))]
+    pub async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError> {
+        let collection = self.notification_collection();
+
+        collection
+            .update_many(
+                doc! {
+                    "customer_id": customer_id,
+                    "status": {
+                        "$in": [DeliveryStatus::Pending.to_string(), DeliveryStatus::Failed.to_string()]
+                    }
+                },
+                doc! { "$set": { "status": DeliveryStatus::Cancelled.to_string() } },
+                None
+            )
+            .await?;
+
+        let update_result = collection
+            .update_many(
+                doc! { "customer_id": customer_id },
+                doc! { "$set": { "to": "", "subject": null, "body": "", "last_error": null } },
+                None
+            )
+            .await?;
+
+        Ok(update_result.modified_count)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{auth::password::verify_password, error::AppError, user::JsonEncodedUser};
+use crate::error::AppError;
 use mongodb::{
     bson::{doc, oid::ObjectId},
     Client as MongoClient, Collection as MongoCollection
@@ -6,7 +6,7 @@ use mongodb::{
 use std::sync::Arc;
 
 /// The `user` that goes OUT
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DeliveryUserOut {
     #[serde(rename = "_id")]
     pub id: ObjectId,
@@ -44,19 +44,37 @@ impl UserCollection {
         Ok(maybe_user)
     }
 
-    /// Checks whether the user trying to log in has typed their password in correctly
-    #[tracing::instrument]
-    pub async fn validate_user_password(&self, user: &JsonEncodedUser) -> Result<bool, AppError> {
-        let maybe_valid = self.get_user(&user.username).await?.is_some_and(|delivery_user_out| {
-            match verify_password(&user.password, &delivery_user_out.password) {
-                Ok(maybe_valid) => maybe_valid,
-                Err(err) => {
-                    tracing::error!("{}", err);
-                    false
-                }
-            }
-        });
-
-        Ok(maybe_valid)
+    /// Store the hash of a new calendar token on the user with `username`
+    ///
+    /// Replaces the previous token, which stops working.
+    #[tracing::instrument(skip(self, token_hash))]
+    pub async fn set_calendar_token(
+        &self,
+        username: &str,
+        token_hash: &str
+    ) -> Result<(), AppError> {
+        let update_result = self
+            .user_collection()
+            .update_one(
+                doc! { "username": username },
+                doc! { "$set": { "calendar_token": token_hash } },
+                None
+            )
+            .await?;
+
+        if update_result.matched_count == 0 {
+            return Err(AppError::NotFound(format!("No user with username={username}")));
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the user a calendar token belongs to, by the hash of the token
+    #[tracing::instrument(skip(self, token_hash))]
+    pub async fn get_user_by_calendar_token(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<DeliveryUserOut>, AppError> {
+        Ok(self.user_collection().find_one(doc! { "calendar_token": token_hash }, None).await?)
     }
 }
@@ -1,17 +1,56 @@
+use futures::TryStreamExt;
 use mongodb::{bson::Document, Cursor};
 
-use crate::{customer::DeliveryCustomerList, error::AppError};
+use crate::customer::{DeliveryCustomerList, DeliveryCustomerOut};
+use crate::error::AppError;
+use crate::query::{PageCursor, SortSpec};
 
-/// Try to drive the cursor to yield `Document`s
+/// Try to drive the cursor to yield [`DeliveryCustomerOut`]s
 pub async fn try_customer_list(
-    mut cursor: Cursor<Document>
+    cursor: Cursor<DeliveryCustomerOut>
 ) -> Result<DeliveryCustomerList, AppError> {
-    let mut buffer = Vec::with_capacity(10);
+    let customers: Vec<DeliveryCustomerOut> = cursor.try_collect().await?;
+
+    Ok(customers.into())
+}
+
+/// Try to drive the cursor of a paginated query to yield a page of `Document`s
+///
+/// The query is expected to fetch `limit + 1` documents, ordered by `sort`.
+/// See [`customer_page`].
+pub async fn try_customer_page(
+    mut cursor: Cursor<Document>,
+    limit: usize,
+    sort: &SortSpec
+) -> Result<(Vec<DeliveryCustomerOut>, Option<String>), AppError> {
+    let mut documents = Vec::with_capacity(limit + 1);
 
     while cursor.advance().await? {
-        let deserialized = cursor.deserialize_current().try_into()?;
-        buffer.push(deserialized)
+        documents.push(cursor.deserialize_current()?);
     }
 
-    Ok(buffer.into())
+    customer_page(documents, limit, sort)
+}
+
+/// Turn up to `limit + 1` `Document`s, ordered by `sort`, into a page of customers
+///
+/// If the extra document is there, it's dropped and the page gets a cursor
+/// pointing after the last document that was kept.
+pub fn customer_page(
+    mut documents: Vec<Document>,
+    limit: usize,
+    sort: &SortSpec
+) -> Result<(Vec<DeliveryCustomerOut>, Option<String>), AppError> {
+    let has_more = documents.len() > limit;
+    documents.truncate(limit);
+
+    let next_cursor = match documents.last() {
+        Some(last) if has_more => Some(PageCursor::after(last, sort).encode()),
+        _ => None
+    };
+
+    let customers =
+        documents.into_iter().map(DeliveryCustomerOut::try_from).collect::<Result<Vec<_>, _>>()?;
+
+    Ok((customers, next_cursor))
 }
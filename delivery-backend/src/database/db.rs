@@ -1,13 +1,17 @@
 use crate::error::AppError;
+use crate::migration::migrate;
 use mongodb::bson::{doc, Document};
-use mongodb::options::ClientOptions;
+use mongodb::options::{ClientOptions, IndexOptions};
 use mongodb::Client as MongoClient;
 use mongodb::IndexModel;
 use std::env;
 use std::sync::Arc;
 use std::time::Duration;
 
-use super::collection::{CustomerCollection, UserCollection};
+use super::collection::{
+    AppointmentCollection, AuditCollection, CounterCollection, CustomerCollection,
+    MigrationCollection, NotificationCollection, UserCollection
+};
 
 /// Represents the connection to the database
 ///
@@ -33,19 +37,86 @@ impl Database {
     pub fn user(&self) -> UserCollection {
         UserCollection::new(Arc::clone(&self.client))
     }
+
+    /// Return an [`AuditCollection`] that allows operations to be
+    /// done on the `audit` MongoDb collection
+    pub fn audit(&self) -> AuditCollection {
+        AuditCollection::new(Arc::clone(&self.client))
+    }
+
+    /// Return an [`AppointmentCollection`] that allows operations to be
+    /// done on the `appointment` MongoDb collection
+    pub fn appointment(&self) -> AppointmentCollection {
+        AppointmentCollection::new(Arc::clone(&self.client))
+    }
+
+    /// Return a [`NotificationCollection`] that allows operations to be
+    /// done on the `notification` MongoDb collection
+    pub fn notification(&self) -> NotificationCollection {
+        NotificationCollection::new(Arc::clone(&self.client))
+    }
+
+    /// Return a [`CounterCollection`] that allows operations to be
+    /// done on the `counter` MongoDb collection
+    pub fn counter(&self) -> CounterCollection {
+        CounterCollection::new(Arc::clone(&self.client))
+    }
+
+    /// Return a [`MigrationCollection`] that allows operations to be
+    /// done on the `_migrations` MongoDb collection
+    pub fn migration(&self) -> MigrationCollection {
+        MigrationCollection::new(Arc::clone(&self.client))
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////
 
-/// Initialize the [`Database`] with a MongoDB [`MongoClient`]
-#[tracing::instrument]
-pub async fn setup_database() -> Result<Arc<Database>, AppError> {
-    let mongo_url = env::var("MONGO_URL").unwrap_or_else(|_| {
+/// The URL of the MongoDB server, `MONGO_URL`
+pub fn mongo_url() -> String {
+    env::var("MONGO_URL").unwrap_or_else(|_| {
         tracing::info!("MONGO_URL not set, using default");
 
         "mongodb://127.0.0.1:27017".into()
-    });
+    })
+}
 
+/// Initialize the [`Database`] with a MongoDB [`MongoClient`] and migrate it
+///
+/// Pending [`migrations`](crate::migration::migrations) are applied before the app starts,
+/// unless `MIGRATE_ON_STARTUP` is `false`.
+#[tracing::instrument]
+pub async fn setup_database(mongo_url: &str) -> Result<Arc<Database>, AppError> {
+    let database = connect_database(mongo_url).await?;
+
+    let migrate_on_startup =
+        match env::var("MIGRATE_ON_STARTUP").map(|migrate| migrate.parse::<bool>()) {
+            Ok(Ok(migrate_on_startup)) => migrate_on_startup,
+            Ok(Err(error)) => {
+                tracing::info!(
+                    "Failed to parse MIGRATE_ON_STARTUP into a boolean: {error}. Using default"
+                );
+                true
+            }
+            Err(_) => {
+                tracing::info!("MIGRATE_ON_STARTUP not set, using default");
+                true
+            }
+        };
+
+    if migrate_on_startup {
+        for report in migrate(&database, false).await? {
+            tracing::info!("Migration {report}");
+        }
+    }
+
+    Ok(database)
+}
+
+/// Initialize the [`Database`] with a MongoDB [`MongoClient`], without migrating it
+///
+/// Connects to `mongo_url` and sets up the indexes.
+#[tracing::instrument]
+pub async fn connect_database(mongo_url: &str) -> Result<Arc<Database>, AppError> {
     let mut options = ClientOptions::parse(mongo_url).await?;
 
     let mongo_timeout_duration_default = 3;
@@ -81,7 +152,61 @@ pub async fn setup_database() -> Result<Arc<Database>, AppError> {
     mongodb_client
         .database("delivery_database")
         .collection::<Document>("customer")
-        .create_index(IndexModel::builder().keys(doc! { "$**": "text" }).build(), None)
+        .create_indexes(
+            [
+                IndexModel::builder().keys(doc! { "$**": "text" }).build(),
+                IndexModel::builder().keys(doc! { "location": "2dsphere" }).build()
+            ],
+            None
+        )
+        .await?;
+
+    let notification_collection =
+        mongodb_client.database("delivery_database").collection::<Document>("notification");
+
+    notification_collection
+        .create_indexes(
+            [
+                IndexModel::builder()
+                    .keys(doc! { "customer_id": 1, "channel": 1, "for_expiration": 1, "offset_days": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                IndexModel::builder().keys(doc! { "status": 1, "next_attempt_at": 1 }).build()
+            ],
+            None
+        )
+        .await?;
+
+    mongodb_client
+        .database("delivery_database")
+        .collection::<Document>("audit")
+        .create_index(IndexModel::builder().keys(doc! { "customer_id": 1, "at": 1 }).build(), None)
+        .await?;
+
+    mongodb_client
+        .database("delivery_database")
+        .collection::<Document>("user")
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "calendar_token": 1 })
+                .options(IndexOptions::builder().unique(true).sparse(true).build())
+                .build(),
+            None
+        )
+        .await?;
+
+    mongodb_client
+        .database("delivery_database")
+        .collection::<Document>("appointment")
+        .create_indexes(
+            [
+                IndexModel::builder()
+                    .keys(doc! { "technician": 1, "status": 1, "start": 1 })
+                    .build(),
+                IndexModel::builder().keys(doc! { "customer_id": 1, "start": 1 }).build()
+            ],
+            None
+        )
         .await?;
 
     tracing::info!("Index setup complete");
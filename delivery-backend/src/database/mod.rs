@@ -2,4 +2,10 @@ mod collection;
 mod customer_list;
 mod db;
 
-pub use db::{setup_database, Database};
+pub use collection::erasure_update;
+pub use collection::{
+    AppointmentCollection, AuditCollection, CounterCollection, CustomerCollection, DeliveryUserOut,
+    NotificationCollection, UpsertedCustomers, UserCollection, ERASED_FIELDS
+};
+pub(crate) use customer_list::customer_page;
+pub use db::{connect_database, mongo_url, setup_database, Database};
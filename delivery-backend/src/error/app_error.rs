@@ -1,8 +1,11 @@
 use axum::extract::rejection::TypedHeaderRejection;
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use mongodb::bson::de::Error as BsonDeError;
+use mongodb::bson::ser::Error as BsonSerError;
 use mongodb::error::Error as MongoError;
+use rust_xlsxwriter::XlsxError;
 use serde_json::json;
+use validator::ValidationErrors;
 
 use crate::auth::jwt::AuthError;
 
@@ -12,10 +15,36 @@ pub enum AppError {
     DatabaseError(#[from] MongoError),
     #[error("BsonDeError: {0}")]
     BsonError(#[from] BsonDeError),
+    #[error("BsonSerError: {0}")]
+    BsonSerializationError(#[from] BsonSerError),
     #[error(transparent)]
     AuthError(#[from] AuthError),
     #[error(transparent)]
-    HeaderError(#[from] TypedHeaderRejection)
+    HeaderError(#[from] TypedHeaderRejection),
+    #[error("NotFound: {0}")]
+    NotFound(String),
+    #[error("PreconditionFailed: {0}")]
+    PreconditionFailed(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error(transparent)]
+    ValidationError(#[from] ValidationErrors),
+    #[error("UnprocessableEntity: {0}")]
+    UnprocessableEntity(String),
+    #[error("BadRequest: {0}")]
+    BadRequest(String),
+    #[error("UnsupportedMediaType: {0}")]
+    UnsupportedMediaType(String),
+    #[error("NotificationError: {0}")]
+    NotificationError(String),
+    #[error("GeocodingError: {0}")]
+    GeocodingError(String),
+    #[error("ExportError: {0}")]
+    ExportError(#[from] XlsxError),
+    #[error("CertificateError: {0}")]
+    CertificateError(String),
+    #[error("SerializationError: {0}")]
+    SerializationError(#[from] serde_json::Error)
 }
 
 impl IntoResponse for AppError {
@@ -32,10 +61,58 @@ impl IntoResponse for AppError {
                 Json(serde_json::json!({"BsonDeError": error.to_string()}))
             )
                 .into_response(),
+            AppError::BsonSerializationError(error) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "BsonSerError": error.to_string() }))
+            )
+                .into_response(),
             AppError::AuthError(error) => {
                 (StatusCode::FORBIDDEN, Json(json!({ "error": error }))).into_response()
             }
-            AppError::HeaderError(_) => StatusCode::NOT_FOUND.into_response()
+            AppError::HeaderError(_) => StatusCode::NOT_FOUND.into_response(),
+            AppError::NotFound(message) => {
+                (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response()
+            }
+            AppError::PreconditionFailed(message) => {
+                (StatusCode::PRECONDITION_FAILED, Json(json!({ "error": message }))).into_response()
+            }
+            AppError::Conflict(message) => {
+                (StatusCode::CONFLICT, Json(json!({ "error": message }))).into_response()
+            }
+            AppError::ValidationError(errors) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, Json(json!(errors))).into_response()
+            }
+            AppError::UnprocessableEntity(message) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "error": message })))
+                    .into_response()
+            }
+            AppError::BadRequest(message) => {
+                (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response()
+            }
+            AppError::UnsupportedMediaType(message) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, Json(json!({ "error": message })))
+                    .into_response()
+            }
+            AppError::NotificationError(message) => {
+                (StatusCode::BAD_GATEWAY, Json(json!({ "error": message }))).into_response()
+            }
+            AppError::GeocodingError(message) => {
+                (StatusCode::BAD_GATEWAY, Json(json!({ "error": message }))).into_response()
+            }
+            AppError::ExportError(error) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "ExportError": error.to_string() }))
+            )
+                .into_response(),
+            AppError::CertificateError(message) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": message })))
+                    .into_response()
+            }
+            AppError::SerializationError(error) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "SerializationError": error.to_string() }))
+            )
+                .into_response()
         }
     }
 }
@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+
+use crate::customer::DeliveryCustomerOut;
+use crate::local_time::local_date;
+
+use super::Locale;
+
+/// A column of an export, with it's Romanian and English header
+pub struct Column {
+    pub ro: &'static str,
+    pub en: &'static str
+}
+
+impl Column {
+    /// The header of the column in `locale`
+    pub fn header(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::Ro => self.ro,
+            Locale::En => self.en
+        }
+    }
+}
+
+/// The columns of an export, in the order of [`customer_row`]
+pub const COLUMNS: [Column; 24] = [
+    Column { ro: "Cod client", en: "Customer ID" },
+    Column { ro: "Nume", en: "Name" },
+    Column { ro: "Activ", en: "Active" },
+    Column { ro: "Județ", en: "County" },
+    Column { ro: "Localitate", en: "Locality" },
+    Column { ro: "Cod poștal", en: "Postal code" },
+    Column { ro: "Stradă", en: "Street" },
+    Column { ro: "Număr", en: "Number" },
+    Column { ro: "Bloc", en: "Building" },
+    Column { ro: "Scară", en: "Staircase" },
+    Column { ro: "Apartament", en: "Apartment" },
+    Column { ro: "Detalii adresă", en: "Address details" },
+    Column { ro: "Telefon", en: "Phone" },
+    Column { ro: "Email", en: "Email" },
+    Column { ro: "Producător", en: "Manufacturer" },
+    Column { ro: "An fabricație", en: "Year of manufacture" },
+    Column { ro: "Model", en: "Model" },
+    Column { ro: "Tip", en: "Type" },
+    Column { ro: "Serie aparat", en: "Appliance number" },
+    Column { ro: "Garanție", en: "Warranty" },
+    Column { ro: "Operație efectuată", en: "Operation performed" },
+    Column { ro: "Data verificării", en: "Check date" },
+    Column { ro: "Data expirării", en: "Expiration date" },
+    Column { ro: "Observații", en: "Observations" }
+];
+
+/// A single value of an exported row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+    Text(String),
+    Date(NaiveDate)
+}
+
+impl Cell {
+    /// The value as text, with dates written the way `locale` writes them
+    pub fn to_text(&self, locale: Locale) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Date(date) => date.format(locale.date_format()).to_string()
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Cell::Text(value)
+    }
+}
+
+impl From<Option<String>> for Cell {
+    fn from(value: Option<String>) -> Self {
+        Cell::Text(value.unwrap_or_default())
+    }
+}
+
+/// The cells of a customer, one for each of the [`COLUMNS`]
+///
+/// Dates are the day they fall on in Romanian local time.
+pub fn customer_row(customer: DeliveryCustomerOut, locale: Locale) -> Vec<Cell> {
+    let address = customer.address;
+    let appliance = customer.appliance;
+    let phones = customer.phones.into_iter().map(|phone| phone.number).collect::<Vec<_>>();
+
+    vec![
+        customer.customer_id.into(),
+        customer.name.into(),
+        locale.yes_no(customer.active).to_owned().into(),
+        address.county.into(),
+        address.locality.into(),
+        address.postal_code.into(),
+        address.street.into(),
+        address.number.into(),
+        address.building.into(),
+        address.staircase.into(),
+        address.apartment.into(),
+        address.additional.into(),
+        phones.join(", ").into(),
+        customer.email.into(),
+        appliance.manufacturer.into(),
+        appliance.year_of_manufacture.into(),
+        appliance.model.into(),
+        appliance.typ.into(),
+        appliance.number.into(),
+        Cell::Date(local_date(appliance.warranty)),
+        appliance.operation_performed.to_string().into(),
+        Cell::Date(local_date(appliance.date)),
+        Cell::Date(local_date(appliance.expiration_date)),
+        appliance.observations.into(),
+    ]
+}
@@ -0,0 +1,65 @@
+use futures::{stream, Stream, StreamExt};
+
+use crate::error::AppError;
+use crate::repository::CustomerStream;
+
+use super::{customer_row, Locale, COLUMNS};
+
+/// Byte order mark, without it Excel reads UTF-8 files as the local code page
+const BOM: &str = "\u{feff}";
+
+/// Characters a spreadsheet reads as the start of a formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Write `fields` as a single CSV record, ended by `CRLF`
+///
+/// Fields containing the delimiter, quotes or line breaks are quoted,
+/// the quotes inside them are doubled. Fields a spreadsheet would run as a formula
+/// are prefixed with `'`, so a customer's name can't be `=HYPERLINK(...)`.
+pub fn csv_record<I>(fields: I, delimiter: char) -> String
+where
+    I: IntoIterator<Item = String>
+{
+    let mut record = String::new();
+
+    for (index, field) in fields.into_iter().enumerate() {
+        if index > 0 {
+            record.push(delimiter);
+        }
+
+        let field = if field.starts_with(FORMULA_PREFIXES) { format!("'{field}") } else { field };
+
+        if field.contains([delimiter, '"', '\r', '\n']) {
+            record.push('"');
+            record.push_str(&field.replace('"', "\"\""));
+            record.push('"');
+        } else {
+            record.push_str(&field);
+        }
+    }
+
+    record.push_str("\r\n");
+    record
+}
+
+/// Stream the `customers` as CSV records, after a header record
+///
+/// Each record is written as soon as the stream yields the customer,
+/// so the whole list is never held in memory.
+pub fn csv_stream(
+    customers: CustomerStream,
+    locale: Locale
+) -> impl Stream<Item = Result<String, AppError>> + Send + 'static {
+    let delimiter = locale.csv_delimiter();
+    let header =
+        csv_record(COLUMNS.iter().map(|column| column.header(locale).to_owned()), delimiter);
+
+    stream::once(async move { Ok(format!("{BOM}{header}")) }).chain(customers.map(
+        move |customer| {
+            let fields =
+                customer_row(customer?, locale).into_iter().map(|cell| cell.to_text(locale));
+
+            Ok(csv_record(fields, delimiter))
+        }
+    ))
+}
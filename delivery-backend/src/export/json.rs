@@ -0,0 +1,38 @@
+use futures::{stream, Stream, StreamExt};
+
+use crate::customer::DeliveryCustomerOut;
+use crate::error::AppError;
+use crate::repository::CustomerStream;
+
+/// Serialize a customer of the stream as JSON
+fn customer_json(customer: Result<DeliveryCustomerOut, AppError>) -> Result<String, AppError> {
+    Ok(serde_json::to_string(&customer?)?)
+}
+
+/// Stream the `customers` as newline delimited JSON, one customer per line
+///
+/// The next batch is only fetched from the repository once the client has read the previous one.
+pub fn ndjson_stream(
+    customers: CustomerStream
+) -> impl Stream<Item = Result<String, AppError>> + Send + 'static {
+    customers.map(|customer| customer_json(customer).map(|json| json + "\n"))
+}
+
+/// Stream the `customers` as a single JSON array
+///
+/// Same as [`ndjson_stream`], but the customers are written between `[` and `]`,
+/// separated by commas, so the response is one JSON document.
+pub fn json_array_stream(
+    customers: CustomerStream
+) -> impl Stream<Item = Result<String, AppError>> + Send + 'static {
+    let mut first = true;
+
+    let customers = customers.map(move |customer| {
+        let separator = if std::mem::take(&mut first) { "" } else { "," };
+        customer_json(customer).map(|json| format!("{separator}{json}"))
+    });
+
+    stream::once(async { Ok("[".to_owned()) })
+        .chain(customers)
+        .chain(stream::once(async { Ok("]".to_owned()) }))
+}
@@ -0,0 +1,95 @@
+mod columns;
+mod csv;
+mod json;
+mod negotiation;
+mod xlsx;
+
+pub use self::csv::{csv_record, csv_stream};
+pub use columns::{customer_row, Cell, Column, COLUMNS};
+pub use json::{json_array_stream, ndjson_stream};
+pub use negotiation::{
+    ExportFormat, Locale, ResponseFormat, CSV_CONTENT_TYPE, NDJSON_CONTENT_TYPE, STREAM_PARAMETER,
+    XLSX_CONTENT_TYPE
+};
+pub use xlsx::xlsx_workbook;
+
+use axum::body::{self, BoxBody, Full, StreamBody};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, VARY};
+use axum::http::HeaderValue;
+use axum::{http::StatusCode, response::IntoResponse};
+use chrono::Utc;
+
+use crate::repository::CustomerStream;
+
+/// A customer list exported to a spreadsheet or streamed as JSON
+///
+/// CSV and JSON exports are streamed straight from the repository, Excel workbooks are
+/// filled row by row from it, but can only be sent once they're complete.
+/// Only spreadsheets are sent as a file to download.
+pub struct CustomerExport {
+    format: ExportFormat,
+    filename: String,
+    body: BoxBody
+}
+
+impl CustomerExport {
+    /// Export the `customers`
+    ///
+    /// `name` is the start of the file name, which ends in the current date.
+    pub async fn from_stream(
+        customers: CustomerStream,
+        format: ExportFormat,
+        locale: Locale,
+        name: &str
+    ) -> Result<Self, crate::error::AppError> {
+        let body = match format {
+            ExportFormat::Csv => body::boxed(StreamBody::new(csv_stream(customers, locale))),
+            ExportFormat::Xlsx => body::boxed(Full::from(xlsx_workbook(customers, locale).await?)),
+            ExportFormat::Ndjson => body::boxed(StreamBody::new(ndjson_stream(customers))),
+            ExportFormat::JsonArray => body::boxed(StreamBody::new(json_array_stream(customers)))
+        };
+
+        let filename = format!("{name}-{}.{}", Utc::now().format("%Y-%m-%d"), format.extension());
+
+        Ok(Self { format, filename, body })
+    }
+}
+
+impl IntoResponse for CustomerExport {
+    fn into_response(self) -> axum::response::Response {
+        let mut response = (
+            StatusCode::OK,
+            [
+                (CONTENT_TYPE, self.format.content_type().to_owned()),
+                (VARY, "Accept, Accept-Language".to_owned())
+            ],
+            self.body
+        )
+            .into_response();
+
+        if self.format.is_spreadsheet() {
+            let disposition = format!("attachment; filename=\"{}\"", self.filename);
+
+            if let Ok(value) = HeaderValue::from_str(&disposition) {
+                response.headers_mut().insert(CONTENT_DISPOSITION, value);
+            }
+        }
+
+        response
+    }
+}
+
+/// The response to a customer list request, picked by it's [`ResponseFormat`]
+pub enum Negotiated<T> {
+    Json(T),
+    Export(CustomerExport)
+}
+
+impl<T: IntoResponse> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Negotiated::Json(list) => list.into_response(),
+            Negotiated::Export(export) => export.into_response()
+        }
+    }
+}
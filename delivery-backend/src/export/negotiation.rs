@@ -0,0 +1,229 @@
+use axum::extract::FromRequestParts;
+use axum::http::header::{ACCEPT, ACCEPT_LANGUAGE};
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use std::convert::Infallible;
+
+/// `Content-Type` of a CSV export
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+
+/// `Content-Type` of an Excel export
+pub const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// `Content-Type` of newline delimited JSON, one customer per line
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// The `Accept` parameter asking for `application/json` as a streamed array
+pub const STREAM_PARAMETER: &str = "stream=true";
+
+/// The formats a customer list can be written in straight from the repository
+///
+/// Unlike the default JSON response, these aren't paged, they hold every matching customer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    Ndjson,
+    JsonArray
+}
+
+impl ExportFormat {
+    /// The `Content-Type` of the export
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => XLSX_CONTENT_TYPE,
+            ExportFormat::Ndjson => NDJSON_CONTENT_TYPE,
+            ExportFormat::JsonArray => "application/json"
+        }
+    }
+
+    /// The extension of the exported file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::JsonArray => "json"
+        }
+    }
+
+    /// Returns `true` if the export is a spreadsheet, which is downloaded as a file
+    pub fn is_spreadsheet(&self) -> bool {
+        matches!(self, ExportFormat::Csv | ExportFormat::Xlsx)
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ExportFormat::Csv => write!(f, "csv"),
+            ExportFormat::Xlsx => write!(f, "xlsx"),
+            ExportFormat::Ndjson => write!(f, "ndjson"),
+            ExportFormat::JsonArray => write!(f, "json_array")
+        }
+    }
+}
+
+/// What the client asked for in the `Accept` header of a customer list request
+///
+/// The media type with the highest quality wins, ties go to the one listed first.
+/// `application/json; stream=true` asks for a streamed JSON array.
+/// Anything else is answered with a buffered page of JSON, like before exports existed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Export(ExportFormat)
+}
+
+impl ResponseFormat {
+    /// Pick the [`ResponseFormat`] from the `Accept` headers
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut best: Option<(f32, Self)> = None;
+
+        for (media_type, quality) in headers.get_all(ACCEPT).iter().flat_map(weighted_values) {
+            let mut parts = media_type.split("; ");
+            let base = parts.next().unwrap_or_default();
+            let streamed = parts.any(|parameter| parameter == STREAM_PARAMETER);
+
+            let format = match base {
+                CSV_CONTENT_TYPE => Self::Export(ExportFormat::Csv),
+                XLSX_CONTENT_TYPE => Self::Export(ExportFormat::Xlsx),
+                NDJSON_CONTENT_TYPE | "application/ndjson" => Self::Export(ExportFormat::Ndjson),
+                "application/json" if streamed => Self::Export(ExportFormat::JsonArray),
+                "application/json" | "application/*" | "*/*" => Self::Json,
+                _ => continue
+            };
+
+            if quality > 0.0 && best.is_none_or(|(best, _)| quality > best) {
+                best = Some((quality, format));
+            }
+        }
+
+        best.map_or(Self::Json, |(_, format)| format)
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// The language of the column headers and the date format of an export
+///
+/// Taken from the `Accept-Language` header, Romanian unless English is preferred.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    Ro,
+    En
+}
+
+impl Locale {
+    /// Pick the [`Locale`] from the `Accept-Language` headers
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut best: Option<(f32, Self)> = None;
+
+        for (language, quality) in headers.get_all(ACCEPT_LANGUAGE).iter().flat_map(weighted_values)
+        {
+            let locale = match language.split('-').next() {
+                Some("ro") => Self::Ro,
+                Some("en") => Self::En,
+                _ => continue
+            };
+
+            if quality > 0.0 && best.is_none_or(|(best, _)| quality > best) {
+                best = Some((quality, locale));
+            }
+        }
+
+        best.map_or_else(Self::default, |(_, locale)| locale)
+    }
+
+    /// How dates are written in a CSV export
+    pub fn date_format(&self) -> &'static str {
+        match self {
+            Locale::Ro => "%d.%m.%Y",
+            Locale::En => "%Y-%m-%d"
+        }
+    }
+
+    /// The number format of dates in an Excel export
+    pub fn excel_date_format(&self) -> &'static str {
+        match self {
+            Locale::Ro => "dd.mm.yyyy",
+            Locale::En => "yyyy-mm-dd"
+        }
+    }
+
+    /// The field separator of a CSV export
+    ///
+    /// Excel set up for Romanian expects `;`, since `,` is the decimal separator.
+    pub fn csv_delimiter(&self) -> char {
+        match self {
+            Locale::Ro => ';',
+            Locale::En => ','
+        }
+    }
+
+    /// How `true` and `false` are written
+    pub fn yes_no(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (Locale::Ro, true) => "Da",
+            (Locale::Ro, false) => "Nu",
+            (Locale::En, true) => "Yes",
+            (Locale::En, false) => "No"
+        }
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for Locale
+where
+    S: Send + Sync
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// Split a header like `text/csv;q=0.9, */*;q=0.1` into lowercase values and their quality
+///
+/// Parameters other than `q` are kept as part of the value, written as `type; name=value`.
+/// Values without a `q` parameter have a quality of 1.
+fn weighted_values(header: &axum::http::HeaderValue) -> Vec<(String, f32)> {
+    let Ok(header) = header.to_str() else {
+        return Vec::new();
+    };
+
+    header
+        .split(',')
+        .filter_map(|value| {
+            let mut parts = value.split(';').map(|part| part.trim().to_lowercase());
+            let mut value = parts.next().filter(|value| !value.is_empty())?;
+            let mut quality = 1.0;
+
+            for parameter in parts {
+                match parameter.strip_prefix("q=") {
+                    Some(q) => quality = q.parse::<f32>().unwrap_or(quality),
+                    None => {
+                        value.push_str("; ");
+                        value.push_str(&parameter.replace(' ', ""));
+                    }
+                }
+            }
+
+            Some((value, quality))
+        })
+        .collect()
+}
@@ -0,0 +1,62 @@
+use chrono::Datelike;
+use futures::TryStreamExt;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+use crate::error::AppError;
+use crate::repository::CustomerStream;
+
+use super::{customer_row, Cell, Locale, COLUMNS};
+
+/// Width of the columns, in characters
+const COLUMN_WIDTH: f64 = 18.0;
+
+/// Write the `customers` to an Excel workbook
+///
+/// Rows are added as the stream yields the customers. The workbook has a frozen,
+/// filterable header row and dates are real dates, formatted the way `locale` writes them.
+pub async fn xlsx_workbook(
+    mut customers: CustomerStream,
+    locale: Locale
+) -> Result<Vec<u8>, AppError> {
+    let header_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format(locale.excel_date_format());
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    let last_column = COLUMNS.len() as u16 - 1;
+
+    for (column, header) in (0..).zip(COLUMNS.iter()) {
+        worksheet.write_string_with_format(0, column, header.header(locale), &header_format)?;
+    }
+
+    let mut row = 0;
+
+    while let Some(customer) = customers.try_next().await? {
+        row += 1;
+
+        for (column, cell) in (0..).zip(customer_row(customer, locale)) {
+            match cell {
+                Cell::Text(text) if text.is_empty() => {}
+                Cell::Text(text) => {
+                    worksheet.write_string(row, column, text)?;
+                }
+                Cell::Date(date) => {
+                    let date = ExcelDateTime::from_ymd(
+                        date.year() as u16,
+                        date.month() as u8,
+                        date.day() as u8
+                    )?;
+                    worksheet.write_datetime_with_format(row, column, &date, &date_format)?;
+                }
+            }
+        }
+    }
+
+    worksheet.set_column_range_width(0, last_column, COLUMN_WIDTH)?;
+    worksheet.set_freeze_panes(1, 0)?;
+    worksheet.autofilter(0, 0, row, last_column)?;
+
+    tracing::info!("Exported {} customers to xlsx", row);
+
+    Ok(workbook.save_to_buffer()?)
+}
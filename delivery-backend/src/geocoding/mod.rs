@@ -0,0 +1,33 @@
+mod nominatim;
+mod stub;
+
+use std::sync::Arc;
+
+use crate::customer::{Address, GeoPoint};
+use crate::error::AppError;
+
+pub use nominatim::NominatimGeocoder;
+pub use stub::StubGeocoder;
+
+/// Something that can find the coordinates of an [`Address`]
+#[axum::async_trait]
+pub trait Geocoder: Send + Sync + std::fmt::Debug {
+    /// Look up the coordinates of `address`
+    ///
+    /// Returns [`None`] if the address couldn't be found.
+    async fn geocode(&self, address: &Address) -> Result<Option<GeoPoint>, AppError>;
+}
+
+/// Set up the geocoder configured in the environment
+///
+/// Uses [`NominatimGeocoder::from_env`] if `GEOCODER_URL` is set,
+/// otherwise an empty [`StubGeocoder`], which doesn't find anything.
+pub fn setup_geocoder() -> Arc<dyn Geocoder> {
+    match NominatimGeocoder::from_env() {
+        Some(geocoder) => Arc::new(geocoder),
+        None => {
+            tracing::info!("GEOCODER_URL not set, geocoding is disabled");
+            Arc::new(StubGeocoder::default())
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::env;
+use std::time::Duration;
+
+use crate::customer::{Address, GeoPoint};
+use crate::error::AppError;
+use crate::locality;
+
+use super::Geocoder;
+
+/// How long connecting to the geocoder may take by default
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a whole geocoding request may take by default
+///
+/// Customers are geocoded while they're created, so this bounds how long that takes.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A [`Geocoder`] backed by a Nominatim compatible HTTP API
+///
+/// Uses the structured `/search` endpoint, limited to Romania.
+#[derive(Debug, Clone)]
+pub struct NominatimGeocoder {
+    client: reqwest::Client,
+    base_url: String
+}
+
+/// A single result of a Nominatim search
+#[derive(serde::Deserialize)]
+struct Place {
+    lat: String,
+    lon: String
+}
+
+impl NominatimGeocoder {
+    /// Creates a new [`NominatimGeocoder`].
+    ///
+    /// Nominatim requires every client to identify itself with a `User-Agent`.
+    /// A request fails if connecting takes longer than `connect_timeout`,
+    /// or the whole request longer than `timeout`.
+    pub fn new(
+        base_url: String,
+        user_agent: &str,
+        connect_timeout: Duration,
+        timeout: Duration
+    ) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .build()
+            .map_err(|err| AppError::GeocodingError(err.to_string()))?;
+
+        Ok(Self { client, base_url: base_url.trim_end_matches('/').to_owned() })
+    }
+
+    /// Read the configuration from the environment
+    ///
+    /// `GEOCODER_URL` enables the geocoder, e.g. `https://nominatim.openstreetmap.org`.
+    /// `GEOCODER_USER_AGENT` falls back to it's default if it's missing.
+    /// `GEOCODER_CONNECT_TIMEOUT` and `GEOCODER_TIMEOUT` set the timeouts in seconds,
+    /// either one falls back to it's default if it's missing, invalid or not positive.
+    pub fn from_env() -> Option<Self> {
+        let base_url = env::var("GEOCODER_URL").ok()?;
+
+        let user_agent = env::var("GEOCODER_USER_AGENT").unwrap_or_else(|_| {
+            tracing::info!("GEOCODER_USER_AGENT not set, using default");
+            "delivery-backend".into()
+        });
+
+        let connect_timeout = timeout_from_env("GEOCODER_CONNECT_TIMEOUT", DEFAULT_CONNECT_TIMEOUT);
+        let timeout = timeout_from_env("GEOCODER_TIMEOUT", DEFAULT_TIMEOUT);
+
+        match Self::new(base_url, &user_agent, connect_timeout, timeout) {
+            Ok(geocoder) => Some(geocoder),
+            Err(err) => {
+                tracing::error!("Failed to set up the geocoder: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// Read a timeout in seconds from the environment variable `name`
+fn timeout_from_env(name: &str, default: Duration) -> Duration {
+    match env::var(name).map(|secs| secs.parse::<u64>()) {
+        Ok(Ok(0)) => tracing::info!("{name} must be positive. Using default"),
+        Ok(Ok(secs)) => return Duration::from_secs(secs),
+        Ok(Err(error)) => {
+            tracing::info!("Failed to parse {name} into integer: {error}. Using default")
+        }
+        Err(_) => tracing::info!("{name} not set, using default")
+    }
+
+    default
+}
+
+#[axum::async_trait]
+impl Geocoder for NominatimGeocoder {
+    async fn geocode(&self, address: &Address) -> Result<Option<GeoPoint>, AppError> {
+        let county = locality::county(&address.county).map_or(address.county.as_str(), |c| c.name);
+        let street = format!("{} {}", address.number, address.street);
+
+        let mut query = vec![
+            ("format", "jsonv2"),
+            ("limit", "1"),
+            ("countrycodes", "ro"),
+            ("street", street.trim()),
+            ("county", county),
+        ];
+
+        if !address.locality.is_empty() {
+            query.push(("city", &address.locality));
+        }
+
+        if let Some(postal_code) = &address.postal_code {
+            query.push(("postalcode", postal_code));
+        }
+
+        let places: Vec<Place> = self
+            .client
+            .get(format!("{}/search", self.base_url))
+            .query(&query)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AppError::GeocodingError(err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| AppError::GeocodingError(err.to_string()))?;
+
+        let Some(place) = places.into_iter().next() else {
+            return Ok(None);
+        };
+
+        match (place.lat.parse(), place.lon.parse()) {
+            (Ok(latitude), Ok(longitude)) => Ok(Some(GeoPoint::new(latitude, longitude))),
+            _ => Err(AppError::GeocodingError(format!(
+                "Invalid coordinates in response: {}, {}",
+                place.lat, place.lon
+            )))
+        }
+    }
+}
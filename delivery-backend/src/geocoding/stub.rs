@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use crate::customer::{Address, GeoPoint};
+use crate::error::AppError;
+
+use super::Geocoder;
+
+/// An offline [`Geocoder`] that only knows the localities it was given
+///
+/// Addresses are looked up by county code and locality, case insensitively.
+#[derive(Debug, Default, Clone)]
+pub struct StubGeocoder {
+    locations: HashMap<(String, String), GeoPoint>
+}
+
+impl StubGeocoder {
+    /// Creates a new, empty [`StubGeocoder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every address in `locality` of `county` resolve to `location`
+    pub fn with_locality(mut self, county: &str, locality: &str, location: GeoPoint) -> Self {
+        self.locations.insert(key(county, locality), location);
+        self
+    }
+}
+
+fn key(county: &str, locality: &str) -> (String, String) {
+    (county.trim().to_uppercase(), locality.trim().to_lowercase())
+}
+
+#[axum::async_trait]
+impl Geocoder for StubGeocoder {
+    async fn geocode(&self, address: &Address) -> Result<Option<GeoPoint>, AppError> {
+        Ok(self.locations.get(&key(&address.county, &address.locality)).copied())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::error::AppError;
+
+/// A record of a CSV file, with the line it starts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRecord {
+    /// Line of the file the record starts on, starting at 1
+    pub line: usize,
+    pub fields: Vec<String>
+}
+
+/// Guess the delimiter of `input` from it's header row
+///
+/// Spreadsheets set to Romanian, and Romanian exports of this service, separate fields by `;`
+/// instead of `,`. Whichever of the two appears more often outside quotes is the delimiter,
+/// `,` if neither does.
+pub fn detect_delimiter(input: &str) -> char {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let header = input.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+
+    let mut in_quotes = false;
+    let (mut commas, mut semicolons) = (0, 0);
+
+    for c in header.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => commas += 1,
+            ';' if !in_quotes => semicolons += 1,
+            _ => {}
+        }
+    }
+
+    if semicolons > commas {
+        ';'
+    } else {
+        ','
+    }
+}
+
+/// Split `input` into records, following RFC 4180
+///
+/// Fields can be quoted with `"`, quotes inside quoted fields are doubled.
+/// Quoted fields can span lines. Both `\n` and `\r\n` end a record, blank lines are skipped.
+/// A leading byte order mark is ignored.
+pub fn parse_csv(input: &str, delimiter: char) -> Result<Vec<CsvRecord>, AppError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                line += 1;
+                field.push('\n');
+            }
+            '\r' if in_quotes => {}
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                push_record(&mut records, std::mem::take(&mut fields), record_line);
+                line += 1;
+                record_line = line;
+            }
+            c => field.push(c)
+        }
+    }
+
+    if in_quotes {
+        return Err(AppError::BadRequest(format!(
+            "Unterminated quoted field in the record on line {record_line}"
+        )));
+    }
+
+    fields.push(field);
+    push_record(&mut records, fields, record_line);
+
+    Ok(records)
+}
+
+/// Add a record, unless it's a blank line
+fn push_record(records: &mut Vec<CsvRecord>, fields: Vec<String>, line: usize) {
+    if fields.iter().all(|field| field.trim().is_empty()) {
+        return;
+    }
+
+    records.push(CsvRecord { line, fields });
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::local_time::local_midnight;
+
+/// Parse a date like spreadsheets write them
+///
+/// Accepts `dd.mm.yyyy`, `dd/mm/yyyy`, `dd-mm-yyyy`, `yyyy-mm-dd` and RFC 3339 timestamps.
+/// Dates without a time are midnight in Romanian local time.
+pub fn parse_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime);
+    }
+
+    let date = ["%d.%m.%Y", "%d/%m/%Y", "%d-%m-%Y", "%Y-%m-%d"]
+        .into_iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())?;
+
+    Some(local_midnight(date))
+}
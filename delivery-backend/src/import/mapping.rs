@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Map, Value};
+
+use crate::error::AppError;
+
+use super::date::parse_date;
+
+/// The fields a CSV column can be mapped onto
+///
+/// They're named like the fields of a [`PartialDeliveryCustomer`](crate::query::PartialDeliveryCustomer).
+/// `phones` can hold several numbers separated by `,`, `;` or `/`.
+pub const IMPORT_FIELDS: [&str; 25] = [
+    "customer_id",
+    "name",
+    "active",
+    "county",
+    "locality",
+    "postal_code",
+    "street",
+    "number",
+    "building",
+    "staircase",
+    "apartment",
+    "additional",
+    "manufacturer",
+    "year_of_manufacture",
+    "model",
+    "type",
+    "warranty",
+    "operation_performed",
+    "appliance_number",
+    "date",
+    "expiration_date",
+    "observations",
+    "phones",
+    "email",
+    "preferred_channel"
+];
+
+/// The path in the stored document of an import field, other than `customer_id`
+fn stored_path(field: &str) -> Option<&'static str> {
+    let path = match field {
+        "name" => "name",
+        "active" => "active",
+        "county" => "address.county",
+        "locality" => "address.locality",
+        "postal_code" => "address.postal_code",
+        "street" => "address.street",
+        "number" => "address.number",
+        "building" => "address.building",
+        "staircase" => "address.staircase",
+        "apartment" => "address.apartment",
+        "additional" => "address.additional",
+        "manufacturer" => "appliance.manufacturer",
+        "year_of_manufacture" => "appliance.year_of_manufacture",
+        "model" => "appliance.model",
+        "type" => "appliance.type",
+        "warranty" => "appliance.warranty",
+        "operation_performed" => "appliance.operation_performed",
+        "appliance_number" => "appliance.number",
+        "date" => "appliance.date",
+        "expiration_date" => "appliance.expiration_date",
+        "observations" => "appliance.observations",
+        "phones" => "phones",
+        "email" => "email",
+        "preferred_channel" => "preferred_channel",
+        _ => return None
+    };
+
+    Some(path)
+}
+
+/// Fields that hold dates
+const DATE_FIELDS: [&str; 3] = ["warranty", "date", "expiration_date"];
+
+/// Which column of the CSV file each field is read from
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    /// Index of the column of every mapped field
+    columns: HashMap<&'static str, usize>
+}
+
+/// The values of `active` that mean a customer is active, compared case insensitively
+const ACTIVE_VALUES: [&str; 5] = ["true", "1", "da", "activ", "yes"];
+
+/// The values of `active` that mean a customer is inactive, compared case insensitively
+const INACTIVE_VALUES: [&str; 5] = ["false", "0", "nu", "inactiv", "no"];
+
+impl ColumnMapping {
+    /// Map the fields onto the columns of `header`
+    ///
+    /// `overrides` maps field names onto column names, every other field is read
+    /// from the column with the same name, if there is one. Column names are compared
+    /// case insensitively.
+    pub fn new(header: &[String], overrides: &HashMap<String, String>) -> Result<Self, AppError> {
+        let column_index = |name: &str| {
+            header.iter().position(|column| column.trim().eq_ignore_ascii_case(name.trim()))
+        };
+
+        if let Some(field) = overrides.keys().find(|field| !IMPORT_FIELDS.contains(&field.as_str()))
+        {
+            return Err(AppError::BadRequest(format!("Cannot map a column onto {field}")));
+        }
+
+        let mut columns = HashMap::new();
+
+        for field in IMPORT_FIELDS {
+            let index = match overrides.get(field) {
+                Some(column) => Some(column_index(column).ok_or_else(|| {
+                    AppError::BadRequest(format!("No column {column} for {field}"))
+                })?),
+                None => column_index(field)
+            };
+
+            if let Some(index) = index {
+                columns.insert(field, index);
+            }
+        }
+
+        if !columns.contains_key("customer_id") {
+            return Err(AppError::BadRequest("No column for customer_id".into()));
+        }
+
+        Ok(Self { columns })
+    }
+
+    /// The paths in the stored document of the mapped fields, other than `customer_id`
+    ///
+    /// Only these are overwritten when an existing customer is imported again.
+    pub fn stored_paths(&self) -> Vec<&'static str> {
+        let mut paths =
+            self.columns.keys().filter_map(|field| stored_path(field)).collect::<Vec<_>>();
+        paths.sort_unstable();
+        paths
+    }
+
+    /// The trimmed value of `field` in `record`, if it's mapped and not empty
+    pub fn value<'r>(&self, record: &'r [String], field: &str) -> Option<&'r str> {
+        let index = *self.columns.get(field)?;
+        record.get(index).map(|value| value.trim()).filter(|value| !value.is_empty())
+    }
+
+    /// Convert a record into the JSON shape of a [`DeliveryCustomerIn`](crate::customer::DeliveryCustomerIn)
+    ///
+    /// Values that can't be read, like malformed dates, are reported by field name.
+    /// Missing customers are active.
+    pub fn to_customer_json(&self, record: &[String]) -> Result<Value, Vec<String>> {
+        let mut errors = Vec::new();
+        let text = |field: &str| {
+            self.value(record, field).map(|value| json!(value)).unwrap_or(Value::Null)
+        };
+
+        let mut dates = HashMap::new();
+        for field in DATE_FIELDS {
+            let value = match self.value(record, field) {
+                Some(value) => match parse_date(value) {
+                    Some(date) => json!(date.to_rfc3339()),
+                    None => {
+                        errors.push(format!("{field}: {value} is not a date"));
+                        Value::Null
+                    }
+                },
+                None => Value::Null
+            };
+            dates.insert(field, value);
+        }
+
+        let active = match self.value(record, "active").map(str::to_lowercase) {
+            None => true,
+            Some(value) if ACTIVE_VALUES.contains(&value.as_str()) => true,
+            Some(value) if INACTIVE_VALUES.contains(&value.as_str()) => false,
+            Some(value) => {
+                errors.push(format!("active: {value} is not a yes or no"));
+                true
+            }
+        };
+
+        let phones = self
+            .value(record, "phones")
+            .map(|phones| {
+                phones
+                    .split([',', ';', '/'])
+                    .map(str::trim)
+                    .filter(|number| !number.is_empty())
+                    .map(|number| json!({ "label": "phone", "number": number }))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let strip_nulls = |value: Value| match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter().filter(|(_, value)| !value.is_null()).collect::<Map<_, _>>()
+            ),
+            value => value
+        };
+
+        Ok(strip_nulls(json!({
+            "_id": { "$oid": ObjectId::new().to_hex() },
+            "customer_id": text("customer_id"),
+            "name": text("name"),
+            "active": active,
+            "address": strip_nulls(json!({
+                "county": text("county"),
+                "locality": text("locality"),
+                "postal_code": text("postal_code"),
+                "street": text("street"),
+                "number": text("number"),
+                "building": text("building"),
+                "staircase": text("staircase"),
+                "apartment": text("apartment"),
+                "additional": text("additional")
+            })),
+            "appliance": strip_nulls(json!({
+                "manufacturer": text("manufacturer"),
+                "year_of_manufacture": text("year_of_manufacture"),
+                "model": text("model"),
+                "type": text("type"),
+                "warranty": dates["warranty"],
+                "operation_performed": text("operation_performed").as_str().map(str::to_uppercase),
+                "number": text("appliance_number"),
+                "date": dates["date"],
+                "expiration_date": dates["expiration_date"],
+                "observations": text("observations")
+            })),
+            "phones": phones,
+            "email": text("email"),
+            "preferred_channel": text("preferred_channel").as_str().map(str::to_lowercase)
+        })))
+    }
+}
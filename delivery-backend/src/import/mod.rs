@@ -0,0 +1,9 @@
+mod csv;
+mod date;
+mod mapping;
+mod report;
+
+pub use self::csv::{detect_delimiter, parse_csv, CsvRecord};
+pub use date::parse_date;
+pub use mapping::{ColumnMapping, IMPORT_FIELDS};
+pub use report::{import_customers, ImportOptions, ImportReport, RowError};
@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use serde_json::{json, Value};
+use validator::Validate;
+
+use crate::customer::DeliveryCustomerIn;
+use crate::error::AppError;
+use crate::repository::CustomerRepository;
+
+use super::{detect_delimiter, parse_csv, ColumnMapping};
+
+/// How a CSV file is imported
+///
+/// `mapping` is a JSON object mapping field names onto column names,
+/// e.g. `{"name": "Nume client", "expiration_date": "Scadenta"}`.
+/// Without a `delimiter`, it's detected from the header row, see [`detect_delimiter`].
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub dry_run: bool,
+    pub delimiter: Option<char>,
+    pub mapping: Option<String>
+}
+
+impl ImportOptions {
+    /// The field to column overrides of `mapping`
+    pub fn mapping(&self) -> Result<HashMap<String, String>, AppError> {
+        match &self.mapping {
+            Some(mapping) => serde_json::from_str(mapping)
+                .map_err(|err| AppError::BadRequest(format!("Invalid mapping: {err}"))),
+            None => Ok(HashMap::new())
+        }
+    }
+}
+
+/// Why a row of the CSV file can't be imported
+#[derive(Debug, serde::Serialize)]
+pub struct RowError {
+    /// Line of the file the row starts on
+    pub line: usize,
+    pub customer_id: Option<String>,
+    pub errors: Value
+}
+
+/// The outcome of importing a CSV file
+///
+/// Nothing is written unless every row is valid. The rows are then written in batches,
+/// which aren't atomic: a row that fails to be written is reported in `errors`,
+/// while every other row is still written and counted in `inserted` or `updated`.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub rows: usize,
+    pub valid_rows: usize,
+    pub inserted: u64,
+    pub updated: u64,
+    pub errors: Vec<RowError>
+}
+
+impl IntoResponse for ImportReport {
+    fn into_response(self) -> axum::response::Response {
+        let status =
+            if self.errors.is_empty() { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+
+        (status, Json(self)).into_response()
+    }
+}
+
+/// Read, validate and, unless it's a dry run, write the customers of a CSV file
+///
+/// The first record is the header. Customers are upserted by `customer_id`:
+/// new ones are inserted, existing ones only get the fields of the mapped columns overwritten.
+/// Rows of soft deleted customers and repeated `customer_id`s are errors.
+pub async fn import_customers(
+    repository: &dyn CustomerRepository,
+    input: &str,
+    options: &ImportOptions
+) -> Result<ImportReport, AppError> {
+    let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(input));
+    let mut records = parse_csv(input, delimiter)?.into_iter();
+
+    let header = records.next().ok_or_else(|| AppError::BadRequest("Empty CSV file".into()))?;
+    let mapping = ColumnMapping::new(&header.fields, &options.mapping()?)?;
+
+    let mut report = ImportReport { dry_run: options.dry_run, ..Default::default() };
+    let mut customers = Vec::new();
+    let mut seen = HashSet::new();
+
+    for record in records {
+        report.rows += 1;
+
+        let customer_id = mapping.value(&record.fields, "customer_id").map(ToOwned::to_owned);
+        let row_error = |errors: Value| RowError {
+            line: record.line,
+            customer_id: customer_id.clone(),
+            errors
+        };
+
+        if let Some(customer_id) = &customer_id {
+            if !seen.insert(customer_id.clone()) {
+                report
+                    .errors
+                    .push(row_error(json!([format!("customer_id {customer_id} is repeated")])));
+                continue;
+            }
+        }
+
+        let customer = mapping
+            .to_customer_json(&record.fields)
+            .map_err(|errors| json!(errors))
+            .and_then(|value| {
+                serde_json::from_value::<DeliveryCustomerIn>(value)
+                    .map_err(|err| json!([err.to_string()]))
+            })
+            .and_then(|customer| {
+                customer.validate().map(|_| customer).map_err(|errors| json!(errors))
+            });
+
+        match customer {
+            Ok(customer) => customers.push((record.line, customer)),
+            Err(errors) => report.errors.push(row_error(errors))
+        }
+    }
+
+    let customer_ids =
+        customers.iter().map(|(_, customer)| customer.customer_id.clone()).collect::<Vec<_>>();
+    let existing = repository.existing_customer_ids(&customer_ids).await?;
+
+    let lines = customers
+        .iter()
+        .map(|(line, customer)| (customer.customer_id.clone(), *line))
+        .collect::<HashMap<_, _>>();
+    let (mut new, mut updated) = (Vec::new(), Vec::new());
+
+    for (line, customer) in customers {
+        match existing.get(&customer.customer_id) {
+            Some(true) => report.errors.push(RowError {
+                line,
+                errors: json!([format!("customer_id {} was deleted", customer.customer_id)]),
+                customer_id: Some(customer.customer_id)
+            }),
+            Some(false) => updated.push(customer),
+            None => new.push(customer)
+        }
+    }
+
+    report.errors.sort_by_key(|error| error.line);
+    report.valid_rows = new.len() + updated.len();
+
+    tracing::info!(
+        "CSV import: {} rows, {} new, {} existing, {} invalid",
+        report.rows,
+        new.len(),
+        updated.len(),
+        report.errors.len()
+    );
+
+    if options.dry_run || !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    let upserted = repository.upsert_customers(new, updated, &mapping.stored_paths()).await?;
+
+    report.inserted = upserted.inserted;
+    report.updated = upserted.updated;
+    report.errors = upserted
+        .failed
+        .into_iter()
+        .map(|(customer_id, error)| RowError {
+            line: lines.get(&customer_id).copied().unwrap_or_default(),
+            errors: json!([format!("customer_id {customer_id} could not be written: {error}")]),
+            customer_id: Some(customer_id)
+        })
+        .collect();
+    report.errors.sort_by_key(|error| error.line);
+
+    Ok(report)
+}
@@ -0,0 +1,5 @@
+mod purge;
+mod reminder;
+
+pub use purge::{spawn_purge_job, PurgeConfig};
+pub use reminder::{deliver_reminders, queue_reminders, spawn_reminder_job, ReminderConfig};
@@ -0,0 +1,84 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::database::Database;
+
+/// The longest retention period, about a hundred years
+const MAX_RETENTION_DAYS: i64 = 36_500;
+
+/// Configuration of the job that permanently removes soft deleted customers
+#[derive(Debug, Clone)]
+pub struct PurgeConfig {
+    /// How long a soft deleted customer is kept before it's purged
+    pub retention: chrono::Duration,
+    /// How often the job checks for customers to purge
+    pub interval: Duration
+}
+
+impl Default for PurgeConfig {
+    fn default() -> Self {
+        Self { retention: chrono::Duration::days(30), interval: Duration::from_secs(60 * 60) }
+    }
+}
+
+impl PurgeConfig {
+    /// Read the configuration from the environment
+    ///
+    /// `CUSTOMER_RETENTION_DAYS` sets the retention period in days,
+    /// `CUSTOMER_PURGE_INTERVAL` sets the interval between runs in seconds.
+    /// Either one falls back to it's default if it's missing, invalid or not positive.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        match env::var("CUSTOMER_RETENTION_DAYS").map(|days| days.parse::<i64>()) {
+            Ok(Ok(days)) if (1..=MAX_RETENTION_DAYS).contains(&days) => {
+                config.retention = chrono::Duration::days(days)
+            }
+            Ok(Ok(days)) => {
+                tracing::info!("CUSTOMER_RETENTION_DAYS={days} is out of range. Using default")
+            }
+            Ok(Err(error)) => tracing::info!(
+                "Failed to parse CUSTOMER_RETENTION_DAYS into integer: {error}. Using default"
+            ),
+            Err(_) => tracing::info!("CUSTOMER_RETENTION_DAYS not set, using default")
+        }
+
+        match env::var("CUSTOMER_PURGE_INTERVAL").map(|secs| secs.parse::<u64>()) {
+            Ok(Ok(0)) => {
+                tracing::info!("CUSTOMER_PURGE_INTERVAL must be positive. Using default")
+            }
+            Ok(Ok(secs)) => config.interval = Duration::from_secs(secs),
+            Ok(Err(error)) => tracing::info!(
+                "Failed to parse CUSTOMER_PURGE_INTERVAL into integer: {error}. Using default"
+            ),
+            Err(_) => tracing::info!("CUSTOMER_PURGE_INTERVAL not set, using default")
+        }
+
+        config
+    }
+}
+
+/// Spawn a background task that permanently removes soft deleted customers
+///
+/// Customers are purged once they have been deleted for longer than the retention period.
+/// Failures are logged and retried on the next run.
+pub fn spawn_purge_job(database: Arc<Database>, config: PurgeConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+
+        loop {
+            interval.tick().await;
+
+            let deleted_before = Utc::now() - config.retention;
+
+            match database.customer().purge_deleted_customers(deleted_before).await {
+                Ok(result) => tracing::info!("Purged deleted customers: {result:?}"),
+                Err(err) => tracing::error!("Failed to purge deleted customers: {err}")
+            }
+        }
+    })
+}
@@ -0,0 +1,312 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::task::JoinHandle;
+
+use crate::customer::{ContactChannel, ContactOutcome, DeliveryCustomerOut};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::notification::{render_reminder, ChannelKind, Language, Notifier, OutgoingMessage};
+use crate::query::{ExpirationStatus, ExpiredCustomersQuery};
+
+/// The longest wait before retrying a failed delivery, in seconds
+const MAX_RETRY_BACKOFF: i64 = 7 * 24 * 60 * 60;
+
+/// Configuration of the job that queues and delivers reminders
+#[derive(Debug, Clone)]
+pub struct ReminderConfig {
+    /// How many days before the `expiration_date` reminders are sent, in ascending order
+    pub offsets: Vec<u32>,
+    /// How often the job runs
+    pub interval: Duration,
+    /// How many times delivering a reminder is attempted before giving up
+    pub max_attempts: u32,
+    /// How long to wait before retrying a failed delivery, doubled after every failure up to a week
+    pub retry_backoff: chrono::Duration,
+    /// The language reminders are written in
+    pub language: Language
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            offsets: vec![7, 30],
+            interval: Duration::from_secs(60 * 60),
+            max_attempts: 5,
+            retry_backoff: chrono::Duration::minutes(15),
+            language: Language::default()
+        }
+    }
+}
+
+impl ReminderConfig {
+    /// Read the configuration from the environment
+    ///
+    /// `REMINDER_OFFSETS` is a comma separated list of days, e.g. `30,7`,
+    /// `REMINDER_INTERVAL` and `REMINDER_RETRY_BACKOFF` are in seconds,
+    /// `REMINDER_MAX_ATTEMPTS` is a number and `REMINDER_LANGUAGE` is `ro` or `en`.
+    /// Either one falls back to it's default if it's missing or invalid,
+    /// the interval and the attempts have to be positive and the backoff between a second
+    /// and [`MAX_RETRY_BACKOFF`].
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        match env::var("REMINDER_OFFSETS")
+            .map(|offsets| offsets.split(',').map(|days| days.trim().parse::<u32>()).collect())
+        {
+            Ok(Ok(offsets)) => config.offsets = normalize_offsets(offsets),
+            Ok(Err(error)) => tracing::info!(
+                "Failed to parse REMINDER_OFFSETS into a list of integers: {error}. Using default"
+            ),
+            Err(_) => tracing::info!("REMINDER_OFFSETS not set, using default")
+        }
+
+        match env::var("REMINDER_INTERVAL").map(|secs| secs.parse::<u64>()) {
+            Ok(Ok(0)) => tracing::info!("REMINDER_INTERVAL must be positive. Using default"),
+            Ok(Ok(secs)) => config.interval = Duration::from_secs(secs),
+            Ok(Err(error)) => tracing::info!(
+                "Failed to parse REMINDER_INTERVAL into integer: {error}. Using default"
+            ),
+            Err(_) => tracing::info!("REMINDER_INTERVAL not set, using default")
+        }
+
+        match env::var("REMINDER_MAX_ATTEMPTS").map(|attempts| attempts.parse::<u32>()) {
+            // No reminder could ever be claimed with zero attempts
+            Ok(Ok(0)) => tracing::info!("REMINDER_MAX_ATTEMPTS must be positive. Using default"),
+            Ok(Ok(attempts)) => config.max_attempts = attempts,
+            Ok(Err(error)) => tracing::info!(
+                "Failed to parse REMINDER_MAX_ATTEMPTS into integer: {error}. Using default"
+            ),
+            Err(_) => tracing::info!("REMINDER_MAX_ATTEMPTS not set, using default")
+        }
+
+        match env::var("REMINDER_RETRY_BACKOFF").map(|secs| secs.parse::<i64>()) {
+            Ok(Ok(secs)) if (1..=MAX_RETRY_BACKOFF).contains(&secs) => {
+                config.retry_backoff = chrono::Duration::seconds(secs)
+            }
+            Ok(Ok(secs)) => {
+                tracing::info!("REMINDER_RETRY_BACKOFF={secs} is out of range. Using default")
+            }
+            Ok(Err(error)) => tracing::info!(
+                "Failed to parse REMINDER_RETRY_BACKOFF into integer: {error}. Using default"
+            ),
+            Err(_) => tracing::info!("REMINDER_RETRY_BACKOFF not set, using default")
+        }
+
+        match env::var("REMINDER_LANGUAGE").map(|language| language.parse::<Language>()) {
+            Ok(Ok(language)) => config.language = language,
+            Ok(Err(error)) => tracing::info!("{error}. Using default"),
+            Err(_) => tracing::info!("REMINDER_LANGUAGE not set, using default")
+        }
+
+        config
+    }
+
+    /// How long to wait before the next attempt, after `attempts` failed ones
+    ///
+    /// Never longer than [`MAX_RETRY_BACKOFF`].
+    fn backoff(&self, attempts: u32) -> chrono::Duration {
+        let secs = self
+            .retry_backoff
+            .num_seconds()
+            .checked_mul(2i64.pow(attempts.min(10)))
+            .unwrap_or(MAX_RETRY_BACKOFF);
+
+        chrono::Duration::seconds(secs.min(MAX_RETRY_BACKOFF))
+    }
+}
+
+/// Sort the offsets and drop duplicates and zeroes
+fn normalize_offsets(mut offsets: Vec<u32>) -> Vec<u32> {
+    offsets.retain(|days| *days > 0);
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets
+}
+
+/// Spawn a background task that queues and delivers reminders
+///
+/// Every run queues reminders for the customers that became due since the last run,
+/// then delivers everything that's due, including earlier failures.
+/// Failures are logged and retried on the next run.
+pub fn spawn_reminder_job(
+    database: Arc<Database>,
+    notifier: Notifier,
+    config: ReminderConfig
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+
+        loop {
+            interval.tick().await;
+
+            match queue_reminders(&database, &notifier, &config).await {
+                Ok(queued) => tracing::info!("Queued {queued} reminders"),
+                Err(err) => tracing::error!("Failed to queue reminders: {err}")
+            }
+
+            match deliver_reminders(&database, &notifier, &config, Utc::now()).await {
+                Ok((sent, failed)) => tracing::info!("Sent {sent} reminders, {failed} failed"),
+                Err(err) => tracing::error!("Failed to deliver reminders: {err}")
+            }
+        }
+    })
+}
+
+/// Queue a reminder for every customer whose check expires within one of the offsets
+///
+/// Each offset covers the days up to it, starting after the previous offset,
+/// so a customer only gets the reminder of the closest offset that hasn't passed.
+/// Inactive customers, customers without an email or phone, customers that withdrew their
+/// consent to notifications and customers that declined the check they're due for are skipped.
+/// Customers preferring email or SMS only get reminders over that channel.
+/// Returns the number of newly queued reminders.
+pub async fn queue_reminders(
+    database: &Database,
+    notifier: &Notifier,
+    config: &ReminderConfig
+) -> Result<u64, AppError> {
+    let now = Utc::now();
+    let channels = notifier.kinds().collect::<Vec<_>>();
+    let mut queued = 0;
+    let mut previous_offset = 0;
+
+    for &offset in &config.offsets {
+        let start_date = now + chrono::Duration::days(previous_offset.into());
+        let mut cursor = None;
+
+        loop {
+            let query = ExpiredCustomersQuery {
+                status: ExpirationStatus::DueSoon,
+                start_date: Some(start_date.into()),
+                due_within_days: Some(offset),
+                limit: Some(500),
+                cursor: cursor.take(),
+                ..Default::default()
+            };
+
+            let customers = database.customer().expired_customers(query).await?;
+            cursor = customers.next_cursor().map(ToOwned::to_owned);
+
+            for customer in customers.into_iter().filter(wants_reminder) {
+                for &channel in &channels {
+                    let Some(to) = contact_address(&customer, channel) else {
+                        continue;
+                    };
+
+                    let message = render_reminder(config.language, channel, &customer, to);
+
+                    let is_new = database
+                        .notification()
+                        .queue(
+                            &customer.customer_id,
+                            message,
+                            customer.appliance.expiration_date,
+                            offset,
+                            now
+                        )
+                        .await?;
+
+                    queued += u64::from(is_new);
+                }
+            }
+
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        previous_offset = offset;
+    }
+
+    Ok(queued)
+}
+
+/// Deliver every queued reminder that's due at `now`
+///
+/// Returns the number of reminders sent and the number of failed attempts.
+pub async fn deliver_reminders(
+    database: &Database,
+    notifier: &Notifier,
+    config: &ReminderConfig,
+    now: DateTime<Utc>
+) -> Result<(u64, u64), AppError> {
+    let notifications = database.notification();
+    let (mut sent, mut failed) = (0, 0);
+
+    while let Some(notification) =
+        notifications.claim_due(now, config.max_attempts, chrono::Duration::minutes(5)).await?
+    {
+        let message = OutgoingMessage {
+            channel: notification.channel,
+            to: notification.to,
+            subject: notification.subject,
+            body: notification.body
+        };
+
+        match notifier.send(&message).await {
+            Ok(()) => {
+                notifications.mark_sent(notification.id, Utc::now()).await?;
+                sent += 1;
+            }
+            Err(err) => {
+                tracing::error!(
+                    "Failed to send {} reminder to customer_id={}: {err}",
+                    message.channel,
+                    notification.customer_id
+                );
+
+                let next_attempt_at = now + config.backoff(notification.attempts);
+                notifications
+                    .mark_failed(notification.id, err.to_string(), next_attempt_at)
+                    .await?;
+                failed += 1;
+            }
+        }
+    }
+
+    Ok((sent, failed))
+}
+
+/// Only active customers who granted their consent to notifications get reminders,
+/// unless they declined the check they're due for
+///
+/// A customer without a consent record is treated like one who withdrew it.
+fn wants_reminder(customer: &DeliveryCustomerOut) -> bool {
+    let declined = customer.reminder.as_ref().is_some_and(|reminder| {
+        reminder.outcome == ContactOutcome::Declined
+            && reminder.for_expiration == customer.appliance.expiration_date
+    });
+
+    let granted = customer.consent.notifications.as_ref().is_some_and(|consent| consent.granted);
+
+    customer.active && !declined && granted
+}
+
+/// Where to send a reminder over `channel`, if the customer can be reached that way
+///
+/// SMS go to the phone number labelled `mobile`, or the first one if there is no such label.
+fn contact_address(customer: &DeliveryCustomerOut, channel: ChannelKind) -> Option<String> {
+    let preferred = match customer.preferred_channel {
+        Some(ContactChannel::Email) => Some(ChannelKind::Email),
+        Some(ContactChannel::Sms) => Some(ChannelKind::Sms),
+        _ => None
+    };
+
+    if preferred.is_some_and(|preferred| preferred != channel) {
+        return None;
+    }
+
+    match channel {
+        ChannelKind::Email => customer.email.clone(),
+        ChannelKind::Sms => customer
+            .phones
+            .iter()
+            .find(|phone| phone.label.eq_ignore_ascii_case("mobile"))
+            .or_else(|| customer.phones.first())
+            .map(|phone| phone.number.clone())
+    }
+    .filter(|address| !address.trim().is_empty())
+}
@@ -1,10 +1,24 @@
 pub mod admin;
-pub mod appliance_field;
+pub mod app;
+pub mod appointment;
+pub mod audit;
 pub mod auth;
+pub mod calendar;
+pub mod certificate;
 pub mod customer;
 pub mod database;
 pub mod error;
+pub mod export;
+pub mod geocoding;
+pub mod import;
+pub mod jobs;
+pub mod local_time;
+pub mod locality;
+pub mod migration;
+pub mod notification;
+pub mod planning;
 pub mod query;
+pub mod repository;
 pub mod responses;
 pub mod routers;
 pub mod state;
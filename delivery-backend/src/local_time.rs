@@ -0,0 +1,77 @@
+//! Romanian local time, which dates are shown and stored in
+//!
+//! Romania is on EET (UTC+2) in winter and EEST (UTC+3) in summer, following the EU rules
+//! for summer time. Those haven't changed since 1996, so they're kept here instead of
+//! in a time zone database.
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc
+};
+
+/// Romania is on UTC+2 in winter and UTC+3 in summer
+const WINTER_OFFSET_HOURS: i32 = 2;
+
+/// The last Sunday of `month`, the day EU summer time starts or ends
+fn last_sunday(year: i32, month: u32) -> Option<NaiveDate> {
+    let first_of_next = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        month => NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    let last = first_of_next.pred_opt()?;
+
+    Some(last - Duration::days(last.weekday().num_days_from_sunday().into()))
+}
+
+/// When summer time starts and ends in `year`, in UTC
+///
+/// Summer time starts on the last Sunday of March and ends on the last Sunday of October,
+/// both at 01:00 UTC.
+fn summer_time(year: i32) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let change = NaiveTime::from_hms_opt(1, 0, 0)?;
+
+    Some((last_sunday(year, 3)?.and_time(change), last_sunday(year, 10)?.and_time(change)))
+}
+
+fn offset(summer: bool) -> FixedOffset {
+    let hours = if summer { WINTER_OFFSET_HOURS + 1 } else { WINTER_OFFSET_HOURS };
+
+    FixedOffset::east_opt(hours * 3600).expect("offset is in range")
+}
+
+/// The UTC offset of Romanian local time at midnight on `date`
+///
+/// Midnight is still winter time on the day summer time starts, and still summer time on
+/// the day it ends.
+fn offset_at_midnight(date: NaiveDate) -> FixedOffset {
+    let summer = match (last_sunday(date.year(), 3), last_sunday(date.year(), 10)) {
+        (Some(start), Some(end)) => date > start && date <= end,
+        _ => false
+    };
+
+    offset(summer)
+}
+
+/// The UTC offset of Romanian local time at `datetime`
+pub fn romanian_offset(datetime: DateTime<Utc>) -> FixedOffset {
+    let summer = match summer_time(datetime.year()) {
+        Some((start, end)) => datetime.naive_utc() >= start && datetime.naive_utc() < end,
+        None => false
+    };
+
+    offset(summer)
+}
+
+/// Midnight on `date` in Romanian local time
+pub fn local_midnight(date: NaiveDate) -> DateTime<FixedOffset> {
+    offset_at_midnight(date)
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .single()
+        .expect("a fixed offset maps every local time to a single instant")
+}
+
+/// The day `datetime` falls on in Romanian local time
+///
+/// Dates are stored as midnight local time, which is the evening before in UTC.
+pub fn local_date(datetime: DateTime<Utc>) -> NaiveDate {
+    datetime.with_timezone(&romanian_offset(datetime)).date_naive()
+}
@@ -0,0 +1,116 @@
+//! Offline dataset of Romanian counties and their major localities
+//!
+//! Used to validate and normalize addresses. Counties are identified by their
+//! two letter code (`CJ`, `B`, ..), which is what gets stored on customers.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+const COUNTIES_CSV: &str = include_str!("counties.csv");
+const LOCALITIES_CSV: &str = include_str!("localities.csv");
+
+/// A Romanian county
+#[derive(Debug)]
+pub struct County {
+    /// The canonical code, e.g. `CJ`
+    pub code: &'static str,
+    /// The name with diacritics, e.g. `Cluj`
+    pub name: &'static str,
+    /// The first two digits of the postal codes in the county
+    pub postal_prefixes: Vec<&'static str>,
+    /// The major localities of the county, the list isn't exhaustive
+    pub localities: Vec<&'static str>
+}
+
+static COUNTIES: Lazy<Vec<County>> = Lazy::new(|| {
+    let mut counties: Vec<County> = rows(COUNTIES_CSV)
+        .map(|row| County {
+            code: row[0],
+            name: row[1],
+            postal_prefixes: row[2].split(';').collect(),
+            localities: Vec::new()
+        })
+        .collect();
+
+    for row in rows(LOCALITIES_CSV) {
+        if let Some(county) = counties.iter_mut().find(|county| county.code == row[0]) {
+            county.localities.push(row[1]);
+        }
+    }
+
+    counties
+});
+
+/// Every way a county can be written, folded with [`fold`], mapped to it's code
+static COUNTY_LOOKUP: Lazy<HashMap<String, &'static str>> = Lazy::new(|| {
+    let mut lookup = HashMap::new();
+
+    for county in COUNTIES.iter() {
+        lookup.insert(fold(county.code), county.code);
+        lookup.insert(fold(county.name), county.code);
+    }
+
+    lookup.insert(fold("Bucuresti"), "B");
+    lookup.insert(fold("Bucharest"), "B");
+    lookup.insert(fold("Municipiul Bucuresti"), "B");
+
+    lookup
+});
+
+/// The data rows of a bundled CSV file, without the header
+fn rows(csv: &'static str) -> impl Iterator<Item = Vec<&'static str>> {
+    csv.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split(',').map(str::trim).collect())
+}
+
+/// Fold `value` for comparison: lowercase, without diacritics, spaces or punctuation
+fn fold(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'ă' | 'â' => 'a',
+            'î' => 'i',
+            'ș' | 'ş' => 's',
+            'ț' | 'ţ' => 't',
+            other => other
+        })
+        .filter(char::is_ascii_alphanumeric)
+        .collect()
+}
+
+/// Returns every known county
+pub fn counties() -> &'static [County] {
+    &COUNTIES
+}
+
+/// Returns the county with the canonical `code`
+pub fn county(code: &str) -> Option<&'static County> {
+    COUNTIES.iter().find(|county| county.code == code)
+}
+
+/// Returns the canonical code of a county written as a code or a name
+///
+/// Case, diacritics, spaces and dashes don't matter, so `cj`, `Cluj` and `cluj ` are all `CJ`.
+pub fn normalize_county(value: &str) -> Option<&'static str> {
+    COUNTY_LOOKUP.get(&fold(value)).copied()
+}
+
+/// Returns the canonical spelling of a locality of the county with `county_code`,
+/// if it's one of the known localities
+pub fn normalize_locality(county_code: &str, value: &str) -> Option<&'static str> {
+    let folded = fold(value);
+
+    county(county_code)?.localities.iter().find(|locality| fold(locality) == folded).copied()
+}
+
+/// Returns `true` if `postal_code` is a six digit code of the county with `county_code`
+pub fn is_valid_postal_code(county_code: &str, postal_code: &str) -> bool {
+    postal_code.len() == 6
+        && postal_code.chars().all(|c| c.is_ascii_digit())
+        && county(county_code)
+            .is_some_and(|county| county.postal_prefixes.iter().any(|p| postal_code.starts_with(p)))
+}
@@ -1,18 +1,9 @@
-use axum::http::header::AUTHORIZATION;
-use axum::http::HeaderName;
-use axum::routing::{get, post};
 use axum::Router;
-use delivery_backend::error::AppError;
-use delivery_backend::routers;
-use delivery_backend::state::{setup_app_state, AppState};
+use delivery_backend::app::setup_app;
+use delivery_backend::database::{connect_database, mongo_url};
+use delivery_backend::migration::migrate;
 use std::env;
 use std::net::{IpAddr, SocketAddr};
-use tower::ServiceBuilder;
-use tower_http::compression::CompressionLayer;
-use tower_http::propagate_header::PropagateHeaderLayer;
-use tower_http::request_id::{MakeRequestUuid, SetRequestIdLayer};
-use tower_http::sensitive_headers::SetSensitiveRequestHeadersLayer;
-use tower_http::trace::TraceLayer;
 
 /// Run the app
 ///
@@ -42,39 +33,36 @@ async fn run_app(app: Router) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// App setup
+/// Run a maintenance command instead of the app
 ///
-/// Initializes the root router, middlewares and the app state
+/// * `migrate` applies the pending migrations, `migrate --dry-run` only lists them.
 #[tracing::instrument]
-async fn setup_app() -> Result<Router, AppError> {
-    let x_request_id_header = HeaderName::from_static("x-request-id");
+async fn run_command(command: &str, arguments: &[String]) -> anyhow::Result<()> {
+    match command {
+        "migrate" => {
+            let dry_run = arguments.iter().any(|argument| argument == "--dry-run");
+            let database = connect_database(&mongo_url()).await?;
 
-    let middleware_stack = ServiceBuilder::new()
-        .layer(SetSensitiveRequestHeadersLayer::new(std::iter::once(AUTHORIZATION)))
-        .layer(TraceLayer::new_for_http())
-        .layer(CompressionLayer::new())
-        .layer(SetRequestIdLayer::new(x_request_id_header.clone(), MakeRequestUuid))
-        .layer(PropagateHeaderLayer::new(x_request_id_header));
-
-    let app_state = setup_app_state().await?;
-    tracing::info!("Application setup ok");
-
-    let router = Router::<AppState>::new()
-        .route("/search", post(routers::customer_search))
-        .route("/history", get(routers::customer_history))
-        .nest("/customer", routers::customer_router())
-        .nest("/auth", routers::auth_router())
-        .route_layer(middleware_stack)
-        .with_state(app_state);
+            for report in migrate(&database, dry_run).await? {
+                tracing::info!("Migration {report}");
+            }
+        }
+        other => anyhow::bail!("Unknown command {other}")
+    }
 
-    Ok(router)
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    let app = setup_app().await?;
+    let arguments = env::args().skip(1).collect::<Vec<_>>();
+    if let Some((command, arguments)) = arguments.split_first() {
+        return run_command(command, arguments).await;
+    }
+
+    let app = setup_app(&mongo_url()).await?;
 
     run_app(app).await?;
 
@@ -0,0 +1,106 @@
+use crate::database::Database;
+use crate::error::AppError;
+
+use super::Migration;
+
+/// Every [`Migration`], in the order they're applied
+///
+/// New migrations are appended. Once a migration is applied somewhere,
+/// it's name and position must not change.
+pub fn migrations() -> Vec<Box<dyn Migration>> {
+    vec![
+        Box::new(UnwrapSetDocuments),
+        Box::new(SetMissingVersions),
+        Box::new(NormalizeCounties),
+        Box::new(UniqueCustomerIds),
+    ]
+}
+
+/// Repair the customers that were inserted as a `$set` update document
+struct UnwrapSetDocuments;
+
+#[axum::async_trait]
+impl Migration for UnwrapSetDocuments {
+    fn name(&self) -> &'static str {
+        "0001_unwrap_set_documents"
+    }
+
+    fn description(&self) -> &'static str {
+        "Move the fields of customers inserted as a $set document to the top level"
+    }
+
+    async fn apply(&self, database: &Database) -> Result<u64, AppError> {
+        let repaired = database.customer().unwrap_set_documents().await?;
+
+        Ok(repaired.len() as u64)
+    }
+}
+
+/// Store the `version` of customers written before documents were versioned
+struct SetMissingVersions;
+
+#[axum::async_trait]
+impl Migration for SetMissingVersions {
+    fn name(&self) -> &'static str {
+        "0002_set_missing_versions"
+    }
+
+    fn description(&self) -> &'static str {
+        "Set the version of customers without one to 0"
+    }
+
+    async fn apply(&self, database: &Database) -> Result<u64, AppError> {
+        database.customer().set_missing_versions().await
+    }
+}
+
+/// Store the canonical code of counties written before they were normalized
+struct NormalizeCounties;
+
+#[axum::async_trait]
+impl Migration for NormalizeCounties {
+    fn name(&self) -> &'static str {
+        "0003_normalize_counties"
+    }
+
+    fn description(&self) -> &'static str {
+        "Replace the county of customers by it's canonical code"
+    }
+
+    async fn apply(&self, database: &Database) -> Result<u64, AppError> {
+        database.customer().normalize_counties().await
+    }
+}
+
+/// Make `customer_id` unique, once no two customers share one
+///
+/// Customers were never checked for a unique `customer_id` before imports upserted by it.
+/// Which of the customers sharing one is the right one can't be decided here, so they're
+/// reported and the migration fails until they're given distinct ones.
+struct UniqueCustomerIds;
+
+#[axum::async_trait]
+impl Migration for UniqueCustomerIds {
+    fn name(&self) -> &'static str {
+        "0004_unique_customer_ids"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create a unique index on the customer_id of customers"
+    }
+
+    async fn apply(&self, database: &Database) -> Result<u64, AppError> {
+        let duplicates = database.customer().duplicate_customer_ids().await?;
+
+        if !duplicates.is_empty() {
+            return Err(AppError::Conflict(format!(
+                "Customers share the customer_id {}. Give them distinct ones and migrate again",
+                duplicates.join(", ")
+            )));
+        }
+
+        database.customer().create_customer_id_index().await?;
+
+        Ok(0)
+    }
+}
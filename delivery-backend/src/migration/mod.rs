@@ -0,0 +1,187 @@
+mod migrations;
+
+pub use migrations::migrations;
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+use mongodb::bson::oid::ObjectId;
+
+use crate::database::Database;
+use crate::error::AppError;
+
+/// How often to check whether another instance finished migrating
+const LOCK_POLL: Duration = Duration::from_secs(1);
+
+/// How often the lock is renewed while migrating, well within it's time to live
+const LOCK_HEARTBEAT: Duration = Duration::from_secs(60);
+
+/// A change to the stored documents, applied once
+///
+/// Migrations are applied in the order of [`migrations`] and recorded by their `name`
+/// in the `_migrations` collection. They have to be idempotent, since a migration
+/// that fails before it's recorded is applied again in full by the next run.
+#[axum::async_trait]
+pub trait Migration: Send + Sync {
+    /// The unique name of the migration, prefixed by it's position, like `0001_unwrap_set_documents`
+    fn name(&self) -> &'static str;
+
+    /// What the migration changes
+    fn description(&self) -> &'static str;
+
+    /// Apply the migration, returning the number of changed documents
+    async fn apply(&self, database: &Database) -> Result<u64, AppError>;
+}
+
+/// What a run did with a [`Migration`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationStatus {
+    /// Applied by an earlier run
+    AlreadyApplied,
+    /// Not applied yet, reported by a dry run
+    Pending,
+    /// Applied by this run
+    Applied { modified: u64 }
+}
+
+impl std::fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            MigrationStatus::AlreadyApplied => write!(f, "already applied"),
+            MigrationStatus::Pending => write!(f, "pending"),
+            MigrationStatus::Applied { modified } => {
+                write!(f, "applied, {modified} documents changed")
+            }
+        }
+    }
+}
+
+/// The [`MigrationStatus`] of a single [`Migration`] after a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub status: MigrationStatus
+}
+
+impl std::fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.name, self.description, self.status)
+    }
+}
+
+/// Apply the [`migrations`] that weren't applied yet, in order
+///
+/// The lock document in `_migrations` is held while migrating, so instances started
+/// at the same time don't migrate concurrently. It's renewed every [`LOCK_HEARTBEAT`],
+/// so a long migration isn't taken over by another instance, and the run is aborted if it
+/// was anyway. If another instance holds it, it's waited for until every migration is applied,
+/// or until the lock is released or expires and there are still migrations left to apply.
+/// A dry run only reports which migrations are pending, without taking the lock.
+#[tracing::instrument(skip(database))]
+pub async fn migrate(database: &Database, dry_run: bool) -> Result<Vec<MigrationReport>, AppError> {
+    if dry_run {
+        return Ok(without_applying(&database.migration().applied().await?));
+    }
+
+    let owner = ObjectId::new().to_hex();
+    let mut waiting = false;
+
+    loop {
+        match database.migration().lock(&owner).await {
+            Ok(()) => break,
+            Err(AppError::Conflict(message)) => {
+                let applied = database.migration().applied().await?;
+
+                if migrations().iter().all(|migration| applied.contains(migration.name())) {
+                    tracing::info!("Migrations were applied by another instance");
+                    return Ok(without_applying(&applied));
+                }
+
+                if !std::mem::replace(&mut waiting, true) {
+                    tracing::info!("{message}, waiting for the lock");
+                }
+                tokio::time::sleep(LOCK_POLL).await;
+            }
+            Err(error) => return Err(error)
+        }
+    }
+
+    let reports = with_heartbeat(database, &owner, apply_pending(database)).await;
+    database.migration().unlock(&owner).await?;
+
+    reports
+}
+
+/// Run `migrating`, renewing the lock of `owner` every [`LOCK_HEARTBEAT`] until it's done
+///
+/// If the lock was taken over by another instance, `migrating` is dropped and the run fails
+/// with [`AppError::Conflict`], so two instances never migrate at the same time.
+async fn with_heartbeat<F, T>(database: &Database, owner: &str, migrating: F) -> Result<T, AppError>
+where
+    F: Future<Output = Result<T, AppError>>
+{
+    tokio::pin!(migrating);
+    let mut heartbeat = tokio::time::interval(LOCK_HEARTBEAT);
+    // The first tick completes right away, the lock was just taken
+    heartbeat.tick().await;
+
+    loop {
+        tokio::select! {
+            output = &mut migrating => return output,
+            _ = heartbeat.tick() => match database.migration().renew(owner).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::error!("Lost the migration lock while migrating, aborting");
+                    return Err(AppError::Conflict(
+                        "Lost the migration lock to another instance while migrating".into()
+                    ));
+                }
+                Err(error) => tracing::warn!("Failed to renew the migration lock: {error}")
+            }
+        }
+    }
+}
+
+/// Report which migrations are `applied` and which are pending, without applying any
+fn without_applying(applied: &HashSet<String>) -> Vec<MigrationReport> {
+    migrations()
+        .iter()
+        .map(|migration| {
+            let status = if applied.contains(migration.name()) {
+                MigrationStatus::AlreadyApplied
+            } else {
+                MigrationStatus::Pending
+            };
+
+            report(migration.as_ref(), status)
+        })
+        .collect()
+}
+
+/// Apply the migrations that aren't recorded as applied, recording each one right after
+async fn apply_pending(database: &Database) -> Result<Vec<MigrationReport>, AppError> {
+    let applied = database.migration().applied().await?;
+    let mut reports = Vec::new();
+
+    for migration in migrations() {
+        if applied.contains(migration.name()) {
+            reports.push(report(migration.as_ref(), MigrationStatus::AlreadyApplied));
+            continue;
+        }
+
+        tracing::info!("Applying migration {}: {}", migration.name(), migration.description());
+
+        let modified = migration.apply(database).await?;
+        database.migration().record(migration.name(), migration.description(), modified).await?;
+
+        reports.push(report(migration.as_ref(), MigrationStatus::Applied { modified }));
+    }
+
+    Ok(reports)
+}
+
+fn report(migration: &dyn Migration, status: MigrationStatus) -> MigrationReport {
+    MigrationReport { name: migration.name(), description: migration.description(), status }
+}
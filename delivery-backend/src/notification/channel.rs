@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::error::AppError;
+
+/// The ways a customer can be notified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelKind {
+    Email,
+    Sms
+}
+
+impl std::fmt::Display for ChannelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ChannelKind::Email => write!(f, "email"),
+            ChannelKind::Sms => write!(f, "sms")
+        }
+    }
+}
+
+/// A rendered message, ready to be sent over a [`NotificationChannel`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OutgoingMessage {
+    pub channel: ChannelKind,
+    /// Email address or phone number, depending on the `channel`
+    pub to: String,
+    /// Only used by [`ChannelKind::Email`]
+    pub subject: Option<String>,
+    pub body: String
+}
+
+/// Something that can deliver an [`OutgoingMessage`]
+#[axum::async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// The kind of messages this channel delivers
+    fn kind(&self) -> ChannelKind;
+
+    /// Deliver `message`
+    ///
+    /// Failures are reported as [`AppError::NotificationError`], so they can be retried.
+    async fn send(&self, message: &OutgoingMessage) -> Result<(), AppError>;
+}
+
+/// The set of configured [`NotificationChannel`]s, at most one of each [`ChannelKind`]
+#[derive(Clone, Default)]
+pub struct Notifier {
+    channels: Vec<Arc<dyn NotificationChannel>>
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier").field("channels", &self.kinds().collect::<Vec<_>>()).finish()
+    }
+}
+
+impl Notifier {
+    /// Creates a new [`Notifier`] without any channels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set up the channels that are configured in the environment
+    ///
+    /// See [`SmtpChannel::from_env`](super::SmtpChannel::from_env)
+    /// and [`HttpSmsChannel::from_env`](super::HttpSmsChannel::from_env).
+    pub fn from_env() -> Self {
+        let mut notifier = Self::new();
+
+        match super::SmtpChannel::from_env() {
+            Ok(Some(smtp)) => notifier = notifier.with_channel(Arc::new(smtp)),
+            Ok(None) => tracing::info!("SMTP_HOST not set, email notifications are disabled"),
+            Err(err) => tracing::error!("Failed to set up SMTP notifications: {err}")
+        }
+
+        match super::HttpSmsChannel::from_env() {
+            Some(sms) => notifier = notifier.with_channel(Arc::new(sms)),
+            None => tracing::info!("SMS_GATEWAY_URL not set, SMS notifications are disabled")
+        }
+
+        notifier
+    }
+
+    /// Add `channel`, replacing the channel of the same [`ChannelKind`], if there is one
+    pub fn with_channel(mut self, channel: Arc<dyn NotificationChannel>) -> Self {
+        self.channels.retain(|existing| existing.kind() != channel.kind());
+        self.channels.push(channel);
+        self
+    }
+
+    /// The kinds of the configured channels
+    pub fn kinds(&self) -> impl Iterator<Item = ChannelKind> + '_ {
+        self.channels.iter().map(|channel| channel.kind())
+    }
+
+    /// Deliver `message` over the channel of it's kind
+    pub async fn send(&self, message: &OutgoingMessage) -> Result<(), AppError> {
+        match self.channels.iter().find(|channel| channel.kind() == message.channel) {
+            Some(channel) => channel.send(message).await,
+            None => Err(AppError::NotificationError(format!(
+                "No {} channel is configured",
+                message.channel
+            )))
+        }
+    }
+}
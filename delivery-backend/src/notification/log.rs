@@ -0,0 +1,53 @@
+use mongodb::bson::{self, oid::ObjectId};
+
+use super::ChannelKind;
+
+/// Where a queued notification is in it's delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Queued, never attempted
+    Pending,
+    /// Delivered
+    Sent,
+    /// The last attempt failed, it's retried until it runs out of attempts
+    Failed,
+    /// Never delivered, because the personal data of the customer was erased
+    Cancelled
+}
+
+impl std::fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            DeliveryStatus::Pending => write!(f, "pending"),
+            DeliveryStatus::Sent => write!(f, "sent"),
+            DeliveryStatus::Failed => write!(f, "failed"),
+            DeliveryStatus::Cancelled => write!(f, "cancelled")
+        }
+    }
+}
+
+/// An entry of the `notification` collection, the delivery log of reminders
+///
+/// There is at most one entry for every customer, channel, expiration date and offset,
+/// so running the reminder job again never queues the same reminder twice.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NotificationLog {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub customer_id: String,
+    pub channel: ChannelKind,
+    pub to: String,
+    pub subject: Option<String>,
+    pub body: String,
+    /// The `appliance.expiration_date` the reminder is about
+    pub for_expiration: bson::DateTime,
+    /// How many days before the expiration date the reminder was queued for
+    pub offset_days: u32,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: bson::DateTime,
+    pub created_at: bson::DateTime,
+    pub sent_at: Option<bson::DateTime>
+}
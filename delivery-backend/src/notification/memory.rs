@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::AppError;
+
+use super::{ChannelKind, NotificationChannel, OutgoingMessage};
+
+/// A [`NotificationChannel`] that keeps messages in memory instead of sending them
+///
+/// Clones share the same messages, so a clone can be handed to a [`Notifier`](super::Notifier)
+/// and the original inspected afterwards.
+#[derive(Debug, Clone)]
+pub struct InMemoryChannel {
+    kind: ChannelKind,
+    sent: Arc<Mutex<Vec<OutgoingMessage>>>,
+    failures: Arc<AtomicUsize>
+}
+
+impl InMemoryChannel {
+    /// Creates a new [`InMemoryChannel`].
+    pub fn new(kind: ChannelKind) -> Self {
+        Self { kind, sent: Arc::default(), failures: Arc::default() }
+    }
+
+    /// Make the next `count` sends fail
+    pub fn fail_next(&self, count: usize) {
+        self.failures.store(count, Ordering::SeqCst);
+    }
+
+    /// Returns the messages sent so far
+    pub fn sent(&self) -> Vec<OutgoingMessage> {
+        self.sent.lock().expect("sent messages lock is poisoned").clone()
+    }
+}
+
+#[axum::async_trait]
+impl NotificationChannel for InMemoryChannel {
+    fn kind(&self) -> ChannelKind {
+        self.kind
+    }
+
+    async fn send(&self, message: &OutgoingMessage) -> Result<(), AppError> {
+        let failing = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| failures.checked_sub(1))
+            .is_ok();
+
+        if failing {
+            return Err(AppError::NotificationError(format!("Failed to send to {}", message.to)));
+        }
+
+        self.sent.lock().expect("sent messages lock is poisoned").push(message.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+mod channel;
+mod log;
+mod memory;
+mod sms;
+mod smtp;
+mod template;
+
+pub use channel::{ChannelKind, NotificationChannel, Notifier, OutgoingMessage};
+pub use log::{DeliveryStatus, NotificationLog};
+pub use memory::InMemoryChannel;
+pub use sms::HttpSmsChannel;
+pub use smtp::SmtpChannel;
+pub use template::{render_reminder, Language};
@@ -0,0 +1,61 @@
+use std::env;
+
+use crate::error::AppError;
+
+use super::{ChannelKind, NotificationChannel, OutgoingMessage};
+
+/// A [`NotificationChannel`] that sends SMS through a generic HTTP gateway
+///
+/// Every message is a `POST` of `{"to": .., "from": .., "text": ..}` to the gateway url,
+/// authenticated with a bearer token if one is configured.
+/// Any response other than `2xx` counts as a failure.
+#[derive(Debug, Clone)]
+pub struct HttpSmsChannel {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    sender: Option<String>
+}
+
+impl HttpSmsChannel {
+    /// Creates a new [`HttpSmsChannel`].
+    pub fn new(url: String, token: Option<String>, sender: Option<String>) -> Self {
+        Self { client: reqwest::Client::new(), url, token, sender }
+    }
+
+    /// Read the configuration from the environment
+    ///
+    /// `SMS_GATEWAY_URL` enables the channel, `SMS_GATEWAY_TOKEN` and `SMS_SENDER` are optional.
+    pub fn from_env() -> Option<Self> {
+        let url = env::var("SMS_GATEWAY_URL").ok()?;
+
+        Some(Self::new(url, env::var("SMS_GATEWAY_TOKEN").ok(), env::var("SMS_SENDER").ok()))
+    }
+}
+
+#[axum::async_trait]
+impl NotificationChannel for HttpSmsChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Sms
+    }
+
+    async fn send(&self, message: &OutgoingMessage) -> Result<(), AppError> {
+        let mut request = self.client.post(&self.url).json(&serde_json::json!({
+            "to": message.to,
+            "from": self.sender,
+            "text": message.body
+        }));
+
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AppError::NotificationError(err.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use std::env;
+
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::error::AppError;
+
+use super::{ChannelKind, NotificationChannel, OutgoingMessage};
+
+/// A [`NotificationChannel`] that sends emails over SMTP
+#[derive(Debug, Clone)]
+pub struct SmtpChannel {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox
+}
+
+impl SmtpChannel {
+    /// Creates a new [`SmtpChannel`].
+    pub fn new(transport: AsyncSmtpTransport<Tokio1Executor>, from: Mailbox) -> Self {
+        Self { transport, from }
+    }
+
+    /// Read the configuration from the environment
+    ///
+    /// `SMTP_HOST` enables the channel, `SMTP_PORT`, `SMTP_USERNAME` and `SMTP_PASSWORD`
+    /// are optional. `SMTP_FROM` is the sender, e.g. `Delivery SRL <office@example.com>`.
+    /// The connection is upgraded with `STARTTLS`.
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        let Ok(host) = env::var("SMTP_HOST") else {
+            return Ok(None);
+        };
+
+        let invalid = |err: &dyn std::fmt::Display| AppError::NotificationError(err.to_string());
+
+        let mut builder =
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).map_err(|e| invalid(&e))?;
+
+        match env::var("SMTP_PORT").map(|port| port.parse::<u16>()) {
+            Ok(Ok(port)) => builder = builder.port(port),
+            Ok(Err(error)) => {
+                tracing::info!("Failed to parse SMTP_PORT into integer: {error}. Using default")
+            }
+            Err(_) => tracing::info!("SMTP_PORT not set, using default")
+        }
+
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let from = env::var("SMTP_FROM")
+            .map_err(|_| AppError::NotificationError("SMTP_FROM not set".into()))?
+            .parse::<Mailbox>()
+            .map_err(|e| invalid(&e))?;
+
+        Ok(Some(Self::new(builder.build(), from)))
+    }
+}
+
+#[axum::async_trait]
+impl NotificationChannel for SmtpChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Email
+    }
+
+    async fn send(&self, message: &OutgoingMessage) -> Result<(), AppError> {
+        let to = message
+            .to
+            .parse::<Mailbox>()
+            .map_err(|err| AppError::NotificationError(err.to_string()))?;
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject.clone().unwrap_or_default())
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|err| AppError::NotificationError(err.to_string()))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|err| AppError::NotificationError(err.to_string()))?;
+
+        Ok(())
+    }
+}
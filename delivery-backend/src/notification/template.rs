@@ -0,0 +1,92 @@
+use crate::customer::DeliveryCustomerOut;
+
+use super::{ChannelKind, OutgoingMessage};
+
+/// The language reminders are written in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    Ro,
+    En
+}
+
+impl std::str::FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ro" => Ok(Self::Ro),
+            "en" => Ok(Self::En),
+            _ => anyhow::bail!(format!("Cannot create Language from {}", s))
+        }
+    }
+}
+
+/// Render the reminder about the upcoming appliance check of `customer`
+///
+/// Emails get a subject and a longer body, SMS a single short paragraph.
+pub fn render_reminder(
+    language: Language,
+    channel: ChannelKind,
+    customer: &DeliveryCustomerOut,
+    to: String
+) -> OutgoingMessage {
+    let appliance = &customer.appliance;
+    let name = &customer.name;
+    let device = format!("{} {}", appliance.manufacturer, appliance.model);
+    let device = device.trim();
+
+    let (subject, body) = match (language, channel) {
+        (Language::Ro, ChannelKind::Email) => {
+            let date = appliance.expiration_date.format("%d.%m.%Y");
+            (
+                Some(format!("Verificarea aparatului expiră pe {date}")),
+                format!(
+                    "Bună ziua, {name},\n\n\
+                     Vă reamintim că verificarea {operation} pentru aparatul {device} \
+                     expiră pe {date}.\n\
+                     Vă rugăm să ne contactați pentru a programa o nouă verificare.\n\n\
+                     Vă mulțumim,\nDelivery SRL",
+                    operation = appliance.operation_performed
+                )
+            )
+        }
+        (Language::Ro, ChannelKind::Sms) => {
+            let date = appliance.expiration_date.format("%d.%m.%Y");
+            (
+                None,
+                format!(
+                    "Delivery SRL: verificarea aparatului {device} expira pe {date}. \
+                     Sunati-ne pentru programare."
+                )
+            )
+        }
+        (Language::En, ChannelKind::Email) => {
+            let date = appliance.expiration_date.format("%d %B %Y");
+            (
+                Some(format!("Your appliance check expires on {date}")),
+                format!(
+                    "Hello {name},\n\n\
+                     This is a reminder that the {operation} check of your {device} \
+                     expires on {date}.\n\
+                     Please contact us to schedule a new check.\n\n\
+                     Thank you,\nDelivery SRL",
+                    operation = appliance.operation_performed
+                )
+            )
+        }
+        (Language::En, ChannelKind::Sms) => {
+            let date = appliance.expiration_date.format("%d %B %Y");
+            (
+                None,
+                format!(
+                    "Delivery SRL: the check of your {device} expires on {date}. \
+                     Call us to schedule it."
+                )
+            )
+        }
+    };
+
+    OutgoingMessage { channel, to, subject, body }
+}
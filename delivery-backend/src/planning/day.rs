@@ -0,0 +1,51 @@
+use crate::error::AppError;
+use crate::repository::CustomerRepository;
+
+use super::{plan_route, PlannedRoute, RouteRequest, UnplannedReason, UnplannedVisit, Visit};
+
+/// Plan the route of a technician for a day
+///
+/// The candidates are the customers returned by [`CustomerRepository::expired_customers`]
+/// for the expiration filters of the request.
+/// Customers without a `location` can't be planned and are reported as such.
+pub async fn plan_day(
+    customers: &dyn CustomerRepository,
+    request: RouteRequest
+) -> Result<PlannedRoute, AppError> {
+    let mut visits = Vec::new();
+    let mut unlocated = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let page = customers.expired_customers(request.expiration_query(cursor)).await?;
+        cursor = page.next_cursor().map(ToOwned::to_owned);
+
+        for customer in page.into_iter().filter(|customer| request.includes(&customer.customer_id))
+        {
+            let window = request.time_windows.get(&customer.customer_id).copied();
+
+            match customer.location {
+                Some(location) => visits.push(Visit { customer, location, window }),
+                None => unlocated.push(UnplannedVisit {
+                    customer_id: customer.customer_id,
+                    reason: UnplannedReason::NoLocation
+                })
+            }
+        }
+
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    tracing::info!(
+        "Planning a route over {} customers, {} without a location",
+        visits.len(),
+        unlocated.len()
+    );
+
+    let mut route = plan_route(request.depot, visits, &request.options());
+    route.unplanned.extend(unlocated);
+
+    Ok(route)
+}
@@ -0,0 +1,16 @@
+use crate::customer::GeoPoint;
+
+/// Mean radius of the Earth in metres
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// The great circle distance between `from` and `to` in metres
+pub fn haversine_distance(from: &GeoPoint, to: &GeoPoint) -> f64 {
+    let (lat1, lat2) = (from.latitude().to_radians(), to.latitude().to_radians());
+    let delta_lat = lat2 - lat1;
+    let delta_lng = (to.longitude() - from.longitude()).to_radians();
+
+    let a =
+        (delta_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (delta_lng / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
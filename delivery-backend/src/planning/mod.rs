@@ -0,0 +1,11 @@
+mod day;
+mod distance;
+mod planner;
+mod request;
+
+pub use day::plan_day;
+pub use distance::haversine_distance;
+pub use planner::{
+    plan_route, PlannedRoute, PlannedStop, RouteOptions, UnplannedReason, UnplannedVisit, Visit
+};
+pub use request::{RouteRequest, TimeWindow};
@@ -0,0 +1,326 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Duration, FixedOffset};
+
+use crate::customer::{DeliveryCustomerOut, GeoPoint};
+
+use super::{haversine_distance, TimeWindow};
+
+/// How a route is planned
+#[derive(Debug, Clone)]
+pub struct RouteOptions {
+    /// When the technician leaves the depot
+    pub start: DateTime<FixedOffset>,
+    pub max_visits: usize,
+    /// How long a single visit takes
+    pub visit_duration: Duration,
+    /// Average travel speed, applied to straight line distances
+    pub speed_kmh: f64,
+    /// Whether the technician has to get back to the depot at the end of the day
+    pub return_to_depot: bool
+}
+
+/// A customer that could be part of a route
+#[derive(Debug)]
+pub struct Visit {
+    pub customer: DeliveryCustomerOut,
+    pub location: GeoPoint,
+    pub window: Option<TimeWindow>
+}
+
+/// A visit on a planned route
+#[derive(Debug, serde::Serialize)]
+pub struct PlannedStop {
+    /// Position on the route, starting at 1
+    pub order: usize,
+    pub customer: DeliveryCustomerOut,
+    /// Distance from the previous stop in metres
+    pub distance: f64,
+    /// When the technician gets there
+    pub arrival: DateTime<FixedOffset>,
+    /// When the visit starts, after waiting for the time window to open
+    pub visit_start: DateTime<FixedOffset>,
+    pub departure: DateTime<FixedOffset>
+}
+
+/// Why a customer isn't part of a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnplannedReason {
+    /// The customer has no `location`
+    NoLocation,
+    /// The customer's time window can't be met
+    TimeWindow,
+    /// The route already has the maximum number of visits
+    MaxVisits
+}
+
+impl std::fmt::Display for UnplannedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            UnplannedReason::NoLocation => write!(f, "no_location"),
+            UnplannedReason::TimeWindow => write!(f, "time_window"),
+            UnplannedReason::MaxVisits => write!(f, "max_visits")
+        }
+    }
+}
+
+/// A customer left out of a route
+#[derive(Debug, serde::Serialize)]
+pub struct UnplannedVisit {
+    pub customer_id: String,
+    pub reason: UnplannedReason
+}
+
+/// The ordered visits of a technician's day
+#[derive(Debug, serde::Serialize)]
+pub struct PlannedRoute {
+    pub depot: GeoPoint,
+    pub start: DateTime<FixedOffset>,
+    /// When the last visit ends, or the technician is back at the depot
+    pub end: DateTime<FixedOffset>,
+    /// Total travel distance in metres
+    pub total_distance: f64,
+    pub stops: Vec<PlannedStop>,
+    pub unplanned: Vec<UnplannedVisit>
+}
+
+impl IntoResponse for PlannedRoute {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+/// When the visits of a route happen, `(arrival, visit_start, departure)` for every visit
+type Schedule = Vec<(DateTime<FixedOffset>, DateTime<FixedOffset>, DateTime<FixedOffset>)>;
+
+/// Travel time for `distance` metres at the average speed of `options`
+fn travel_time(distance: f64, options: &RouteOptions) -> Duration {
+    Duration::seconds((distance / (options.speed_kmh / 3.6)).round() as i64)
+}
+
+/// The visit can start at `arrival`, or when it's time window opens
+///
+/// Returns [`None`] if the window closed before `arrival`.
+fn visit_start(visit: &Visit, arrival: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    match visit.window {
+        Some(window) if arrival > window.to => None,
+        Some(window) => Some(arrival.max(window.from)),
+        None => Some(arrival)
+    }
+}
+
+/// Returns `true` if the time window of `visit` can be met when going there first
+fn fits_alone(depot: &GeoPoint, visit: &Visit, options: &RouteOptions) -> bool {
+    let arrival = options.start + travel_time(haversine_distance(depot, &visit.location), options);
+    visit_start(visit, arrival).is_some()
+}
+
+/// Schedule the visits in `order`, or [`None`] if a time window can't be met
+fn schedule(
+    depot: &GeoPoint,
+    visits: &[Visit],
+    order: &[usize],
+    options: &RouteOptions
+) -> Option<Schedule> {
+    let mut position = depot;
+    let mut time = options.start;
+    let mut schedule = Vec::with_capacity(order.len());
+
+    for &index in order {
+        let visit = &visits[index];
+        let arrival = time + travel_time(haversine_distance(position, &visit.location), options);
+        let start = visit_start(visit, arrival)?;
+
+        time = start + options.visit_duration;
+        position = &visit.location;
+        schedule.push((arrival, start, time));
+    }
+
+    Some(schedule)
+}
+
+/// Total travel distance of the visits in `order`
+fn route_distance(
+    depot: &GeoPoint,
+    visits: &[Visit],
+    order: &[usize],
+    options: &RouteOptions
+) -> f64 {
+    let mut position = depot;
+    let mut distance = 0.0;
+
+    for &index in order {
+        distance += haversine_distance(position, &visits[index].location);
+        position = &visits[index].location;
+    }
+
+    if options.return_to_depot {
+        distance += haversine_distance(position, depot);
+    }
+
+    distance
+}
+
+/// Build a route by always going to the closest customer that can still be visited
+fn nearest_neighbour(depot: &GeoPoint, visits: &[Visit], options: &RouteOptions) -> Vec<usize> {
+    let mut remaining = (0..visits.len()).collect::<Vec<_>>();
+    let mut order = Vec::new();
+    let mut position = depot;
+    let mut time = options.start;
+
+    while order.len() < options.max_visits {
+        let next = remaining
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, &index)| {
+                let visit = &visits[index];
+                let distance = haversine_distance(position, &visit.location);
+                let start = visit_start(visit, time + travel_time(distance, options))?;
+                Some((slot, distance, start))
+            })
+            .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+
+        let Some((slot, _, start)) = next else {
+            break;
+        };
+
+        let index = remaining.swap_remove(slot);
+        order.push(index);
+        position = &visits[index].location;
+        time = start + options.visit_duration;
+    }
+
+    order
+}
+
+/// Insert the visits that aren't on the route yet where they add the least distance
+///
+/// The nearest neighbour skips a customer whose time window closes before it gets there,
+/// even if going there first would have met it. Visits are inserted one at a time,
+/// as long as the route isn't full and every time window is still met.
+fn insert_remaining(
+    depot: &GeoPoint,
+    visits: &[Visit],
+    order: &mut Vec<usize>,
+    options: &RouteOptions
+) {
+    while order.len() < options.max_visits {
+        let remaining =
+            (0..visits.len()).filter(|index| !order.contains(index)).collect::<Vec<_>>();
+        let mut best: Option<(f64, usize, usize)> = None;
+
+        for index in remaining {
+            for position in 0..=order.len() {
+                order.insert(position, index);
+
+                if schedule(depot, visits, order, options).is_some() {
+                    let distance = route_distance(depot, visits, order, options);
+
+                    match best {
+                        Some((shortest, _, _)) if shortest <= distance => {}
+                        _ => best = Some((distance, index, position))
+                    }
+                }
+
+                order.remove(position);
+            }
+        }
+
+        let Some((_, index, position)) = best else {
+            break;
+        };
+
+        order.insert(position, index);
+    }
+}
+
+/// Shorten the route by reversing parts of it, as long as every time window is still met
+fn two_opt(depot: &GeoPoint, visits: &[Visit], order: &mut [usize], options: &RouteOptions) {
+    let mut best = route_distance(depot, visits, order, options);
+    let mut improved = true;
+
+    while improved {
+        improved = false;
+
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                order[i..=j].reverse();
+
+                let distance = route_distance(depot, visits, order, options);
+
+                if distance + 1e-6 < best && schedule(depot, visits, order, options).is_some() {
+                    best = distance;
+                    improved = true;
+                } else {
+                    order[i..=j].reverse();
+                }
+            }
+        }
+    }
+}
+
+/// Order `visits` into a route starting at `depot`
+///
+/// The route is built with the nearest neighbour heuristic on great circle distances,
+/// the visits it skipped are inserted where they still fit, then it's shortened with 2-opt.
+/// Visits only start within their time window,
+/// if the technician gets there early, they wait.
+/// Customers that don't fit are reported in [`PlannedRoute::unplanned`].
+pub fn plan_route(depot: GeoPoint, visits: Vec<Visit>, options: &RouteOptions) -> PlannedRoute {
+    let mut order = nearest_neighbour(&depot, &visits, options);
+    insert_remaining(&depot, &visits, &mut order, options);
+    two_opt(&depot, &visits, &mut order, options);
+
+    let schedule = schedule(&depot, &visits, &order, options).unwrap_or_default();
+    let total_distance = route_distance(&depot, &visits, &order, options);
+
+    let mut end = schedule.last().map_or(options.start, |(_, _, departure)| *departure);
+    if let (true, Some(&last)) = (options.return_to_depot, order.last()) {
+        end += travel_time(haversine_distance(&visits[last].location, &depot), options);
+    }
+
+    let is_full = order.len() >= options.max_visits;
+
+    let distances = order
+        .iter()
+        .scan(&depot, |position, &index| {
+            let distance = haversine_distance(position, &visits[index].location);
+            *position = &visits[index].location;
+            Some(distance)
+        })
+        .collect::<Vec<_>>();
+
+    let mut visits = visits.into_iter().map(Some).collect::<Vec<_>>();
+    let stops = order
+        .iter()
+        .zip(distances)
+        .zip(schedule)
+        .enumerate()
+        .filter_map(|(position, ((&index, distance), (arrival, visit_start, departure)))| {
+            Some(PlannedStop {
+                order: position + 1,
+                customer: visits[index].take()?.customer,
+                distance,
+                arrival,
+                visit_start,
+                departure
+            })
+        })
+        .collect();
+
+    let unplanned = visits
+        .into_iter()
+        .flatten()
+        .map(|visit| {
+            let reason = if is_full && fits_alone(&depot, &visit, options) {
+                UnplannedReason::MaxVisits
+            } else {
+                UnplannedReason::TimeWindow
+            };
+
+            UnplannedVisit { customer_id: visit.customer.customer_id, reason }
+        })
+        .collect();
+
+    PlannedRoute { depot, start: options.start, end, total_distance, stops, unplanned }
+}
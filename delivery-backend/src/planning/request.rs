@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, FixedOffset};
+use validator::{Validate, ValidationError};
+
+use crate::customer::{validate_location, GeoPoint};
+use crate::query::{ExpirationStatus, ExpiredCustomersQuery};
+
+use super::RouteOptions;
+
+/// When a customer can be visited
+///
+/// The visit has to start between `from` and `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TimeWindow {
+    pub from: DateTime<FixedOffset>,
+    pub to: DateTime<FixedOffset>
+}
+
+/// [`RouteRequest`] describes the day of a technician.
+///
+/// The technician leaves the `depot` at `start` and visits the customers returned
+/// for `status`, `start_date`, `end_date` and `due_within_days`, like for expired customers.
+/// `customer_ids` limits the visits to those customers, `time_windows` are keyed by `customer_id`.
+/// Travel times are estimated from straight line distances at `speed_kmh`, 30 km/h by default.
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+#[validate(schema(function = "validate_time_windows"))]
+pub struct RouteRequest {
+    #[validate(custom = "validate_location")]
+    pub depot: GeoPoint,
+    pub start: DateTime<FixedOffset>,
+    #[serde(default)]
+    pub status: ExpirationStatus,
+    pub start_date: Option<DateTime<FixedOffset>>,
+    pub end_date: Option<DateTime<FixedOffset>>,
+    pub due_within_days: Option<u32>,
+    #[serde(default)]
+    pub customer_ids: Vec<String>,
+    #[serde(default)]
+    pub time_windows: HashMap<String, TimeWindow>,
+    #[validate(range(min = 1, max = 50, message = "max_visits must be between 1 and 50"))]
+    pub max_visits: Option<u32>,
+    #[validate(range(min = 1, max = 480, message = "visit_minutes must be between 1 and 480"))]
+    pub visit_minutes: Option<u32>,
+    #[validate(range(min = 1, max = 130, message = "speed_kmh must be between 1 and 130"))]
+    pub speed_kmh: Option<u32>,
+    #[serde(default = "default_return_to_depot")]
+    pub return_to_depot: bool
+}
+
+fn default_return_to_depot() -> bool {
+    true
+}
+
+/// Every time window has to end after it starts
+fn validate_time_windows(request: &RouteRequest) -> Result<(), ValidationError> {
+    if request.time_windows.values().any(|window| window.to < window.from) {
+        let mut error = ValidationError::new("time_windows");
+        error.message = Some("a time window must not end before it starts".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+impl RouteRequest {
+    /// The [`ExpiredCustomersQuery`] for a page of the customers to visit
+    pub fn expiration_query(&self, cursor: Option<String>) -> ExpiredCustomersQuery {
+        ExpiredCustomersQuery {
+            start_date: self.start_date,
+            end_date: self.end_date,
+            status: self.status,
+            due_within_days: self.due_within_days,
+            limit: Some(500),
+            cursor
+        }
+    }
+
+    /// The [`RouteOptions`] of the request, with the defaults filled in
+    pub fn options(&self) -> RouteOptions {
+        RouteOptions {
+            start: self.start,
+            max_visits: self.max_visits.unwrap_or(8) as usize,
+            visit_duration: Duration::minutes(self.visit_minutes.unwrap_or(45).into()),
+            speed_kmh: self.speed_kmh.unwrap_or(30).into(),
+            return_to_depot: self.return_to_depot
+        }
+    }
+
+    /// Returns `true` if the customer should be part of the route
+    pub fn includes(&self, customer_id: &str) -> bool {
+        self.customer_ids.is_empty() || self.customer_ids.iter().any(|id| id == customer_id)
+    }
+}
@@ -0,0 +1,45 @@
+use chrono::{DateTime, FixedOffset};
+use mongodb::bson::{doc, Document};
+
+use crate::appointment::AppointmentStatus;
+
+/// [`AppointmentQuery`] filters the list of appointments.
+///
+/// Every filter is optional. `from` and `to` match the appointments overlapping that range.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct AppointmentQuery {
+    pub technician: Option<String>,
+    pub customer_id: Option<String>,
+    pub status: Option<AppointmentStatus>,
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>
+}
+
+impl AppointmentQuery {
+    /// Convert [`Self`] into a MongoDB filter [`Document`]
+    pub fn as_filter(&self) -> Document {
+        let mut filter = Document::new();
+
+        if let Some(technician) = &self.technician {
+            filter.insert("technician", technician);
+        }
+
+        if let Some(customer_id) = &self.customer_id {
+            filter.insert("customer_id", customer_id);
+        }
+
+        if let Some(status) = &self.status {
+            filter.insert("status", status.to_string());
+        }
+
+        if let Some(from) = self.from {
+            filter.insert("end", doc! { "$gt": from });
+        }
+
+        if let Some(to) = self.to {
+            filter.insert("start", doc! { "$lt": to });
+        }
+
+        filter
+    }
+}
@@ -0,0 +1,188 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use mongodb::bson::{doc, Bson, Document};
+
+use crate::error::AppError;
+
+/// A single field a list of customers is ordered by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    /// Dotted path of the field in the stored document
+    pub path: String,
+    pub ascending: bool
+}
+
+impl SortKey {
+    /// Creates a new [`SortKey`].
+    pub fn new(path: impl Into<String>, ascending: bool) -> Self {
+        Self { path: path.into(), ascending }
+    }
+}
+
+/// The order of a paginated list of customers
+///
+/// It always ends with `_id`, which makes the order total,
+/// so a [`PageCursor`] can point at exactly one position in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortSpec(Vec<SortKey>);
+
+impl SortSpec {
+    /// Creates a new [`SortSpec`] ordering by `keys`, then by `_id`.
+    pub fn new(mut keys: Vec<SortKey>) -> Self {
+        keys.retain(|key| key.path != "_id");
+        keys.push(SortKey::new("_id", true));
+        Self(keys)
+    }
+
+    /// Parse a comma separated list of field names, each optionally prefixed with `-`
+    /// for descending order, e.g. `expiration_date,-name`
+    ///
+    /// `fields` maps the names clients may use to paths in the stored document.
+    pub fn parse(value: &str, fields: &[(&str, &str)]) -> Result<Self, AppError> {
+        let mut keys = Vec::new();
+
+        for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let (name, ascending) = match name.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (name.strip_prefix('+').unwrap_or(name), true)
+            };
+
+            let path = fields
+                .iter()
+                .find_map(|(field, path)| (*field == name).then_some(*path))
+                .ok_or_else(|| AppError::BadRequest(format!("Cannot sort by {name}")))?;
+
+            keys.push(SortKey::new(path, ascending));
+        }
+
+        Ok(Self::new(keys))
+    }
+
+    /// Returns the keys of this [`SortSpec`].
+    pub fn keys(&self) -> &[SortKey] {
+        &self.0
+    }
+
+    /// The `$sort` specification of this order
+    pub fn as_document(&self) -> Document {
+        self.0
+            .iter()
+            .map(|key| (key.path.clone(), Bson::Int32(if key.ascending { 1 } else { -1 })))
+            .collect()
+    }
+
+    /// A short, stable description of this order, used to tie cursors to it
+    fn fingerprint(&self) -> String {
+        self.0
+            .iter()
+            .map(|key| format!("{}{}", if key.ascending { "" } else { "-" }, key.path))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// An opaque position in a list of customers ordered by a [`SortSpec`]
+///
+/// Holds the values of the sort keys of the last document on a page,
+/// so the next page starts right after it, no matter what was inserted or deleted meanwhile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageCursor {
+    sort: String,
+    values: Vec<Bson>
+}
+
+impl PageCursor {
+    /// The cursor pointing right after `document`, in the order of `sort`
+    pub fn after(document: &Document, sort: &SortSpec) -> Self {
+        let values = sort
+            .keys()
+            .iter()
+            .map(|key| get_path(document, &key.path).unwrap_or(Bson::Null))
+            .collect();
+
+        Self { sort: sort.fingerprint(), values }
+    }
+
+    /// Encode this cursor into an url safe string
+    pub fn encode(&self) -> String {
+        let json = Bson::Array(
+            std::iter::once(Bson::String(self.sort.clone()))
+                .chain(self.values.iter().cloned())
+                .collect()
+        )
+        .into_canonical_extjson();
+
+        URL_SAFE_NO_PAD.encode(json.to_string())
+    }
+
+    /// Decode a cursor produced by [`PageCursor::encode`]
+    pub fn decode(value: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::BadRequest("Invalid cursor".into());
+
+        let bytes = URL_SAFE_NO_PAD.decode(value).map_err(|_| invalid())?;
+        let json = serde_json::from_slice::<serde_json::Value>(&bytes).map_err(|_| invalid())?;
+
+        match Bson::try_from(json).map_err(|_| invalid())? {
+            Bson::Array(mut values) if !values.is_empty() => match values.remove(0) {
+                Bson::String(sort) => Ok(Self { sort, values }),
+                _ => Err(invalid())
+            },
+            _ => Err(invalid())
+        }
+    }
+
+    /// A filter matching the documents that come after this cursor in the order of `sort`
+    ///
+    /// For keys `k1, k2, .., kn` this is `k1 > v1 OR (k1 = v1 AND k2 > v2) OR ...`,
+    /// where `>` is `<` for descending keys.
+    /// Missing and `null` values sort before every other value, see [`after_value`].
+    pub fn keyset_filter(&self, sort: &SortSpec) -> Result<Document, AppError> {
+        if self.sort != sort.fingerprint() || self.values.len() != sort.keys().len() {
+            return Err(AppError::BadRequest("Cursor does not match the requested order".into()));
+        }
+
+        let mut equal_so_far = Document::new();
+        let mut branches = Vec::with_capacity(self.values.len());
+
+        for (key, value) in sort.keys().iter().zip(&self.values) {
+            if let Some(after) = after_value(key, value) {
+                let mut branch = equal_so_far.clone();
+                branch.extend(after);
+                branches.push(Bson::Document(branch));
+            }
+
+            equal_so_far.insert(key.path.clone(), value.clone());
+        }
+
+        Ok(doc! { "$or": branches })
+    }
+}
+
+/// A filter matching the values of `key` that come after `value`
+///
+/// MongoDB sorts missing and `null` values first, but range operators never match them,
+/// so they're handled on their own. [`None`] if nothing comes after `value`.
+fn after_value(key: &SortKey, value: &Bson) -> Option<Document> {
+    let path = key.path.clone();
+
+    match (key.ascending, value) {
+        (true, Bson::Null) => Some(doc! { path: { "$ne": Bson::Null } }),
+        (true, value) => Some(doc! { path: { "$gt": value.clone() } }),
+        (false, Bson::Null) => None,
+        (false, value) => Some(doc! {
+            "$or": [{ &path: { "$lt": value.clone() } }, { &path: Bson::Null }]
+        })
+    }
+}
+
+/// Look up the value at a dotted `path` in `document`
+fn get_path(document: &Document, path: &str) -> Option<Bson> {
+    let mut segments = path.split('.');
+    let mut value = document.get(segments.next()?)?;
+
+    for segment in segments {
+        value = value.as_document()?.get(segment)?;
+    }
+
+    Some(value.clone())
+}
@@ -1,61 +1,131 @@
-use mongodb::bson::{doc, oid::ObjectId, Document};
-use std::ops::Sub;
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use mongodb::bson::{doc, Document};
+
+use crate::error::AppError;
+
+use super::cursor::{PageCursor, SortKey, SortSpec};
+
+/// Which customers an [`ExpiredCustomersQuery`] is looking for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpirationStatus {
+    /// The appliance check has already expired
+    #[default]
+    Expired,
+    /// The appliance check expires in the next `due_within_days` days
+    DueSoon,
+    /// Any expiration date, only limited by `start_date` and `end_date`
+    All
+}
 
 /// [`ExpiredCustomersQuery`] is a used to fetch expired customers falling within that range.
 ///
 /// Users can provide either one or both fields to specify a lower and/or upper bound for the search.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// The results are ordered by `appliance.expiration_date`, then `_id`, and can be paged through
+/// by passing back the `next_cursor` of the previous page as `cursor`.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct ExpiredCustomersQuery {
-    pub start_date: Option<chrono::DateTime<chrono::FixedOffset>>,
-    pub end_date: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub start_date: Option<DateTime<FixedOffset>>,
+    pub end_date: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub status: ExpirationStatus,
+    pub due_within_days: Option<u32>,
     pub limit: Option<u32>,
-    pub last_seen: Option<ObjectId>
+    pub cursor: Option<String>
 }
 
 impl ExpiredCustomersQuery {
+    /// The number of customers on a page
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(50).clamp(1, 500)
+    }
+
+    /// The order of the results
+    pub fn sort_spec() -> SortSpec {
+        SortSpec::new(vec![SortKey::new("appliance.expiration_date", true)])
+    }
+
     /// Convert [`Self`] into an aggregation pipeline
     ///
-    /// If `start_date` and `end_date` are both optional,
-    /// we want to match every document where the `expiration_date`
-    /// is in the last year.
-    pub fn as_aggregation(self) -> Vec<Document> {
-        let mut aggregation = Document::new();
+    /// If `start_date` and `end_date` are both optional, expired customers are the ones
+    /// whose `expiration_date` is in the last year.
+    /// The pipeline fetches one more customer than the `limit`, so the caller can tell
+    /// whether there is a next page.
+    pub fn as_aggregation(&self) -> Result<Vec<Document>, AppError> {
+        self.as_aggregation_at(Utc::now())
+    }
 
-        let limit = doc! { "$limit": self.limit.unwrap_or(50)};
+    /// Same as [`ExpiredCustomersQuery::as_aggregation`], with `now` as the current time
+    pub fn as_aggregation_at(&self, now: DateTime<Utc>) -> Result<Vec<Document>, AppError> {
+        let sort = Self::sort_spec();
 
-        let return_from = self
-            .last_seen
-            .map(|oid| doc! {"$match": {"_id": {"$gt": oid}}});
+        let mut pipeline = vec![doc! { "$match": self.expiration_filter(now) }];
 
-        if let (None, None) = (self.start_date.as_ref(), self.end_date.as_ref()) {
-            let now_minus_one_year = chrono::Local::now().sub(chrono::Duration::days(365));
+        if let Some(cursor) = self.cursor.as_deref().map(PageCursor::decode).transpose()? {
+            pipeline.push(doc! { "$match": cursor.keyset_filter(&sort)? });
+        }
+
+        pipeline.push(doc! { "$sort": sort.as_document() });
+        pipeline.push(doc! { "$limit": i64::from(self.limit()) + 1 });
 
-            aggregation.insert(
-                "$match",
-                doc! { "appliance.expiration_date": { "$gt": now_minus_one_year } }
-            );
+        Ok(pipeline)
+    }
 
-            if let Some(document) = return_from {
-                return vec![document, aggregation, limit];
+    /// Convert [`Self`] into the aggregation pipeline of an export
+    ///
+    /// Exports aren't paged, `limit` and `cursor` are ignored.
+    pub fn as_export_aggregation(&self) -> Vec<Document> {
+        vec![
+            doc! { "$match": self.expiration_filter(Utc::now()) },
+            doc! { "$sort": Self::sort_spec().as_document() },
+        ]
+    }
+
+    /// A filter matching the customers with an `appliance.expiration_date`
+    /// in the range of the requested [`ExpirationStatus`]
+    pub fn expiration_filter(&self, now: DateTime<Utc>) -> Document {
+        doc! { "appliance.expiration_date": self.expiration_range(now) }
+    }
+
+    /// The range of `appliance.expiration_date` matching the requested [`ExpirationStatus`]
+    ///
+    /// The lower bound is inclusive, the upper bound is exclusive.
+    fn expiration_range(&self, now: DateTime<Utc>) -> Document {
+        let start_date = self.start_date.map(|date| date.with_timezone(&Utc));
+        let end_date = self.end_date.map(|date| date.with_timezone(&Utc));
+
+        let (lower, upper) = match self.status {
+            ExpirationStatus::Expired => {
+                let lower = match (start_date, end_date) {
+                    (None, None) => Some(now - Duration::days(365)),
+                    (start_date, _) => start_date
+                };
+                (lower, Some(end_date.map_or(now, |end_date| end_date.min(now))))
             }
-            return vec![aggregation, limit];
-        }
+            ExpirationStatus::DueSoon => {
+                let horizon = now + Duration::days(i64::from(self.due_within_days.unwrap_or(30)));
+                (
+                    Some(start_date.map_or(now, |start_date| start_date.max(now))),
+                    Some(end_date.map_or(horizon, |end_date| end_date.min(horizon)))
+                )
+            }
+            ExpirationStatus::All => (start_date, end_date)
+        };
 
-        let mut inner_doc = Document::new();
+        let mut range = Document::new();
 
-        if let Some(start_date) = self.start_date {
-            inner_doc.insert("$gt", start_date);
+        if let Some(lower) = lower {
+            range.insert("$gte", lower);
         }
 
-        if let Some(end_date) = self.end_date {
-            inner_doc.insert("$lt", end_date);
+        if let Some(upper) = upper {
+            range.insert("$lt", upper);
         }
 
-        aggregation.insert("$match", doc! { "appliance.expiration_date": inner_doc });
-
-        if let Some(document) = return_from {
-            return vec![document, aggregation, limit];
+        if range.is_empty() {
+            range.insert("$exists", true);
         }
-        vec![aggregation, limit]
+
+        range
     }
 }
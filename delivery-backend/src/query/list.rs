@@ -0,0 +1,166 @@
+use chrono::{DateTime, FixedOffset};
+use mongodb::bson::{doc, Document};
+
+use crate::customer::OperationPerformed;
+use crate::error::AppError;
+use crate::locality::{self, normalize_county};
+
+use super::cursor::{PageCursor, SortSpec};
+
+/// Names clients can sort the customer list by, mapped to paths in the stored document
+const SORTABLE_FIELDS: [(&str, &str); 13] = [
+    ("customer_id", "customer_id"),
+    ("name", "name"),
+    ("active", "active"),
+    ("county", "address.county"),
+    ("locality", "address.locality"),
+    ("postal_code", "address.postal_code"),
+    ("street", "address.street"),
+    ("manufacturer", "appliance.manufacturer"),
+    ("type", "appliance.type"),
+    ("operation_performed", "appliance.operation_performed"),
+    ("date", "appliance.date"),
+    ("expiration_date", "appliance.expiration_date"),
+    ("warranty", "appliance.warranty")
+];
+
+/// [`CustomerListQuery`] holds the filters, order and position of a page of customers.
+///
+/// Every filter is optional, text filters are case insensitive.
+/// `county` can be a county code or name.
+/// `sort` is a comma separated list of fields, prefixed with `-` for descending order.
+/// `cursor` is the `next_cursor` of the previous page.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct CustomerListQuery {
+    pub active: Option<bool>,
+    pub county: Option<String>,
+    pub locality: Option<String>,
+    pub street: Option<String>,
+    pub manufacturer: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    pub operation_performed: Option<OperationPerformed>,
+    pub date_from: Option<DateTime<FixedOffset>>,
+    pub date_to: Option<DateTime<FixedOffset>>,
+    pub expiration_date_from: Option<DateTime<FixedOffset>>,
+    pub expiration_date_to: Option<DateTime<FixedOffset>>,
+    pub warranty_from: Option<DateTime<FixedOffset>>,
+    pub warranty_to: Option<DateTime<FixedOffset>>,
+    pub sort: Option<String>,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>
+}
+
+impl CustomerListQuery {
+    /// The number of customers on a page
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(50).clamp(1, 500)
+    }
+
+    /// The order of the list
+    pub fn sort_spec(&self) -> Result<SortSpec, AppError> {
+        SortSpec::parse(self.sort.as_deref().unwrap_or_default(), &SORTABLE_FIELDS)
+    }
+
+    /// The decoded `cursor`, if there is one
+    pub fn page_cursor(&self) -> Result<Option<PageCursor>, AppError> {
+        self.cursor.as_deref().map(PageCursor::decode).transpose()
+    }
+
+    /// Convert the filters of [`Self`] into a MongoDB filter [`Document`]
+    ///
+    /// Doesn't include the position of the page, only which customers are part of the list.
+    pub fn as_filter(&self) -> Document {
+        let mut filter = Document::new();
+
+        if let Some(active) = self.active {
+            filter.insert("active", active);
+        }
+
+        if let Some(county) = &self.county {
+            filter.insert("address.county", county_filter(county));
+        }
+
+        if let Some(locality) = &self.locality {
+            filter.insert("address.locality", exact_case_insensitive(locality));
+        }
+
+        if let Some(street) = &self.street {
+            filter.insert("address.street", contains_case_insensitive(street));
+        }
+
+        if let Some(manufacturer) = &self.manufacturer {
+            filter.insert("appliance.manufacturer", exact_case_insensitive(manufacturer));
+        }
+
+        if let Some(typ) = &self.typ {
+            filter.insert("appliance.type", exact_case_insensitive(typ));
+        }
+
+        if let Some(operation_performed) = &self.operation_performed {
+            filter.insert("appliance.operation_performed", operation_performed.to_string());
+        }
+
+        let date_ranges = [
+            ("appliance.date", self.date_from, self.date_to),
+            ("appliance.expiration_date", self.expiration_date_from, self.expiration_date_to),
+            ("appliance.warranty", self.warranty_from, self.warranty_to)
+        ];
+
+        for (path, from, to) in date_ranges {
+            let mut range = Document::new();
+
+            if let Some(from) = from {
+                range.insert("$gte", from);
+            }
+
+            if let Some(to) = to {
+                range.insert("$lte", to);
+            }
+
+            if !range.is_empty() {
+                filter.insert(path, range);
+            }
+        }
+
+        filter
+    }
+}
+
+/// Escape every character that has a special meaning in a regular expression
+pub fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if "\\^$.|?*+()[]{}/-".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+pub(super) fn exact_case_insensitive(value: &str) -> Document {
+    doc! { "$regex": format!("^{}$", escape_regex(value.trim())), "$options": "i" }
+}
+
+/// Matches the canonical code of `county`, or `county` as written if it's not a known county
+///
+/// Customers stored before counties were normalized may still have the county as it was written,
+/// so a known county also matches it's code, it's name and `county` itself in any case,
+/// until the `0003_normalize_counties` migration has been applied everywhere.
+pub(super) fn county_filter(county: &str) -> Document {
+    let Some(code) = normalize_county(county) else {
+        return exact_case_insensitive(county);
+    };
+
+    let name = locality::county(code).map_or(code, |county| county.name);
+    let spellings = [code, name, county.trim()].map(escape_regex).join("|");
+
+    doc! { "$regex": format!("^(?:{spellings})$"), "$options": "i" }
+}
+
+fn contains_case_insensitive(value: &str) -> Document {
+    doc! { "$regex": escape_regex(value.trim()), "$options": "i" }
+}
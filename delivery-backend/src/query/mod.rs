@@ -1,7 +1,21 @@
+mod appointment;
+mod cursor;
 mod expired;
+mod list;
+mod near;
+mod patch;
+mod precondition;
 mod search;
 mod update;
+mod worklist;
 
-pub use expired::ExpiredCustomersQuery;
+pub use appointment::AppointmentQuery;
+pub use cursor::{PageCursor, SortKey, SortSpec};
+pub use expired::{ExpirationStatus, ExpiredCustomersQuery};
+pub use list::{escape_regex, CustomerListQuery};
+pub use near::NearQuery;
+pub use patch::{CustomerPatch, JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE, PATCH_ATTEMPTS};
+pub use precondition::{version_etag, VersionPrecondition};
 pub use search::SearchQuery;
 pub use update::PartialDeliveryCustomer;
+pub use worklist::WorklistQuery;
@@ -0,0 +1,94 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use mongodb::bson::{doc, Document};
+
+use crate::customer::GeoPoint;
+use crate::error::AppError;
+
+use super::expired::{ExpirationStatus, ExpiredCustomersQuery};
+
+/// [`NearQuery`] looks for customers around a point, e.g. where a technician already is.
+///
+/// `lat` and `lng` are the center, `radius` is in metres, 5 km by default and 100 km at most.
+/// `status`, `start_date`, `end_date` and `due_within_days` work the same way
+/// as on an [`ExpiredCustomersQuery`].
+/// The results are ordered by distance, closest first.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct NearQuery {
+    pub lat: f64,
+    pub lng: f64,
+    pub radius: Option<u32>,
+    #[serde(default)]
+    pub status: ExpirationStatus,
+    pub start_date: Option<DateTime<FixedOffset>>,
+    pub end_date: Option<DateTime<FixedOffset>>,
+    pub due_within_days: Option<u32>,
+    pub limit: Option<u32>
+}
+
+impl NearQuery {
+    /// The center of the search
+    pub fn center(&self) -> Result<GeoPoint, AppError> {
+        let center = GeoPoint::new(self.lat, self.lng);
+
+        if !center.is_valid() {
+            return Err(AppError::BadRequest(format!(
+                "Invalid coordinates: lat={}, lng={}",
+                self.lat, self.lng
+            )));
+        }
+
+        Ok(center)
+    }
+
+    /// The search radius in metres
+    pub fn radius(&self) -> u32 {
+        self.radius.unwrap_or(5_000).clamp(1, 100_000)
+    }
+
+    /// The maximum number of customers returned
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(50).clamp(1, 500)
+    }
+
+    /// The [`ExpiredCustomersQuery`] with the expiration part of [`Self`]
+    pub fn expiration_query(&self) -> ExpiredCustomersQuery {
+        ExpiredCustomersQuery {
+            start_date: self.start_date,
+            end_date: self.end_date,
+            status: self.status,
+            due_within_days: self.due_within_days,
+            ..Default::default()
+        }
+    }
+
+    /// Convert [`Self`] into an aggregation pipeline
+    ///
+    /// `filter` is added to the expiration filter, the distance of every customer
+    /// from the center ends up in a `distance` field.
+    pub fn as_aggregation(&self, filter: Document) -> Result<Vec<Document>, AppError> {
+        self.as_aggregation_at(Utc::now(), filter)
+    }
+
+    /// Same as [`NearQuery::as_aggregation`], with `now` as the current time
+    pub fn as_aggregation_at(
+        &self,
+        now: DateTime<Utc>,
+        filter: Document
+    ) -> Result<Vec<Document>, AppError> {
+        let mut query = self.expiration_query().expiration_filter(now);
+        query.extend(filter);
+
+        Ok(vec![
+            doc! {
+                "$geoNear": {
+                    "near": self.center()?.into_document(),
+                    "distanceField": "distance",
+                    "maxDistance": f64::from(self.radius()),
+                    "spherical": true,
+                    "query": query
+                }
+            },
+            doc! { "$limit": i64::from(self.limit()) },
+        ])
+    }
+}
@@ -0,0 +1,161 @@
+use axum::body::Bytes;
+use axum::extract::FromRequest;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::Request;
+use json_patch::{Patch, PatchOperation};
+use serde_json::Value;
+use validator::Validate;
+
+use crate::customer::{DeliveryCustomerIn, DeliveryCustomerOut};
+use crate::error::AppError;
+
+/// `Content-Type` of a JSON Merge Patch (RFC 7396)
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// `Content-Type` of a JSON Patch (RFC 6902)
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+/// Fields of a customer that can never be changed by a patch
+const IMMUTABLE_FIELDS: [&str; 3] = ["_id", "customer_id", "version"];
+
+/// Fields of a customer a patch is allowed to change
+///
+/// Everything else, like the `reminder` or `certificate` of a [`DeliveryCustomerOut`],
+/// is only written by the service itself.
+const PATCHABLE_FIELDS: [&str; 9] = [
+    "name",
+    "active",
+    "address",
+    "appliance",
+    "phones",
+    "email",
+    "preferred_channel",
+    "consent",
+    "location"
+];
+
+/// How often a patch without a precondition is applied again,
+/// because the customer changed between reading and writing it
+pub const PATCH_ATTEMPTS: usize = 5;
+
+/// A patch against the [`DeliveryCustomerOut`] shape of a customer
+///
+/// Which kind of patch it is, is decided by the `Content-Type` of the request.
+#[derive(Debug)]
+pub enum CustomerPatch {
+    /// `application/merge-patch+json`
+    Merge(Value),
+    /// `application/json-patch+json`
+    Json(Patch)
+}
+
+impl CustomerPatch {
+    /// Apply the patch to `customer`
+    ///
+    /// The patched customer is validated with the same rules as a newly created one.
+    /// Patches that touch any of the immutable fields (`_id`, `customer_id`, `version`),
+    /// or anything else that isn't one of the [`PATCHABLE_FIELDS`], like `reminder`,
+    /// are rejected with [`AppError::UnprocessableEntity`].
+    pub fn apply(&self, customer: &DeliveryCustomerOut) -> Result<DeliveryCustomerIn, AppError> {
+        self.check_touched_fields()?;
+
+        let mut document = serde_json::to_value(customer)
+            .map_err(|err| AppError::UnprocessableEntity(err.to_string()))?;
+
+        match self {
+            CustomerPatch::Merge(patch) => json_patch::merge(&mut document, patch),
+            CustomerPatch::Json(patch) => json_patch::patch(&mut document, patch)
+                .map_err(|err| AppError::UnprocessableEntity(err.to_string()))?
+        }
+
+        let patched: DeliveryCustomerIn = serde_json::from_value(document)
+            .map_err(|err| AppError::UnprocessableEntity(err.to_string()))?;
+
+        patched.validate()?;
+
+        Ok(patched)
+    }
+
+    /// Reject the patch if it would modify anything but the [`PATCHABLE_FIELDS`]
+    ///
+    /// Those changes would otherwise be dropped when the patched customer is read
+    /// as a [`DeliveryCustomerIn`].
+    fn check_touched_fields(&self) -> Result<(), AppError> {
+        let touched = match self {
+            CustomerPatch::Merge(Value::Object(patch)) => {
+                patch.keys().find(|field| !PATCHABLE_FIELDS.contains(&field.as_str())).cloned()
+            }
+            // Anything other than an object replaces the whole document
+            CustomerPatch::Merge(_) => Some(IMMUTABLE_FIELDS[0].to_owned()),
+            CustomerPatch::Json(patch) => patch.0.iter().find_map(|operation| {
+                let paths = match operation {
+                    PatchOperation::Add(op) => vec![op.path.as_str()],
+                    PatchOperation::Remove(op) => vec![op.path.as_str()],
+                    PatchOperation::Replace(op) => vec![op.path.as_str()],
+                    PatchOperation::Move(op) => vec![op.from.as_str(), op.path.as_str()],
+                    PatchOperation::Copy(op) => vec![op.path.as_str()],
+                    PatchOperation::Test(_) => vec![]
+                };
+
+                paths.into_iter().find_map(touched_field)
+            })
+        };
+
+        match touched {
+            Some(field) => {
+                Err(AppError::UnprocessableEntity(format!("Field {field} cannot be modified")))
+            }
+            None => Ok(())
+        }
+    }
+}
+
+/// Returns the top level field a JSON Pointer refers to, if it isn't one of the [`PATCHABLE_FIELDS`]
+///
+/// The root pointer (`""`) refers to the whole document, so it touches the immutable fields too.
+fn touched_field(pointer: &str) -> Option<String> {
+    let top_level = match pointer.strip_prefix('/') {
+        Some(rest) => rest.split('/').next().unwrap_or_default(),
+        None => return Some(IMMUTABLE_FIELDS[0].to_owned())
+    };
+
+    let top_level = top_level.replace("~1", "/").replace("~0", "~");
+
+    (!PATCHABLE_FIELDS.contains(&top_level.as_str())).then_some(top_level)
+}
+
+#[axum::async_trait]
+impl<S, B> FromRequest<S, B> for CustomerPatch
+where
+    Bytes: FromRequest<S, B>,
+    B: Send + 'static,
+    S: Send + Sync
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_lowercase())
+            .unwrap_or_default();
+
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| AppError::BadRequest("Failed to read request body".into()))?;
+
+        match content_type.as_str() {
+            MERGE_PATCH_CONTENT_TYPE => serde_json::from_slice(&body)
+                .map(CustomerPatch::Merge)
+                .map_err(|err| AppError::BadRequest(err.to_string())),
+            JSON_PATCH_CONTENT_TYPE => serde_json::from_slice(&body)
+                .map(CustomerPatch::Json)
+                .map_err(|err| AppError::BadRequest(err.to_string())),
+            other => Err(AppError::UnsupportedMediaType(format!(
+                "Expected {MERGE_PATCH_CONTENT_TYPE} or {JSON_PATCH_CONTENT_TYPE}, got {other:?}"
+            )))
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use axum::headers::{ETag, Error as HeaderError, Header};
+use axum::http::header::IF_MATCH;
+use axum::http::{HeaderName, HeaderValue};
+use mongodb::bson::{doc, Bson, Document};
+
+/// Format a customer document `version` as an `ETag`
+pub fn version_etag(version: i64) -> ETag {
+    format!("\"v{version}\"").parse().expect("version is a valid ETag")
+}
+
+/// An `If-Match` precondition on the `version` of a customer document
+///
+/// Entity tags that weren't produced by [`version_etag`] can never match,
+/// so a request carrying only those will fail the precondition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionPrecondition {
+    /// `If-Match: *`, any existing version is acceptable
+    Any,
+    /// The document has to be at one of these versions
+    OneOf(Vec<i64>)
+}
+
+impl VersionPrecondition {
+    /// Returns `true` if a document at `version` satisfies this precondition
+    pub fn is_satisfied_by(&self, version: i64) -> bool {
+        match self {
+            VersionPrecondition::Any => true,
+            VersionPrecondition::OneOf(versions) => versions.contains(&version)
+        }
+    }
+
+    /// Restrict `filter` to documents that satisfy this precondition
+    pub fn apply_to(&self, filter: &mut Document) {
+        match self {
+            VersionPrecondition::Any => {}
+            VersionPrecondition::OneOf(versions) => {
+                let mut versions = versions.iter().copied().map(Bson::from).collect::<Vec<_>>();
+
+                // Documents written before versioning have no `version` and are reported as `v0`
+                if versions.contains(&Bson::Int64(0)) {
+                    versions.push(Bson::Null);
+                }
+
+                filter.insert("version", doc! { "$in": versions });
+            }
+        }
+    }
+}
+
+impl Header for VersionPrecondition {
+    fn name() -> &'static HeaderName {
+        &IF_MATCH
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, HeaderError>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>
+    {
+        let mut values = values.peekable();
+        let mut versions = Vec::new();
+
+        // Without a header, `Option<TypedHeader<_>>` has to be `None`, not an unmet `OneOf([])`
+        if values.peek().is_none() {
+            return Err(HeaderError::invalid());
+        }
+
+        for value in values {
+            let value = value.to_str().map_err(|_| HeaderError::invalid())?;
+
+            for tag in value.split(',').map(str::trim) {
+                if tag == "*" {
+                    return Ok(Self::Any);
+                }
+
+                // Weak tags never match under the strong comparison `If-Match` requires
+                if let Some(version) = tag
+                    .strip_prefix("\"v")
+                    .and_then(|tag| tag.strip_suffix('"'))
+                    .and_then(|version| version.parse::<i64>().ok())
+                {
+                    versions.push(version);
+                }
+            }
+        }
+
+        Ok(Self::OneOf(versions))
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        let value = match self {
+            VersionPrecondition::Any => "*".to_owned(),
+            VersionPrecondition::OneOf(versions) => versions
+                .iter()
+                .map(|version| format!("\"v{version}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            values.extend(std::iter::once(value))
+        }
+    }
+}
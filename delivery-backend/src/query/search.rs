@@ -1,11 +1,60 @@
 use std::fmt::Display;
 
+use mongodb::bson::{doc, Document};
+
+use crate::customer::normalize_phone;
+
+use super::list::exact_case_insensitive;
+
 /// Used by `MongoDB` full-text search to query the `customer` collection
+///
+/// Queries that look like a phone number or an email address
+/// are looked up in the contact details of the customers instead.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct SearchQuery {
     pub query: String
 }
 
+impl SearchQuery {
+    /// Returns the query as an E.164 phone number, if it's written like one
+    ///
+    /// Only queries starting with `+` or `0` count, so numeric ids are still searched as text.
+    fn as_phone(&self) -> Option<String> {
+        let query = self.query.trim();
+
+        if query.starts_with('+') || query.starts_with('0') {
+            normalize_phone(query)
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if the query is run as a `MongoDB` full-text search
+    pub fn is_full_text(&self) -> bool {
+        self.as_phone().is_none() && !self.query.contains('@')
+    }
+
+    /// Convert [`Self`] into a MongoDB filter [`Document`]
+    pub fn as_filter(&self) -> Document {
+        if let Some(phone) = self.as_phone() {
+            doc! { "phones.number": phone }
+        } else if self.query.contains('@') {
+            doc! { "email": exact_case_insensitive(&self.query) }
+        } else {
+            doc! { "$text": { "$search": &self.query } }
+        }
+    }
+
+    /// The order of the results, best matches first for full-text searches
+    pub fn sort(&self) -> Document {
+        if self.is_full_text() {
+            doc! { "score": { "$meta": "textScore" } }
+        } else {
+            doc! { "_id": 1 }
+        }
+    }
+}
+
 impl Display for SearchQuery {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.query)
@@ -1,19 +1,46 @@
-use crate::appliance_field::ApplianceField;
-use crate::customer::{CustomerStatus, OperationPerformed};
-use mongodb::bson::{doc, Document};
+use crate::customer::{
+    validate_location, ConsentRecordIn, ContactChannel, CustomerStatus, GeoPoint,
+    OperationPerformed, PhoneNumber
+};
+use crate::error::AppError;
+use crate::locality::{self, normalize_county, normalize_locality};
+use chrono::{DateTime, FixedOffset};
+use mongodb::bson::{self, Bson, Document};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use validator::{Validate, ValidationError};
 
 /// Represents a request for searching or update [`DeliveryCustomer`]s
 ///
 /// It's fields are analogous to a flattened [`DeliveryCustomer`],
 /// except that all fields are optional.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+///
+/// Fields that are optional on the [`DeliveryCustomer`] itself, like `postal_code`,
+/// can be cleared by explicitly sending `null`. Cleared `observations` are stored as an empty
+/// string, the same way a new customer without them is.
+/// Counties and localities are normalized the same way as on an [`Address`](crate::customer::Address),
+/// and the fields that are part of the update are validated like the ones of a new customer.
+#[derive(Debug, Validate, serde::Serialize, serde::Deserialize)]
+#[validate(schema(function = "validate_partial_address"))]
+#[validate(schema(function = "validate_partial_dates"))]
 pub struct PartialDeliveryCustomer {
+    #[validate(length(min = 1, message = "customer_id must not be empty"))]
     pub customer_id: String,
+    #[validate(length(min = 1, message = "name must not be empty"))]
     pub name: Option<String>,
     pub status: Option<CustomerStatus>,
     pub county: Option<String>,
+    #[serde(default)]
+    pub locality: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub postal_code: Option<Option<String>>,
     pub street: Option<String>,
     pub number: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub building: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub staircase: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub apartment: Option<Option<String>>,
     pub additional: Option<String>,
     pub manufacturer: Option<String>,
     pub year_of_manufacture: Option<u16>,
@@ -25,97 +52,248 @@ pub struct PartialDeliveryCustomer {
     pub appliance_number: Option<String>,
     pub date: Option<chrono::DateTime<chrono::FixedOffset>>,
     pub expiration_date: Option<chrono::DateTime<chrono::FixedOffset>>,
-    pub observations: Option<String>
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub observations: Option<Option<String>>,
+    #[validate]
+    pub phones: Option<Vec<PhoneNumber>>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    #[validate(email(message = "email must be a valid email address"))]
+    pub email: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub preferred_channel: Option<Option<ContactChannel>>,
+    pub marketing_consent: Option<ConsentRecordIn>,
+    pub notification_consent: Option<ConsentRecordIn>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    #[validate(custom = "validate_location")]
+    pub location: Option<Option<GeoPoint>>
+}
+
+/// Deserializes a field that's present, but possibly `null`, as `Some(value)`
+///
+/// Combined with `#[serde(default)]`, a missing field stays [`None`],
+/// while `null` becomes `Some(None)`.
+fn deserialize_explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// A county has to be known, a postal code has to have six digits
+/// and belong to the county, if that's part of the update too
+fn validate_partial_address(customer: &PartialDeliveryCustomer) -> Result<(), ValidationError> {
+    let county = match &customer.county {
+        Some(county) => match normalize_county(county) {
+            Some(code) => Some(code),
+            None => {
+                let mut error = ValidationError::new("county");
+                error.message = Some("county must be a Romanian county code or name".into());
+                return Err(error);
+            }
+        },
+        None => None
+    };
+
+    if let Some(Some(postal_code)) = &customer.postal_code {
+        let postal_code = postal_code.trim();
+        let is_valid = match county {
+            Some(county) => locality::is_valid_postal_code(county, postal_code),
+            None => postal_code.len() == 6 && postal_code.chars().all(|c| c.is_ascii_digit())
+        };
+
+        if !is_valid {
+            let mut error = ValidationError::new("postal_code");
+            error.message = Some("postal_code must be a postal code of the county".into());
+            return Err(error);
+        }
+    }
+
+    Ok(())
+}
+
+/// The check can't expire before it was performed, if both dates are part of the update
+///
+/// A single date can only be checked against the stored one, which isn't known here.
+fn validate_partial_dates(customer: &PartialDeliveryCustomer) -> Result<(), ValidationError> {
+    if let (Some(date), Some(expiration_date)) = (customer.date, customer.expiration_date) {
+        if expiration_date < date {
+            let mut error = ValidationError::new("expiration_date");
+            error.message = Some("expiration_date must not be before date".into());
+            return Err(error);
+        }
+    }
+
+    Ok(())
 }
 
 impl PartialDeliveryCustomer {
     /// Converts a [`PartialDeliveryCustomer`] into a MongoDB [`Document`]
     ///
-    /// Filters out all fields that are [`None`].
-    pub fn into_update_document_no_none(self) -> Document {
-        let all_some = self.into_iter().filter(|(_, value)| value.is_some());
-        let mut inner_document = Document::default();
-        let mut address_document = Document::default();
-        let mut appliance_document = Document::default();
+    /// Filters out all fields that are [`None`]. Nested fields are addressed with dotted paths,
+    /// (`address.street`, `appliance.type`) so the fields of `address` and `appliance`
+    /// that aren't part of the update are left untouched.
+    /// Fields that were explicitly `null` are removed with `$unset`.
+    pub fn into_update_document_no_none(self) -> Result<Document, AppError> {
+        let fields = bson::to_document(&StoredFields::from(self))?;
+        let mut set_document = Document::default();
+        let mut unset_document = Document::default();
         let mut document = Document::default();
 
-        for (key, value) in all_some {
-            match key.as_str() {
-                "county" | "street" | "number" | "additional" => {
-                    address_document.insert(key, value);
+        for (path, value) in fields {
+            match value {
+                Bson::Null => {
+                    unset_document.insert(path, "");
                 }
-                "manufacturer"
-                | "year_of_manufacture"
-                | "model"
-                | "typ"
-                | "warranty"
-                | "operation_performed"
-                | "appliance_number"
-                | "date"
-                | "expiration_date"
-                | "observations" => {
-                    appliance_document.insert(key, value);
-                }
-                _ => {
-                    inner_document.insert(key, value);
+                value => {
+                    set_document.insert(path, value);
                 }
             }
         }
 
-        inner_document.insert("address", address_document);
-        inner_document.insert("appliance", appliance_document);
-        document.insert("$set", inner_document);
+        if !set_document.is_empty() {
+            document.insert("$set", set_document);
+        }
+
+        if !unset_document.is_empty() {
+            document.insert("$unset", unset_document);
+        }
 
-        document
+        Ok(document)
     }
 }
 
-impl IntoIterator for PartialDeliveryCustomer {
-    type Item = (String, Option<ApplianceField>);
-
-    type IntoIter = std::vec::IntoIter<Self::Item>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        vec![
-            ("customer_id".into(), Some(self.customer_id.into())),
-            ("name".into(), self.name.map(ApplianceField::from)),
-            ("status".into(), self.status.map(ApplianceField::from)),
-            ("county".into(), self.county.map(ApplianceField::from)),
-            ("street".into(), self.street.map(ApplianceField::from)),
-            ("number".into(), self.number.map(ApplianceField::from)),
-            (
-                "additional".into(),
-                self.additional.map(ApplianceField::from)
-            ),
-            (
-                "manufacturer".into(),
-                self.manufacturer.map(ApplianceField::from)
-            ),
-            (
-                "year_of_manufacture".into(),
-                self.year_of_manufacture.map(ApplianceField::from)
-            ),
-            ("model".into(), self.model.map(ApplianceField::from)),
-            ("type".into(), self.typ.map(ApplianceField::from)),
-            ("warranty".into(), self.warranty.map(ApplianceField::from)),
-            (
-                "operation_performed".into(),
-                self.operation_performed.map(ApplianceField::from)
-            ),
-            (
-                "appliance_number".into(),
-                self.appliance_number.map(ApplianceField::from)
-            ),
-            ("date".into(), self.date.map(ApplianceField::from)),
-            (
-                "expiration_date".into(),
-                self.expiration_date.map(ApplianceField::from)
-            ),
-            (
-                "observations".into(),
-                self.observations.map(ApplianceField::from)
-            ),
-        ]
-        .into_iter()
+/// The updatable fields of a [`PartialDeliveryCustomer`], named by their path in the stored document
+///
+/// A field that's [`None`] is left out, one that's `Some(None)` is serialized as `null`,
+/// which means it's cleared. `customer_id` identifies the document, so it's never part of the update.
+/// The year of manufacture is stored as a string, like on an [`ApplianceIn`](crate::customer::ApplianceIn).
+#[derive(serde::Serialize)]
+struct StoredFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active: Option<bool>,
+    #[serde(rename = "address.county", skip_serializing_if = "Option::is_none")]
+    county: Option<String>,
+    #[serde(rename = "address.locality", skip_serializing_if = "Option::is_none")]
+    locality: Option<String>,
+    #[serde(rename = "address.postal_code", skip_serializing_if = "Option::is_none")]
+    postal_code: Option<Option<String>>,
+    #[serde(rename = "address.street", skip_serializing_if = "Option::is_none")]
+    street: Option<String>,
+    #[serde(rename = "address.number", skip_serializing_if = "Option::is_none")]
+    number: Option<String>,
+    #[serde(rename = "address.building", skip_serializing_if = "Option::is_none")]
+    building: Option<Option<String>>,
+    #[serde(rename = "address.staircase", skip_serializing_if = "Option::is_none")]
+    staircase: Option<Option<String>>,
+    #[serde(rename = "address.apartment", skip_serializing_if = "Option::is_none")]
+    apartment: Option<Option<String>>,
+    #[serde(rename = "address.additional", skip_serializing_if = "Option::is_none")]
+    additional: Option<String>,
+    #[serde(rename = "appliance.manufacturer", skip_serializing_if = "Option::is_none")]
+    manufacturer: Option<String>,
+    #[serde(rename = "appliance.year_of_manufacture", skip_serializing_if = "Option::is_none")]
+    year_of_manufacture: Option<String>,
+    #[serde(rename = "appliance.model", skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(rename = "appliance.type", skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(
+        rename = "appliance.warranty",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_bson_datetime"
+    )]
+    warranty: Option<DateTime<FixedOffset>>,
+    #[serde(rename = "appliance.operation_performed", skip_serializing_if = "Option::is_none")]
+    operation_performed: Option<OperationPerformed>,
+    #[serde(rename = "appliance.number", skip_serializing_if = "Option::is_none")]
+    appliance_number: Option<String>,
+    #[serde(
+        rename = "appliance.date",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_bson_datetime"
+    )]
+    date: Option<DateTime<FixedOffset>>,
+    #[serde(
+        rename = "appliance.expiration_date",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_bson_datetime"
+    )]
+    expiration_date: Option<DateTime<FixedOffset>>,
+    #[serde(rename = "appliance.observations", skip_serializing_if = "Option::is_none")]
+    observations: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phones: Option<Vec<PhoneNumber>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preferred_channel: Option<Option<ContactChannel>>,
+    #[serde(rename = "consent.marketing", skip_serializing_if = "Option::is_none")]
+    marketing_consent: Option<ConsentRecordIn>,
+    #[serde(rename = "consent.notifications", skip_serializing_if = "Option::is_none")]
+    notification_consent: Option<ConsentRecordIn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Option<GeoPoint>>
+}
+
+/// Customer documents store the status as the `active` flag,
+/// counties as their code and known localities with their canonical spelling.
+/// Missing `observations` are stored as an empty string, so clearing them sets one.
+impl From<PartialDeliveryCustomer> for StoredFields {
+    fn from(value: PartialDeliveryCustomer) -> Self {
+        let county = value.county.map(|county| match normalize_county(&county) {
+            Some(code) => code.to_owned(),
+            None => county
+        });
+
+        let locality = value.locality.map(|locality| {
+            match county.as_deref().and_then(|county| normalize_locality(county, &locality)) {
+                Some(canonical) => canonical.to_owned(),
+                None => locality.trim().to_owned()
+            }
+        });
+
+        Self {
+            name: value.name,
+            active: value.status.map(|status| matches!(status, CustomerStatus::Active)),
+            county,
+            locality,
+            postal_code: value.postal_code,
+            street: value.street,
+            number: value.number,
+            building: value.building,
+            staircase: value.staircase,
+            apartment: value.apartment,
+            additional: value.additional,
+            manufacturer: value.manufacturer,
+            year_of_manufacture: value.year_of_manufacture.map(|year| year.to_string()),
+            model: value.model,
+            typ: value.typ,
+            warranty: value.warranty,
+            operation_performed: value.operation_performed,
+            appliance_number: value.appliance_number,
+            date: value.date,
+            expiration_date: value.expiration_date,
+            observations: value.observations.map(Option::unwrap_or_default),
+            phones: value.phones,
+            email: value.email,
+            preferred_channel: value.preferred_channel,
+            marketing_consent: value.marketing_consent,
+            notification_consent: value.notification_consent,
+            location: value.location
+        }
     }
 }
+
+/// Serializes a date that's part of the update as a [`bson::DateTime`]
+fn serialize_optional_bson_datetime<S>(
+    datetime: &Option<DateTime<FixedOffset>>,
+    serializer: S
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer
+{
+    datetime.map(bson::DateTime::from_chrono).serialize(serializer)
+}
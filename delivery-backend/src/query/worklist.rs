@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+
+use super::expired::{ExpirationStatus, ExpiredCustomersQuery};
+use super::list::county_filter;
+
+/// [`WorklistQuery`] selects the customers to call about their upcoming appliance check.
+///
+/// `days` is how far ahead to look, 30 days by default.
+/// `county` optionally limits the worklist to a single county, given as a code or name.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct WorklistQuery {
+    pub days: Option<u32>,
+    pub county: Option<String>
+}
+
+impl WorklistQuery {
+    /// The number of days to look ahead
+    pub fn days(&self) -> u32 {
+        self.days.unwrap_or(30).clamp(1, 366)
+    }
+
+    /// Convert [`Self`] into a MongoDB filter [`Document`]
+    pub fn as_filter(&self) -> Document {
+        self.as_filter_at(Utc::now())
+    }
+
+    /// Same as [`WorklistQuery::as_filter`], with `now` as the current time
+    ///
+    /// Matches active customers whose check expires in the next [`WorklistQuery::days`] days,
+    /// and who haven't been contacted about that expiration yet.
+    pub fn as_filter_at(&self, now: DateTime<Utc>) -> Document {
+        let due_soon = ExpiredCustomersQuery {
+            status: ExpirationStatus::DueSoon,
+            due_within_days: Some(self.days()),
+            ..Default::default()
+        };
+
+        let mut filter = due_soon.expiration_filter(now);
+
+        filter.insert("active", true);
+        filter.insert(
+            "$expr",
+            doc! { "$ne": ["$reminder.for_expiration", "$appliance.expiration_date"] }
+        );
+
+        if let Some(county) = &self.county {
+            filter.insert("address.county", county_filter(county));
+        }
+
+        filter
+    }
+
+    /// The order customers are collected in
+    pub fn sort() -> Document {
+        doc! { "appliance.expiration_date": 1, "_id": 1 }
+    }
+}
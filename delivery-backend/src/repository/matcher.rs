@@ -0,0 +1,465 @@
+use std::cmp::Ordering;
+
+use mongodb::bson::{self, Bson, Document};
+use regex::RegexBuilder;
+
+use crate::customer::GeoPoint;
+use crate::error::AppError;
+use crate::planning::haversine_distance;
+
+fn unsupported(operator: &str) -> AppError {
+    AppError::BadRequest(format!("Unsupported query operator {operator}"))
+}
+
+/// Returns `true` if `document` matches the MongoDB query `filter`
+///
+/// Supports the operators the queries of this crate produce: `$and`, `$or`, `$text`,
+/// `$expr` with `$eq` and `$ne`, and `$eq`, `$ne`, `$gt`, `$gte`, `$lt`, `$lte`, `$in`,
+/// `$exists` and `$regex` on fields. Anything else is an error, instead of being ignored.
+pub fn matches(document: &Document, filter: &Document) -> Result<bool, AppError> {
+    for (key, condition) in filter {
+        let matched = match key.as_str() {
+            "$and" => {
+                let mut all = true;
+                for clause in clauses(condition)? {
+                    all = all && matches(document, clause)?;
+                }
+                all
+            }
+            "$or" => {
+                let mut any = false;
+                for clause in clauses(condition)? {
+                    any = any || matches(document, clause)?;
+                }
+                any
+            }
+            "$expr" => expression_matches(document, condition)?,
+            "$text" => {
+                let search = condition
+                    .as_document()
+                    .and_then(|text| text.get_str("$search").ok())
+                    .ok_or_else(|| unsupported("$text"))?;
+                text_score(document, search) > 0
+            }
+            operator if operator.starts_with('$') => return Err(unsupported(operator)),
+            path => field_matches(document, path, condition)?
+        };
+
+        if !matched {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// The filters of an `$and` or `$or`
+fn clauses(condition: &Bson) -> Result<Vec<&Document>, AppError> {
+    let clauses = condition.as_array().ok_or_else(|| unsupported("$and/$or without an array"))?;
+
+    clauses
+        .iter()
+        .map(|clause| clause.as_document().ok_or_else(|| unsupported("$and/$or of a non document")))
+        .collect()
+}
+
+/// The values at a dotted `path`, looking into the documents of arrays along the way
+///
+/// `phones.number` yields the number of every phone, like it does in a MongoDB query.
+fn field_values<'a>(document: &'a Document, path: &str) -> Vec<&'a Bson> {
+    let segments = path.split('.').collect::<Vec<_>>();
+    let mut values = Vec::new();
+
+    if let Some(value) = document.get(segments[0]) {
+        collect_values(value, &segments[1..], &mut values);
+    }
+
+    values
+}
+
+fn collect_values<'a>(value: &'a Bson, path: &[&str], values: &mut Vec<&'a Bson>) {
+    let Some((segment, rest)) = path.split_first() else {
+        values.push(value);
+        return;
+    };
+
+    match value {
+        Bson::Document(document) => {
+            if let Some(value) = document.get(*segment) {
+                collect_values(value, rest, values);
+            }
+        }
+        Bson::Array(items) => {
+            for item in items.iter().filter(|item| matches!(item, Bson::Document(_))) {
+                collect_values(item, path, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The first value at a dotted `path`, without looking into arrays
+fn field_value<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut segments = path.split('.');
+    let mut value = document.get(segments.next()?)?;
+
+    for segment in segments {
+        value = value.as_document()?.get(segment)?;
+    }
+
+    Some(value)
+}
+
+fn field_matches(document: &Document, path: &str, condition: &Bson) -> Result<bool, AppError> {
+    let values = field_values(document, path);
+
+    let operators = match condition {
+        Bson::Document(operators) if operators.keys().all(|key| key.starts_with('$')) => operators,
+        condition => return Ok(equals_any(&values, condition))
+    };
+
+    for (operator, operand) in operators {
+        let matched = match operator.as_str() {
+            "$eq" => equals_any(&values, operand),
+            "$ne" => !equals_any(&values, operand),
+            "$in" => {
+                let candidates = operand.as_array().ok_or_else(|| unsupported("$in"))?;
+                candidates.iter().any(|candidate| equals_any(&values, candidate))
+            }
+            "$exists" => values.is_empty() != operand.as_bool().unwrap_or(true),
+            "$gt" => compares(&values, operand, Ordering::is_gt),
+            "$gte" => compares(&values, operand, Ordering::is_ge),
+            "$lt" => compares(&values, operand, Ordering::is_lt),
+            "$lte" => compares(&values, operand, Ordering::is_le),
+            "$regex" => {
+                let pattern = operand.as_str().ok_or_else(|| unsupported("$regex"))?;
+                let options = operators.get_str("$options").unwrap_or_default();
+                // PCRE allows escaping `/`, which the `regex` crate rejects
+                let regex = RegexBuilder::new(&pattern.replace("\\/", "/"))
+                    .case_insensitive(options.contains('i'))
+                    .build()
+                    .map_err(|error| AppError::BadRequest(format!("Invalid $regex: {error}")))?;
+
+                values.iter().any(|value| value.as_str().is_some_and(|value| regex.is_match(value)))
+            }
+            "$options" => true,
+            operator => return Err(unsupported(operator))
+        };
+
+        if !matched {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// A missing field is equal to `null`, an array is equal to any of it's items
+fn equals_any(values: &[&Bson], target: &Bson) -> bool {
+    if values.is_empty() {
+        return matches!(target, Bson::Null);
+    }
+
+    values.iter().any(|value| {
+        equal(value, target)
+            || matches!(value, Bson::Array(items) if items.iter().any(|item| equal(item, target)))
+    })
+}
+
+/// Range operators only match values of the same type, a missing field counts as `null`
+fn compares(values: &[&Bson], operand: &Bson, accept: fn(Ordering) -> bool) -> bool {
+    if values.is_empty() {
+        return compare(&Bson::Null, operand).is_some_and(accept);
+    }
+
+    values.iter().any(|value| compare(value, operand).is_some_and(accept))
+}
+
+fn equal(a: &Bson, b: &Bson) -> bool {
+    match compare(a, b) {
+        Some(ordering) => ordering.is_eq() && (type_rank(a) != 4 && type_rank(a) != 5 || a == b),
+        None => false
+    }
+}
+
+/// Where a value of a type is in the MongoDB sort order of types
+fn type_rank(value: &Bson) -> u8 {
+    match value {
+        Bson::MinKey => 0,
+        Bson::Null | Bson::Undefined => 1,
+        Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_) => 2,
+        Bson::String(_) | Bson::Symbol(_) => 3,
+        Bson::Document(_) => 4,
+        Bson::Array(_) => 5,
+        Bson::Binary(_) => 6,
+        Bson::ObjectId(_) => 7,
+        Bson::Boolean(_) => 8,
+        Bson::DateTime(_) => 9,
+        Bson::Timestamp(_) => 10,
+        Bson::RegularExpression(_) => 11,
+        Bson::MaxKey => 13,
+        _ => 12
+    }
+}
+
+fn as_number(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(number) => Some(f64::from(*number)),
+        Bson::Int64(number) => Some(*number as f64),
+        Bson::Double(number) => Some(*number),
+        _ => None
+    }
+}
+
+/// Compare two values of the same type, [`None`] if their types differ
+fn compare(a: &Bson, b: &Bson) -> Option<Ordering> {
+    if type_rank(a) != type_rank(b) {
+        return None;
+    }
+
+    let ordering = match (a, b) {
+        (Bson::String(a), Bson::String(b)) => a.cmp(b),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => a.cmp(b),
+        (Bson::Boolean(a), Bson::Boolean(b)) => a.cmp(b),
+        (Bson::DateTime(a), Bson::DateTime(b)) => a.cmp(b),
+        (a, b) => match (as_number(a), as_number(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b)?,
+            _ => Ordering::Equal
+        }
+    };
+
+    Some(ordering)
+}
+
+/// Compare two values like MongoDB sorts them, first by type, then by value
+fn sort_order(a: Option<&Bson>, b: Option<&Bson>) -> Ordering {
+    let (a, b) = (a.unwrap_or(&Bson::Null), b.unwrap_or(&Bson::Null));
+
+    type_rank(a).cmp(&type_rank(b)).then_with(|| compare(a, b).unwrap_or(Ordering::Equal))
+}
+
+/// Compare two documents by a `$sort` specification, like `{ "name": 1, "_id": -1 }`
+pub fn compare_by(a: &Document, b: &Document, sort: &Document) -> Ordering {
+    for (path, direction) in sort {
+        let ordering = sort_order(field_value(a, path), field_value(b, path));
+        let ordering = match as_number(direction) {
+            Some(direction) if direction < 0.0 => ordering.reverse(),
+            _ => ordering
+        };
+
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// Evaluate the `$expr` of a filter, `$eq` and `$ne` of two fields or values
+fn expression_matches(document: &Document, expression: &Bson) -> Result<bool, AppError> {
+    let expression = expression.as_document().ok_or_else(|| unsupported("$expr"))?;
+
+    for (operator, operands) in expression {
+        let operands = match operands.as_array().map(Vec::as_slice) {
+            Some([a, b]) => [a, b].map(|operand| expression_value(document, operand)),
+            _ => return Err(unsupported(operator))
+        };
+
+        let equal = match operands {
+            [None, None] => true,
+            [Some(a), Some(b)] => equal(a, b),
+            _ => false
+        };
+
+        let matched = match operator.as_str() {
+            "$eq" => equal,
+            "$ne" => !equal,
+            operator => return Err(unsupported(operator))
+        };
+
+        if !matched {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// `"$path"` is the value of the field at `path`, anything else is a literal
+fn expression_value<'a>(document: &'a Document, operand: &'a Bson) -> Option<&'a Bson> {
+    match operand {
+        Bson::String(path) if path.starts_with('$') => field_value(document, &path[1..]),
+        literal => Some(literal)
+    }
+}
+
+/// How many of the words of `search` appear in the text fields of `document`
+///
+/// Approximates a MongoDB text index over every field: words are compared case insensitively,
+/// but without the stemming, phrases and negations of a real full-text search.
+pub fn text_score(document: &Document, search: &str) -> usize {
+    let mut words = Vec::new();
+    collect_words(&Bson::Document(document.clone()), &mut words);
+
+    search
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .filter(|term| words.iter().any(|word| word == term))
+        .count()
+}
+
+fn collect_words(value: &Bson, words: &mut Vec<String>) {
+    match value {
+        Bson::String(text) => words.extend(
+            text.split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+        ),
+        Bson::Document(document) => document.values().for_each(|value| collect_words(value, words)),
+        Bson::Array(items) => items.iter().for_each(|item| collect_words(item, words)),
+        _ => {}
+    }
+}
+
+/// Run an aggregation `pipeline` of `$geoNear`, `$match`, `$sort` and `$limit` stages
+/// over `documents`
+pub fn aggregate(
+    mut documents: Vec<Document>,
+    pipeline: &[Document]
+) -> Result<Vec<Document>, AppError> {
+    for stage in pipeline {
+        let Some((name, specification)) = stage.iter().next() else {
+            continue;
+        };
+
+        match (name.as_str(), specification) {
+            ("$geoNear", Bson::Document(near)) => documents = geo_near(documents, near)?,
+            ("$match", Bson::Document(filter)) => {
+                let mut matching = Vec::with_capacity(documents.len());
+                for document in documents {
+                    if matches(&document, filter)? {
+                        matching.push(document);
+                    }
+                }
+                documents = matching;
+            }
+            ("$sort", Bson::Document(sort)) => documents.sort_by(|a, b| compare_by(a, b, sort)),
+            ("$limit", limit) => {
+                let limit = as_number(limit).ok_or_else(|| unsupported("$limit"))?;
+                documents.truncate(limit as usize);
+            }
+            (name, _) => return Err(unsupported(name))
+        }
+    }
+
+    Ok(documents)
+}
+
+/// The `documents` matching the `query` of a `$geoNear` stage within it's `maxDistance`,
+/// closest first, with their distance in metres in the `distanceField`
+///
+/// Like the `2dsphere` index on `location`, only documents with a GeoJSON point there are found.
+/// Distances are great circle distances, as `spherical: true` computes them.
+fn geo_near(documents: Vec<Document>, near: &Document) -> Result<Vec<Document>, AppError> {
+    let center = near
+        .get("near")
+        .and_then(|center| bson::from_bson::<GeoPoint>(center.clone()).ok())
+        .ok_or_else(|| unsupported("$geoNear without a point"))?;
+    let field =
+        near.get_str("distanceField").map_err(|_| unsupported("$geoNear without distanceField"))?;
+    let max_distance = near.get("maxDistance").and_then(as_number).unwrap_or(f64::INFINITY);
+
+    let mut found = Vec::new();
+
+    for mut document in documents {
+        let location = document
+            .get("location")
+            .and_then(|location| bson::from_bson::<GeoPoint>(location.clone()).ok());
+        let Some(location) = location else {
+            continue;
+        };
+
+        if let Ok(query) = near.get_document("query") {
+            if !matches(&document, query)? {
+                continue;
+            }
+        }
+
+        let distance = haversine_distance(&center, &location);
+        if distance <= max_distance {
+            document.insert(field, distance);
+            found.push((distance, document));
+        }
+    }
+
+    found.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    Ok(found.into_iter().map(|(_, document)| document).collect())
+}
+
+/// Apply the `$set`, `$unset` and `$inc` operators of `update` to `document`, like MongoDB would
+///
+/// Dotted paths address nested fields, missing parents are created by `$set` and `$inc`.
+pub fn apply_update(document: &mut Document, update: &Document) -> Result<(), AppError> {
+    for (operator, fields) in update {
+        let fields = fields.as_document().ok_or_else(|| unsupported(operator))?;
+
+        for (path, value) in fields {
+            let (parents, key) = match path.rsplit_once('.') {
+                Some((parents, key)) => (parents.split('.').collect::<Vec<_>>(), key),
+                None => (Vec::new(), path.as_str())
+            };
+
+            if operator == "$unset" {
+                if let Some(parent) = parent_mut(document, &parents, false) {
+                    parent.remove(key);
+                }
+                continue;
+            }
+
+            let parent = parent_mut(document, &parents, true)
+                .ok_or_else(|| AppError::BadRequest(format!("Cannot update {path}")))?;
+
+            match operator.as_str() {
+                "$set" => {
+                    parent.insert(key, value.clone());
+                }
+                "$inc" => {
+                    let incremented = match (parent.get(key), value) {
+                        (None, value) => value.clone(),
+                        (Some(Bson::Int32(a)), Bson::Int32(b)) => Bson::Int32(a + b),
+                        (Some(current), value) => match (current, as_number(value)) {
+                            (Bson::Int64(a), Some(b)) => Bson::Int64(a + b as i64),
+                            (Bson::Int32(a), Some(b)) => Bson::Int64(i64::from(*a) + b as i64),
+                            (Bson::Double(a), Some(b)) => Bson::Double(a + b),
+                            _ => return Err(AppError::BadRequest(format!("Cannot $inc {path}")))
+                        }
+                    };
+                    parent.insert(key, incremented);
+                }
+                operator => return Err(unsupported(operator))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The document holding the last segment of a dotted path, created on the way if `create` is set
+fn parent_mut<'a>(
+    document: &'a mut Document,
+    parents: &[&str],
+    create: bool
+) -> Option<&'a mut Document> {
+    let mut current = document;
+
+    for parent in parents {
+        if create && !current.contains_key(*parent) {
+            current.insert(*parent, Document::new());
+        }
+
+        current = current.get_document_mut(*parent).ok()?;
+    }
+
+    Some(current)
+}
@@ -0,0 +1,991 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use futures::{stream, StreamExt};
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+
+use super::matcher::{aggregate, apply_update, compare_by, matches, text_score};
+use super::{AppointmentRepository, AuditRepository, CounterRepository, CustomerRepository};
+use super::{CustomerStream, NotificationRepository, UserRepository};
+use crate::appointment::{AppointmentIn, AppointmentList, AppointmentOut, AppointmentStatus};
+use crate::appointment::{CancelRequest, RescheduleRequest};
+use crate::audit::AuditEntry;
+use crate::auth::password::gen_password_hash;
+use crate::customer::{
+    Certificate, ContactRecord, CustomerPage, DeliveryCustomerIn, DeliveryCustomerList,
+    DeliveryCustomerOut, GeoPoint, NearbyCustomer, NearbyCustomerList, OperationPerformed,
+    ReminderWorklist
+};
+use crate::database::{customer_page, erasure_update, DeliveryUserOut, UpsertedCustomers};
+use crate::error::AppError;
+use crate::notification::DeliveryStatus;
+use crate::query::{
+    AppointmentQuery, CustomerListQuery, CustomerPatch, ExpiredCustomersQuery, NearQuery,
+    PartialDeliveryCustomer, SearchQuery, VersionPrecondition, WorklistQuery, PATCH_ATTEMPTS
+};
+use crate::responses::{DeleteResultResponse, InsertOneResultResponse, UpdateResultResponse};
+
+/// Matches customers that haven't been soft deleted
+fn not_deleted() -> Document {
+    doc! { "deleted": { "$exists": false } }
+}
+
+/// The documents of an in-memory collection
+///
+/// Queried and updated with the same filter and update documents MongoDB gets,
+/// evaluated by the matcher.
+#[derive(Debug, Default)]
+struct Documents(Mutex<Vec<Document>>);
+
+impl Documents {
+    fn lock(&self) -> MutexGuard<'_, Vec<Document>> {
+        self.0.lock().expect("the in-memory documents are poisoned")
+    }
+
+    /// The documents matching `filter`, in the order of `sort` or in insertion order
+    fn find(&self, filter: &Document, sort: Option<&Document>) -> Result<Vec<Document>, AppError> {
+        let mut found = Vec::new();
+
+        for document in self.lock().iter() {
+            if matches(document, filter)? {
+                found.push(document.clone());
+            }
+        }
+
+        if let Some(sort) = sort {
+            found.sort_by(|a, b| compare_by(a, b, sort));
+        }
+
+        Ok(found)
+    }
+
+    /// Apply `update` to the first document matching `filter`, returning the updated document
+    ///
+    /// The update is applied to a copy, so a failed update leaves the document as it was.
+    fn update_one(
+        &self,
+        filter: &Document,
+        update: &Document
+    ) -> Result<Option<Document>, AppError> {
+        let mut documents = self.lock();
+
+        for document in documents.iter_mut() {
+            if matches(document, filter)? {
+                let mut updated = document.clone();
+                apply_update(&mut updated, update)?;
+                *document = updated.clone();
+
+                return Ok(Some(updated));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Apply `update` to every document matching `filter`, returning how many were changed
+    fn update_many(&self, filter: &Document, update: &Document) -> Result<u64, AppError> {
+        let mut documents = self.lock();
+        let mut modified = 0;
+
+        for document in documents.iter_mut() {
+            if matches(document, filter)? {
+                let mut updated = document.clone();
+                apply_update(&mut updated, update)?;
+
+                if updated != *document {
+                    *document = updated;
+                    modified += 1;
+                }
+            }
+        }
+
+        Ok(modified)
+    }
+}
+
+/// The customers of an export, from the documents that were found
+fn customer_stream(documents: Vec<Document>) -> CustomerStream {
+    stream::iter(documents.into_iter().map(DeliveryCustomerOut::try_from)).boxed()
+}
+
+/// A [`CustomerRepository`] that holds the customers in memory, for tests
+///
+/// Customers are kept as the exact documents the customer collection stores,
+/// and every method builds the same filter, pipeline and update documents it does.
+/// They're evaluated by a small matcher instead of MongoDB, so a handler behaves the same
+/// on both, apart from full-text search, which only matches whole words.
+#[derive(Debug, Default)]
+pub struct InMemoryCustomerRepository {
+    documents: Documents
+}
+
+impl InMemoryCustomerRepository {
+    /// Creates a new, empty [`InMemoryCustomerRepository`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `customers`, stored the way [`CustomerRepository::insert_customer`] stores them
+    pub fn with_customers(self, customers: impl IntoIterator<Item = DeliveryCustomerIn>) -> Self {
+        self.with_documents(customers.into_iter().map(|customer| {
+            customer.into_document().expect("a test customer serializes to a document")
+        }))
+    }
+
+    /// Add raw `documents`, like customers stored before the current document shape
+    pub fn with_documents(self, documents: impl IntoIterator<Item = Document>) -> Self {
+        self.documents().extend(documents);
+        self
+    }
+
+    /// The stored document of the customer with `customer_id`, soft deleted or not
+    pub fn raw_customer(&self, customer_id: &str) -> Option<Document> {
+        self.documents()
+            .iter()
+            .find(|document| document.get_str("customer_id") == Ok(customer_id))
+            .cloned()
+    }
+
+    fn documents(&self) -> MutexGuard<'_, Vec<Document>> {
+        self.documents.lock()
+    }
+
+    fn find(&self, filter: &Document, sort: Option<&Document>) -> Result<Vec<Document>, AppError> {
+        self.documents.find(filter, sort)
+    }
+
+    /// Fetch a single [`DeliveryCustomerOut`] matching `filter`, like the customer collection
+    fn find_customer(&self, mut filter: Document) -> Result<DeliveryCustomerOut, AppError> {
+        filter.extend(not_deleted());
+
+        match self.find(&filter, None)?.into_iter().next() {
+            Some(document) => DeliveryCustomerOut::try_from(document),
+            None => Err(AppError::NotFound(format!("No customer matching {filter}")))
+        }
+    }
+
+    /// Apply `update` to the first document matching `filter`, returning how many matched
+    fn update_one(&self, filter: &Document, update: &Document) -> Result<u64, AppError> {
+        Ok(self.documents.update_one(filter, update)?.map_or(0, |_| 1))
+    }
+
+    /// Apply `update` to the customer with a matching `customer_id`, incrementing it's `version`
+    ///
+    /// Same rules as the customer collection: soft deleted customers are never updated,
+    /// and an unmet [`VersionPrecondition`] is an [`AppError::PreconditionFailed`].
+    fn update_versioned(
+        &self,
+        customer_id: &str,
+        mut update: Document,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<u64, AppError> {
+        let mut filter = doc! { "customer_id": customer_id };
+        filter.extend(not_deleted());
+        if let Some(precondition) = &precondition {
+            precondition.apply_to(&mut filter);
+        }
+
+        update.insert("$inc", doc! { "version": 1 });
+
+        let matched_count = self.update_one(&filter, &update)?;
+
+        if precondition.is_some() && matched_count == 0 {
+            return Err(AppError::PreconditionFailed(format!(
+                "Customer with customer_id={customer_id} is not at the expected version"
+            )));
+        }
+
+        Ok(matched_count)
+    }
+
+    fn into_customers(documents: Vec<Document>) -> Result<Vec<DeliveryCustomerOut>, AppError> {
+        documents.into_iter().map(DeliveryCustomerOut::try_from).collect()
+    }
+
+    /// The documents of the customers matching `search`, best matches first for a full-text one
+    fn search(&self, search: &SearchQuery) -> Result<Vec<Document>, AppError> {
+        let mut filter = search.as_filter();
+        filter.extend(not_deleted());
+
+        if search.is_full_text() {
+            // Sorting by `{ "$meta": "textScore" }` puts the best matches first
+            let mut documents = self.find(&filter, None)?;
+            documents.sort_by_key(|document| Reverse(text_score(document, &search.query)));
+            Ok(documents)
+        } else {
+            self.find(&filter, Some(&search.sort()))
+        }
+    }
+}
+
+/// Every update of the customer collection reports as many modified as matched documents
+fn update_result(matched_count: u64) -> UpdateResultResponse {
+    UpdateResultResponse::new(matched_count, matched_count)
+}
+
+#[axum::async_trait]
+impl CustomerRepository for InMemoryCustomerRepository {
+    async fn get_customer(&self, customer_id: &str) -> Result<DeliveryCustomerOut, AppError> {
+        self.find_customer(doc! { "customer_id": customer_id })
+    }
+
+    async fn get_customer_by_oid(&self, oid: ObjectId) -> Result<DeliveryCustomerOut, AppError> {
+        self.find_customer(doc! { "_id": oid })
+    }
+
+    async fn get_customers(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<DeliveryCustomerList, AppError> {
+        let mut filter = doc! { "customer_id": { "$in": customer_ids } };
+        filter.extend(not_deleted());
+
+        Ok(Self::into_customers(self.find(&filter, None)?)?.into())
+    }
+
+    async fn get_raw_customer(&self, customer_id: &str) -> Result<Option<Document>, AppError> {
+        Ok(self.raw_customer(customer_id))
+    }
+
+    async fn insert_customer(
+        &self,
+        customer: DeliveryCustomerIn
+    ) -> Result<InsertOneResultResponse, AppError> {
+        let id = customer.id;
+        let mut documents = self.documents();
+
+        if documents.iter().any(|document| document.get_object_id("_id") == Ok(id)) {
+            return Err(AppError::Conflict(format!("A document with _id={id} already exists")));
+        }
+
+        // Like the unique index on `customer_id`
+        let customer_id = customer.customer_id.as_str();
+        if documents.iter().any(|document| document.get_str("customer_id") == Ok(customer_id)) {
+            return Err(AppError::Conflict(format!(
+                "A customer with customer_id={customer_id} already exists"
+            )));
+        }
+
+        documents.push(customer.into_document()?);
+
+        Ok(InsertOneResultResponse::new(id))
+    }
+
+    async fn existing_customer_ids(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<HashMap<String, bool>, AppError> {
+        let filter = doc! { "customer_id": { "$in": customer_ids } };
+
+        Ok(self
+            .find(&filter, None)?
+            .into_iter()
+            .filter_map(|document| {
+                let deleted = document.contains_key("deleted");
+                document.get_str("customer_id").ok().map(|id| (id.to_owned(), deleted))
+            })
+            .collect())
+    }
+
+    async fn upsert_customers(
+        &self,
+        new: Vec<DeliveryCustomerIn>,
+        existing: Vec<DeliveryCustomerIn>,
+        paths: &[&str]
+    ) -> Result<UpsertedCustomers, AppError> {
+        let mut upserted = UpsertedCustomers::default();
+
+        for customer in new {
+            let customer_id = customer.customer_id.clone();
+            let mut documents = self.documents();
+
+            // Like `$setOnInsert`, a customer inserted meanwhile is left as it is
+            if documents.iter().all(|document| document.get_str("customer_id") != Ok(&customer_id))
+            {
+                documents.push(customer.into_document()?);
+                upserted.inserted += 1;
+            }
+        }
+
+        for customer in existing {
+            let mut filter = doc! { "customer_id": &customer.customer_id };
+            filter.extend(not_deleted());
+
+            upserted.updated += self.update_one(&filter, &customer.into_update_document(paths)?)?;
+        }
+
+        Ok(upserted)
+    }
+
+    async fn update_customer(
+        &self,
+        customer: PartialDeliveryCustomer,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        let customer_id = customer.customer_id.clone();
+        let update = customer.into_update_document_no_none()?;
+
+        Ok(update_result(self.update_versioned(&customer_id, update, precondition)?))
+    }
+
+    async fn patch_customer(
+        &self,
+        customer_id: &str,
+        patch: CustomerPatch,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<DeliveryCustomerOut, AppError> {
+        for _ in 0..PATCH_ATTEMPTS {
+            let current = self.get_customer(customer_id).await?;
+
+            if precondition.as_ref().is_some_and(|p| !p.is_satisfied_by(current.version)) {
+                return Err(AppError::PreconditionFailed(format!(
+                    "Customer with customer_id={customer_id} is not at the expected version"
+                )));
+            }
+
+            let patched = patch.apply(&current)?;
+
+            // The patch was applied to `current`, so it may only replace that version
+            let updated = self.update_versioned(
+                customer_id,
+                patched.into_replacement_document()?,
+                Some(VersionPrecondition::OneOf(vec![current.version]))
+            );
+
+            match updated {
+                Ok(_) => return self.get_customer(customer_id).await,
+                // Without a precondition from the client, apply the patch to the newer version
+                Err(AppError::PreconditionFailed(_)) if precondition.is_none() => continue,
+                Err(err) => return Err(err)
+            }
+        }
+
+        Err(AppError::Conflict(format!(
+            "Customer with customer_id={customer_id} kept changing while it was patched"
+        )))
+    }
+
+    async fn activate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        let update = doc! { "$set": { "active": true } };
+
+        Ok(update_result(self.update_versioned(&customer_id, update, precondition)?))
+    }
+
+    async fn deactivate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        let update = doc! { "$set": { "active": false } };
+
+        Ok(update_result(self.update_versioned(&customer_id, update, precondition)?))
+    }
+
+    async fn delete_customer(
+        &self,
+        customer_id: String,
+        deleted_by: &str
+    ) -> Result<DeleteResultResponse, AppError> {
+        let update = doc! {
+            "$set": { "deleted": { "at": bson::DateTime::now(), "by": deleted_by } }
+        };
+
+        Ok(DeleteResultResponse::new(self.update_versioned(&customer_id, update, None)?))
+    }
+
+    async fn restore_customer(
+        &self,
+        customer_id: String
+    ) -> Result<UpdateResultResponse, AppError> {
+        let matched_count = self.update_one(
+            &doc! { "customer_id": customer_id, "deleted": { "$exists": true } },
+            &doc! { "$unset": { "deleted": "" }, "$inc": { "version": 1 } }
+        )?;
+
+        Ok(update_result(matched_count))
+    }
+
+    async fn erase_customer(
+        &self,
+        customer_id: &str,
+        erased_by: &str
+    ) -> Result<UpdateResultResponse, AppError> {
+        let matched_count =
+            self.update_one(&doc! { "customer_id": customer_id }, &erasure_update(erased_by))?;
+
+        if matched_count == 0 {
+            return Err(AppError::NotFound(format!("No customer with customer_id={customer_id}")));
+        }
+
+        Ok(update_result(matched_count))
+    }
+
+    async fn list_customers(&self, query: CustomerListQuery) -> Result<CustomerPage, AppError> {
+        let sort = query.sort_spec()?;
+        let limit = query.limit() as usize;
+
+        let mut filter = query.as_filter();
+        filter.extend(not_deleted());
+
+        let total = self.find(&filter, None)?.len() as u64;
+
+        let page_filter = match query.page_cursor()? {
+            Some(cursor) => doc! { "$and": [filter, cursor.keyset_filter(&sort)?] },
+            None => filter
+        };
+
+        let mut documents = self.find(&page_filter, Some(&sort.as_document()))?;
+        documents.truncate(limit + 1);
+
+        let (customers, next_cursor) = customer_page(documents, limit, &sort)?;
+
+        Ok(CustomerPage { customers, next_cursor, total })
+    }
+
+    async fn export_customers(&self, query: CustomerListQuery) -> Result<CustomerStream, AppError> {
+        let mut filter = query.as_filter();
+        filter.extend(not_deleted());
+
+        Ok(customer_stream(self.find(&filter, Some(&query.sort_spec()?.as_document()))?))
+    }
+
+    async fn expired_customers(
+        &self,
+        query: ExpiredCustomersQuery
+    ) -> Result<DeliveryCustomerList, AppError> {
+        let mut pipeline = vec![doc! { "$match": not_deleted() }];
+        pipeline.extend(query.as_aggregation()?);
+
+        let documents = aggregate(self.documents().clone(), &pipeline)?;
+        let (customers, next_cursor) =
+            customer_page(documents, query.limit() as usize, &ExpiredCustomersQuery::sort_spec())?;
+
+        Ok(DeliveryCustomerList::from(customers).with_next_cursor(next_cursor))
+    }
+
+    async fn export_expired_customers(
+        &self,
+        query: ExpiredCustomersQuery
+    ) -> Result<CustomerStream, AppError> {
+        let mut pipeline = vec![doc! { "$match": not_deleted() }];
+        pipeline.extend(query.as_export_aggregation());
+
+        Ok(customer_stream(aggregate(self.documents().clone(), &pipeline)?))
+    }
+
+    async fn customers_near(&self, query: NearQuery) -> Result<NearbyCustomerList, AppError> {
+        let pipeline = query.as_aggregation(not_deleted())?;
+
+        let customers = aggregate(self.documents().clone(), &pipeline)?
+            .into_iter()
+            .map(NearbyCustomer::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(NearbyCustomerList { center: query.center()?, radius: query.radius(), customers })
+    }
+
+    async fn search_customers(
+        &self,
+        search: SearchQuery
+    ) -> Result<DeliveryCustomerList, AppError> {
+        Ok(Self::into_customers(self.search(&search)?)?.into())
+    }
+
+    async fn export_search(&self, search: SearchQuery) -> Result<CustomerStream, AppError> {
+        Ok(customer_stream(self.search(&search)?))
+    }
+
+    async fn reminder_worklist(&self, query: WorklistQuery) -> Result<ReminderWorklist, AppError> {
+        let mut filter = query.as_filter();
+        filter.extend(not_deleted());
+
+        let documents = self.find(&filter, Some(&WorklistQuery::sort()))?;
+
+        Ok(Self::into_customers(documents)?.into_iter().collect())
+    }
+
+    async fn record_contact(
+        &self,
+        customer_id: &str,
+        contact: ContactRecord,
+        contacted_by: &str
+    ) -> Result<UpdateResultResponse, AppError> {
+        let current = self.get_customer(customer_id).await?;
+
+        let reminder =
+            contact.into_reminder_document(current.appliance.expiration_date, contacted_by);
+
+        let matched_count = self.update_versioned(
+            customer_id,
+            doc! { "$set": { "reminder": reminder } },
+            Some(VersionPrecondition::OneOf(vec![current.version]))
+        )?;
+
+        Ok(update_result(matched_count))
+    }
+
+    async fn record_intervention(
+        &self,
+        customer_id: &str,
+        operation_performed: OperationPerformed,
+        date: DateTime<Utc>,
+        expiration_date: DateTime<Utc>,
+        observations: Option<String>
+    ) -> Result<UpdateResultResponse, AppError> {
+        let mut fields = doc! {
+            "appliance.operation_performed": operation_performed.to_string(),
+            "appliance.date": bson::DateTime::from_chrono(date),
+            "appliance.expiration_date": bson::DateTime::from_chrono(expiration_date)
+        };
+
+        if let Some(observations) = observations {
+            fields.insert("appliance.observations", observations);
+        }
+
+        let matched_count = self.update_versioned(customer_id, doc! { "$set": fields }, None)?;
+
+        if matched_count == 0 {
+            return Err(AppError::NotFound(format!("No customer with customer_id={customer_id}")));
+        }
+
+        Ok(update_result(matched_count))
+    }
+
+    async fn set_location(
+        &self,
+        customer_id: &str,
+        location: GeoPoint
+    ) -> Result<UpdateResultResponse, AppError> {
+        let matched_count = self.update_versioned(
+            customer_id,
+            doc! { "$set": { "location": location.into_document() } },
+            None
+        )?;
+
+        if matched_count == 0 {
+            return Err(AppError::NotFound(format!("No customer with customer_id={customer_id}")));
+        }
+
+        Ok(update_result(matched_count))
+    }
+
+    async fn set_certificate(
+        &self,
+        customer_id: &str,
+        certificate: &Certificate
+    ) -> Result<bool, AppError> {
+        let date = bson::DateTime::from_chrono(certificate.date);
+        let mut filter = doc! {
+            "customer_id": customer_id,
+            "appliance.date": date,
+            "certificate.date": { "$ne": date }
+        };
+        filter.extend(not_deleted());
+
+        let update = doc! {
+            "$set": { "certificate": certificate.to_document() },
+            "$inc": { "version": 1 }
+        };
+
+        Ok(self.update_one(&filter, &update)? == 1)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////
+
+/// A user of the [`InMemoryUserRepository`], along with the hash of it's calendar token
+#[derive(Debug)]
+struct StoredUser {
+    user: DeliveryUserOut,
+    calendar_token: Option<String>
+}
+
+/// A [`UserRepository`] that holds the users in memory, for tests
+#[derive(Debug, Default)]
+pub struct InMemoryUserRepository {
+    users: Mutex<Vec<StoredUser>>
+}
+
+impl InMemoryUserRepository {
+    /// Creates a new [`InMemoryUserRepository`] without any users.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a user that logs in with `username` and `password`
+    ///
+    /// The password is hashed, like the ones stored in the `user` collection.
+    pub fn with_user(self, username: &str, password: &str) -> Self {
+        let password = gen_password_hash(password).expect("a password can always be hashed");
+        let user = DeliveryUserOut { id: ObjectId::new(), username: username.to_owned(), password };
+
+        self.users().push(StoredUser { user, calendar_token: None });
+        self
+    }
+
+    fn users(&self) -> MutexGuard<'_, Vec<StoredUser>> {
+        self.users.lock().expect("the in-memory users are poisoned")
+    }
+}
+
+#[axum::async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn get_user(&self, username: &str) -> Result<Option<DeliveryUserOut>, AppError> {
+        let users = self.users();
+
+        Ok(users.iter().find(|stored| stored.user.username == username).map(|s| s.user.clone()))
+    }
+
+    async fn set_calendar_token(&self, username: &str, token_hash: &str) -> Result<(), AppError> {
+        let mut users = self.users();
+
+        match users.iter_mut().find(|stored| stored.user.username == username) {
+            Some(stored) => {
+                stored.calendar_token = Some(token_hash.to_owned());
+                Ok(())
+            }
+            None => Err(AppError::NotFound(format!("No user with username={username}")))
+        }
+    }
+
+    async fn get_user_by_calendar_token(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<DeliveryUserOut>, AppError> {
+        let users = self.users();
+
+        Ok(users
+            .iter()
+            .find(|stored| stored.calendar_token.as_deref() == Some(token_hash))
+            .map(|stored| stored.user.clone()))
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////
+
+/// An [`AppointmentRepository`] that holds the appointments in memory, for tests
+///
+/// Like the appointment collection, a booking is checked against the schedule of the technician
+/// and written in one step, so concurrent bookings can't overlap.
+#[derive(Debug, Default)]
+pub struct InMemoryAppointmentRepository {
+    documents: Documents,
+    /// Held while a technician's schedule is checked and changed
+    schedule: Mutex<()>
+}
+
+impl InMemoryAppointmentRepository {
+    /// Creates a new [`InMemoryAppointmentRepository`] without any appointments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find_appointment(&self, id: ObjectId) -> Result<AppointmentOut, AppError> {
+        match self.documents.find(&doc! { "_id": id }, None)?.into_iter().next() {
+            Some(appointment) => appointment.try_into(),
+            None => Err(AppError::NotFound(format!("No appointment with _id={id}")))
+        }
+    }
+
+    fn lock_schedule(&self) -> MutexGuard<'_, ()> {
+        self.schedule.lock().expect("the in-memory schedule is poisoned")
+    }
+
+    /// Fail with [`AppError::Conflict`] if `technician` already has a scheduled appointment
+    /// overlapping `start` and `end`, other than the one with the `_id` of `except`
+    fn check_conflict(
+        &self,
+        technician: &str,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        except: Option<ObjectId>
+    ) -> Result<(), AppError> {
+        let mut filter = doc! {
+            "technician": technician,
+            "status": AppointmentStatus::Scheduled.to_string(),
+            "start": { "$lt": end },
+            "end": { "$gt": start }
+        };
+
+        if let Some(except) = except {
+            filter.insert("_id", doc! { "$ne": except });
+        }
+
+        match self.documents.find(&filter, None)?.into_iter().next() {
+            Some(conflict) => {
+                let conflict = AppointmentOut::try_from(conflict)?;
+                Err(AppError::Conflict(format!(
+                    "{technician} already has appointment {} from {} to {}",
+                    conflict.id, conflict.start, conflict.end
+                )))
+            }
+            None => Ok(())
+        }
+    }
+
+    /// Apply `update` to the scheduled appointment with a matching `_id`, and `sequence` if given
+    ///
+    /// Fails the same way the appointment collection does, if there's no such appointment.
+    fn update_scheduled(
+        &self,
+        id: ObjectId,
+        sequence: Option<i64>,
+        mut update: Document
+    ) -> Result<AppointmentOut, AppError> {
+        update.insert("$inc", doc! { "sequence": 1 });
+
+        let mut filter = doc! { "_id": id, "status": AppointmentStatus::Scheduled.to_string() };
+        if let Some(sequence) = sequence {
+            filter.insert("sequence", sequence);
+        }
+
+        if let Some(appointment) = self.documents.update_one(&filter, &update)? {
+            return appointment.try_into();
+        }
+
+        let current = self.find_appointment(id)?;
+
+        if current.status == AppointmentStatus::Scheduled {
+            return Err(AppError::Conflict(format!(
+                "Appointment with _id={id} was changed meanwhile, try again"
+            )));
+        }
+
+        Err(AppError::UnprocessableEntity(format!(
+            "Appointment with _id={id} is {}",
+            current.status
+        )))
+    }
+}
+
+#[axum::async_trait]
+impl AppointmentRepository for InMemoryAppointmentRepository {
+    async fn get_appointment(&self, id: ObjectId) -> Result<AppointmentOut, AppError> {
+        self.find_appointment(id)
+    }
+
+    async fn list_appointments(
+        &self,
+        query: AppointmentQuery
+    ) -> Result<AppointmentList, AppError> {
+        let appointments = self
+            .documents
+            .find(&query.as_filter(), Some(&doc! { "start": 1, "_id": 1 }))?
+            .into_iter()
+            .map(AppointmentOut::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(AppointmentList { appointments })
+    }
+
+    async fn create_appointment(
+        &self,
+        appointment: AppointmentIn,
+        created_by: &str
+    ) -> Result<InsertOneResultResponse, AppError> {
+        let _schedule = self.lock_schedule();
+        self.check_conflict(&appointment.technician, appointment.start, appointment.end, None)?;
+
+        let id = ObjectId::new();
+        let mut document = doc! { "_id": id };
+        document.extend(appointment.into_document(created_by));
+        self.documents.lock().push(document);
+
+        Ok(InsertOneResultResponse::new(id))
+    }
+
+    async fn reschedule_appointment(
+        &self,
+        id: ObjectId,
+        request: RescheduleRequest
+    ) -> Result<AppointmentOut, AppError> {
+        let current = self.find_appointment(id)?;
+        let technician = request.technician.unwrap_or(current.technician);
+
+        let _schedule = self.lock_schedule();
+        self.check_conflict(&technician, request.start, request.end, Some(id))?;
+
+        self.update_scheduled(
+            id,
+            Some(current.sequence),
+            doc! {
+                "$set": {
+                    "technician": technician,
+                    "start": bson::DateTime::from_chrono(request.start),
+                    "end": bson::DateTime::from_chrono(request.end)
+                }
+            }
+        )
+    }
+
+    async fn cancel_appointment(
+        &self,
+        id: ObjectId,
+        request: CancelRequest,
+        cancelled_by: &str
+    ) -> Result<AppointmentOut, AppError> {
+        self.update_scheduled(
+            id,
+            None,
+            doc! {
+                "$set": {
+                    "status": AppointmentStatus::Cancelled.to_string(),
+                    "cancellation": {
+                        "reason": request.reason,
+                        "at": bson::DateTime::now(),
+                        "by": cancelled_by
+                    }
+                }
+            }
+        )
+    }
+
+    async fn mark_completed(
+        &self,
+        id: ObjectId,
+        completion: Document
+    ) -> Result<AppointmentOut, AppError> {
+        self.update_scheduled(
+            id,
+            None,
+            doc! {
+                "$set": {
+                    "status": AppointmentStatus::Completed.to_string(),
+                    "completion": completion
+                }
+            }
+        )
+    }
+
+    async fn revert_completion(&self, id: ObjectId) -> Result<(), AppError> {
+        self.documents.update_one(
+            &doc! { "_id": id, "status": AppointmentStatus::Completed.to_string() },
+            &doc! {
+                "$set": { "status": AppointmentStatus::Scheduled.to_string() },
+                "$unset": { "completion": "" },
+                "$inc": { "sequence": 1 }
+            }
+        )?;
+
+        Ok(())
+    }
+
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        self.documents.find(&doc! { "customer_id": customer_id }, Some(&doc! { "start": 1 }))
+    }
+
+    async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError> {
+        self.documents.update_many(
+            &doc! { "customer_id": customer_id, "cancellation": { "$exists": true } },
+            &doc! { "$set": { "cancellation.reason": null } }
+        )?;
+
+        self.documents
+            .update_many(&doc! { "customer_id": customer_id }, &doc! { "$set": { "notes": null } })
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////
+
+/// A [`CounterRepository`] that holds the counters in memory, for tests
+#[derive(Debug, Default)]
+pub struct InMemoryCounterRepository {
+    counters: Mutex<HashMap<String, i64>>
+}
+
+impl InMemoryCounterRepository {
+    /// Creates a new [`InMemoryCounterRepository`], every counter starts at 1.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[axum::async_trait]
+impl CounterRepository for InMemoryCounterRepository {
+    async fn next(&self, name: &str) -> Result<i64, AppError> {
+        let mut counters = self.counters.lock().expect("the in-memory counters are poisoned");
+        let value = counters.entry(name.to_owned()).or_default();
+        *value += 1;
+
+        Ok(*value)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////
+
+/// An [`AuditRepository`] that holds the audit trail in memory, for tests
+#[derive(Debug, Default)]
+pub struct InMemoryAuditRepository {
+    documents: Documents
+}
+
+impl InMemoryAuditRepository {
+    /// Creates a new, empty [`InMemoryAuditRepository`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[axum::async_trait]
+impl AuditRepository for InMemoryAuditRepository {
+    async fn record(&self, entry: AuditEntry) -> Result<(), AppError> {
+        self.documents.lock().push(bson::to_document(&entry)?);
+
+        Ok(())
+    }
+
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        self.documents.find(&doc! { "customer_id": customer_id }, Some(&doc! { "at": 1 }))
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////
+
+/// A [`NotificationRepository`] that holds the delivery log in memory, for tests
+#[derive(Debug, Default)]
+pub struct InMemoryNotificationRepository {
+    documents: Documents
+}
+
+impl InMemoryNotificationRepository {
+    /// Creates a new, empty [`InMemoryNotificationRepository`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add entries of the delivery log, stored the way the notification collection stores them
+    pub fn with_documents(self, documents: impl IntoIterator<Item = Document>) -> Self {
+        self.documents.lock().extend(documents);
+        self
+    }
+}
+
+#[axum::async_trait]
+impl NotificationRepository for InMemoryNotificationRepository {
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        self.documents.find(&doc! { "customer_id": customer_id }, Some(&doc! { "created_at": 1 }))
+    }
+
+    async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError> {
+        self.documents.update_many(
+            &doc! {
+                "customer_id": customer_id,
+                "status": {
+                    "$in": [DeliveryStatus::Pending.to_string(), DeliveryStatus::Failed.to_string()]
+                }
+            },
+            &doc! { "$set": { "status": DeliveryStatus::Cancelled.to_string() } }
+        )?;
+
+        self.documents.update_many(
+            &doc! { "customer_id": customer_id },
+            &doc! { "$set": { "to": "", "subject": null, "body": "", "last_error": null } }
+        )
+    }
+}
@@ -0,0 +1,359 @@
+mod matcher;
+mod memory;
+mod mongo;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use mongodb::bson::{oid::ObjectId, Document};
+
+use crate::appointment::{AppointmentIn, AppointmentList, AppointmentOut};
+use crate::appointment::{CancelRequest, RescheduleRequest};
+use crate::audit::AuditEntry;
+use crate::auth::password::verify_password;
+use crate::customer::{
+    Certificate, ContactRecord, CustomerPage, DeliveryCustomerIn, DeliveryCustomerList,
+    DeliveryCustomerOut, GeoPoint, NearbyCustomerList, OperationPerformed, ReminderWorklist
+};
+use crate::database::{Database, DeliveryUserOut, UpsertedCustomers};
+use crate::error::AppError;
+use crate::query::{
+    AppointmentQuery, CustomerListQuery, CustomerPatch, ExpiredCustomersQuery, NearQuery,
+    PartialDeliveryCustomer, SearchQuery, VersionPrecondition, WorklistQuery
+};
+use crate::responses::{DeleteResultResponse, InsertOneResultResponse, UpdateResultResponse};
+use crate::user::JsonEncodedUser;
+
+pub use memory::{
+    InMemoryAppointmentRepository, InMemoryAuditRepository, InMemoryCounterRepository,
+    InMemoryCustomerRepository, InMemoryNotificationRepository, InMemoryUserRepository
+};
+
+/// The customers of an export, yielded one by one as they're read
+pub type CustomerStream = BoxStream<'static, Result<DeliveryCustomerOut, AppError>>;
+
+/// Every repository the handlers go through
+///
+/// [`Repositories::mongo`] are the MongoDB collections, [`Repositories::in_memory`]
+/// hold everything in memory, for tests.
+#[derive(Clone, Debug)]
+pub struct Repositories {
+    pub customers: Arc<dyn CustomerRepository>,
+    pub users: Arc<dyn UserRepository>,
+    pub appointments: Arc<dyn AppointmentRepository>,
+    pub counters: Arc<dyn CounterRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub notifications: Arc<dyn NotificationRepository>
+}
+
+impl Repositories {
+    /// The collections of `database`
+    pub fn mongo(database: &Database) -> Self {
+        Self {
+            customers: Arc::new(database.customer()),
+            users: Arc::new(database.user()),
+            appointments: Arc::new(database.appointment()),
+            counters: Arc::new(database.counter()),
+            audit: Arc::new(database.audit()),
+            notifications: Arc::new(database.notification())
+        }
+    }
+
+    /// In-memory repositories over `customers` and `users`, without anything else stored
+    pub fn in_memory(customers: InMemoryCustomerRepository, users: InMemoryUserRepository) -> Self {
+        Self {
+            customers: Arc::new(customers),
+            users: Arc::new(users),
+            appointments: Arc::new(InMemoryAppointmentRepository::new()),
+            counters: Arc::new(InMemoryCounterRepository::new()),
+            audit: Arc::new(InMemoryAuditRepository::new()),
+            notifications: Arc::new(InMemoryNotificationRepository::new())
+        }
+    }
+}
+
+/// Where the customers served by the handlers are stored
+///
+/// Implemented by [`CustomerCollection`](crate::database::CustomerCollection) over MongoDB
+/// and by [`InMemoryCustomerRepository`], which evaluates the same queries over a `Vec`.
+#[axum::async_trait]
+pub trait CustomerRepository: Send + Sync + std::fmt::Debug {
+    /// Fetch a customer by it's `customer_id`
+    async fn get_customer(&self, customer_id: &str) -> Result<DeliveryCustomerOut, AppError>;
+
+    /// Fetch a customer by it's `ObjectId`
+    async fn get_customer_by_oid(&self, oid: ObjectId) -> Result<DeliveryCustomerOut, AppError>;
+
+    /// Fetch the customers with any of the `customer_ids`, skipping the ids without one
+    async fn get_customers(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<DeliveryCustomerList, AppError>;
+
+    /// Fetch the stored document of a customer, soft deleted or not
+    async fn get_raw_customer(&self, customer_id: &str) -> Result<Option<Document>, AppError>;
+
+    /// Store a new customer
+    ///
+    /// Fails with [`AppError::Conflict`] if it's `_id` or `customer_id` is already taken.
+    async fn insert_customer(
+        &self,
+        customer: DeliveryCustomerIn
+    ) -> Result<InsertOneResultResponse, AppError>;
+
+    /// Find which of the `customer_ids` are taken, and whether that customer was soft deleted
+    async fn existing_customer_ids(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<HashMap<String, bool>, AppError>;
+
+    /// Insert the `new` customers and overwrite the fields at `paths` of the `existing` ones
+    async fn upsert_customers(
+        &self,
+        new: Vec<DeliveryCustomerIn>,
+        existing: Vec<DeliveryCustomerIn>,
+        paths: &[&str]
+    ) -> Result<UpsertedCustomers, AppError>;
+
+    /// Overwrite the fields of a customer that are set in `customer`
+    async fn update_customer(
+        &self,
+        customer: PartialDeliveryCustomer,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Apply a [`CustomerPatch`] to a customer, returning the patched customer
+    async fn patch_customer(
+        &self,
+        customer_id: &str,
+        patch: CustomerPatch,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<DeliveryCustomerOut, AppError>;
+
+    /// Mark a customer as active
+    async fn activate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Mark a customer as inactive
+    async fn deactivate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Soft delete a customer
+    async fn delete_customer(
+        &self,
+        customer_id: String,
+        deleted_by: &str
+    ) -> Result<DeleteResultResponse, AppError>;
+
+    /// Restore a soft deleted customer
+    async fn restore_customer(&self, customer_id: String)
+        -> Result<UpdateResultResponse, AppError>;
+
+    /// Anonymize the personal data of a customer, soft deleted or not
+    async fn erase_customer(
+        &self,
+        customer_id: &str,
+        erased_by: &str
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Fetch a page of customers
+    async fn list_customers(&self, query: CustomerListQuery) -> Result<CustomerPage, AppError>;
+
+    /// Every customer of a list, in the order of the list, to export them
+    async fn export_customers(&self, query: CustomerListQuery) -> Result<CustomerStream, AppError>;
+
+    /// Fetch a page of expired customers
+    async fn expired_customers(
+        &self,
+        query: ExpiredCustomersQuery
+    ) -> Result<DeliveryCustomerList, AppError>;
+
+    /// Every expired customer, in the order of the pages, to export them
+    async fn export_expired_customers(
+        &self,
+        query: ExpiredCustomersQuery
+    ) -> Result<CustomerStream, AppError>;
+
+    /// Fetch the located customers around a point, closest first
+    async fn customers_near(&self, query: NearQuery) -> Result<NearbyCustomerList, AppError>;
+
+    /// Search customers by text, phone number or email address
+    async fn search_customers(&self, search: SearchQuery)
+        -> Result<DeliveryCustomerList, AppError>;
+
+    /// Every result of a search, to export them
+    async fn export_search(&self, search: SearchQuery) -> Result<CustomerStream, AppError>;
+
+    /// Fetch the customers to call about their upcoming appliance check
+    async fn reminder_worklist(&self, query: WorklistQuery) -> Result<ReminderWorklist, AppError>;
+
+    /// Record the outcome of contacting a customer
+    async fn record_contact(
+        &self,
+        customer_id: &str,
+        contact: ContactRecord,
+        contacted_by: &str
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Record an intervention on the appliance of a customer
+    async fn record_intervention(
+        &self,
+        customer_id: &str,
+        operation_performed: OperationPerformed,
+        date: DateTime<Utc>,
+        expiration_date: DateTime<Utc>,
+        observations: Option<String>
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Store the coordinates of a customer's address
+    async fn set_location(
+        &self,
+        customer_id: &str,
+        location: GeoPoint
+    ) -> Result<UpdateResultResponse, AppError>;
+
+    /// Store the [`Certificate`] issued for the current visit of a customer
+    ///
+    /// Returns `false` if the visit changed or already got a certificate meanwhile.
+    async fn set_certificate(
+        &self,
+        customer_id: &str,
+        certificate: &Certificate
+    ) -> Result<bool, AppError>;
+}
+
+/// Where the users that log in are stored
+///
+/// Implemented by [`UserCollection`](crate::database::UserCollection) over MongoDB
+/// and by [`InMemoryUserRepository`].
+#[axum::async_trait]
+pub trait UserRepository: Send + Sync + std::fmt::Debug {
+    /// Fetch a user by it's username
+    async fn get_user(&self, username: &str) -> Result<Option<DeliveryUserOut>, AppError>;
+
+    /// Store the hash of a new calendar token on the user with `username`
+    async fn set_calendar_token(&self, username: &str, token_hash: &str) -> Result<(), AppError>;
+
+    /// Fetch the user a calendar token belongs to, by the hash of the token
+    async fn get_user_by_calendar_token(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<DeliveryUserOut>, AppError>;
+
+    /// Checks whether the user trying to log in has typed their password in correctly
+    async fn validate_user_password(&self, user: &JsonEncodedUser) -> Result<bool, AppError> {
+        let maybe_valid = self.get_user(&user.username).await?.is_some_and(|delivery_user_out| {
+            match verify_password(&user.password, &delivery_user_out.password) {
+                Ok(maybe_valid) => maybe_valid,
+                Err(err) => {
+                    tracing::error!("{}", err);
+                    false
+                }
+            }
+        });
+
+        Ok(maybe_valid)
+    }
+}
+
+/// Where the appointments of the technicians are stored
+///
+/// Implemented by [`AppointmentCollection`](crate::database::AppointmentCollection) over MongoDB
+/// and by [`InMemoryAppointmentRepository`].
+#[axum::async_trait]
+pub trait AppointmentRepository: Send + Sync + std::fmt::Debug {
+    /// Fetch an appointment by it's `_id`
+    async fn get_appointment(&self, id: ObjectId) -> Result<AppointmentOut, AppError>;
+
+    /// Fetch the appointments matching an [`AppointmentQuery`], ordered by their start
+    async fn list_appointments(&self, query: AppointmentQuery)
+        -> Result<AppointmentList, AppError>;
+
+    /// Schedule a new appointment
+    ///
+    /// Fails with [`AppError::Conflict`] if the technician is already booked for that slot.
+    async fn create_appointment(
+        &self,
+        appointment: AppointmentIn,
+        created_by: &str
+    ) -> Result<InsertOneResultResponse, AppError>;
+
+    /// Move a scheduled appointment to another slot, and maybe another technician
+    ///
+    /// Fails with [`AppError::Conflict`] if the technician is already booked for the new slot.
+    async fn reschedule_appointment(
+        &self,
+        id: ObjectId,
+        request: RescheduleRequest
+    ) -> Result<AppointmentOut, AppError>;
+
+    /// Cancel a scheduled appointment
+    async fn cancel_appointment(
+        &self,
+        id: ObjectId,
+        request: CancelRequest,
+        cancelled_by: &str
+    ) -> Result<AppointmentOut, AppError>;
+
+    /// Mark a scheduled appointment as completed, with the intervention done during it
+    async fn mark_completed(
+        &self,
+        id: ObjectId,
+        completion: Document
+    ) -> Result<AppointmentOut, AppError>;
+
+    /// Undo [`AppointmentRepository::mark_completed`]
+    async fn revert_completion(&self, id: ObjectId) -> Result<(), AppError>;
+
+    /// Fetch the stored appointments of a customer, oldest first
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError>;
+
+    /// Drop the free text of the appointments of a customer, returning how many there were
+    async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError>;
+}
+
+/// Where the named sequences, like the certificate numbers, are stored
+///
+/// Implemented by [`CounterCollection`](crate::database::CounterCollection) over MongoDB
+/// and by [`InMemoryCounterRepository`].
+#[axum::async_trait]
+pub trait CounterRepository: Send + Sync + std::fmt::Debug {
+    /// Increment the counter called `name` and return it's new value, starting at 1
+    async fn next(&self, name: &str) -> Result<i64, AppError>;
+}
+
+/// Where the audit trail of the customers is stored
+///
+/// Implemented by [`AuditCollection`](crate::database::AuditCollection) over MongoDB
+/// and by [`InMemoryAuditRepository`].
+#[axum::async_trait]
+pub trait AuditRepository: Send + Sync + std::fmt::Debug {
+    /// Append an [`AuditEntry`] to the audit trail
+    async fn record(&self, entry: AuditEntry) -> Result<(), AppError>;
+
+    /// Fetch the audit trail of a customer, oldest first
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError>;
+}
+
+/// Where the delivery log of the reminders is read and erased by the handlers
+///
+/// Implemented by [`NotificationCollection`](crate::database::NotificationCollection)
+/// over MongoDB and by [`InMemoryNotificationRepository`].
+/// The reminder job queues and delivers the notifications on the collection itself.
+#[axum::async_trait]
+pub trait NotificationRepository: Send + Sync + std::fmt::Debug {
+    /// Fetch the delivery log of a customer, oldest first
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError>;
+
+    /// Remove the personal data from the delivery log of a customer,
+    /// returning the number of anonymized entries
+    async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError>;
+}
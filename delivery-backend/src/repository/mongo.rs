@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
+use mongodb::bson::{oid::ObjectId, Document};
+use mongodb::Cursor;
+
+use super::{AppointmentRepository, AuditRepository, CounterRepository, CustomerRepository};
+use super::{CustomerStream, NotificationRepository, UserRepository};
+use crate::appointment::{AppointmentIn, AppointmentList, AppointmentOut};
+use crate::appointment::{CancelRequest, RescheduleRequest};
+use crate::audit::AuditEntry;
+use crate::customer::{
+    Certificate, ContactRecord, CustomerPage, DeliveryCustomerIn, DeliveryCustomerList,
+    DeliveryCustomerOut, GeoPoint, NearbyCustomerList, OperationPerformed, ReminderWorklist
+};
+use crate::database::{
+    AppointmentCollection, AuditCollection, CounterCollection, CustomerCollection, DeliveryUserOut,
+    NotificationCollection, UpsertedCustomers, UserCollection
+};
+use crate::error::AppError;
+use crate::query::{
+    AppointmentQuery, CustomerListQuery, CustomerPatch, ExpiredCustomersQuery, NearQuery,
+    PartialDeliveryCustomer, SearchQuery, VersionPrecondition, WorklistQuery
+};
+use crate::responses::{DeleteResultResponse, InsertOneResultResponse, UpdateResultResponse};
+
+/// The customers of `cursor`, read as the stream is polled
+fn customer_stream(cursor: Cursor<DeliveryCustomerOut>) -> CustomerStream {
+    cursor.map_err(AppError::from).boxed()
+}
+
+#[axum::async_trait]
+impl CustomerRepository for CustomerCollection {
+    async fn get_customer(&self, customer_id: &str) -> Result<DeliveryCustomerOut, AppError> {
+        CustomerCollection::get_customer(self, customer_id).await
+    }
+
+    async fn get_customer_by_oid(&self, oid: ObjectId) -> Result<DeliveryCustomerOut, AppError> {
+        CustomerCollection::get_customer_by_oid(self, oid).await
+    }
+
+    async fn get_customers(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<DeliveryCustomerList, AppError> {
+        CustomerCollection::get_customers(self, customer_ids).await
+    }
+
+    async fn get_raw_customer(&self, customer_id: &str) -> Result<Option<Document>, AppError> {
+        CustomerCollection::get_raw_customer(self, customer_id).await
+    }
+
+    async fn insert_customer(
+        &self,
+        customer: DeliveryCustomerIn
+    ) -> Result<InsertOneResultResponse, AppError> {
+        CustomerCollection::insert_customer(self, customer).await
+    }
+
+    async fn existing_customer_ids(
+        &self,
+        customer_ids: &[String]
+    ) -> Result<HashMap<String, bool>, AppError> {
+        CustomerCollection::existing_customer_ids(self, customer_ids).await
+    }
+
+    async fn upsert_customers(
+        &self,
+        new: Vec<DeliveryCustomerIn>,
+        existing: Vec<DeliveryCustomerIn>,
+        paths: &[&str]
+    ) -> Result<UpsertedCustomers, AppError> {
+        CustomerCollection::upsert_customers(self, new, existing, paths).await
+    }
+
+    async fn update_customer(
+        &self,
+        customer: PartialDeliveryCustomer,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::update_customer(self, customer, precondition).await
+    }
+
+    async fn patch_customer(
+        &self,
+        customer_id: &str,
+        patch: CustomerPatch,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<DeliveryCustomerOut, AppError> {
+        CustomerCollection::patch_customer(self, customer_id, patch, precondition).await
+    }
+
+    async fn activate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::activate_customer(self, customer_id, precondition).await
+    }
+
+    async fn deactivate_customer(
+        &self,
+        customer_id: String,
+        precondition: Option<VersionPrecondition>
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::deactivate_customer(self, customer_id, precondition).await
+    }
+
+    async fn delete_customer(
+        &self,
+        customer_id: String,
+        deleted_by: &str
+    ) -> Result<DeleteResultResponse, AppError> {
+        CustomerCollection::delete_customer(self, customer_id, deleted_by).await
+    }
+
+    async fn restore_customer(
+        &self,
+        customer_id: String
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::restore_customer(self, customer_id).await
+    }
+
+    async fn erase_customer(
+        &self,
+        customer_id: &str,
+        erased_by: &str
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::erase_customer(self, customer_id, erased_by).await
+    }
+
+    async fn list_customers(&self, query: CustomerListQuery) -> Result<CustomerPage, AppError> {
+        CustomerCollection::list_customers(self, query).await
+    }
+
+    async fn export_customers(&self, query: CustomerListQuery) -> Result<CustomerStream, AppError> {
+        Ok(customer_stream(CustomerCollection::export_customers(self, query).await?))
+    }
+
+    async fn expired_customers(
+        &self,
+        query: ExpiredCustomersQuery
+    ) -> Result<DeliveryCustomerList, AppError> {
+        CustomerCollection::expired_customers(self, query).await
+    }
+
+    async fn export_expired_customers(
+        &self,
+        query: ExpiredCustomersQuery
+    ) -> Result<CustomerStream, AppError> {
+        Ok(customer_stream(CustomerCollection::export_expired_customers(self, query).await?))
+    }
+
+    async fn customers_near(&self, query: NearQuery) -> Result<NearbyCustomerList, AppError> {
+        CustomerCollection::customers_near(self, query).await
+    }
+
+    async fn search_customers(
+        &self,
+        search: SearchQuery
+    ) -> Result<DeliveryCustomerList, AppError> {
+        CustomerCollection::search_customers(self, search).await
+    }
+
+    async fn export_search(&self, search: SearchQuery) -> Result<CustomerStream, AppError> {
+        Ok(customer_stream(CustomerCollection::export_search(self, search).await?))
+    }
+
+    async fn reminder_worklist(&self, query: WorklistQuery) -> Result<ReminderWorklist, AppError> {
+        CustomerCollection::reminder_worklist(self, query).await
+    }
+
+    async fn record_contact(
+        &self,
+        customer_id: &str,
+        contact: ContactRecord,
+        contacted_by: &str
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::record_contact(self, customer_id, contact, contacted_by).await
+    }
+
+    async fn record_intervention(
+        &self,
+        customer_id: &str,
+        operation_performed: OperationPerformed,
+        date: DateTime<Utc>,
+        expiration_date: DateTime<Utc>,
+        observations: Option<String>
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::record_intervention(
+            self,
+            customer_id,
+            operation_performed,
+            date,
+            expiration_date,
+            observations
+        )
+        .await
+    }
+
+    async fn set_location(
+        &self,
+        customer_id: &str,
+        location: GeoPoint
+    ) -> Result<UpdateResultResponse, AppError> {
+        CustomerCollection::set_location(self, customer_id, location).await
+    }
+
+    async fn set_certificate(
+        &self,
+        customer_id: &str,
+        certificate: &Certificate
+    ) -> Result<bool, AppError> {
+        CustomerCollection::set_certificate(self, customer_id, certificate).await
+    }
+}
+
+#[axum::async_trait]
+impl UserRepository for UserCollection {
+    async fn get_user(&self, username: &str) -> Result<Option<DeliveryUserOut>, AppError> {
+        UserCollection::get_user(self, username).await
+    }
+
+    async fn set_calendar_token(&self, username: &str, token_hash: &str) -> Result<(), AppError> {
+        UserCollection::set_calendar_token(self, username, token_hash).await
+    }
+
+    async fn get_user_by_calendar_token(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<DeliveryUserOut>, AppError> {
+        UserCollection::get_user_by_calendar_token(self, token_hash).await
+    }
+}
+
+#[axum::async_trait]
+impl AppointmentRepository for AppointmentCollection {
+    async fn get_appointment(&self, id: ObjectId) -> Result<AppointmentOut, AppError> {
+        AppointmentCollection::get_appointment(self, id).await
+    }
+
+    async fn list_appointments(
+        &self,
+        query: AppointmentQuery
+    ) -> Result<AppointmentList, AppError> {
+        AppointmentCollection::list_appointments(self, query).await
+    }
+
+    async fn create_appointment(
+        &self,
+        appointment: AppointmentIn,
+        created_by: &str
+    ) -> Result<InsertOneResultResponse, AppError> {
+        AppointmentCollection::create_appointment(self, appointment, created_by).await
+    }
+
+    async fn reschedule_appointment(
+        &self,
+        id: ObjectId,
+        request: RescheduleRequest
+    ) -> Result<AppointmentOut, AppError> {
+        AppointmentCollection::reschedule_appointment(self, id, request).await
+    }
+
+    async fn cancel_appointment(
+        &self,
+        id: ObjectId,
+        request: CancelRequest,
+        cancelled_by: &str
+    ) -> Result<AppointmentOut, AppError> {
+        AppointmentCollection::cancel_appointment(self, id, request, cancelled_by).await
+    }
+
+    async fn mark_completed(
+        &self,
+        id: ObjectId,
+        completion: Document
+    ) -> Result<AppointmentOut, AppError> {
+        AppointmentCollection::mark_completed(self, id, completion).await
+    }
+
+    async fn revert_completion(&self, id: ObjectId) -> Result<(), AppError> {
+        AppointmentCollection::revert_completion(self, id).await
+    }
+
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        AppointmentCollection::for_customer(self, customer_id).await
+    }
+
+    async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError> {
+        AppointmentCollection::erase_for_customer(self, customer_id).await
+    }
+}
+
+#[axum::async_trait]
+impl CounterRepository for CounterCollection {
+    async fn next(&self, name: &str) -> Result<i64, AppError> {
+        CounterCollection::next(self, name).await
+    }
+}
+
+#[axum::async_trait]
+impl AuditRepository for AuditCollection {
+    async fn record(&self, entry: AuditEntry) -> Result<(), AppError> {
+        AuditCollection::record(self, entry).await
+    }
+
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        AuditCollection::for_customer(self, customer_id).await
+    }
+}
+
+#[axum::async_trait]
+impl NotificationRepository for NotificationCollection {
+    async fn for_customer(&self, customer_id: &str) -> Result<Vec<Document>, AppError> {
+        NotificationCollection::for_customer(self, customer_id).await
+    }
+
+    async fn erase_for_customer(&self, customer_id: &str) -> Result<u64, AppError> {
+        NotificationCollection::erase_for_customer(self, customer_id).await
+    }
+}
@@ -1,15 +1,25 @@
+use axum::headers::{ETag, IfNoneMatch};
 use axum::response::IntoResponse;
-use axum::{http::StatusCode, Json};
+use axum::{http::StatusCode, Json, TypedHeader};
 use mongodb::bson::oid::ObjectId;
 use mongodb::results::DeleteResult;
 use mongodb::results::{InsertOneResult, UpdateResult};
 
+use crate::customer::DeliveryCustomerOut;
+
 /// The result of inserting a single document to MongoDb
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct InsertOneResultResponse {
     inserted_id: ObjectId
 }
 
+impl InsertOneResultResponse {
+    /// Creates a new [`InsertOneResultResponse`].
+    pub fn new(inserted_id: ObjectId) -> Self {
+        Self { inserted_id }
+    }
+}
+
 impl IntoResponse for InsertOneResultResponse {
     fn into_response(self) -> axum::response::Response {
         (
@@ -38,6 +48,13 @@ pub struct UpdateResultResponse {
     upserted_id: Option<ObjectId>
 }
 
+impl UpdateResultResponse {
+    /// Creates a new [`UpdateResultResponse`], for an update that didn't upsert.
+    pub fn new(matched_count: u64, modified_count: u64) -> Self {
+        Self { matched_count, modified_count, upserted_id: None }
+    }
+}
+
 impl IntoResponse for UpdateResultResponse {
     fn into_response(self) -> axum::response::Response {
         (
@@ -65,11 +82,18 @@ impl From<UpdateResult> for UpdateResultResponse {
 //////////////////////////////////////////////////////////////////////////////////////////
 
 /// The result of deleting a single document in MongoDb
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct DeleteResultResponse {
     deleted_count: u64
 }
 
+impl DeleteResultResponse {
+    /// Creates a new [`DeleteResultResponse`].
+    pub fn new(deleted_count: u64) -> Self {
+        Self { deleted_count }
+    }
+}
+
 impl IntoResponse for DeleteResultResponse {
     fn into_response(self) -> axum::response::Response {
         (StatusCode::OK, Json(self)).into_response()
@@ -83,3 +107,38 @@ impl From<DeleteResult> for DeleteResultResponse {
         }
     }
 }
+
+//////////////////////////////////////////////////////////////////////////////////////////
+
+/// A single [`DeliveryCustomerOut`] along with the `ETag` of its current version
+#[derive(Debug)]
+pub struct CustomerResponse {
+    customer: DeliveryCustomerOut,
+    etag: ETag,
+    not_modified: bool
+}
+
+impl CustomerResponse {
+    /// Creates a new [`CustomerResponse`].
+    pub fn new(customer: DeliveryCustomerOut) -> Self {
+        let etag = customer.etag();
+        Self { customer, etag, not_modified: false }
+    }
+
+    /// Respond with `304 Not Modified` if the client already holds the current version
+    pub fn with_if_none_match(mut self, if_none_match: Option<IfNoneMatch>) -> Self {
+        self.not_modified =
+            if_none_match.is_some_and(|header| !header.precondition_passes(&self.etag));
+        self
+    }
+}
+
+impl IntoResponse for CustomerResponse {
+    fn into_response(self) -> axum::response::Response {
+        if self.not_modified {
+            return (StatusCode::NOT_MODIFIED, TypedHeader(self.etag)).into_response();
+        }
+
+        (StatusCode::OK, TypedHeader(self.etag), Json(self.customer)).into_response()
+    }
+}
@@ -0,0 +1,53 @@
+use crate::admin::gdpr::{erase_customer_data, export_customer_data};
+use crate::admin::gdpr::{DataSubjectExport, ErasureReport};
+use crate::admin::header::AdminHeader;
+use crate::auth::jwt::Claims;
+use crate::error::AppError;
+use crate::state::AppState;
+
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
+use axum::Router;
+
+/// Export everything held on a [`DeliveryCustomer`]
+///
+/// Responds with a JSON bundle of the customer document, it's audit trail, the delivery log
+/// of it's reminders and it's appointments. The export is recorded in the audit trail.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn export_customer(
+    State(state): State<AppState>,
+    _admin: AdminHeader,
+    claims: Claims,
+    Path(customer_id): Path<String>
+) -> Result<DataSubjectExport, AppError> {
+    tracing::info!("Exporting data of customer_id={} by {}", &customer_id, claims.sub());
+
+    export_customer_data(state.repositories(), &customer_id, claims.sub()).await
+}
+
+/// Erase the personal data of a [`DeliveryCustomer`]
+///
+/// Anonymizes the name, address and contact details of the customer,
+/// keeping the appliance inspection records. The erasure is recorded in the audit trail.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn erase_customer(
+    State(state): State<AppState>,
+    _admin: AdminHeader,
+    claims: Claims,
+    Path(customer_id): Path<String>
+) -> Result<ErasureReport, AppError> {
+    tracing::info!("Erasing data of customer_id={} by {}", &customer_id, claims.sub());
+
+    erase_customer_data(state.repositories(), &customer_id, claims.sub()).await
+}
+
+/// Router for the admin endpoints
+///
+/// Every endpoint requires the `x-admin-secret` header, besides a valid token.
+pub fn admin_router() -> Router<AppState> {
+    Router::new()
+        .route("/customer/:customer_id/export", get(export_customer))
+        .route("/customer/:customer_id/erase", post(erase_customer))
+}
@@ -0,0 +1,128 @@
+use crate::appointment::RescheduleRequest;
+use crate::appointment::{complete_appointment, reschedule_appointment, schedule_appointment};
+use crate::appointment::{
+    AppointmentIn, AppointmentList, AppointmentOut, CancelRequest, CompletionRequest
+};
+use crate::auth::jwt::Claims;
+use crate::error::AppError;
+use crate::query::AppointmentQuery;
+use crate::responses::InsertOneResultResponse;
+use crate::state::AppState;
+
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, patch, post};
+use axum::{Json, Router};
+use mongodb::bson::oid::ObjectId;
+use validator::Validate;
+
+/// Retrieve a single appointment by it's `_id`
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn get_appointment(
+    State(state): State<AppState>,
+    Path(id): Path<ObjectId>
+) -> Result<AppointmentOut, AppError> {
+    tracing::info!("Retrieving appointment with _id={}", &id);
+
+    state.appointments().get_appointment(id).await
+}
+
+/// List appointments
+///
+/// Retrieve the appointments matching the filters in [`AppointmentQuery`], ordered by their start.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn list_appointments(
+    State(state): State<AppState>,
+    Query(query): Query<AppointmentQuery>
+) -> Result<AppointmentList, AppError> {
+    tracing::info!("Listing appointments");
+
+    state.appointments().list_appointments(query).await
+}
+
+/// Schedule a visit of a technician to a [`DeliveryCustomer`]
+///
+/// Responds with `409 Conflict` if the technician already has an appointment overlapping the slot.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn create_appointment(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(appointment): Json<AppointmentIn>
+) -> Result<InsertOneResultResponse, AppError> {
+    tracing::info!(
+        "Scheduling appointment of customer_id={} by {}",
+        &appointment.customer_id,
+        claims.sub()
+    );
+
+    appointment.validate()?;
+
+    schedule_appointment(state.repositories(), appointment, claims.sub()).await
+}
+
+/// Reschedule an appointment
+///
+/// Moves a scheduled appointment to another slot, optionally with another technician.
+/// Responds with `409 Conflict` if the technician already has an appointment overlapping the slot.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn reschedule(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<ObjectId>,
+    Json(request): Json<RescheduleRequest>
+) -> Result<AppointmentOut, AppError> {
+    tracing::info!("Rescheduling appointment with _id={} by {}", &id, claims.sub());
+
+    request.validate()?;
+
+    reschedule_appointment(state.repositories(), id, request).await
+}
+
+/// Cancel an appointment
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn cancel(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<ObjectId>,
+    Json(request): Json<CancelRequest>
+) -> Result<AppointmentOut, AppError> {
+    tracing::info!("Cancelling appointment with _id={} by {}", &id, claims.sub());
+
+    request.validate()?;
+
+    state.appointments().cancel_appointment(id, request, claims.sub()).await
+}
+
+/// Complete an appointment
+///
+/// Records the intervention on the appliance of the [`DeliveryCustomer`],
+/// which moves it's `expiration_date` to the next check.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn complete(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<ObjectId>,
+    Json(request): Json<CompletionRequest>
+) -> Result<AppointmentOut, AppError> {
+    tracing::info!("Completing appointment with _id={} by {}", &id, claims.sub());
+
+    request.validate()?;
+
+    complete_appointment(state.repositories(), id, request, claims.sub()).await
+}
+
+/// Router for appointment related operations.
+pub fn appointment_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_appointments))
+        .route("/create", post(create_appointment))
+        .route("/reschedule/:id", patch(reschedule))
+        .route("/cancel/:id", patch(cancel))
+        .route("/complete/:id", post(complete))
+        .route("/:id", get(get_appointment))
+}
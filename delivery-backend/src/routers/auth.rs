@@ -18,7 +18,7 @@ async fn login(
     State(state): State<AppState>,
     user: JsonEncodedUser
 ) -> Result<AuthBodyWithRefreshToken, AppError> {
-    match state.database().user().validate_user_password(&user).await? {
+    match state.users().validate_user_password(&user).await? {
         true => {
             tracing::info!(username = &user.username, auth = "successful");
             let refresh_token = generate_refresh_token();
@@ -0,0 +1,96 @@
+use crate::auth::jwt::{AuthError, Claims};
+use crate::calendar::{expiration_calendar, technician_calendar};
+use crate::calendar::{generate_calendar_token, hash_calendar_token, Calendar, CalendarToken};
+use crate::error::AppError;
+use crate::repository::UserRepository;
+use crate::state::AppState;
+
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, post};
+use axum::Router;
+use chrono::Utc;
+
+/// The token of a calendar feed, passed in the url
+#[derive(serde::Deserialize)]
+struct FeedToken {
+    token: String
+}
+
+impl std::fmt::Debug for FeedToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeedToken").field("token", &"REDACTED" as &dyn std::fmt::Debug).finish()
+    }
+}
+
+/// The `username` of the user the calendar token belongs to
+async fn calendar_user(users: &dyn UserRepository, token: &FeedToken) -> Result<String, AppError> {
+    match users.get_user_by_calendar_token(&hash_calendar_token(&token.token)).await? {
+        Some(user) => Ok(user.username),
+        None => Err(AuthError::WrongCredentials.into())
+    }
+}
+
+/// Generate a calendar token
+///
+/// Responds with a new token for the feeds of the logged in user and their urls.
+/// The previous token of the user stops working.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn create_calendar_token(
+    State(state): State<AppState>,
+    claims: Claims
+) -> Result<CalendarToken, AppError> {
+    tracing::info!("Generating calendar token for {}", claims.sub());
+
+    let token = generate_calendar_token();
+    state.users().set_calendar_token(claims.sub(), &hash_calendar_token(&token)).await?;
+
+    Ok(CalendarToken::new(claims.sub(), token))
+}
+
+/// The calendar of a technician's visits
+///
+/// Served at `/calendar/:username.ics?token=`, the token has to belong to the same user.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn user_calendar(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+    Query(token): Query<FeedToken>
+) -> Result<Calendar, AppError> {
+    let Some(username) = file.strip_suffix(".ics") else {
+        return Err(AppError::NotFound(format!("No calendar {file}")));
+    };
+
+    if calendar_user(state.users().as_ref(), &token).await? != username {
+        return Err(AuthError::WrongCredentials.into());
+    }
+
+    tracing::info!("Serving the calendar of {}", username);
+
+    technician_calendar(state.repositories(), username, Utc::now()).await
+}
+
+/// The office wide calendar of appliance expiration dates
+///
+/// Any user's calendar token opens it.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn expirations_calendar(
+    State(state): State<AppState>,
+    Query(token): Query<FeedToken>
+) -> Result<Calendar, AppError> {
+    let username = calendar_user(state.users().as_ref(), &token).await?;
+
+    tracing::info!("Serving the expirations calendar to {}", username);
+
+    expiration_calendar(state.customers().as_ref(), Utc::now()).await
+}
+
+/// Router for the calendar feeds.
+pub fn calendar_router() -> Router<AppState> {
+    Router::new()
+        .route("/token", post(create_calendar_token))
+        .route("/office/expirations.ics", get(expirations_calendar))
+        .route("/:file", get(user_calendar))
+}
@@ -1,42 +1,124 @@
-use crate::customer::DeliveryCustomerList;
-use crate::query::ExpiredCustomersQuery;
-use crate::query::PartialDeliveryCustomer;
+use crate::auth::jwt::Claims;
+use crate::certificate::{certificate_pdf, issue_certificate, CertificatePdf, IssuedCertificate};
+use crate::customer::NearbyCustomerList;
+use crate::customer::{ContactRecord, CustomerPage, DeliveryCustomerList, ReminderWorklist};
+use crate::export::{CustomerExport, Locale, Negotiated, ResponseFormat};
+use crate::import::{import_customers, ImportOptions, ImportReport};
+use crate::query::{CustomerListQuery, ExpiredCustomersQuery, NearQuery, WorklistQuery};
+use crate::query::{CustomerPatch, PartialDeliveryCustomer, VersionPrecondition};
 use crate::responses::InsertOneResultResponse;
-use crate::responses::{DeleteResultResponse, UpdateResultResponse};
+use crate::responses::{CustomerResponse, DeleteResultResponse, UpdateResultResponse};
 use crate::state::AppState;
 use crate::{customer::DeliveryCustomerIn, error::AppError};
 
 use axum::extract::{Path, Query, State};
+use axum::headers::IfNoneMatch;
 use axum::routing::{delete, patch};
 use axum::routing::{get, post, put};
-use axum::{Json, Router};
+use axum::{Json, Router, TypedHeader};
+use mongodb::bson::oid::ObjectId;
+use validator::Validate;
+
+/// Retrieve a single [`DeliveryCustomer`]
+///
+/// Looks up a [`DeliveryCustomer`] by it's `customer_id`.
+/// The response carries an `ETag`, so clients can revalidate with `If-None-Match`.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn get_customer(
+    State(state): State<AppState>,
+    Path(customer_id): Path<String>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>
+) -> Result<CustomerResponse, AppError> {
+    tracing::info!("Retrieving customer with customer_id={}", &customer_id);
+
+    let customer = state.customers().get_customer(&customer_id).await?;
+
+    Ok(CustomerResponse::new(customer).with_if_none_match(if_none_match.map(|h| h.0)))
+}
+
+/// Retrieve a single [`DeliveryCustomer`] by it's `ObjectId`
+///
+/// Same as [`get_customer`], but looks up the document by it's `_id`.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn get_customer_by_oid(
+    State(state): State<AppState>,
+    Path(oid): Path<ObjectId>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>
+) -> Result<CustomerResponse, AppError> {
+    tracing::info!("Retrieving customer with _id={}", &oid);
+
+    let customer = state.customers().get_customer_by_oid(oid).await?;
+
+    Ok(CustomerResponse::new(customer).with_if_none_match(if_none_match.map(|h| h.0)))
+}
 
 /// Add a new [`DeliveryCustomer`]
 ///
 /// Adds a new [`DeliveryCustomer`] to the database.
+/// If the request has no `location`, the address is geocoded.
+/// A failed lookup doesn't stop the customer from being added.
 #[tracing::instrument(skip(state))]
 #[axum_macros::debug_handler]
 async fn create_customer(
     State(state): State<AppState>,
-    Json(customer): Json<DeliveryCustomerIn>
+    Json(mut customer): Json<DeliveryCustomerIn>
 ) -> Result<InsertOneResultResponse, AppError> {
     tracing::info!("Inserting customer with customer_id={}", &customer.customer_id);
 
-    state.database().customer().insert_customer(customer).await
+    customer.validate()?;
+
+    if customer.location.is_none() {
+        match state.geocoder().geocode(&customer.address).await {
+            Ok(location) => customer.location = location,
+            Err(err) => {
+                tracing::error!("Failed to geocode customer_id={}: {err}", &customer.customer_id)
+            }
+        }
+    }
+
+    state.customers().insert_customer(customer).await
 }
 
 /// Edit a [`DeliveryCustomer`]
 ///
 /// Edits an existing [`DeliveryCustomer`] in the database.
+/// If the request carries an `If-Match` header, the update only goes through
+/// when the stored document is still at that version.
 #[tracing::instrument(skip(state))]
 #[axum_macros::debug_handler]
 async fn update_customer(
     State(state): State<AppState>,
+    precondition: Option<TypedHeader<VersionPrecondition>>,
     Json(customer): Json<PartialDeliveryCustomer>
 ) -> Result<UpdateResultResponse, AppError> {
     tracing::info!("Updating customer with customer_id={}", &customer.customer_id);
 
-    state.database().customer().update_customer(customer).await
+    customer.validate()?;
+
+    state.customers().update_customer(customer, precondition.map(|h| h.0)).await
+}
+
+/// Patch a [`DeliveryCustomer`]
+///
+/// Accepts either a JSON Merge Patch (`application/merge-patch+json`)
+/// or a JSON Patch (`application/json-patch+json`) against the [`DeliveryCustomerOut`] shape.
+/// Responds with the patched customer and it's new `ETag`.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn patch_customer(
+    State(state): State<AppState>,
+    Path(customer_id): Path<String>,
+    precondition: Option<TypedHeader<VersionPrecondition>>,
+    patch: CustomerPatch
+) -> Result<CustomerResponse, AppError> {
+    tracing::info!("Patching customer with customer_id={}", &customer_id);
+
+    let customer =
+        state.customers().patch_customer(&customer_id, patch, precondition.map(|h| h.0)).await?;
+
+    Ok(CustomerResponse::new(customer))
 }
 
 /// Activate a [`DeliveryCustomer`]
@@ -49,11 +131,12 @@ async fn update_customer(
 #[axum_macros::debug_handler]
 async fn activate_customer(
     State(state): State<AppState>,
-    Path(customer_id): Path<String>
+    Path(customer_id): Path<String>,
+    precondition: Option<TypedHeader<VersionPrecondition>>
 ) -> Result<UpdateResultResponse, AppError> {
     tracing::info!("Activating customer with customer_id={}", &customer_id);
 
-    state.database().customer().activate_customer(customer_id).await
+    state.customers().activate_customer(customer_id, precondition.map(|h| h.0)).await
 }
 
 /// Deactivate a [`DeliveryCustomer`]
@@ -66,39 +149,226 @@ async fn activate_customer(
 #[axum_macros::debug_handler]
 async fn deactivate_customer(
     State(state): State<AppState>,
-    Path(customer_id): Path<String>
+    Path(customer_id): Path<String>,
+    precondition: Option<TypedHeader<VersionPrecondition>>
 ) -> Result<UpdateResultResponse, AppError> {
     tracing::info!("Deactivating customer with customer_id={}", &customer_id);
 
-    state.database().customer().deactivate_customer(customer_id).await
+    state.customers().deactivate_customer(customer_id, precondition.map(|h| h.0)).await
 }
 
 /// Delete a [`DeliveryCustomer`]
 ///
-/// Removes a [`DeliveryCustomer`] with matching `customer_id` from MongoDb
+/// Marks a [`DeliveryCustomer`] with matching `customer_id` as deleted.
+/// It's a requirement to keep the data associated with them, so the document
+/// is only removed from MongoDb once the retention period is over.
 #[tracing::instrument(skip(state))]
 #[axum_macros::debug_handler]
 async fn delete_customer(
     State(state): State<AppState>,
+    claims: Claims,
     Path(customer_id): Path<String>
 ) -> Result<DeleteResultResponse, AppError> {
-    tracing::info!("Deleting customer with customer_id={}", &customer_id);
+    tracing::info!("Deleting customer with customer_id={} by {}", &customer_id, claims.sub());
 
-    state.database().customer().delete_customer(customer_id).await
+    state.customers().delete_customer(customer_id, claims.sub()).await
+}
+
+/// Restore a deleted [`DeliveryCustomer`]
+///
+/// Undoes a deletion, as long as the [`DeliveryCustomer`] hasn't been purged yet.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn restore_customer(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(customer_id): Path<String>
+) -> Result<UpdateResultResponse, AppError> {
+    tracing::info!("Restoring customer with customer_id={} by {}", &customer_id, claims.sub());
+
+    state.customers().restore_customer(customer_id).await
 }
 
 /// Retrieve expired [`DeliveryCustomer`]s
 ///
 /// Retrieve [`DeliveryCustomer`]s that are expired (their appliance is due for a checkup).
+/// `status=due_soon` retrieves the ones expiring soon instead, `status=all` doesn't look at the current date.
+/// The next page is requested by passing back the `next_cursor` of the response.
+/// The `Accept` header can ask for CSV, Excel, NDJSON or a streamed JSON array instead,
+/// which hold every matching customer, see [`ResponseFormat`].
 #[tracing::instrument(skip(state))]
 #[axum_macros::debug_handler]
 async fn expired_customers(
     State(state): State<AppState>,
+    format: ResponseFormat,
+    locale: Locale,
     Query(query): Query<ExpiredCustomersQuery>
-) -> Result<DeliveryCustomerList, AppError> {
+) -> Result<Negotiated<DeliveryCustomerList>, AppError> {
     tracing::info!("Retrieving expired customers");
 
-    state.database().customer().expired_customers(query).await
+    match format {
+        ResponseFormat::Json => {
+            Ok(Negotiated::Json(state.customers().expired_customers(query).await?))
+        }
+        ResponseFormat::Export(format) => {
+            let customers = state.customers().export_expired_customers(query).await?;
+            let export =
+                CustomerExport::from_stream(customers, format, locale, "expired-customers").await?;
+
+            Ok(Negotiated::Export(export))
+        }
+    }
+}
+
+/// Retrieve the reminder worklist
+///
+/// Retrieve the active [`DeliveryCustomer`]s whose appliance check expires in the next days
+/// and who haven't been contacted about it yet, grouped by county and week.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn reminder_worklist(
+    State(state): State<AppState>,
+    Query(query): Query<WorklistQuery>
+) -> Result<ReminderWorklist, AppError> {
+    tracing::info!("Retrieving the reminder worklist");
+
+    state.customers().reminder_worklist(query).await
+}
+
+/// Record contacting a [`DeliveryCustomer`]
+///
+/// Marks a [`DeliveryCustomer`] as contacted, scheduled or declined, with an optional note.
+/// This takes them off the reminder worklist until their next check is due.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn record_contact(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(customer_id): Path<String>,
+    Json(contact): Json<ContactRecord>
+) -> Result<UpdateResultResponse, AppError> {
+    tracing::info!("Recording contact with customer_id={} by {}", &customer_id, claims.sub());
+
+    contact.validate()?;
+
+    state.customers().record_contact(&customer_id, contact, claims.sub()).await
+}
+
+/// List [`DeliveryCustomer`]s
+///
+/// Retrieve a page of [`DeliveryCustomer`]s matching the filters in [`CustomerListQuery`].
+/// The next page is requested by passing back the `next_cursor` of the response.
+/// The `Accept` header can ask for CSV, Excel, NDJSON or a streamed JSON array instead,
+/// which hold every matching customer, see [`ResponseFormat`].
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn list_customers(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    locale: Locale,
+    Query(query): Query<CustomerListQuery>
+) -> Result<Negotiated<CustomerPage>, AppError> {
+    tracing::info!("Listing customers");
+
+    match format {
+        ResponseFormat::Json => {
+            Ok(Negotiated::Json(state.customers().list_customers(query).await?))
+        }
+        ResponseFormat::Export(format) => {
+            let customers = state.customers().export_customers(query).await?;
+            let export =
+                CustomerExport::from_stream(customers, format, locale, "customers").await?;
+
+            Ok(Negotiated::Export(export))
+        }
+    }
+}
+
+/// Retrieve [`DeliveryCustomer`]s around a point
+///
+/// Retrieve the [`DeliveryCustomer`]s within `radius` metres of `lat` and `lng`, closest first.
+/// By default only expired ones are included, `status` works the same way as for expired customers.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn customers_near(
+    State(state): State<AppState>,
+    Query(query): Query<NearQuery>
+) -> Result<NearbyCustomerList, AppError> {
+    tracing::info!("Retrieving customers near lat={}, lng={}", query.lat, query.lng);
+
+    state.customers().customers_near(query).await
+}
+
+/// Geocode a [`DeliveryCustomer`]
+///
+/// Looks up the coordinates of the stored address and saves them as the `location`.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn geocode_customer(
+    State(state): State<AppState>,
+    Path(customer_id): Path<String>
+) -> Result<UpdateResultResponse, AppError> {
+    tracing::info!("Geocoding customer with customer_id={}", &customer_id);
+
+    let customer = state.customers().get_customer(&customer_id).await?;
+
+    let location = state.geocoder().geocode(&customer.address).await?.ok_or_else(|| {
+        AppError::NotFound(format!("Address of customer_id={customer_id} could not be found"))
+    })?;
+
+    state.customers().set_location(&customer_id, location).await
+}
+
+/// Import [`DeliveryCustomer`]s from a CSV file
+///
+/// The body is the CSV file, with a header row. Columns are matched to fields by name,
+/// unless `mapping` says otherwise. Dates can be written like `dd.mm.yyyy`.
+/// New customers are inserted and existing ones get the mapped columns overwritten,
+/// matched by `customer_id`, but only if every row is valid.
+/// With `dry_run=true` nothing is written, the response only reports the invalid rows.
+#[tracing::instrument(skip(state, body))]
+#[axum_macros::debug_handler]
+async fn import(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(options): Query<ImportOptions>,
+    body: String
+) -> Result<ImportReport, AppError> {
+    tracing::info!("Importing customers from CSV by {}", claims.sub());
+
+    import_customers(state.customers().as_ref(), &body, &options).await
+}
+
+/// Issue the inspection certificate of a [`DeliveryCustomer`]
+///
+/// The certificate of the last VTP, RGAZ or VGAZ visit gets the next certificate number
+/// and is answered with `201 Created`. Once issued, it's answered with `200 OK` and keeps
+/// it's number until the appliance is checked again.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn issue(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(customer_id): Path<String>
+) -> Result<IssuedCertificate, AppError> {
+    tracing::info!("Issuing certificate of customer_id={} by {}", &customer_id, claims.sub());
+
+    issue_certificate(state.customers().as_ref(), state.counters().as_ref(), &customer_id).await
+}
+
+/// Retrieve the inspection certificate of a [`DeliveryCustomer`]
+///
+/// Renders the certificate issued for the last visit as a PDF.
+/// It has to be issued first, with a `POST` to `/customer/:customer_id/certificate`.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+async fn certificate(
+    State(state): State<AppState>,
+    Path(customer_id): Path<String>
+) -> Result<CertificatePdf, AppError> {
+    tracing::info!("Rendering certificate of customer_id={}", &customer_id);
+
+    certificate_pdf(state.customers().as_ref(), &state.certificate_template(), &customer_id).await
 }
 
 /// Router for client related operations.
@@ -106,10 +376,21 @@ async fn expired_customers(
 /// Any action done on a client resource is registered here.
 pub fn customer_router() -> Router<AppState> {
     Router::new()
+        .route("/", get(list_customers))
         .route("/create", post(create_customer))
+        .route("/import", post(import))
         .route("/update", put(update_customer))
         .route("/activate/:customer_id", patch(activate_customer))
         .route("/deactivate/:customer_id", patch(deactivate_customer))
         .route("/delete/:customer_id", delete(delete_customer))
+        .route("/restore/:customer_id", post(restore_customer))
         .route("/expired", get(expired_customers))
+        .route("/near", get(customers_near))
+        .route("/geocode/:customer_id", post(geocode_customer))
+        .route("/worklist", get(reminder_worklist))
+        .route("/contact/:customer_id", post(record_contact))
+        .route("/by-oid/:oid", get(get_customer_by_oid))
+        .route("/:customer_id/certificate", post(issue))
+        .route("/:customer_id/certificate.pdf", get(certificate))
+        .route("/:customer_id", get(get_customer).patch(patch_customer))
 }
@@ -1,11 +1,13 @@
 use crate::state::AppState;
 use axum::extract::State;
+use axum::http::StatusCode;
 
 /// Recent [`DeliveryCustomer`] history
 ///
 /// Get the recently added, edited, activated or deactivated [`DeliveryCustomer`]s.
+/// Not implemented yet, answered with `501 Not Implemented`.
 #[axum_macros::debug_handler]
 #[allow(unused_variables)]
-pub async fn customer_history(State(state): State<AppState>) {
-    todo!()
+pub async fn customer_history(State(state): State<AppState>) -> StatusCode {
+    StatusCode::NOT_IMPLEMENTED
 }
@@ -1,9 +1,17 @@
+mod admin;
+mod appointment;
 mod auth;
+mod calendar;
 mod customer;
 mod history;
+mod route;
 mod search;
 
+pub use admin::admin_router;
+pub use appointment::appointment_router;
 pub use auth::auth_router;
+pub use calendar::calendar_router;
 pub use customer::customer_router;
 pub use history::customer_history;
+pub use route::plan_route;
 pub use search::customer_search;
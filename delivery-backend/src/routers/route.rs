@@ -0,0 +1,26 @@
+use crate::{
+    error::AppError,
+    planning::{plan_day, PlannedRoute, RouteRequest},
+    state::AppState
+};
+use axum::{extract::State, Json};
+use validator::Validate;
+
+/// Plan the route of a technician for a day
+///
+/// Orders the customers due for inspection into a route starting at the depot,
+/// respecting their time windows and the maximum number of visits.
+/// Responds with the ordered visits, their estimated arrival times
+/// and the customers that didn't fit.
+#[tracing::instrument(skip(state))]
+#[axum_macros::debug_handler]
+pub async fn plan_route(
+    State(state): State<AppState>,
+    Json(request): Json<RouteRequest>
+) -> Result<PlannedRoute, AppError> {
+    tracing::info!("Planning a route starting at {}", request.start);
+
+    request.validate()?;
+
+    plan_day(state.customers().as_ref(), request).await
+}
@@ -1,16 +1,35 @@
-use crate::{customer::DeliveryCustomerList, error::AppError, query::SearchQuery, state::AppState};
+use crate::export::{CustomerExport, Locale, Negotiated, ResponseFormat};
+use crate::{customer::DeliveryCustomerOut, error::AppError, query::SearchQuery, state::AppState};
 use axum::extract::{Query, State};
+use axum::Json;
 
 /// Search for a `DeliveryCustomer` in the database.
 ///
 /// Accepts a [`String`] which contains all the possible fields to search for.
+/// Responds with a bare JSON array of the matching customers, unlike the paginated lists.
+/// The `Accept` header can ask for CSV, Excel, NDJSON or a streamed JSON array instead,
+/// which hold every matching customer, see [`ResponseFormat`].
 #[tracing::instrument(skip(state))]
 #[axum_macros::debug_handler]
 pub async fn customer_search(
     State(state): State<AppState>,
+    format: ResponseFormat,
+    locale: Locale,
     Query(search): Query<SearchQuery>
-) -> Result<DeliveryCustomerList, AppError> {
+) -> Result<Negotiated<Json<Vec<DeliveryCustomerOut>>>, AppError> {
     tracing::info!("Search query: {}", search);
 
-    state.database().customer().search_customers(search).await
+    match format {
+        ResponseFormat::Json => {
+            let customers = state.customers().search_customers(search).await?;
+
+            Ok(Negotiated::Json(Json(customers.into_iter().collect())))
+        }
+        ResponseFormat::Export(format) => {
+            let customers = state.customers().export_search(search).await?;
+            let export = CustomerExport::from_stream(customers, format, locale, "search").await?;
+
+            Ok(Negotiated::Export(export))
+        }
+    }
 }
@@ -2,43 +2,93 @@ use std::sync::Arc;
 
 use crate::{
     auth::store::{setup_store, Store},
-    database::{setup_database, Database},
-    error::AppError
+    certificate::CertificateTemplate,
+    database::Database,
+    error::AppError,
+    geocoding::{setup_geocoder, Geocoder},
+    repository::{
+        AppointmentRepository, CounterRepository, CustomerRepository, Repositories, UserRepository
+    }
 };
 
 /// Global, app level state
 ///
 /// Is injected to every handler that requires database access.
-/// Wraps [`Database`] in an [`Arc`] to help with usage inside tokio tasks
+/// Every repository is behind an [`Arc`] to help with usage inside tokio tasks
+///
+/// The handlers go through the [`Repositories`], which are the MongoDB collections
+/// in the app and held in memory in tests.
 #[derive(Clone, Debug)]
 pub struct AppState {
-    database: Arc<Database>,
-    store: Arc<Store>
+    repositories: Repositories,
+    store: Arc<Store>,
+    geocoder: Arc<dyn Geocoder>,
+    certificate_template: Arc<CertificateTemplate>
 }
 
 impl AppState {
     /// Creates a new [`AppState`].
-    pub fn new(database: Arc<Database>, store: Arc<Store>) -> Self {
-        Self { database, store }
+    pub fn new(
+        repositories: Repositories,
+        store: Arc<Store>,
+        geocoder: Arc<dyn Geocoder>,
+        certificate_template: Arc<CertificateTemplate>
+    ) -> Self {
+        Self { repositories, store, geocoder, certificate_template }
+    }
+
+    /// Return every repository of this [`AppState`]
+    pub fn repositories(&self) -> &Repositories {
+        &self.repositories
+    }
+
+    /// Return the customer repository of this [`AppState`]
+    pub fn customers(&self) -> Arc<dyn CustomerRepository> {
+        Arc::clone(&self.repositories.customers)
+    }
+
+    /// Return the user repository of this [`AppState`]
+    pub fn users(&self) -> Arc<dyn UserRepository> {
+        Arc::clone(&self.repositories.users)
+    }
+
+    /// Return the appointment repository of this [`AppState`]
+    pub fn appointments(&self) -> Arc<dyn AppointmentRepository> {
+        Arc::clone(&self.repositories.appointments)
     }
 
-    /// Returns the database of this [`AppState`].
-    pub fn database(&self) -> Arc<Database> {
-        Arc::clone(&self.database)
+    /// Return the counter repository of this [`AppState`]
+    pub fn counters(&self) -> Arc<dyn CounterRepository> {
+        Arc::clone(&self.repositories.counters)
     }
 
     /// Return the store of this [`AppState`]
     pub fn store(&self) -> Arc<Store> {
         Arc::clone(&self.store)
     }
+
+    /// Return the geocoder of this [`AppState`]
+    pub fn geocoder(&self) -> Arc<dyn Geocoder> {
+        Arc::clone(&self.geocoder)
+    }
+
+    /// Return the certificate template of this [`AppState`]
+    pub fn certificate_template(&self) -> Arc<CertificateTemplate> {
+        Arc::clone(&self.certificate_template)
+    }
 }
 
-/// Setup the application wide state
+/// Setup the application wide state over `database`
 ///
-/// Will initialize a [`Database`] instance and wrap it in [`AppState`]
-/// Initialization might fail, because a MongoDb instance might not be running, etc
+/// Wraps the collections of the [`Database`] in [`AppState`].
+/// Initialization might fail, because the certificate template can't be read, etc
 #[tracing::instrument]
-pub async fn setup_app_state() -> Result<AppState, AppError> {
+pub fn setup_app_state(database: &Database) -> Result<AppState, AppError> {
     tracing::info!("Setting up AppState");
-    Ok(AppState::new(setup_database().await?, setup_store()))
+    Ok(AppState::new(
+        Repositories::mongo(database),
+        setup_store(),
+        setup_geocoder(),
+        Arc::new(CertificateTemplate::from_env()?)
+    ))
 }
@@ -0,0 +1,86 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use delivery_backend::calendar::{Calendar, Event, EventTime};
+
+fn calendar() -> Calendar {
+    let appointment = Event {
+        uid: "appointment-1@delivery-srl".into(),
+        sequence: 2,
+        start: EventTime::DateTime(Utc.with_ymd_and_hms(2024, 3, 31, 7, 0, 0).unwrap()),
+        end: EventTime::DateTime(Utc.with_ymd_and_hms(2024, 3, 31, 8, 30, 0).unwrap()),
+        summary: "VTP: Ion Popescu".into(),
+        description: Some("Ion Popescu (C-1)\ncentrala Vaillant; ecoTEC\r\nC:\\temp".into()),
+        // The `Ț` doesn't fit on the first line, so it's folded before it, not inside it
+        location: Some(
+            "Strada Mihail Kogălniceanu 12, bloc A, ap. 14, Cluj-Napoca, Țara Moților".into()
+        ),
+        cancelled: false
+    };
+
+    let expiration = Event {
+        uid: "expiration-2@delivery-srl".into(),
+        sequence: 0,
+        start: EventTime::Date(NaiveDate::from_ymd_opt(2024, 10, 27).unwrap()),
+        end: EventTime::Date(NaiveDate::from_ymd_opt(2024, 10, 28).unwrap()),
+        summary: "VTP expires: Maria Ionescu".into(),
+        description: None,
+        location: None,
+        cancelled: true
+    };
+
+    Calendar { name: "Programări, tehnician".into(), events: vec![appointment, expiration] }
+}
+
+#[test]
+fn calendar_is_rendered_as_icalendar() {
+    let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 5).unwrap();
+
+    let expected = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Delivery SRL//delivery-backend//RO",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+        "X-WR-CALNAME:Programări\\, tehnician",
+        "BEGIN:VEVENT",
+        "UID:appointment-1@delivery-srl",
+        "DTSTAMP:20240301T120005Z",
+        "SEQUENCE:2",
+        "DTSTART:20240331T070000Z",
+        "DTEND:20240331T083000Z",
+        "SUMMARY:VTP: Ion Popescu",
+        "DESCRIPTION:Ion Popescu (C-1)\\ncentrala Vaillant\\; ecoTEC\\nC:\\\\temp",
+        "LOCATION:Strada Mihail Kogălniceanu 12\\, bloc A\\, ap. 14\\, Cluj-Napoca\\, ",
+        " Țara Moților",
+        "STATUS:CONFIRMED",
+        "END:VEVENT",
+        "BEGIN:VEVENT",
+        "UID:expiration-2@delivery-srl",
+        "DTSTAMP:20240301T120005Z",
+        "SEQUENCE:0",
+        "DTSTART;VALUE=DATE:20241027",
+        "DTEND;VALUE=DATE:20241028",
+        "SUMMARY:VTP expires: Maria Ionescu",
+        "STATUS:CANCELLED",
+        "TRANSP:TRANSPARENT",
+        "END:VEVENT",
+        "END:VCALENDAR",
+        ""
+    ]
+    .join("\r\n");
+
+    assert_eq!(calendar().render(now), expected);
+}
+
+#[test]
+fn long_lines_are_folded_at_75_octets() {
+    let mut calendar = calendar();
+    calendar.events[0].description = Some("ă".repeat(100));
+
+    let rendered = calendar.render(Utc::now());
+
+    assert!(rendered.split("\r\n").all(|line| line.len() <= 75));
+    assert!(rendered.contains(&format!("DESCRIPTION:{}\r\n {}", "ă".repeat(31), "ă".repeat(37))));
+    assert!(rendered
+        .replace("\r\n ", "")
+        .contains(&format!("DESCRIPTION:{}\r\n", "ă".repeat(100))));
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use delivery_backend::certificate::{CertificateTemplate, Font, LineStyle, Page, PdfDocument};
+use delivery_backend::error::AppError;
+
+fn render(pages: Vec<Page>) -> String {
+    let mut document = PdfDocument::new("Certificat C-1");
+
+    for page in pages {
+        document.push_page(page);
+    }
+
+    String::from_utf8(document.render()).expect("PDF is written as ASCII")
+}
+
+fn page(text: &str) -> Page {
+    let mut page = Page::default();
+    page.text(50.0, 800.0, Font::Regular, 12.0, text);
+    page
+}
+
+fn template_error(template: &str) -> String {
+    match CertificateTemplate::parse(template) {
+        Err(AppError::CertificateError(message)) => message,
+        other => panic!("{template:?} was parsed: {other:?}")
+    }
+}
+
+#[test]
+fn xref_offsets_point_at_their_objects() {
+    let mut second = page("Pagina 2");
+    second.line((50.0, 700.0), (545.0, 700.0), 0.5);
+    let pdf = render(vec![page("Pagina 1"), second]);
+
+    let startxref = pdf.rfind("startxref\n").unwrap() + "startxref\n".len();
+    let xref: usize = pdf[startxref..].lines().next().unwrap().parse().unwrap();
+    let mut lines = pdf[xref..].lines();
+
+    assert_eq!(lines.next(), Some("xref"));
+    // Catalog, pages, two fonts, encoding, info and an object and a stream per page
+    assert_eq!(lines.next(), Some("0 11"));
+    assert_eq!(lines.next(), Some("0000000000 65535 f "));
+
+    for number in 1..11 {
+        let entry = lines.next().unwrap();
+        assert_eq!(entry.len(), 19, "{entry:?}");
+        assert!(entry.ends_with(" 00000 n "), "{entry:?}");
+
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(&format!("{number} 0 obj\n")), "object {number}");
+    }
+
+    assert_eq!(lines.next(), Some("trailer"));
+    assert!(pdf.contains("<< /Size 11 /Root 1 0 R /Info 6 0 R >>"));
+    assert!(pdf.ends_with("%%EOF\n"));
+}
+
+#[test]
+fn stream_length_is_the_length_of_the_content() {
+    let pdf = render(vec![page("Țară")]);
+
+    let stream = pdf.find("stream\n").unwrap() + "stream\n".len();
+    let end = pdf.find("endstream").unwrap();
+    let length = format!("<< /Length {} >>\nstream\n", end - stream);
+
+    assert!(pdf.contains(&length), "{pdf}");
+}
+
+#[test]
+fn diacritics_are_written_with_the_codes_of_the_encoding() {
+    let letters = [
+        ('ă', 141, Some("abreve")),
+        ('Ă', 129, Some("Abreve")),
+        ('ș', 144, Some("scommaaccent")),
+        ('ş', 144, Some("scommaaccent")),
+        ('Ș', 143, Some("Scommaaccent")),
+        ('Ş', 143, Some("Scommaaccent")),
+        ('ț', 127, Some("tcommaaccent")),
+        ('ţ', 127, Some("tcommaaccent")),
+        ('Ț', 157, Some("Tcommaaccent")),
+        ('Ţ', 157, Some("Tcommaaccent")),
+        // Part of WinAnsiEncoding already
+        ('â', 226, None),
+        ('Â', 194, None),
+        ('î', 238, None),
+        ('Î', 206, None)
+    ];
+
+    for (letter, code, name) in letters {
+        let pdf = render(vec![page(&format!("a{letter}b"))]);
+
+        assert!(pdf.contains(&format!("(a\\{code:03o}b) Tj")), "{letter}");
+
+        if let Some(name) = name {
+            let start = pdf.find("/Differences [").unwrap() + "/Differences [".len();
+            let differences = &pdf[start..start + pdf[start..].find(']').unwrap()];
+            let pairs: Vec<_> = differences.split_whitespace().collect();
+            let pair = [code.to_string(), format!("/{name}")];
+
+            assert!(pairs.chunks(2).any(|chunk| chunk == pair), "{letter}");
+        }
+    }
+}
+
+#[test]
+fn text_is_escaped_in_string_literals() {
+    let pdf = render(vec![page("(a\\b) 😀")]);
+
+    assert!(pdf.contains("(\\(a\\\\b\\) ?) Tj"), "{pdf}");
+}
+
+#[test]
+fn template_lines_are_styled_and_rendered() {
+    let template = CertificateTemplate::parse(
+        "# Certificat\n## Nr. {certificate_number}\n### Client\n---\n\n{name} {{{ county }}}\n"
+    )
+    .unwrap();
+    let fields =
+        HashMap::from([("certificate_number", "42".to_owned()), ("name", "Ion".to_owned())]);
+
+    let lines: Vec<_> =
+        template.lines().iter().map(|line| (line.style, line.render(&fields))).collect();
+
+    assert_eq!(
+        lines,
+        [
+            (LineStyle::Title, "Certificat".to_owned()),
+            (LineStyle::Subtitle, "Nr. 42".to_owned()),
+            (LineStyle::Heading, "Client".to_owned()),
+            (LineStyle::Rule, String::new()),
+            (LineStyle::Blank, String::new()),
+            (LineStyle::Text, "Ion {}".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn unknown_placeholders_are_rejected() {
+    assert_eq!(
+        template_error("# Certificat\nNume: {nume}"),
+        "line 2 of template: unknown placeholder {nume}"
+    );
+    assert_eq!(template_error("Nume: {name"), "line 1 of template: unclosed placeholder {name");
+    assert_eq!(template_error("Nume: name}"), "line 1 of template: unmatched }");
+}
+
+#[test]
+fn default_template_is_valid() {
+    assert!(!CertificateTemplate::default().lines().is_empty());
+}
@@ -0,0 +1,362 @@
+//! The harness of the end-to-end tests
+//!
+//! Builds the real router of [`app_router`] or [`setup_app`], signs tokens with throwaway keys
+//! and sends requests through it with `oneshot`, like a client would.
+
+#![allow(dead_code)]
+
+pub mod mongod;
+
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, Once};
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use delivery_backend::app::{app_router, setup_app};
+use delivery_backend::auth::password::gen_password_hash;
+use delivery_backend::auth::store::setup_store;
+use delivery_backend::certificate::CertificateTemplate;
+use delivery_backend::customer::{
+    Address, ApplianceIn, DeliveryCustomerIn, GeoPoint, OperationPerformed, PhoneNumber
+};
+use delivery_backend::database::{connect_database, Database};
+use delivery_backend::geocoding::{Geocoder, StubGeocoder};
+use delivery_backend::repository::{
+    InMemoryCustomerRepository, InMemoryUserRepository, Repositories
+};
+use delivery_backend::state::AppState;
+use mongodb::bson::doc;
+use mongodb::Client as MongoClient;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use self::mongod::Mongod;
+
+/// The dispatcher the harness logs in as
+pub const USERNAME: &str = "dispecer";
+pub const PASSWORD: &str = "parola-buna";
+
+/// A second user, the technician appointments are made for
+pub const TECHNICIAN: &str = "tehnician";
+
+/// The value of `ADMIN_SECRET` while the tests run
+pub const ADMIN_SECRET: &str = "secret-de-test";
+
+/// Where Cluj-Napoca is, for the geocoder and the geo queries
+pub fn cluj() -> GeoPoint {
+    GeoPoint::new(46.77, 23.59)
+}
+
+pub fn customer(
+    customer_id: &str,
+    name: &str,
+    expiration_date: DateTime<FixedOffset>
+) -> DeliveryCustomerIn {
+    let appliance = ApplianceIn {
+        manufacturer: "Vaillant".into(),
+        year_of_manufacture: "2015".into(),
+        model: "ecoTEC".into(),
+        typ: "centrala".into(),
+        warranty: expiration_date - Duration::days(3 * 365),
+        operation_performed: OperationPerformed::VTP,
+        number: "A-42".into(),
+        date: expiration_date - Duration::days(2 * 365),
+        expiration_date,
+        observations: None
+    };
+
+    DeliveryCustomerIn::new(
+        customer_id.into(),
+        name.into(),
+        true,
+        Address::new("CJ".into(), "Strada Lunga".into(), "12".into(), String::new()),
+        appliance
+    )
+}
+
+pub fn days_from_now(days: i64) -> DateTime<FixedOffset> {
+    (Utc::now() + Duration::days(days)).into()
+}
+
+/// The seeded customers
+///
+/// * `C-1` and `C-2` are expired, `C-1` has a phone number, an email and a location
+/// * `C-3` is due in 5 days, so it's the only one on the reminder worklist
+/// * `C-4` lives in a locality the in-memory geocoder knows
+/// * `C-5` to `C-7` are far from expiring, they're there to be changed by the tests
+pub fn customers() -> Vec<DeliveryCustomerIn> {
+    let mut reachable = customer("C-1", "Ion Popescu", days_from_now(-10));
+    reachable.phones.push(PhoneNumber { label: "mobil".into(), number: "+40722123456".into() });
+    reachable.email = Some("ion.popescu@example.com".into());
+    reachable.location = Some(cluj());
+
+    let mut locatable = customer("C-4", "Ana Marin", days_from_now(400));
+    locatable.address.locality = "Cluj-Napoca".into();
+
+    vec![
+        reachable,
+        customer("C-2", "Maria Ionescu", days_from_now(-20)),
+        customer("C-3", "Vasile Pop", days_from_now(5)),
+        locatable,
+        customer("C-5", "Elena Dumitrescu", days_from_now(300)),
+        customer("C-6", "Radu Stan", days_from_now(200)),
+        customer("C-7", "Ioana Vlad", days_from_now(250)),
+    ]
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Generate a throwaway ES256 key pair and point the app at it
+///
+/// The keys are read once, the first time a token is signed or verified,
+/// so every test of the binary shares the same pair. Also sets `ADMIN_SECRET`.
+pub fn install_keys() {
+    static KEYS: Once = Once::new();
+
+    KEYS.call_once(|| {
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .expect("a P-256 key pair is generated");
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref())
+            .expect("the generated key pair is valid");
+
+        // SubjectPublicKeyInfo of an uncompressed P-256 point, up to the point itself
+        let mut public_key = hex("3059301306072a8648ce3d020106082a8648ce3d030107034200");
+        public_key.extend_from_slice(key_pair.public_key().as_ref());
+
+        let directory = env::temp_dir().join(format!("delivery-e2e-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let private_key_path = directory.join("private_key.pem");
+        let public_key_path = directory.join("public_key.pem");
+        write_pem(&private_key_path, "PRIVATE KEY", pkcs8.as_ref());
+        write_pem(&public_key_path, "PUBLIC KEY", &public_key);
+
+        env::set_var("PRIVATE_KEY_PATH", private_key_path);
+        env::set_var("PUBLIC_KEY_PATH", public_key_path);
+        env::set_var("ADMIN_SECRET", ADMIN_SECRET);
+    });
+}
+
+fn hex(digits: &str) -> Vec<u8> {
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn write_pem(path: &Path, label: &str, der: &[u8]) {
+    use base64::Engine;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let lines = encoded.as_bytes().chunks(64).map(|line| std::str::from_utf8(line).unwrap());
+    let pem = format!(
+        "-----BEGIN {label}-----\n{}\n-----END {label}-----\n",
+        lines.collect::<Vec<_>>().join("\n")
+    );
+
+    std::fs::write(path, pem).unwrap();
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Both users, stored in memory
+pub fn in_memory_users() -> InMemoryUserRepository {
+    InMemoryUserRepository::new().with_user(USERNAME, PASSWORD).with_user(TECHNICIAN, PASSWORD)
+}
+
+/// An [`AppState`] over the in-memory repositories, seeded with `customers` and both users
+pub async fn in_memory_state(
+    customers: InMemoryCustomerRepository,
+    geocoder: Arc<dyn Geocoder>
+) -> AppState {
+    AppState::new(
+        Repositories::in_memory(customers, in_memory_users()),
+        setup_store(),
+        geocoder,
+        Arc::new(CertificateTemplate::default())
+    )
+}
+
+/// An [`AppState`] whose users are in memory and everything else is on a MongoDB that's down
+///
+/// The client points at a port nothing listens on and gives up quickly.
+pub async fn unreachable_mongo_state() -> AppState {
+    let client = MongoClient::with_uri_str(
+        "mongodb://127.0.0.1:9/?serverSelectionTimeoutMS=200&connectTimeoutMS=200"
+    )
+    .await
+    .unwrap();
+
+    let repositories = Repositories {
+        users: Arc::new(in_memory_users()),
+        ..Repositories::mongo(&Database::new(client))
+    };
+
+    AppState::new(
+        repositories,
+        setup_store(),
+        stub_geocoder(),
+        Arc::new(CertificateTemplate::default())
+    )
+}
+
+/// The geocoder of the in-memory harness, it only knows Cluj-Napoca
+pub fn stub_geocoder() -> Arc<dyn Geocoder> {
+    Arc::new(StubGeocoder::new().with_locality("CJ", "Cluj-Napoca", cluj()))
+}
+
+/// The router of the app, logged in as [`USERNAME`]
+pub struct Harness {
+    router: Router,
+    access_token: String,
+    /// Kept until the harness is dropped, which stops the server
+    _mongod: Option<Mongod>
+}
+
+impl Harness {
+    /// A [`Harness`] over the in-memory repositories
+    pub async fn in_memory() -> Self {
+        let customers = InMemoryCustomerRepository::new().with_customers(customers());
+
+        Self::over(in_memory_state(customers, stub_geocoder()).await).await
+    }
+
+    /// A [`Harness`] over `state`
+    pub async fn over(state: AppState) -> Self {
+        install_keys();
+
+        Self::login(app_router(state), None).await
+    }
+
+    /// A [`Harness`] built by [`setup_app`] over a `mongod` started for it
+    ///
+    /// `None` if there's no `mongod` binary to start, see [`Mongod::start`].
+    pub async fn mongod() -> Option<Self> {
+        install_keys();
+
+        let mongod = Mongod::start()?;
+
+        let router = setup_app(mongod.url()).await.expect("the app is set up against mongod");
+        seed_mongo(mongod.url()).await;
+
+        Some(Self::login(router, Some(mongod)).await)
+    }
+
+    async fn login(router: Router, mongod: Option<Mongod>) -> Self {
+        let login = json!({ "username": USERNAME, "password": PASSWORD });
+        let mut harness = Self { router, access_token: String::new(), _mongod: mongod };
+
+        let reply = harness.send(json(Method::POST, "/auth/login", login)).await;
+        assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+
+        let access_token = reply.json()["auth_body"]["access_token"].as_str().unwrap().to_owned();
+        harness.access_token = access_token;
+        harness
+    }
+
+    /// The bearer token of [`USERNAME`]
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    /// Send `request` through the router
+    pub async fn send(&self, request: Request<Body>) -> Reply {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        Reply { status, headers, body }
+    }
+
+    /// Send `request` with the bearer token of [`USERNAME`]
+    pub async fn send_authorized(&self, mut request: Request<Body>) -> Reply {
+        let authorization = format!("Bearer {}", self.access_token);
+        request.headers_mut().insert(header::AUTHORIZATION, authorization.parse().unwrap());
+
+        self.send(request).await
+    }
+
+    pub async fn get(&self, uri: &str) -> Reply {
+        self.send(empty(Method::GET, uri)).await
+    }
+}
+
+/// Insert both users and the seeded customers into the database at `url`
+async fn seed_mongo(url: &str) {
+    let client = MongoClient::with_uri_str(url).await.unwrap();
+    let users = [USERNAME, TECHNICIAN].map(|username| {
+        doc! { "username": username, "password": gen_password_hash(PASSWORD).unwrap() }
+    });
+    client.database("delivery_database").collection("user").insert_many(users, None).await.unwrap();
+
+    let database = connect_database(url).await.unwrap();
+    database.customer().insert_customers(customers()).await.unwrap();
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The response to a request, with it's body read
+#[derive(Debug)]
+pub struct Reply {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes
+}
+
+impl Reply {
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|err| panic!("{err}: {}", String::from_utf8_lossy(&self.body)))
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn header(&self, name: header::HeaderName) -> &str {
+        self.headers.get(&name).and_then(|value| value.to_str().ok()).unwrap_or_default()
+    }
+
+    /// The `customer_id`s of a page of customers, or of the bare array `/search` responds with
+    pub fn customer_ids(&self) -> Vec<String> {
+        let body = self.json();
+        let customers = body.get("customers").unwrap_or(&body);
+        customers
+            .as_array()
+            .unwrap_or_else(|| panic!("not a list of customers: {body}"))
+            .iter()
+            .map(|customer| customer["customer_id"].as_str().unwrap().to_owned())
+            .collect()
+    }
+}
+
+pub fn empty(method: Method, uri: &str) -> Request<Body> {
+    Request::builder().method(method).uri(uri).body(Body::empty()).unwrap()
+}
+
+pub fn json(method: Method, uri: &str, body: Value) -> Request<Body> {
+    with_body(method, uri, "application/json", body.to_string())
+}
+
+pub fn with_body(method: Method, uri: &str, content_type: &str, body: String) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// `request` with the extra `name: value` header
+pub fn with_header(mut request: Request<Body>, name: &'static str, value: &str) -> Request<Body> {
+    request.headers_mut().insert(name, value.parse().unwrap());
+    request
+}
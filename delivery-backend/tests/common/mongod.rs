@@ -0,0 +1,98 @@
+//! A throwaway `mongod`, started for a single test
+
+use std::env;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long `mongod` gets to start accepting connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many servers this test binary started, so each gets it's own data directory
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+/// A `mongod` listening on a free port of `127.0.0.1`, with it's own data directory
+///
+/// The process is killed and the data directory removed when it's dropped.
+#[derive(Debug)]
+pub struct Mongod {
+    process: Child,
+    dbpath: PathBuf,
+    url: String
+}
+
+impl Mongod {
+    /// The `mongod` binary, `MONGOD` if it's set, otherwise the first one on the `PATH`
+    pub fn binary() -> Option<PathBuf> {
+        if let Some(binary) = env::var_os("MONGOD") {
+            return Some(PathBuf::from(binary));
+        }
+
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|directory| directory.join("mongod"))
+            .find(|binary| binary.is_file())
+    }
+
+    /// Start `mongod`, `None` if there's no binary to start
+    ///
+    /// Panics if the binary is there, but the server doesn't come up.
+    pub fn start() -> Option<Self> {
+        let binary = Self::binary()?;
+
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let dbpath = env::temp_dir().join(format!(
+            "delivery-e2e-mongod-{}-{}",
+            std::process::id(),
+            STARTED.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dbpath).unwrap();
+
+        let process = Command::new(&binary)
+            .arg("--dbpath")
+            .arg(&dbpath)
+            .args(["--bind_ip", "127.0.0.1", "--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|err| panic!("Failed to start {}: {err}", binary.display()));
+
+        let mut mongod = Self {
+            process,
+            dbpath,
+            url: format!("mongodb://127.0.0.1:{port}/?directConnection=true")
+        };
+        mongod.wait_until_listening(SocketAddr::from(([127, 0, 0, 1], port)));
+
+        Some(mongod)
+    }
+
+    /// The URL of the server
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn wait_until_listening(&mut self, address: SocketAddr) {
+        let started = Instant::now();
+
+        while TcpStream::connect_timeout(&address, Duration::from_millis(100)).is_err() {
+            if let Some(status) = self.process.try_wait().unwrap() {
+                panic!("mongod exited with {status} before accepting connections");
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                panic!("mongod didn't accept connections on {address} in {STARTUP_TIMEOUT:?}");
+            }
+
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Mongod {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.dbpath);
+    }
+}
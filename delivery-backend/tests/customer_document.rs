@@ -0,0 +1,231 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use delivery_backend::customer::{
+    Address, ApplianceIn, ConsentRecordIn, ConsentSource, ContactChannel, DeliveryCustomerIn,
+    DeliveryCustomerOut, GeoPoint, OperationPerformed, PhoneNumber
+};
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+
+fn datetime(millis: i64) -> DateTime<FixedOffset> {
+    FixedOffset::east_opt(2 * 3600).unwrap().timestamp_millis_opt(millis).unwrap()
+}
+
+fn bson_datetime(millis: i64) -> bson::DateTime {
+    bson::DateTime::from_millis(millis)
+}
+
+fn appliance(observations: Option<String>) -> ApplianceIn {
+    ApplianceIn {
+        manufacturer: "Vaillant".into(),
+        year_of_manufacture: "2015".into(),
+        model: "ecoTEC".into(),
+        typ: "centrala".into(),
+        warranty: datetime(1_500_000_000_000),
+        operation_performed: OperationPerformed::VTP,
+        number: "A-42".into(),
+        date: datetime(1_600_000_000_000),
+        expiration_date: datetime(1_700_000_000_000),
+        observations
+    }
+}
+
+/// A customer with every optional field filled in
+fn full_customer(id: ObjectId) -> DeliveryCustomerIn {
+    let mut address =
+        Address::new("CJ".into(), "Strada Lunga".into(), "12".into(), "langa parc".into());
+    address.locality = "Cluj-Napoca".into();
+    address.postal_code = Some("400001".into());
+    address.apartment = Some("3".into());
+
+    let mut customer = DeliveryCustomerIn::new(
+        "C-1".into(),
+        "Ion Popescu".into(),
+        true,
+        address,
+        appliance(Some("fara probleme".into()))
+    );
+    customer.id = id;
+    customer.phones = vec![PhoneNumber { label: "mobile".into(), number: "+40712345678".into() }];
+    customer.email = Some("ion@example.com".into());
+    customer.preferred_channel = Some(ContactChannel::Sms);
+    customer.consent.notifications = Some(ConsentRecordIn {
+        granted: true,
+        at: datetime(1_650_000_000_000),
+        source: ConsentSource::InPerson
+    });
+    customer.location = Some(GeoPoint::new(46.77, 23.59));
+
+    customer
+}
+
+/// The document [`full_customer`] was always stored as
+fn full_customer_document(id: ObjectId) -> Document {
+    doc! {
+        "_id": id,
+        "customer_id": "C-1",
+        "name": "Ion Popescu",
+        "active": true,
+        "address": {
+            "county": "CJ",
+            "locality": "Cluj-Napoca",
+            "postal_code": "400001",
+            "street": "Strada Lunga",
+            "number": "12",
+            "building": null,
+            "staircase": null,
+            "apartment": "3",
+            "additional": "langa parc"
+        },
+        "appliance": {
+            "manufacturer": "Vaillant",
+            "year_of_manufacture": "2015",
+            "model": "ecoTEC",
+            "type": "centrala",
+            "warranty": bson_datetime(1_500_000_000_000),
+            "operation_performed": "VTP",
+            "number": "A-42",
+            "date": bson_datetime(1_600_000_000_000),
+            "expiration_date": bson_datetime(1_700_000_000_000),
+            "observations": "fara probleme"
+        },
+        "phones": [{ "label": "mobile", "number": "+40712345678" }],
+        "email": "ion@example.com",
+        "preferred_channel": "sms",
+        "consent": {
+            "marketing": null,
+            "notifications": {
+                "granted": true,
+                "at": bson_datetime(1_650_000_000_000),
+                "source": "in_person"
+            }
+        },
+        "location": { "type": "Point", "coordinates": [23.59, 46.77] },
+        "version": 0i64
+    }
+}
+
+#[test]
+fn customer_serializes_to_the_stored_shape() {
+    let id = ObjectId::new();
+
+    assert_eq!(bson::to_document(&full_customer(id)).unwrap(), full_customer_document(id));
+}
+
+#[test]
+fn missing_observations_are_stored_as_empty_string() {
+    let customer = DeliveryCustomerIn::new(
+        "C-2".into(),
+        "Maria".into(),
+        false,
+        Address::new("B".into(), "Bulevardul Unirii".into(), "1".into(), String::new()),
+        appliance(None)
+    );
+
+    let document = customer.into_document().unwrap();
+    let appliance = document.get_document("appliance").unwrap();
+
+    assert_eq!(appliance.get_str("observations"), Ok(""));
+    assert_eq!(document.get("phones"), Some(&bson::Bson::Array(Vec::new())));
+    assert_eq!(document.get("email"), Some(&bson::Bson::Null));
+    assert_eq!(document.get("location"), Some(&bson::Bson::Null));
+    assert_eq!(
+        document.get_document("consent").unwrap(),
+        &doc! {
+            "marketing": null,
+            "notifications": null
+        }
+    );
+}
+
+#[test]
+fn replacement_document_leaves_identity_and_version_untouched() {
+    let id = ObjectId::new();
+    let mut expected = full_customer_document(id);
+    for key in ["_id", "customer_id", "version"] {
+        expected.remove(key);
+    }
+
+    assert_eq!(full_customer(id).into_replacement_document().unwrap(), doc! { "$set": expected });
+}
+
+#[test]
+fn update_document_only_sets_the_given_paths() {
+    let paths = ["name", "address.building", "address.street", "appliance.number", "email"];
+
+    assert_eq!(
+        full_customer(ObjectId::new()).into_update_document(&paths).unwrap(),
+        doc! {
+            "$set": {
+                "name": "Ion Popescu",
+                "address.building": null,
+                "address.street": "Strada Lunga",
+                "appliance.number": "A-42",
+                "email": "ion@example.com"
+            },
+            "$inc": { "version": 1 }
+        }
+    );
+}
+
+#[test]
+fn stored_customer_reads_back_unchanged() {
+    let id = ObjectId::new();
+    let customer =
+        DeliveryCustomerOut::try_from(full_customer(id).into_document().unwrap()).unwrap();
+
+    assert_eq!(customer.id, id);
+    assert_eq!(customer.customer_id, "C-1");
+    assert_eq!(customer.address.postal_code.as_deref(), Some("400001"));
+    assert_eq!(customer.appliance.operation_performed, OperationPerformed::VTP);
+    assert_eq!(customer.appliance.warranty, Utc.timestamp_millis_opt(1_500_000_000_000).unwrap());
+    assert_eq!(
+        customer.appliance.expiration_date,
+        Utc.timestamp_millis_opt(1_700_000_000_000).unwrap()
+    );
+    assert_eq!(customer.appliance.observations.as_deref(), Some("fara probleme"));
+    assert_eq!(customer.phones[0].number, "+40712345678");
+    assert_eq!(customer.preferred_channel, Some(ContactChannel::Sms));
+    assert!(customer.consent.marketing.is_none());
+
+    let notifications = customer.consent.notifications.unwrap();
+    assert!(notifications.granted);
+    assert_eq!(notifications.at, Utc.timestamp_millis_opt(1_650_000_000_000).unwrap());
+    assert_eq!(notifications.source, ConsentSource::InPerson);
+    assert_eq!(customer.location, Some(GeoPoint::new(46.77, 23.59)));
+    assert_eq!(customer.version, 0);
+}
+
+#[test]
+fn cleared_update_fields_are_unset_and_set_fields_keep_their_stored_shape() {
+    let patch: delivery_backend::query::PartialDeliveryCustomer =
+        serde_json::from_value(serde_json::json!({
+            "customer_id": "C-1",
+            "date": "2024-03-01T10:00:00+02:00",
+            "phones": [{ "label": "home", "number": "0264 123 456" }],
+            "preferred_channel": "email",
+            "marketing_consent": {
+                "granted": false,
+                "at": "2024-03-01T10:00:00+02:00",
+                "source": "paper"
+            },
+            "email": null,
+            "location": null
+        }))
+        .unwrap();
+
+    let at = bson::DateTime::from_chrono(
+        DateTime::parse_from_rfc3339("2024-03-01T10:00:00+02:00").unwrap()
+    );
+
+    assert_eq!(
+        patch.into_update_document_no_none().unwrap(),
+        doc! {
+            "$set": {
+                "appliance.date": at,
+                "phones": [{ "label": "home", "number": "+40264123456" }],
+                "preferred_channel": "email",
+                "consent.marketing": { "granted": false, "at": at, "source": "paper" }
+            },
+            "$unset": { "email": "", "location": "" }
+        }
+    );
+}
@@ -0,0 +1,156 @@
+//! Runs the customer collection against a local mongod, like the one in `docker-compose.yml`
+//!
+//! Ignored by default, run them with `just test-mongo`. The database is taken from `MONGO_URL`.
+//! Every test uses it's own customers and removes them afterwards.
+
+mod common;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use common::customer;
+use delivery_backend::customer::DeliveryCustomerIn;
+use delivery_backend::database::{mongo_url, setup_database, Database};
+use delivery_backend::query::{ExpiredCustomersQuery, PartialDeliveryCustomer};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::{Client as MongoClient, Collection as MongoCollection};
+
+async fn customer_collection() -> MongoCollection<Document> {
+    let client = MongoClient::with_uri_str(mongo_url()).await.unwrap();
+
+    client.database("delivery_database").collection("customer")
+}
+
+async fn database() -> Arc<Database> {
+    setup_database(&mongo_url()).await.expect("a local mongod is running")
+}
+
+/// Removes the customers with `ids` once a test is done, even if it failed
+struct Cleanup(Vec<ObjectId>);
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        let ids = std::mem::take(&mut self.0);
+
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new().unwrap().block_on(async {
+                customer_collection()
+                    .await
+                    .delete_many(doc! { "_id": { "$in": ids } }, None)
+                    .await
+                    .unwrap();
+            })
+        })
+        .join()
+        .unwrap();
+    }
+}
+
+fn expired_customer(
+    customer_id: &str,
+    expiration_date: DateTime<FixedOffset>
+) -> DeliveryCustomerIn {
+    let mut customer = customer(customer_id, "Ion Popescu", expiration_date);
+    customer.appliance.observations = Some("fara probleme".into());
+    customer
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn inserted_customer_is_fetched_updated_and_listed_as_expired() {
+    let database = database().await;
+    let customer_id = format!("T-{}", ObjectId::new().to_hex());
+    let expiration_date = (Utc::now() - Duration::days(10)).into();
+
+    let customer = expired_customer(&customer_id, expiration_date);
+    let _cleanup = Cleanup(vec![customer.id]);
+    database.customer().insert_customer(customer).await.unwrap();
+
+    let stored = database.customer().get_raw_customer(&customer_id).await.unwrap().unwrap();
+    assert!(!stored.contains_key("$set"));
+    assert_eq!(stored.get_i64("version"), Ok(0));
+
+    let fetched = database.customer().get_customer(&customer_id).await.unwrap();
+    assert_eq!(fetched.name, "Ion Popescu");
+    assert_eq!(fetched.appliance.observations.as_deref(), Some("fara probleme"));
+
+    let update: PartialDeliveryCustomer = serde_json::from_value(serde_json::json!({
+        "customer_id": customer_id,
+        "name": "Ion Ionescu",
+        "observations": null
+    }))
+    .unwrap();
+    database.customer().update_customer(update, None).await.unwrap();
+
+    let updated = database.customer().get_customer(&customer_id).await.unwrap();
+    assert_eq!(updated.name, "Ion Ionescu");
+    assert_eq!(updated.appliance.observations.as_deref(), Some(""));
+    assert_eq!(updated.version, 1);
+
+    let query = ExpiredCustomersQuery {
+        start_date: Some(expiration_date - Duration::days(1)),
+        end_date: Some(expiration_date + Duration::days(1)),
+        limit: Some(500),
+        ..Default::default()
+    };
+    let expired = database.customer().expired_customers(query).await.unwrap();
+
+    assert!(expired.into_iter().any(|customer| customer.customer_id == customer_id));
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn customers_stored_as_set_documents_are_unwrapped() {
+    let database = database().await;
+    let customer = expired_customer("ignored", (Utc::now() - Duration::days(10)).into());
+
+    // The shape `insert_customer` used to store, without `_id` and `customer_id`
+    let mut fields = customer.into_document().unwrap();
+    fields.remove("_id");
+    fields.remove("customer_id");
+
+    let inserted = customer_collection().await.insert_one(doc! { "$set": fields }, None).await;
+    let id = inserted.unwrap().inserted_id.as_object_id().unwrap();
+    let _cleanup = Cleanup(vec![id]);
+
+    let repaired = database.customer().unwrap_set_documents().await.unwrap();
+    assert!(repaired.contains(&id));
+
+    // Already repaired, so it isn't counted again
+    let repaired = database.customer().unwrap_set_documents().await.unwrap();
+    assert!(!repaired.contains(&id));
+
+    let fetched = database.customer().get_customer(&id.to_hex()).await.unwrap();
+    assert_eq!(fetched.id, id);
+    assert_eq!(fetched.name, "Ion Popescu");
+
+    let stored = database.customer().get_raw_customer(&id.to_hex()).await.unwrap().unwrap();
+    assert!(!stored.contains_key("$set"));
+    assert_eq!(stored.get_str("customer_id"), Ok(id.to_hex().as_str()));
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn counties_are_normalized_once() {
+    let database = database().await;
+    let customer_id = format!("T-{}", ObjectId::new().to_hex());
+
+    let mut fields = expired_customer(&customer_id, (Utc::now() - Duration::days(10)).into())
+        .into_document()
+        .unwrap();
+    fields.get_document_mut("address").unwrap().insert("county", "cluj");
+
+    let inserted = customer_collection().await.insert_one(fields, None).await;
+    let id = inserted.unwrap().inserted_id.as_object_id().unwrap();
+    let _cleanup = Cleanup(vec![id]);
+
+    assert!(database.customer().normalize_counties().await.unwrap() >= 1);
+
+    let fetched = database.customer().get_customer(&customer_id).await.unwrap();
+    assert_eq!(fetched.address.county, "CJ");
+    assert_eq!(fetched.version, 1);
+
+    database.customer().normalize_counties().await.unwrap();
+    let fetched = database.customer().get_customer(&customer_id).await.unwrap();
+    assert_eq!(fetched.version, 1);
+}
@@ -0,0 +1,668 @@
+//! Runs every route of the app end-to-end, through the router of `app_router`
+//!
+//! The checks run against the in-memory repositories by default. The routes that only exist
+//! on the MongoDB collections (exports, geo queries, imports, certificates, appointments,
+//! route planning, calendar feeds and the admin endpoints) are checked by
+//! `every_route_against_mongod`, which is ignored by default and starts it's own `mongod`,
+//! run it with `just test-e2e`.
+//!
+//! `/history` isn't implemented yet, `history_is_not_implemented_yet` checks it says so.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::{header, Method, StatusCode};
+use common::{
+    customers, empty, in_memory_state, json, unreachable_mongo_state, with_body, with_header,
+    Harness, ADMIN_SECRET, PASSWORD, TECHNICIAN, USERNAME
+};
+use delivery_backend::customer::Address;
+use delivery_backend::error::AppError;
+use delivery_backend::geocoding::{Geocoder, StubGeocoder};
+use delivery_backend::repository::InMemoryCustomerRepository;
+use mongodb::bson::{doc, oid::ObjectId};
+use serde_json::json;
+
+/// A valid new customer, in the JSON a client sends
+fn new_customer(id: ObjectId, customer_id: &str) -> serde_json::Value {
+    json!({
+        "_id": { "$oid": id.to_hex() },
+        "customer_id": customer_id,
+        "name": "Dan Georgescu",
+        "active": true,
+        "address": { "county": "CJ", "street": "Strada Scurta", "number": "3" },
+        "appliance": {
+            "manufacturer": "Ariston",
+            "year_of_manufacture": "2019",
+            "model": "Clas One",
+            "type": "centrala",
+            "warranty": "2027-05-01T00:00:00+03:00",
+            "operation_performed": "VTP",
+            "number": "B-7",
+            "date": "2026-05-01T00:00:00+03:00",
+            "expiration_date": "2028-05-01T00:00:00+03:00"
+        },
+        "location": { "type": "Point", "coordinates": [23.59, 46.77] }
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////
+// Checks over the repositories, they pass against the in-memory ones and MongoDB alike.
+// Every check changes it's own customers, so they can run one after the other on the same data.
+
+async fn customer_reads(harness: &Harness) {
+    let reply = harness.get("/customer/C-1").await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.header(header::ETAG), "\"v0\"");
+    assert_eq!(reply.json()["name"], "Ion Popescu");
+    let oid = reply.json()["_id"].as_str().unwrap().to_owned();
+
+    let revalidate = with_header(empty(Method::GET, "/customer/C-1"), "if-none-match", "\"v0\"");
+    assert_eq!(harness.send(revalidate).await.status, StatusCode::NOT_MODIFIED);
+
+    let reply = harness.get(&format!("/customer/by-oid/{oid}")).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.json()["customer_id"], "C-1");
+
+    let reply = harness.get("/customer/C-404").await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND);
+    assert!(reply.json()["error"].is_string());
+
+    let reply = harness.get("/customer/by-oid/not-an-object-id").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+}
+
+async fn customer_lists(harness: &Harness) {
+    let reply = harness.get("/customer?manufacturer=Vaillant&sort=customer_id&limit=4").await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.customer_ids(), ["C-1", "C-2", "C-3", "C-4"]);
+    assert_eq!(reply.json()["total"], 7);
+
+    let cursor = reply.json()["next_cursor"].as_str().expect("there is a next page").to_owned();
+    let uri = format!("/customer?manufacturer=Vaillant&sort=customer_id&limit=4&cursor={cursor}");
+    let reply = harness.get(&uri).await;
+    assert_eq!(reply.customer_ids(), ["C-5", "C-6", "C-7"]);
+    assert!(reply.json()["next_cursor"].is_null());
+
+    let reply = harness.get("/customer?cursor=not-a-cursor").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert!(reply.json()["error"].is_string());
+
+    let reply = harness.get("/customer/expired").await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.customer_ids(), ["C-2", "C-1"]);
+}
+
+async fn customer_search(harness: &Harness) {
+    let search = |query: &str| json(Method::POST, &format!("/search?query={query}"), json!({}));
+
+    let reply = harness.send(search("0722123456")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.customer_ids(), ["C-1"]);
+
+    assert_eq!(harness.send(search("ion.popescu@example.com")).await.customer_ids(), ["C-1"]);
+    assert_eq!(harness.send(search("Ionescu")).await.customer_ids(), ["C-2"]);
+}
+
+async fn customer_writes(harness: &Harness) {
+    let id = ObjectId::new();
+    let reply = harness.send(json(Method::POST, "/customer/create", new_customer(id, "C-8"))).await;
+    assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+    assert_eq!(reply.json()["inserted_id"], id.to_hex());
+    assert_eq!(harness.get("/customer/C-8").await.json()["name"], "Dan Georgescu");
+
+    let mut nameless = new_customer(ObjectId::new(), "C-9");
+    nameless["name"] = json!("");
+    let reply = harness.send(json(Method::POST, "/customer/create", nameless)).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(reply.json().get("name").is_some(), "{}", reply.text());
+
+    let update = json!({ "customer_id": "C-5", "name": "Elena Dumitrescu-Pop" });
+    let reply = harness.send(json(Method::PUT, "/customer/update", update.clone())).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["matched_count"], 1);
+
+    let stale = with_header(json(Method::PUT, "/customer/update", update), "if-match", "\"v0\"");
+    let reply = harness.send(stale).await;
+    assert_eq!(reply.status, StatusCode::PRECONDITION_FAILED);
+    assert!(reply.json()["error"].is_string());
+
+    let merge = with_body(
+        Method::PATCH,
+        "/customer/C-5",
+        "application/merge-patch+json",
+        json!({ "email": "elena@example.com" }).to_string()
+    );
+    let reply = harness.send(with_header(merge, "if-match", "\"v1\"")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.header(header::ETAG), "\"v2\"");
+    assert_eq!(reply.json()["email"], "elena@example.com");
+
+    let immutable = json!([{ "op": "replace", "path": "/customer_id", "value": "C-50" }]);
+    let patch = with_body(
+        Method::PATCH,
+        "/customer/C-5",
+        "application/json-patch+json",
+        immutable.to_string()
+    );
+    let reply = harness.send(patch).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(reply.json()["error"].is_string());
+
+    let plain = with_body(Method::PATCH, "/customer/C-5", "text/plain", "name=Elena".into());
+    let reply = harness.send(plain).await;
+    assert_eq!(reply.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(reply.json()["error"].is_string());
+
+    let reply = harness.send(empty(Method::PATCH, "/customer/deactivate/C-5")).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(harness.get("/customer/C-5").await.json()["active"], false);
+
+    let reply = harness.send(empty(Method::PATCH, "/customer/activate/C-5")).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(harness.get("/customer/C-5").await.json()["active"], true);
+}
+
+async fn customer_deletion(harness: &Harness) {
+    let reply = harness.send(empty(Method::DELETE, "/customer/delete/C-6")).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+    assert_eq!(reply.json()["error"], "Invalid token");
+
+    let forged =
+        with_header(empty(Method::DELETE, "/customer/delete/C-6"), "authorization", "Bearer x.y.z");
+    assert_eq!(harness.send(forged).await.status, StatusCode::BAD_REQUEST);
+
+    let reply = harness.send_authorized(empty(Method::DELETE, "/customer/delete/C-6")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["deleted_count"], 1);
+    assert_eq!(harness.get("/customer/C-6").await.status, StatusCode::NOT_FOUND);
+
+    let reply = harness.send_authorized(empty(Method::POST, "/customer/restore/C-6")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(harness.get("/customer/C-6").await.status, StatusCode::OK);
+}
+
+async fn reminder_worklist(harness: &Harness) {
+    let worklist_ids = |worklist: serde_json::Value| {
+        let groups = worklist["groups"].as_array().unwrap().clone();
+        groups
+            .into_iter()
+            .flat_map(|group| group["customers"].as_array().unwrap().clone())
+            .map(|customer| customer["customer_id"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let reply = harness.get("/customer/worklist").await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(worklist_ids(reply.json()), ["C-3"]);
+
+    let contact = json!({ "outcome": "scheduled", "note": "Vine joi" });
+    let reply = harness.send(json(Method::POST, "/customer/contact/C-3", contact.clone())).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+
+    let unknown_outcome = json!({ "outcome": "maybe" });
+    let reply =
+        harness.send_authorized(json(Method::POST, "/customer/contact/C-3", unknown_outcome)).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let reply = harness.send_authorized(json(Method::POST, "/customer/contact/C-3", contact)).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert!(worklist_ids(harness.get("/customer/worklist").await.json()).is_empty());
+}
+
+async fn geocoding(harness: &Harness) {
+    let reply = harness.send(empty(Method::POST, "/customer/geocode/C-2")).await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND);
+    assert!(reply.json()["error"].is_string());
+
+    let reply = harness.send(empty(Method::POST, "/customer/geocode/C-404")).await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND);
+}
+
+async fn authentication(harness: &Harness) {
+    let login = |username: &str, password: &str| {
+        json(Method::POST, "/auth/login", json!({ "username": username, "password": password }))
+    };
+
+    let reply = harness.send(login(USERNAME, "parola-gresita")).await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+    assert_eq!(reply.json()["error"], "WrongCredentials");
+
+    let reply = harness.send(login("ab", PASSWORD)).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(reply.json().get("username").is_some(), "{}", reply.text());
+
+    let reply =
+        harness.send(json(Method::POST, "/auth/login", json!({ "username": USERNAME }))).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let reply = harness.send(login(TECHNICIAN, PASSWORD)).await;
+    assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+    let body = reply.json();
+    assert_eq!(body["auth_body"]["token_type"], "Bearer");
+    let refresh_token = body["refresh_token"].clone();
+
+    let reply = harness.send(json(Method::POST, "/auth/refresh", refresh_token.clone())).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert!(reply.json()["access_token"].is_string());
+
+    let mut tampered = refresh_token.clone();
+    tampered["token"] = json!("not-the-token");
+    let reply = harness.send(json(Method::POST, "/auth/refresh", tampered)).await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+    assert_eq!(reply.json()["error"], "InvalidToken");
+
+    let mut unknown = refresh_token;
+    unknown["id"] = json!("not-an-id");
+    let reply = harness.send(json(Method::POST, "/auth/refresh", unknown)).await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+    assert_eq!(reply.json()["error"], "WrongCredentials");
+
+    assert_eq!(harness.send(empty(Method::POST, "/auth/logout")).await.status, StatusCode::OK);
+}
+
+/// Creates a calendar token, returns it
+async fn calendar_token(harness: &Harness) -> String {
+    let reply = harness.send(empty(Method::POST, "/calendar/token")).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+
+    let reply = harness.send_authorized(empty(Method::POST, "/calendar/token")).await;
+    assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+    let body = reply.json();
+    let token = body["token"].as_str().unwrap().to_owned();
+    assert_eq!(body["feed"], format!("/calendar/{USERNAME}.ics?token={token}"));
+
+    let reply = harness.get("/calendar/office/expirations.ics?token=not-the-token").await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+
+    let reply = harness.get("/calendar/office/expirations.ics").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+
+    let reply = harness.get(&format!("/calendar/{TECHNICIAN}.ics?token={token}")).await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+
+    let reply = harness.get(&format!("/calendar/{USERNAME}?token={token}")).await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND);
+
+    token
+}
+
+async fn admin_access(harness: &Harness) {
+    let export = || empty(Method::GET, "/admin/customer/C-6/export");
+
+    let reply = harness.send_authorized(export()).await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND);
+    assert!(reply.body.is_empty());
+
+    let reply = harness.send_authorized(with_header(export(), "x-admin-secret", "ghicit")).await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+    assert_eq!(reply.json()["error"], "WrongCredentials");
+
+    let reply = harness.send(with_header(export(), "x-admin-secret", ADMIN_SECRET)).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+}
+
+/// The requests the appointment and route planning handlers reject before using the database
+async fn rejected_plans(harness: &Harness) {
+    let appointment = json!({
+        "customer_id": "C-7",
+        "technician": TECHNICIAN,
+        "start": "2030-03-04T10:00:00+02:00",
+        "end": "2030-03-04T09:00:00+02:00"
+    });
+
+    let reply = harness.send(json(Method::POST, "/appointment/create", appointment.clone())).await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+
+    let reply =
+        harness.send_authorized(json(Method::POST, "/appointment/create", appointment)).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", reply.text());
+
+    let reply = harness.get("/appointment/not-an-object-id").await;
+    assert_eq!(reply.status, StatusCode::BAD_REQUEST);
+
+    let route = json!({
+        "depot": { "type": "Point", "coordinates": [230.0, 46.77] },
+        "start": "2030-03-04T08:00:00+02:00",
+        "max_visits": 0
+    });
+    let reply = harness.send(json(Method::POST, "/route", route)).await;
+    assert_eq!(reply.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let errors = reply.json();
+    assert!(errors.get("depot").is_some() && errors.get("max_visits").is_some(), "{errors}");
+}
+
+/// Exports, geo queries, certificates, imports, appointments, routes, calendars and erasure
+async fn bulk_and_scheduling_routes(harness: &Harness, calendar_token: &str) {
+    let csv = with_header(empty(Method::GET, "/customer?sort=customer_id"), "accept", "text/csv");
+    let reply = harness.send(csv).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert!(reply.header(header::CONTENT_TYPE).starts_with("text/csv"));
+    assert!(reply.text().contains("C-1"));
+
+    let ndjson =
+        with_header(empty(Method::GET, "/customer/expired"), "accept", "application/x-ndjson");
+    let reply = harness.send(ndjson).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.text().lines().count(), 2);
+
+    let array = with_header(
+        empty(Method::GET, "/customer/expired"),
+        "accept",
+        "application/json; stream=true"
+    );
+    let reply = harness.send(array).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.header(header::CONTENT_TYPE), "application/json");
+    assert_eq!(reply.json().as_array().map(Vec::len), Some(2), "{}", reply.text());
+
+    let reply = harness.get("/customer/near?lat=46.77&lng=23.59&radius=1000").await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert!(reply.text().contains("\"C-1\""));
+
+    let reply = harness.get("/customer/C-1/certificate.pdf").await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND, "{}", reply.text());
+
+    let issue = empty(Method::POST, "/customer/C-1/certificate");
+    let reply = harness.send_authorized(issue).await;
+    assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+    let number = reply.json()["number"].clone();
+
+    let issue = empty(Method::POST, "/customer/C-1/certificate");
+    let reply = harness.send_authorized(issue).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["number"], number);
+
+    let reply = harness.get("/customer/C-1/certificate.pdf").await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.header(header::CONTENT_TYPE), "application/pdf");
+    assert!(reply.body.starts_with(b"%PDF"));
+
+    let header = "customer_id,name,county,street,number,manufacturer,year_of_manufacture,model,\
+                  type,warranty,operation_performed,appliance_number,date,expiration_date";
+    let row = "C-20,Mihai Rusu,CJ,Strada Noua,1,Vaillant,2018,ecoTEC,centrala,01.05.2020,VTP,\
+               A-1,01.05.2024,01.05.2026";
+    let import = with_body(
+        Method::POST,
+        "/customer/import?dry_run=true",
+        "text/csv",
+        format!("{header}\n{row}\n")
+    );
+    let reply = harness.send_authorized(import).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["valid_rows"], 1);
+
+    // Importing an existing customer again leaves the fields without a column alone
+    let row = "C-1,Ion Popescu,CJ,Strada Noua,5,Vaillant,2015,ecoTEC,centrala,01.05.2020,VTP,\
+               A-42,01.05.2024,01.05.2026";
+    let import =
+        with_body(Method::POST, "/customer/import", "text/csv", format!("{header}\n{row}\n"));
+    let reply = harness.send_authorized(import).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(
+        (reply.json()["inserted"].clone(), reply.json()["updated"].clone()),
+        (json!(0), json!(1))
+    );
+
+    let customer = harness.get("/customer/C-1").await.json();
+    assert_eq!(customer["address"]["street"], "Strada Noua");
+    assert_eq!(customer["email"], "ion.popescu@example.com");
+    assert_eq!(customer["phones"][0]["number"], "+40722123456");
+    assert!(customer["location"].is_object(), "{customer}");
+
+    let slot = json!({
+        "customer_id": "C-7",
+        "technician": TECHNICIAN,
+        "start": "2030-03-04T10:00:00+02:00",
+        "end": "2030-03-04T11:00:00+02:00"
+    });
+    let reply =
+        harness.send_authorized(json(Method::POST, "/appointment/create", slot.clone())).await;
+    assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+    let id = reply.json()["inserted_id"].as_str().unwrap().to_owned();
+
+    let reply = harness.send_authorized(json(Method::POST, "/appointment/create", slot)).await;
+    assert_eq!(reply.status, StatusCode::CONFLICT, "{}", reply.text());
+
+    let reply = harness.get(&format!("/appointment/{id}")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["customer_id"], "C-7");
+
+    let reply = harness.get("/appointment?customer_id=C-7").await;
+    assert_eq!(reply.json()["appointments"].as_array().unwrap().len(), 1);
+
+    let moved = json!({ "start": "2030-03-05T10:00:00+02:00", "end": "2030-03-05T11:00:00+02:00" });
+    let reschedule = json(Method::PATCH, &format!("/appointment/reschedule/{id}"), moved);
+    let reply = harness.send_authorized(reschedule).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+
+    let completion =
+        json!({ "operation_performed": "VTP", "expiration_date": "2032-03-05T00:00:00+02:00" });
+    let complete = json(Method::POST, &format!("/appointment/complete/{id}"), completion);
+    let reply = harness.send_authorized(complete).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+
+    let cancel = json(Method::PATCH, &format!("/appointment/cancel/{id}"), json!({}));
+    assert_eq!(harness.send(cancel).await.status, StatusCode::BAD_REQUEST);
+
+    let route = json!({
+        "depot": { "type": "Point", "coordinates": [23.6, 46.78] },
+        "start": "2030-03-04T08:00:00+02:00",
+        "customer_ids": ["C-1"]
+    });
+    let reply = harness.send(json(Method::POST, "/route", route)).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["stops"][0]["customer"]["customer_id"], "C-1");
+
+    let reply = harness.get(&format!("/calendar/{USERNAME}.ics?token={calendar_token}")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert!(reply.text().starts_with("BEGIN:VCALENDAR"));
+
+    let reply =
+        harness.get(&format!("/calendar/office/expirations.ics?token={calendar_token}")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert!(reply.header(header::CONTENT_TYPE).starts_with("text/calendar"));
+
+    let admin =
+        |method: Method, uri: &str| with_header(empty(method, uri), "x-admin-secret", ADMIN_SECRET);
+    let reply = harness.send_authorized(admin(Method::GET, "/admin/customer/C-6/export")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["customer"]["customer_id"], "C-6");
+
+    let reply = harness.send_authorized(admin(Method::POST, "/admin/customer/C-6/erase")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_ne!(harness.get("/customer/C-6").await.json()["name"], "Radu Stan");
+}
+
+/// Bookings of the same slot made at the same time, only one of them gets it
+async fn concurrent_bookings(harness: &Harness) {
+    let slot = json!({
+        "customer_id": "C-5",
+        "technician": TECHNICIAN,
+        "start": "2030-03-06T10:00:00+02:00",
+        "end": "2030-03-06T11:00:00+02:00"
+    });
+
+    let bookings = (0..5)
+        .map(|_| harness.send_authorized(json(Method::POST, "/appointment/create", slot.clone())));
+    let statuses = futures::future::join_all(bookings)
+        .await
+        .into_iter()
+        .map(|reply| reply.status)
+        .collect::<Vec<_>>();
+
+    assert_eq!(statuses.iter().filter(|&&status| status == StatusCode::CREATED).count(), 1);
+    assert!(
+        statuses
+            .iter()
+            .all(|&status| status == StatusCode::CREATED || status == StatusCode::CONFLICT),
+        "{statuses:?}"
+    );
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////
+// Against the in-memory repositories
+
+#[tokio::test]
+async fn customers_are_read_by_id_and_object_id() {
+    customer_reads(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn customer_lists_are_paged_and_filtered() {
+    customer_lists(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn customers_are_searched_by_phone_email_and_name() {
+    customer_search(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn customers_are_created_updated_and_patched() {
+    customer_writes(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn deleted_customers_are_restored() {
+    customer_deletion(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn contacted_customers_leave_the_worklist() {
+    reminder_worklist(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn customers_are_geocoded() {
+    let harness = Harness::in_memory().await;
+    geocoding(&harness).await;
+
+    let reply = harness.send(empty(Method::POST, "/customer/geocode/C-4")).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    let location = harness.get("/customer/C-4").await.json()["location"].clone();
+    assert_eq!(location, json!({ "type": "Point", "coordinates": [23.59, 46.77] }));
+}
+
+#[tokio::test]
+async fn users_log_in_and_refresh_their_token() {
+    authentication(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn calendar_tokens_only_open_their_own_feeds() {
+    calendar_token(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn admin_endpoints_need_the_admin_secret() {
+    admin_access(&Harness::in_memory().await).await;
+}
+
+#[tokio::test]
+async fn invalid_appointments_and_routes_are_rejected() {
+    rejected_plans(&Harness::in_memory().await).await;
+}
+
+/// A geocoder whose service is down
+#[derive(Debug)]
+struct UnreachableGeocoder;
+
+#[axum::async_trait]
+impl Geocoder for UnreachableGeocoder {
+    async fn geocode(
+        &self,
+        _: &Address
+    ) -> Result<Option<delivery_backend::customer::GeoPoint>, AppError> {
+        Err(AppError::GeocodingError("connection refused".into()))
+    }
+}
+
+#[tokio::test]
+async fn geocoder_failures_are_a_bad_gateway() {
+    let customers = InMemoryCustomerRepository::new().with_customers(customers());
+    let harness =
+        Harness::over(in_memory_state(customers, Arc::new(UnreachableGeocoder)).await).await;
+
+    let reply = harness.send(empty(Method::POST, "/customer/geocode/C-4")).await;
+    assert_eq!(reply.status, StatusCode::BAD_GATEWAY);
+    assert_eq!(reply.json()["error"], "connection refused");
+
+    // Creating a customer doesn't depend on the geocoder
+    let mut customer = new_customer(ObjectId::new(), "C-8");
+    customer.as_object_mut().unwrap().remove("location");
+    let reply = harness.send(json(Method::POST, "/customer/create", customer)).await;
+    assert_eq!(reply.status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn undecodable_customers_are_a_bson_error() {
+    let broken = doc! { "_id": ObjectId::new(), "customer_id": "C-X", "name": 42, "active": true };
+    let customers = InMemoryCustomerRepository::new().with_documents([broken]);
+    let harness =
+        Harness::over(in_memory_state(customers, Arc::new(StubGeocoder::new())).await).await;
+
+    let reply = harness.get("/customer/C-X").await;
+    assert_eq!(reply.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(reply.json()["BsonDeError"].is_string(), "{}", reply.text());
+}
+
+#[tokio::test]
+async fn unreachable_database_is_a_database_error() {
+    let harness = Harness::over(unreachable_mongo_state().await).await;
+
+    let reply = harness.get("/appointment").await;
+    assert_eq!(reply.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(reply.json()["DatabaseError"].is_string(), "{}", reply.text());
+
+    let export = with_header(empty(Method::GET, "/customer/expired"), "accept", "text/csv");
+    let reply = harness.send(export).await;
+    assert_eq!(reply.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(reply.json()["DatabaseError"].is_string(), "{}", reply.text());
+}
+
+#[tokio::test]
+async fn history_is_not_implemented_yet() {
+    let reply = Harness::in_memory().await.get("/history").await;
+    assert_eq!(reply.status, StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn customers_are_exported_imported_and_scheduled() {
+    let harness = Harness::in_memory().await;
+    let token = calendar_token(&harness).await;
+
+    bulk_and_scheduling_routes(&harness, &token).await;
+}
+
+#[tokio::test]
+async fn concurrent_bookings_get_a_single_slot() {
+    concurrent_bookings(&Harness::in_memory().await).await;
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////
+// Against a mongod started for the test
+
+#[tokio::test]
+#[ignore = "needs a mongod binary"]
+async fn every_route_against_mongod() {
+    let Some(harness) = Harness::mongod().await else {
+        eprintln!("No mongod binary on the PATH and MONGOD isn't set, skipping");
+        return;
+    };
+
+    customer_reads(&harness).await;
+    customer_lists(&harness).await;
+    customer_search(&harness).await;
+    customer_writes(&harness).await;
+    customer_deletion(&harness).await;
+    reminder_worklist(&harness).await;
+    geocoding(&harness).await;
+    authentication(&harness).await;
+    let token = calendar_token(&harness).await;
+    admin_access(&harness).await;
+    rejected_plans(&harness).await;
+    bulk_and_scheduling_routes(&harness, &token).await;
+    concurrent_bookings(&harness).await;
+}
@@ -0,0 +1,95 @@
+use axum::http::header::{ACCEPT, ACCEPT_LANGUAGE};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use delivery_backend::export::{csv_record, ExportFormat, Locale, ResponseFormat};
+
+fn headers(name: HeaderName, values: &[&str]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    for value in values {
+        headers.append(name.clone(), HeaderValue::from_str(value).unwrap());
+    }
+
+    headers
+}
+
+fn record(fields: &[&str], delimiter: char) -> String {
+    csv_record(fields.iter().map(|field| field.to_string()), delimiter)
+}
+
+#[test]
+fn csv_fields_are_quoted_when_needed() {
+    assert_eq!(record(&["C-1", "Ion Popescu", ""], ';'), "C-1;Ion Popescu;\r\n");
+    assert_eq!(
+        record(&["a;b", "a,b", "a \"b\"", "a\nb"], ';'),
+        "\"a;b\";a,b;\"a \"\"b\"\"\";\"a\nb\"\r\n"
+    );
+    assert_eq!(record(&["a;b", "a,b"], ','), "a;b,\"a,b\"\r\n");
+}
+
+#[test]
+fn csv_fields_that_start_a_formula_are_neutralized() {
+    let fields = ["=HYPERLINK(\"http://x\")", "+40 722", "-1", "@SUM(A1)", "\tTab", "\rCR", "Ion"];
+
+    assert_eq!(
+        record(&fields, ';'),
+        "\"'=HYPERLINK(\"\"http://x\"\")\";'+40 722;'-1;'@SUM(A1);'\tTab;\"'\rCR\";Ion\r\n"
+    );
+}
+
+#[test]
+fn response_format_is_negotiated_from_accept() {
+    let cases: &[(&[&str], ResponseFormat)] = &[
+        (&[], ResponseFormat::Json),
+        (&["*/*"], ResponseFormat::Json),
+        (&["application/json"], ResponseFormat::Json),
+        (&["text/csv"], ResponseFormat::Export(ExportFormat::Csv)),
+        (&["Text/CSV; charset=utf-8"], ResponseFormat::Export(ExportFormat::Csv)),
+        (&["text/csv;q=0.5, application/json"], ResponseFormat::Json),
+        (&["application/json;q=0.5, text/csv"], ResponseFormat::Export(ExportFormat::Csv)),
+        (&["text/csv, application/json"], ResponseFormat::Export(ExportFormat::Csv)),
+        (&["text/csv;q=0"], ResponseFormat::Json),
+        (&["text/csv;q=0, */*;q=0.1"], ResponseFormat::Json),
+        (&["text/html, image/png"], ResponseFormat::Json),
+        (&["text/html", "text/csv"], ResponseFormat::Export(ExportFormat::Csv)),
+        (
+            &["application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"],
+            ResponseFormat::Export(ExportFormat::Xlsx)
+        ),
+        (&["application/x-ndjson"], ResponseFormat::Export(ExportFormat::Ndjson)),
+        (&["application/ndjson"], ResponseFormat::Export(ExportFormat::Ndjson)),
+        (&["application/json; stream=true"], ResponseFormat::Export(ExportFormat::JsonArray)),
+        (&["application/json;stream=true"], ResponseFormat::Export(ExportFormat::JsonArray)),
+        (
+            &["application/json; stream=true; q=0.8, text/csv; q=0.9"],
+            ResponseFormat::Export(ExportFormat::Csv)
+        ),
+        (&["application/json; stream=false"], ResponseFormat::Json)
+    ];
+
+    for (values, expected) in cases {
+        assert_eq!(ResponseFormat::from_headers(&headers(ACCEPT, values)), *expected, "{values:?}");
+    }
+}
+
+#[test]
+fn locale_is_negotiated_from_accept_language() {
+    let cases: &[(&[&str], Locale)] = &[
+        (&[], Locale::Ro),
+        (&["*"], Locale::Ro),
+        (&["en"], Locale::En),
+        (&["en-GB,en;q=0.9"], Locale::En),
+        (&["ro-RO, en;q=0.8"], Locale::Ro),
+        (&["ro;q=0.5, en"], Locale::En),
+        (&["en;q=0"], Locale::Ro),
+        (&["de, fr;q=0.9"], Locale::Ro),
+        (&["de", "en;q=0.1"], Locale::En)
+    ];
+
+    for (values, expected) in cases {
+        assert_eq!(
+            Locale::from_headers(&headers(ACCEPT_LANGUAGE, values)),
+            *expected,
+            "{values:?}"
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use delivery_backend::customer::Address;
+use delivery_backend::error::AppError;
+use delivery_backend::geocoding::{Geocoder, NominatimGeocoder};
+
+#[tokio::test]
+async fn geocoder_that_doesnt_answer_times_out() {
+    // Accepts connections, but never answers them
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    let geocoder = NominatimGeocoder::new(
+        base_url,
+        "delivery-backend-tests",
+        Duration::from_secs(1),
+        Duration::from_millis(300)
+    )
+    .unwrap();
+    let address = Address::new("CJ".into(), "Memorandumului".into(), "1".into(), String::new());
+
+    let started = Instant::now();
+    let result = geocoder.geocode(&address).await;
+
+    assert!(matches!(result, Err(AppError::GeocodingError(_))), "{result:?}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
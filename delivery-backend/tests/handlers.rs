@@ -0,0 +1,314 @@
+//! Runs the handlers against the in-memory repositories, without a MongoDB
+//!
+//! Every request goes through the routers with `oneshot`, like it would from a client.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::response::Response;
+use axum::routing::post;
+use axum::Router;
+use common::{customer, days_from_now};
+use delivery_backend::auth::store::setup_store;
+use delivery_backend::certificate::CertificateTemplate;
+use delivery_backend::customer::{DeliveryCustomerIn, PhoneNumber};
+use delivery_backend::geocoding::StubGeocoder;
+use delivery_backend::repository::{
+    InMemoryCustomerRepository, InMemoryUserRepository, Repositories
+};
+use delivery_backend::routers;
+use delivery_backend::state::AppState;
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+/// The seeded customers: two expired ones, one due soon and one far in the future
+///
+/// Only `C-2` has a postal code. `C-4` has it's county as it was stored before
+/// counties were normalized.
+fn customers() -> Vec<DeliveryCustomerIn> {
+    let mut expired = customer("C-1", "Ion Popescu", days_from_now(-10));
+    expired.phones.push(PhoneNumber { label: "mobil".into(), number: "+40722123456".into() });
+
+    let mut with_postal_code = customer("C-2", "Maria Ionescu", days_from_now(-20));
+    with_postal_code.address.postal_code = Some("400001".into());
+
+    let due_soon = customer("C-3", "Vasile Pop", days_from_now(5));
+
+    let mut not_normalized = customer("C-4", "Ana Marin", days_from_now(400));
+    not_normalized.address.county = "cluj".into();
+
+    vec![expired, with_postal_code, due_soon, not_normalized]
+}
+
+/// An [`AppState`] over the in-memory repositories
+async fn app() -> Router {
+    let repositories = Repositories::in_memory(
+        InMemoryCustomerRepository::new().with_customers(customers()),
+        InMemoryUserRepository::new().with_user("dispecer", "parola-buna")
+    );
+    let state = AppState::new(
+        repositories,
+        setup_store(),
+        Arc::new(StubGeocoder::new()),
+        Arc::new(CertificateTemplate::default())
+    );
+
+    Router::new()
+        .route("/search", post(routers::customer_search))
+        .nest("/customer", routers::customer_router())
+        .nest("/auth", routers::auth_router())
+        .with_state(state)
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+fn with_json(method: &str, uri: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+fn customer_ids(list: &Value) -> Vec<&str> {
+    ids(&list["customers"])
+}
+
+/// The `customer_id`s of a JSON array of customers
+fn ids(customers: &Value) -> Vec<&str> {
+    customers
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|customer| customer["customer_id"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn customer_is_fetched_with_it_s_etag() {
+    let app = app().await;
+
+    let response = app.clone().oneshot(get("/customer/C-1")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ETAG], "\"v0\"");
+    assert_eq!(json_body(response).await["name"], "Ion Popescu");
+
+    let revalidate =
+        Request::get("/customer/C-1").header(header::IF_NONE_MATCH, "\"v0\"").body(Body::empty());
+    let response = app.oneshot(revalidate.unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn unknown_customer_is_not_found() {
+    let response = app().await.oneshot(get("/customer/C-404")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn created_customer_is_fetched_by_it_s_object_id() {
+    let app = app().await;
+    let id = ObjectId::new();
+
+    let body = json!({
+        "_id": { "$oid": id.to_hex() },
+        "customer_id": "C-5",
+        "name": "Elena Dumitru",
+        "active": true,
+        "address": { "county": "CJ", "street": "Strada Scurta", "number": "3" },
+        "appliance": {
+            "manufacturer": "Ariston",
+            "year_of_manufacture": "2019",
+            "model": "Clas One",
+            "type": "centrala",
+            "warranty": "2021-05-01T00:00:00+03:00",
+            "operation_performed": "VTP",
+            "number": "B-7",
+            "date": "2023-05-01T00:00:00+03:00",
+            "expiration_date": "2025-05-01T00:00:00+03:00"
+        },
+        "location": { "type": "Point", "coordinates": [23.59, 46.77] }
+    });
+
+    let response = app.clone().oneshot(with_json("POST", "/customer/create", body)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(response).await["inserted_id"], id.to_hex());
+
+    let response = app.oneshot(get(&format!("/customer/by-oid/{id}"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["customer_id"], "C-5");
+}
+
+#[tokio::test]
+async fn update_without_if_match_succeeds() {
+    let app = app().await;
+    let update = json!({ "customer_id": "C-3", "name": "Vasile Pop-Ionescu" });
+
+    let response = app.clone().oneshot(with_json("PUT", "/customer/update", update)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["modified_count"], 1);
+
+    let response = app.oneshot(get("/customer/C-3")).await.unwrap();
+    assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+}
+
+#[tokio::test]
+async fn update_with_a_stale_if_match_is_rejected() {
+    let app = app().await;
+    let update = json!({ "customer_id": "C-2", "name": "Maria Ionescu-Pop" });
+
+    let response =
+        app.clone().oneshot(with_json("PUT", "/customer/update", update.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["matched_count"], 1);
+
+    let mut stale = with_json("PUT", "/customer/update", update);
+    stale.headers_mut().insert(header::IF_MATCH, "\"v0\"".parse().unwrap());
+    let response = app.clone().oneshot(stale).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = app.oneshot(get("/customer/C-2")).await.unwrap();
+    assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+    assert_eq!(json_body(response).await["name"], "Maria Ionescu-Pop");
+}
+
+#[tokio::test]
+async fn patch_of_a_field_only_the_service_writes_is_rejected() {
+    let app = app().await;
+    let patches = [
+        ("application/merge-patch+json", json!({ "certificate": null })),
+        ("application/merge-patch+json", json!({ "name": "Ion", "reminder": { "attempts": 0 } })),
+        ("application/json-patch+json", json!([{ "op": "add", "path": "/erased", "value": true }]))
+    ];
+
+    for (content_type, patch) in patches {
+        let mut request = with_json("PATCH", "/customer/C-1", patch.clone());
+        request.headers_mut().insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY, "{patch}");
+    }
+
+    let response = app.oneshot(get("/customer/C-1")).await.unwrap();
+    assert_eq!(response.headers()[header::ETAG], "\"v0\"");
+    assert_eq!(json_body(response).await["name"], "Ion Popescu");
+}
+
+#[tokio::test]
+async fn patch_of_a_nested_field_is_applied() {
+    let app = app().await;
+    let mut request =
+        with_json("PATCH", "/customer/C-1", json!({ "address": { "street": "Strada Noua" } }));
+    request
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, "application/merge-patch+json".parse().unwrap());
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+    assert_eq!(json_body(response).await["address"]["street"], "Strada Noua");
+}
+
+#[tokio::test]
+async fn deactivated_customer_is_left_out_of_active_lists() {
+    let app = app().await;
+
+    let deactivate = Request::patch("/customer/deactivate/C-1").body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(deactivate).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.oneshot(get("/customer?active=true&sort=customer_id")).await.unwrap();
+    let page = json_body(response).await;
+    assert_eq!(customer_ids(&page), ["C-2", "C-3", "C-4"]);
+    assert_eq!(page["total"], 3);
+}
+
+#[tokio::test]
+async fn customer_list_is_paged_with_the_next_cursor() {
+    let app = app().await;
+
+    let response = app.clone().oneshot(get("/customer?sort=-name&limit=3")).await.unwrap();
+    let first = json_body(response).await;
+    assert_eq!(customer_ids(&first), ["C-3", "C-2", "C-1"]);
+    assert_eq!(first["total"], 4);
+
+    let cursor = first["next_cursor"].as_str().expect("there is a next page");
+    let uri = format!("/customer?sort=-name&limit=3&cursor={cursor}");
+    let second = json_body(app.oneshot(get(&uri)).await.unwrap()).await;
+    assert_eq!(customer_ids(&second), ["C-4"]);
+    assert!(second["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn customers_without_the_sort_field_are_paged_first() {
+    let app = app().await;
+
+    for (sort, pages) in [
+        ("postal_code", [["C-1", "C-3"], ["C-4", "C-2"]]),
+        ("-postal_code", [["C-2", "C-1"], ["C-3", "C-4"]])
+    ] {
+        let uri = format!("/customer?sort={sort}&limit=2");
+        let first = json_body(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+        assert_eq!(customer_ids(&first), pages[0], "first page by {sort}");
+
+        let cursor = first["next_cursor"].as_str().expect("there is a next page");
+        let uri = format!("/customer?sort={sort}&limit=2&cursor={cursor}");
+        let second = json_body(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+        assert_eq!(customer_ids(&second), pages[1], "second page by {sort}");
+    }
+}
+
+#[tokio::test]
+async fn county_filter_matches_counties_stored_before_normalization() {
+    let app = app().await;
+
+    for county in ["CJ", "cj", "Cluj", "cluj"] {
+        let uri = format!("/customer?county={county}&sort=customer_id");
+        let page = json_body(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+        assert_eq!(customer_ids(&page), ["C-1", "C-2", "C-3", "C-4"], "county={county}");
+    }
+
+    let page = json_body(app.oneshot(get("/customer?county=AB")).await.unwrap()).await;
+    assert!(customer_ids(&page).is_empty());
+}
+
+#[tokio::test]
+async fn expired_customers_are_ordered_by_expiration_date() {
+    let response = app().await.oneshot(get("/customer/expired")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(customer_ids(&json_body(response).await), ["C-2", "C-1"]);
+}
+
+#[tokio::test]
+async fn search_finds_customers_by_phone_and_by_name() {
+    let app = app().await;
+
+    let response = app.clone().oneshot(with_json("POST", "/search?query=0722123456", json!({})));
+    assert_eq!(ids(&json_body(response.await.unwrap()).await), ["C-1"]);
+
+    let response = app.oneshot(with_json("POST", "/search?query=maria", json!({})));
+    assert_eq!(ids(&json_body(response.await.unwrap()).await), ["C-2"]);
+}
+
+#[tokio::test]
+async fn login_with_a_wrong_password_is_forbidden() {
+    let login = json!({ "username": "dispecer", "password": "parola-gresita" });
+
+    let response = app().await.oneshot(with_json("POST", "/auth/login", login)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
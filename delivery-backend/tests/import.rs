@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use delivery_backend::error::AppError;
+use delivery_backend::export::{csv_record, Locale, COLUMNS};
+use delivery_backend::import::{detect_delimiter, parse_csv, parse_date, ColumnMapping, CsvRecord};
+use delivery_backend::local_time::local_date;
+use serde_json::json;
+
+fn record(line: usize, fields: &[&str]) -> CsvRecord {
+    CsvRecord { line, fields: fields.iter().map(|field| field.to_string()).collect() }
+}
+
+fn rfc3339(value: &str) -> Option<DateTime<FixedOffset>> {
+    Some(DateTime::parse_from_rfc3339(value).unwrap())
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[test]
+fn csv_fields_are_split_on_the_delimiter() {
+    let records = parse_csv("customer_id;name\r\nC-1;Ion Popescu\r\n\r\nC-2;\n", ';').unwrap();
+
+    assert_eq!(
+        records,
+        [
+            record(1, &["customer_id", "name"]),
+            record(2, &["C-1", "Ion Popescu"]),
+            record(4, &["C-2", ""])
+        ]
+    );
+}
+
+#[test]
+fn delimiter_is_detected_from_the_header_row() {
+    assert_eq!(detect_delimiter("customer_id;name\r\nC-1;Popescu, Ion\r\n"), ';');
+    assert_eq!(detect_delimiter("\u{feff}customer_id,name\nC-1,Ion; Maria\n"), ',');
+    assert_eq!(detect_delimiter("\n\"Nume; prenume\",Scadenta\n"), ',');
+    assert_eq!(detect_delimiter("customer_id\nC-1\n"), ',');
+    assert_eq!(detect_delimiter(""), ',');
+}
+
+#[test]
+fn delimiter_of_an_export_is_detected() {
+    for locale in [Locale::Ro, Locale::En] {
+        let header = csv_record(
+            COLUMNS.iter().map(|column| column.header(locale).to_owned()),
+            locale.csv_delimiter()
+        );
+
+        assert_eq!(detect_delimiter(&header), locale.csv_delimiter(), "{locale:?}");
+    }
+}
+
+#[test]
+fn quoted_csv_fields_keep_delimiters_quotes_and_line_breaks() {
+    let input = "\u{feff}customer_id,observations\n\
+                 C-1,\"fara probleme, verificat\"\n\
+                 C-2,\"a zis \"\"revin\"\"\"\n\
+                 C-3,\"prima linie\r\na doua linie\"\n\
+                 C-4,3\"\n";
+
+    let records = parse_csv(input, ',').unwrap();
+
+    assert_eq!(
+        records,
+        [
+            record(1, &["customer_id", "observations"]),
+            record(2, &["C-1", "fara probleme, verificat"]),
+            record(3, &["C-2", "a zis \"revin\""]),
+            record(4, &["C-3", "prima linie\na doua linie"]),
+            record(6, &["C-4", "3\""]),
+        ]
+    );
+}
+
+#[test]
+fn unterminated_quoted_csv_field_is_rejected() {
+    let result = parse_csv("customer_id,name\nC-1,\"Ion Popescu\n", ',');
+
+    assert!(matches!(result, Err(AppError::BadRequest(message)) if message.contains("line 2")));
+}
+
+#[test]
+fn dates_are_read_like_spreadsheets_write_them() {
+    for value in ["14.05.2024", "14/05/2024", "14-05-2024", "2024-05-14"] {
+        assert_eq!(parse_date(value), rfc3339("2024-05-14T00:00:00+03:00"), "{value}");
+    }
+
+    assert_eq!(parse_date("2024-05-14T10:30:00Z"), rfc3339("2024-05-14T10:30:00+00:00"));
+    assert_eq!(parse_date("31.02.2024"), None);
+    assert_eq!(parse_date("05.14.2024"), None);
+    assert_eq!(parse_date(""), None);
+}
+
+#[test]
+fn dates_are_midnight_on_both_sides_of_the_summer_time_changes() {
+    // Summer time started on 31 March 2024 and ended on 27 October 2024, both at 01:00 UTC
+    let dates = [
+        ("30.03.2024", "2024-03-30T00:00:00+02:00"),
+        ("31.03.2024", "2024-03-31T00:00:00+02:00"),
+        ("01.04.2024", "2024-04-01T00:00:00+03:00"),
+        ("26.10.2024", "2024-10-26T00:00:00+03:00"),
+        ("27.10.2024", "2024-10-27T00:00:00+03:00"),
+        ("28.10.2024", "2024-10-28T00:00:00+02:00"),
+        ("01.01.2025", "2025-01-01T00:00:00+02:00")
+    ];
+
+    for (value, expected) in dates {
+        let date = parse_date(value);
+        assert_eq!(date, rfc3339(expected), "{value}");
+
+        let day = NaiveDate::parse_from_str(value, "%d.%m.%Y").unwrap();
+        assert_eq!(local_date(date.unwrap().with_timezone(&Utc)), day, "{value}");
+    }
+}
+
+#[test]
+fn columns_are_matched_by_name_unless_mapped() {
+    let header = strings(&["Cod", " NAME ", "Scadenta", "email"]);
+    let overrides = HashMap::from([
+        ("customer_id".to_owned(), "cod".to_owned()),
+        ("expiration_date".to_owned(), "Scadenta".to_owned())
+    ]);
+
+    let mapping = ColumnMapping::new(&header, &overrides).unwrap();
+    let row = strings(&["C-1", " Ion Popescu ", "14.05.2026", ""]);
+
+    assert_eq!(mapping.value(&row, "customer_id"), Some("C-1"));
+    assert_eq!(mapping.value(&row, "name"), Some("Ion Popescu"));
+    assert_eq!(mapping.value(&row, "email"), None);
+    assert_eq!(mapping.value(&row, "phones"), None);
+    assert_eq!(mapping.stored_paths(), ["appliance.expiration_date", "email", "name"]);
+}
+
+#[test]
+fn unusable_mappings_are_rejected() {
+    let header = strings(&["customer_id", "name"]);
+
+    let unknown_field = HashMap::from([("nume".to_owned(), "name".to_owned())]);
+    assert!(ColumnMapping::new(&header, &unknown_field).is_err());
+
+    let unknown_column = HashMap::from([("name".to_owned(), "Nume client".to_owned())]);
+    assert!(ColumnMapping::new(&header, &unknown_column).is_err());
+
+    assert!(ColumnMapping::new(&strings(&["name"]), &HashMap::new()).is_err());
+}
+
+#[test]
+fn record_is_converted_to_the_json_of_a_customer() {
+    let header = strings(&[
+        "customer_id",
+        "name",
+        "active",
+        "county",
+        "operation_performed",
+        "expiration_date",
+        "phones",
+        "preferred_channel"
+    ]);
+    let mapping = ColumnMapping::new(&header, &HashMap::new()).unwrap();
+    let row = strings(&[
+        "C-1",
+        "Ion Popescu",
+        "Nu",
+        "CJ",
+        "vtp",
+        "01.04.2024",
+        "0722 123 456; 0264 123 456",
+        "SMS"
+    ]);
+
+    let mut customer = mapping.to_customer_json(&row).unwrap();
+    customer.as_object_mut().unwrap().remove("_id");
+
+    assert_eq!(
+        customer,
+        json!({
+            "customer_id": "C-1",
+            "name": "Ion Popescu",
+            "active": false,
+            "address": { "county": "CJ" },
+            "appliance": {
+                "operation_performed": "VTP",
+                "expiration_date": "2024-04-01T00:00:00+03:00"
+            },
+            "phones": [
+                { "label": "phone", "number": "0722 123 456" },
+                { "label": "phone", "number": "0264 123 456" }
+            ],
+            "preferred_channel": "sms"
+        })
+    );
+}
+
+#[test]
+fn unreadable_values_are_reported_by_field() {
+    let header = strings(&["customer_id", "active", "date", "expiration_date"]);
+    let mapping = ColumnMapping::new(&header, &HashMap::new()).unwrap();
+    let row = strings(&["C-1", "poate", "32.01.2024", "01.02.2026"]);
+
+    assert_eq!(
+        mapping.to_customer_json(&row).unwrap_err(),
+        ["date: 32.01.2024 is not a date", "active: poate is not a yes or no"]
+    );
+}
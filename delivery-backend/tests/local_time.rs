@@ -0,0 +1,50 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use delivery_backend::local_time::{local_date, local_midnight, romanian_offset};
+
+fn utc(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+}
+
+fn hours(hours: i32) -> FixedOffset {
+    FixedOffset::east_opt(hours * 3600).unwrap()
+}
+
+#[test]
+fn offset_changes_at_one_in_the_morning_utc() {
+    // Summer time started on 31 March 2024 and ended on 27 October 2024
+    let offsets = [
+        ("2024-03-31T00:59:59Z", 2),
+        ("2024-03-31T01:00:00Z", 3),
+        ("2024-10-27T00:59:59Z", 3),
+        ("2024-10-27T01:00:00Z", 2),
+        ("2024-07-01T12:00:00Z", 3),
+        ("2024-12-31T23:00:00Z", 2)
+    ];
+
+    for (instant, expected) in offsets {
+        assert_eq!(romanian_offset(utc(instant)), hours(expected), "{instant}");
+    }
+}
+
+#[test]
+fn midnight_is_the_evening_before_in_utc() {
+    let winter = local_midnight(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+    let summer = local_midnight(NaiveDate::from_ymd_opt(2024, 7, 15).unwrap());
+
+    assert_eq!(winter.with_timezone(&Utc), utc("2024-01-14T22:00:00Z"));
+    assert_eq!(summer.with_timezone(&Utc), utc("2024-07-14T21:00:00Z"));
+}
+
+#[test]
+fn local_date_is_the_romanian_day() {
+    let days = [
+        ("2024-01-14T21:59:59Z", (2024, 1, 14)),
+        ("2024-01-14T22:00:00Z", (2024, 1, 15)),
+        ("2024-07-14T21:00:00Z", (2024, 7, 15)),
+        ("2024-12-31T22:30:00Z", (2025, 1, 1))
+    ];
+
+    for (instant, (year, month, day)) in days {
+        assert_eq!(local_date(utc(instant)), NaiveDate::from_ymd_opt(year, month, day).unwrap());
+    }
+}
@@ -0,0 +1,181 @@
+//! Runs the migrations against a `mongod` started for each test
+//!
+//! Ignored by default, run them with `just test-mongo`. The binary is taken from `MONGOD`,
+//! or the `PATH`. Every test gets an empty database, so the migrations start from scratch.
+
+mod common;
+
+use common::mongod::Mongod;
+use common::{customer, days_from_now};
+use delivery_backend::database::Database;
+use delivery_backend::error::AppError;
+use delivery_backend::migration::{migrate, migrations, MigrationStatus};
+use mongodb::bson::{self, doc, Document};
+use mongodb::{Client as MongoClient, Collection as MongoCollection};
+
+/// A `mongod` and the [`Database`] on it
+struct Server {
+    database: Database,
+    client: MongoClient,
+    _mongod: Mongod
+}
+
+impl Server {
+    async fn start() -> Self {
+        let mongod = Mongod::start().expect("a mongod binary is installed");
+        let client = MongoClient::with_uri_str(mongod.url()).await.unwrap();
+
+        Self { database: Database::new(client.clone()), client, _mongod: mongod }
+    }
+
+    fn collection(&self, name: &str) -> MongoCollection<Document> {
+        self.client.database("delivery_database").collection(name)
+    }
+
+    async fn insert_customer(&self, customer_id: &str) {
+        let customer = customer(customer_id, "Ion Popescu", days_from_now(-10));
+        let document = bson::to_document(&customer).unwrap();
+
+        self.collection("customer").insert_one(document, None).await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn shared_customer_ids_are_reported_before_they_are_made_unique() {
+    let server = Server::start().await;
+    server.insert_customer("C-1").await;
+    server.insert_customer("C-2").await;
+    server.insert_customer("C-2").await;
+
+    let error = migrate(&server.database, false).await.unwrap_err();
+    assert!(matches!(&error, AppError::Conflict(message) if message.contains("C-2")), "{error}");
+    assert!(!server
+        .database
+        .migration()
+        .applied()
+        .await
+        .unwrap()
+        .contains("0004_unique_customer_ids"));
+
+    server.collection("customer").delete_one(doc! { "customer_id": "C-2" }, None).await.unwrap();
+
+    let reports = migrate(&server.database, false).await.unwrap();
+    let last = reports.last().unwrap();
+    assert_eq!(last.name, "0004_unique_customer_ids");
+    assert_eq!(last.status, MigrationStatus::Applied { modified: 0 });
+
+    let duplicate = customer("C-1", "Maria Ionescu", days_from_now(30));
+    let result = server.database.customer().insert_customer(duplicate).await;
+    assert!(matches!(result, Err(AppError::Conflict(_))));
+    assert_eq!(migrations().len(), reports.len());
+}
+
+/// The names of the migrations, in the order they are applied
+fn names() -> Vec<&'static str> {
+    migrations().iter().map(|migration| migration.name()).collect()
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn migrations_are_applied_in_order_and_recorded_once() {
+    let server = Server::start().await;
+    server.insert_customer("C-1").await;
+
+    let reports = migrate(&server.database, false).await.unwrap();
+    assert_eq!(reports.iter().map(|report| report.name).collect::<Vec<_>>(), names());
+    assert!(reports.iter().all(|report| matches!(report.status, MigrationStatus::Applied { .. })));
+
+    let mut sorted = names();
+    sorted.sort_unstable();
+    assert_eq!(sorted, names());
+
+    let applied = server.database.migration().applied().await.unwrap();
+    assert_eq!(applied.len(), migrations().len());
+    assert!(names().iter().all(|name| applied.contains(*name)));
+
+    let reports = migrate(&server.database, false).await.unwrap();
+    assert!(reports.iter().all(|report| report.status == MigrationStatus::AlreadyApplied));
+
+    // The lock was released after each run
+    server.database.migration().lock("another instance").await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn dry_run_reports_pending_migrations_without_applying_them() {
+    let server = Server::start().await;
+    server.database.migration().lock("another instance").await.unwrap();
+
+    // The lock held by another instance isn't waited for
+    let reports = migrate(&server.database, true).await.unwrap();
+    assert_eq!(reports.iter().map(|report| report.name).collect::<Vec<_>>(), names());
+    assert!(reports.iter().all(|report| report.status == MigrationStatus::Pending));
+    assert!(server.database.migration().applied().await.unwrap().is_empty());
+
+    server.database.migration().unlock("another instance").await.unwrap();
+    migrate(&server.database, false).await.unwrap();
+
+    let reports = migrate(&server.database, true).await.unwrap();
+    assert!(reports.iter().all(|report| report.status == MigrationStatus::AlreadyApplied));
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn lock_is_held_by_a_single_owner() {
+    let server = Server::start().await;
+    let migration = server.database.migration();
+
+    migration.lock("first").await.unwrap();
+    assert!(matches!(migration.lock("second").await, Err(AppError::Conflict(_))));
+
+    assert!(migration.renew("first").await.unwrap());
+    assert!(!migration.renew("second").await.unwrap());
+
+    // Only the owner releases the lock
+    migration.unlock("second").await.unwrap();
+    assert!(matches!(migration.lock("second").await, Err(AppError::Conflict(_))));
+
+    migration.unlock("first").await.unwrap();
+    assert!(!migration.renew("first").await.unwrap());
+    migration.lock("second").await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn migrate_waits_for_the_lock_of_another_instance() {
+    let server = Server::start().await;
+    let migration = server.database.migration();
+    migration.lock("another instance").await.unwrap();
+
+    let release = async {
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        assert!(migration.applied().await.unwrap().is_empty());
+        migration.unlock("another instance").await.unwrap();
+    };
+    let (reports, ()) = tokio::join!(migrate(&server.database, false), release);
+
+    let reports = reports.unwrap();
+    assert!(reports.iter().all(|report| matches!(report.status, MigrationStatus::Applied { .. })));
+}
+
+#[tokio::test]
+#[ignore = "needs a local mongod"]
+async fn migrate_returns_once_another_instance_applied_every_migration() {
+    let server = Server::start().await;
+    let migration = server.database.migration();
+    migration.lock("another instance").await.unwrap();
+
+    let apply = async {
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        for pending in migrations() {
+            migration.record(pending.name(), pending.description(), 0).await.unwrap();
+        }
+    };
+    let (reports, ()) = tokio::join!(migrate(&server.database, false), apply);
+
+    let reports = reports.unwrap();
+    assert!(reports.iter().all(|report| report.status == MigrationStatus::AlreadyApplied));
+    // The lock of the other instance was never taken over
+    assert!(migration.renew("another instance").await.unwrap());
+}
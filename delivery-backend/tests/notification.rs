@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use chrono::{TimeZone, Utc};
+use delivery_backend::customer::DeliveryCustomerOut;
+use delivery_backend::notification::{
+    render_reminder, ChannelKind, InMemoryChannel, Language, Notifier
+};
+use mongodb::bson::{self, doc, oid::ObjectId};
+
+fn customer() -> DeliveryCustomerOut {
+    let date = Utc.with_ymd_and_hms(2023, 5, 14, 9, 0, 0).unwrap();
+    let expiration_date = Utc.with_ymd_and_hms(2025, 5, 14, 9, 0, 0).unwrap();
+
+    DeliveryCustomerOut::try_from(doc! {
+        "_id": ObjectId::new(),
+        "customer_id": "C-1",
+        "name": "Ion Popescu",
+        "active": true,
+        "address": { "county": "Cluj", "street": "Memorandumului", "number": "1", "additional": "" },
+        "appliance": {
+            "manufacturer": "Vaillant",
+            "year_of_manufacture": "2018",
+            "model": "ecoTEC",
+            "type": "centrala",
+            "warranty": bson::DateTime::from_chrono(date),
+            "operation_performed": "VTP",
+            "number": "42",
+            "date": bson::DateTime::from_chrono(date),
+            "expiration_date": bson::DateTime::from_chrono(expiration_date),
+            "observations": null
+        },
+        "phones": [{ "label": "mobile", "number": "+40712345678" }],
+        "email": "ion@example.com"
+    })
+    .unwrap()
+}
+
+#[test]
+fn reminders_are_rendered_in_both_languages() {
+    let customer = customer();
+
+    let email =
+        render_reminder(Language::Ro, ChannelKind::Email, &customer, "ion@example.com".into());
+    assert_eq!(email.subject.as_deref(), Some("Verificarea aparatului expiră pe 14.05.2025"));
+    assert!(email.body.contains("Ion Popescu"));
+    assert!(email.body.contains("Vaillant ecoTEC"));
+
+    let sms = render_reminder(Language::En, ChannelKind::Sms, &customer, "+40712345678".into());
+    assert_eq!(sms.subject, None);
+    assert!(sms.body.contains("14 May 2025"));
+}
+
+#[tokio::test]
+async fn notifier_sends_over_the_channel_of_the_message() {
+    let email = InMemoryChannel::new(ChannelKind::Email);
+    let sms = InMemoryChannel::new(ChannelKind::Sms);
+    let notifier =
+        Notifier::new().with_channel(Arc::new(email.clone())).with_channel(Arc::new(sms.clone()));
+
+    let message =
+        render_reminder(Language::Ro, ChannelKind::Sms, &customer(), "+40712345678".into());
+    notifier.send(&message).await.unwrap();
+
+    assert!(email.sent().is_empty());
+    assert_eq!(sms.sent(), vec![message]);
+}
+
+#[tokio::test]
+async fn failed_sends_are_reported() {
+    let email = InMemoryChannel::new(ChannelKind::Email);
+    let notifier = Notifier::new().with_channel(Arc::new(email.clone()));
+    let message =
+        render_reminder(Language::En, ChannelKind::Email, &customer(), "ion@example.com".into());
+
+    email.fail_next(1);
+    assert!(notifier.send(&message).await.is_err());
+    notifier.send(&message).await.unwrap();
+    assert_eq!(email.sent().len(), 1);
+
+    let sms = render_reminder(Language::En, ChannelKind::Sms, &customer(), "+40712345678".into());
+    assert!(notifier.send(&sms).await.is_err());
+}
@@ -0,0 +1,356 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use delivery_backend::customer::{CustomerStatus, DeliveryCustomerOut, OperationPerformed};
+use delivery_backend::locality::counties;
+use delivery_backend::query::PartialDeliveryCustomer;
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+use proptest::prelude::*;
+use validator::Validate;
+
+/// Applies the `$set` and `$unset` operators of `update` to `document`, like MongoDB would
+fn apply_update(document: &mut Document, update: &Document) {
+    for (operator, fields) in update {
+        let fields = fields.as_document().expect("operator fields are a document");
+
+        for (path, value) in fields {
+            let (parents, key) = match path.rsplit_once('.') {
+                Some((parents, key)) => (parents.split('.').collect::<Vec<_>>(), key),
+                None => (vec![], path.as_str())
+            };
+
+            let mut target = &mut *document;
+            for parent in parents {
+                target = target.get_document_mut(parent).expect("parent document exists");
+            }
+
+            match operator.as_str() {
+                "$set" => {
+                    target.insert(key, value.clone());
+                }
+                "$unset" => {
+                    target.remove(key);
+                }
+                other => panic!("unexpected update operator {other}")
+            }
+        }
+    }
+}
+
+fn datetime() -> impl Strategy<Value = DateTime<FixedOffset>> {
+    (0i64..4_102_444_800_000, -12i32..=12).prop_map(|(millis, offset_hours)| {
+        FixedOffset::east_opt(offset_hours * 3600).unwrap().timestamp_millis_opt(millis).unwrap()
+    })
+}
+
+fn operation_performed() -> impl Strategy<Value = OperationPerformed> {
+    prop_oneof![
+        Just(OperationPerformed::VTP),
+        Just(OperationPerformed::INT),
+        Just(OperationPerformed::PIF),
+        Just(OperationPerformed::RGAZ),
+        Just(OperationPerformed::VGAZ),
+    ]
+}
+
+fn status() -> impl Strategy<Value = CustomerStatus> {
+    prop_oneof![Just(CustomerStatus::Active), Just(CustomerStatus::Inactive)]
+}
+
+fn text() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9 ]{0,12}"
+}
+
+/// Text without surrounding whitespace, which addresses are trimmed of
+fn trimmed_text() -> impl Strategy<Value = String> {
+    "([a-zA-Z0-9]([a-zA-Z0-9 ]{0,10}[a-zA-Z0-9])?)?"
+}
+
+/// A canonical county code
+fn county() -> impl Strategy<Value = String> {
+    proptest::sample::select(counties().iter().map(|county| county.code).collect::<Vec<_>>())
+        .prop_map(ToOwned::to_owned)
+}
+
+/// A customer document, as it's stored in the `customer` collection
+fn stored_customer() -> impl Strategy<Value = Document> {
+    (
+        (text(), text(), any::<bool>(), county(), trimmed_text(), trimmed_text(), trimmed_text()),
+        (text(), text(), text(), text(), datetime(), operation_performed(), text()),
+        (datetime(), datetime(), proptest::option::of(text()))
+    )
+        .prop_map(
+            |(
+                (customer_id, name, active, county, street, number, additional),
+                (manufacturer, year, model, typ, warranty, operation, appliance_number),
+                (date, expiration_date, observations)
+            )| {
+                doc! {
+                    "_id": ObjectId::new(),
+                    "customer_id": customer_id,
+                    "name": name,
+                    "active": active,
+                    "address": {
+                        "county": county,
+                        "street": street,
+                        "number": number,
+                        "additional": additional
+                    },
+                    "appliance": {
+                        "manufacturer": manufacturer,
+                        "year_of_manufacture": year,
+                        "model": model,
+                        "type": typ,
+                        "warranty": bson::DateTime::from_chrono(warranty),
+                        "operation_performed": operation.to_string(),
+                        "number": appliance_number,
+                        "date": bson::DateTime::from_chrono(date),
+                        "expiration_date": bson::DateTime::from_chrono(expiration_date),
+                        "observations": observations
+                    },
+                    "version": 0i64
+                }
+            }
+        )
+}
+
+fn partial_customer() -> impl Strategy<Value = PartialDeliveryCustomer> {
+    use proptest::option::of;
+
+    (
+        (
+            of(text()),
+            of(status()),
+            of(county()),
+            of(trimmed_text()),
+            of(trimmed_text()),
+            of(trimmed_text())
+        ),
+        (
+            of(text()),
+            of(any::<u16>()),
+            of(text()),
+            of(text()),
+            of(datetime()),
+            of(operation_performed())
+        ),
+        (of(text()), of(datetime()), of(datetime()), of(of(text())))
+    )
+        .prop_map(
+            |(
+                (name, status, county, street, number, additional),
+                (manufacturer, year_of_manufacture, model, typ, warranty, operation_performed),
+                (appliance_number, date, expiration_date, observations)
+            )| PartialDeliveryCustomer {
+                customer_id: "ignored".into(),
+                name,
+                status,
+                county,
+                locality: None,
+                postal_code: None,
+                street,
+                number,
+                building: None,
+                staircase: None,
+                apartment: None,
+                additional,
+                manufacturer,
+                year_of_manufacture,
+                model,
+                typ,
+                warranty,
+                operation_performed,
+                appliance_number,
+                date,
+                expiration_date,
+                observations,
+                phones: None,
+                email: None,
+                preferred_channel: None,
+                marketing_consent: None,
+                notification_consent: None,
+                location: None
+            }
+        )
+}
+
+/// Field by field snapshot of a [`PartialDeliveryCustomer`], taken before it's consumed
+struct Expected {
+    name: Option<String>,
+    active: Option<bool>,
+    address: [Option<String>; 4],
+    appliance: [Option<String>; 6],
+    dates: [Option<DateTime<Utc>>; 3],
+    observations: Option<String>
+}
+
+impl From<&PartialDeliveryCustomer> for Expected {
+    fn from(patch: &PartialDeliveryCustomer) -> Self {
+        Self {
+            name: patch.name.clone(),
+            active: patch.status.as_ref().map(|status| matches!(status, CustomerStatus::Active)),
+            address: [
+                patch.county.clone(),
+                patch.street.clone(),
+                patch.number.clone(),
+                patch.additional.clone()
+            ],
+            appliance: [
+                patch.manufacturer.clone(),
+                patch.year_of_manufacture.map(|year| year.to_string()),
+                patch.model.clone(),
+                patch.typ.clone(),
+                patch.operation_performed.as_ref().map(ToString::to_string),
+                patch.appliance_number.clone()
+            ],
+            dates: [
+                patch.warranty.map(|date| date.with_timezone(&Utc)),
+                patch.date.map(|date| date.with_timezone(&Utc)),
+                patch.expiration_date.map(|date| date.with_timezone(&Utc))
+            ],
+            observations: patch.observations.clone().map(Option::unwrap_or_default)
+        }
+    }
+}
+
+fn address(customer: &DeliveryCustomerOut) -> [String; 4] {
+    let address = &customer.address;
+    [
+        address.county.clone(),
+        address.street.clone(),
+        address.number.clone(),
+        address.additional.clone()
+    ]
+}
+
+fn appliance(customer: &DeliveryCustomerOut) -> [String; 6] {
+    let appliance = &customer.appliance;
+    [
+        appliance.manufacturer.clone(),
+        appliance.year_of_manufacture.clone(),
+        appliance.model.clone(),
+        appliance.typ.clone(),
+        appliance.operation_performed.to_string(),
+        appliance.number.clone()
+    ]
+}
+
+fn dates(customer: &DeliveryCustomerOut) -> [DateTime<Utc>; 3] {
+    let appliance = &customer.appliance;
+    [appliance.warranty, appliance.date, appliance.expiration_date]
+}
+
+proptest! {
+    #[test]
+    fn update_document_round_trips(stored in stored_customer(), patch in partial_customer()) {
+        let before = DeliveryCustomerOut::try_from(stored.clone()).unwrap();
+        let expected = Expected::from(&patch);
+
+        let mut updated = stored;
+        apply_update(&mut updated, &patch.into_update_document_no_none().unwrap());
+        let after = DeliveryCustomerOut::try_from(updated).unwrap();
+
+        prop_assert_eq!(after.id, before.id);
+        prop_assert_eq!(&after.customer_id, &before.customer_id);
+        prop_assert_eq!(&after.name, expected.name.as_ref().unwrap_or(&before.name));
+        prop_assert_eq!(after.active, expected.active.unwrap_or(before.active));
+
+        for ((after, before), expected) in
+            address(&after).iter().zip(address(&before)).zip(expected.address)
+        {
+            prop_assert_eq!(after, &expected.unwrap_or(before));
+        }
+
+        for ((after, before), expected) in
+            appliance(&after).iter().zip(appliance(&before)).zip(expected.appliance)
+        {
+            prop_assert_eq!(after, &expected.unwrap_or(before));
+        }
+
+        for ((after, before), expected) in
+            dates(&after).iter().zip(dates(&before)).zip(expected.dates)
+        {
+            prop_assert_eq!(after, &expected.unwrap_or(before));
+        }
+
+        prop_assert_eq!(
+            after.appliance.observations,
+            expected.observations.map(Some).unwrap_or(before.appliance.observations)
+        );
+    }
+}
+
+#[test]
+fn update_document_uses_dotted_paths_and_stored_keys() {
+    let patch: PartialDeliveryCustomer = serde_json::from_value(serde_json::json!({
+        "customer_id": "C-1",
+        "status": "inactive",
+        "street": "Strada Lunga",
+        "type": "centrala",
+        "appliance_number": "42",
+        "observations": null
+    }))
+    .unwrap();
+
+    assert_eq!(
+        patch.into_update_document_no_none().unwrap(),
+        doc! {
+            "$set": {
+                "active": false,
+                "address.street": "Strada Lunga",
+                "appliance.type": "centrala",
+                "appliance.number": "42",
+                "appliance.observations": ""
+            }
+        }
+    );
+}
+
+#[test]
+fn missing_optional_field_is_left_untouched() {
+    let patch: PartialDeliveryCustomer =
+        serde_json::from_value(serde_json::json!({ "customer_id": "C-1", "name": "Ion" })).unwrap();
+
+    assert!(patch.observations.is_none());
+    assert_eq!(patch.into_update_document_no_none().unwrap(), doc! { "$set": { "name": "Ion" } });
+}
+
+#[test]
+fn empty_patch_produces_no_operators() {
+    let patch: PartialDeliveryCustomer =
+        serde_json::from_value(serde_json::json!({ "customer_id": "C-1" })).unwrap();
+
+    assert_eq!(patch.into_update_document_no_none().unwrap(), Document::new());
+}
+
+#[test]
+fn year_of_manufacture_is_stored_as_text() {
+    let patch: PartialDeliveryCustomer = serde_json::from_value(serde_json::json!({
+        "customer_id": "C-1",
+        "year_of_manufacture": 2019
+    }))
+    .unwrap();
+
+    assert_eq!(
+        patch.into_update_document_no_none().unwrap(),
+        doc! { "$set": { "appliance.year_of_manufacture": "2019" } }
+    );
+}
+
+#[test]
+fn empty_name_is_rejected() {
+    let patch: PartialDeliveryCustomer =
+        serde_json::from_value(serde_json::json!({ "customer_id": "C-1", "name": "" })).unwrap();
+
+    assert!(patch.validate().unwrap_err().field_errors().contains_key("name"));
+}
+
+#[test]
+fn expiration_before_date_is_rejected() {
+    let patch: PartialDeliveryCustomer = serde_json::from_value(serde_json::json!({
+        "customer_id": "C-1",
+        "date": "2024-05-01T10:00:00+03:00",
+        "expiration_date": "2024-04-01T10:00:00+03:00"
+    }))
+    .unwrap();
+
+    assert!(patch.validate().is_err());
+}
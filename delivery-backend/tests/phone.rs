@@ -0,0 +1,54 @@
+use delivery_backend::customer::{normalize_phone, validate_phone};
+
+#[test]
+fn romanian_numbers_get_the_country_code() {
+    for phone in ["0712 345 678", "0712-345-678", "0712.345.678", "(0712) 345 678"] {
+        assert_eq!(normalize_phone(phone).as_deref(), Some("+40712345678"), "{phone}");
+    }
+}
+
+#[test]
+fn double_zero_prefix_becomes_a_plus() {
+    assert_eq!(normalize_phone("0040 712 345 678").as_deref(), Some("+40712345678"));
+    assert_eq!(normalize_phone("0044 20 7946 0958").as_deref(), Some("+442079460958"));
+}
+
+#[test]
+fn plus_prefix_is_kept() {
+    assert_eq!(normalize_phone("+40 712 345 678").as_deref(), Some("+40712345678"));
+    assert_eq!(normalize_phone("+1 (212) 555-0100").as_deref(), Some("+12125550100"));
+}
+
+#[test]
+fn trunk_zero_after_the_country_code_is_dropped() {
+    for phone in ["+40 (0) 712 345 678", "0040 (0)712 345 678", "+40 0712 345 678", "+400712345678"]
+    {
+        assert_eq!(normalize_phone(phone).as_deref(), Some("+40712345678"), "{phone}");
+    }
+
+    assert_eq!(normalize_phone("+44 (0) 20 7946 0958").as_deref(), Some("+442079460958"));
+}
+
+#[test]
+fn leading_zero_of_other_countries_is_kept() {
+    // Italian numbers are dialed with their `0` from abroad too
+    assert_eq!(normalize_phone("+39 06 1234 5678").as_deref(), Some("+390612345678"));
+}
+
+#[test]
+fn invalid_numbers_are_not_normalized() {
+    for phone in
+        ["", "+", "00", "0", "12345", "+40 712 ABC 678", "+0712345678", "+40 712 345 678 901 234"]
+    {
+        assert_eq!(normalize_phone(phone), None, "{phone}");
+    }
+}
+
+#[test]
+fn only_normalized_numbers_are_valid() {
+    assert!(validate_phone("+40712345678").is_ok());
+
+    for phone in ["0712345678", "+40 712 345 678", "+400712345678", "not a phone"] {
+        assert!(validate_phone(phone).is_err(), "{phone}");
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+use delivery_backend::customer::{DeliveryCustomerOut, GeoPoint};
+use delivery_backend::planning::{
+    haversine_distance, plan_route, PlannedRoute, RouteOptions, TimeWindow, UnplannedReason, Visit
+};
+use mongodb::bson::{self, doc, oid::ObjectId};
+
+/// Every visit is north of the depot, 0.1° of latitude is about 11 km
+const DEPOT: (f64, f64) = (46.0, 23.0);
+
+/// 60 km/h, so a kilometre takes a minute
+const SPEED_KMH: f64 = 60.0;
+
+fn depot() -> GeoPoint {
+    GeoPoint::new(DEPOT.0, DEPOT.1)
+}
+
+fn at(hour: u32, minute: u32) -> DateTime<FixedOffset> {
+    FixedOffset::east_opt(3 * 3600).unwrap().with_ymd_and_hms(2024, 5, 14, hour, minute, 0).unwrap()
+}
+
+fn options(max_visits: usize, return_to_depot: bool) -> RouteOptions {
+    RouteOptions {
+        start: at(8, 0),
+        max_visits,
+        visit_duration: Duration::minutes(30),
+        speed_kmh: SPEED_KMH,
+        return_to_depot
+    }
+}
+
+fn customer(customer_id: &str) -> DeliveryCustomerOut {
+    let date = bson::DateTime::from_chrono(Utc.with_ymd_and_hms(2022, 5, 14, 9, 0, 0).unwrap());
+
+    DeliveryCustomerOut::try_from(doc! {
+        "_id": ObjectId::new(),
+        "customer_id": customer_id,
+        "name": "Ion Popescu",
+        "active": true,
+        "address": { "county": "CJ", "street": "Strada Lunga", "number": "12", "additional": "" },
+        "appliance": {
+            "manufacturer": "Vaillant",
+            "year_of_manufacture": "2015",
+            "model": "ecoTEC",
+            "type": "centrala",
+            "warranty": date,
+            "operation_performed": "VTP",
+            "number": "A-42",
+            "date": date,
+            "expiration_date": date,
+            "observations": null
+        }
+    })
+    .unwrap()
+}
+
+/// A visit `tenths` tenths of a degree north of the depot
+fn visit(customer_id: &str, tenths: u32, window: Option<TimeWindow>) -> Visit {
+    Visit {
+        customer: customer(customer_id),
+        location: GeoPoint::new(DEPOT.0 + f64::from(tenths) / 10.0, DEPOT.1),
+        window
+    }
+}
+
+fn window(from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> Option<TimeWindow> {
+    Some(TimeWindow { from, to })
+}
+
+fn stop_ids(route: &PlannedRoute) -> Vec<&str> {
+    route.stops.iter().map(|stop| stop.customer.customer_id.as_str()).collect()
+}
+
+fn unplanned(route: &PlannedRoute) -> Vec<(&str, UnplannedReason)> {
+    route.unplanned.iter().map(|visit| (visit.customer_id.as_str(), visit.reason)).collect()
+}
+
+#[test]
+fn visits_are_ordered_by_distance() {
+    let visits = vec![visit("far", 3, None), visit("near", 1, None), visit("middle", 2, None)];
+
+    let route = plan_route(depot(), visits, &options(10, false));
+
+    assert_eq!(stop_ids(&route), ["near", "middle", "far"]);
+    assert!(route.unplanned.is_empty());
+    assert_eq!(route.stops.iter().map(|stop| stop.order).collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn window_closing_early_is_visited_first() {
+    // Going to `near` first gets to `far` at 08:52, after it's window closed
+    let visits = vec![visit("near", 1, None), visit("far", 2, window(at(8, 0), at(8, 30)))];
+
+    let route = plan_route(depot(), visits, &options(10, false));
+
+    assert_eq!(stop_ids(&route), ["far", "near"]);
+    assert!(route.unplanned.is_empty());
+    assert!(route.stops[0].visit_start <= at(8, 30));
+}
+
+#[test]
+fn technician_waits_for_the_window_to_open() {
+    let visits = vec![visit("late", 1, window(at(10, 0), at(11, 0)))];
+
+    let route = plan_route(depot(), visits, &options(10, false));
+
+    let stop = &route.stops[0];
+    assert!(stop.arrival < at(10, 0));
+    assert_eq!(stop.visit_start, at(10, 0));
+    assert_eq!(stop.departure, at(10, 30));
+}
+
+#[test]
+fn visits_past_max_visits_are_left_out() {
+    let visits = vec![visit("first", 1, None), visit("second", 2, None), visit("third", 3, None)];
+
+    let route = plan_route(depot(), visits, &options(2, false));
+
+    assert_eq!(stop_ids(&route), ["first", "second"]);
+    assert_eq!(unplanned(&route), [("third", UnplannedReason::MaxVisits)]);
+}
+
+#[test]
+fn window_that_cant_be_met_is_left_out() {
+    // The technician gets there at 08:11
+    let visits = vec![visit("closed", 1, window(at(8, 0), at(8, 5))), visit("open", 2, None)];
+
+    let route = plan_route(depot(), visits, &options(10, false));
+
+    assert_eq!(stop_ids(&route), ["open"]);
+    assert_eq!(unplanned(&route), [("closed", UnplannedReason::TimeWindow)]);
+}
+
+#[test]
+fn return_to_depot_is_part_of_the_route() {
+    let one_way =
+        plan_route(depot(), vec![visit("A", 1, None), visit("B", 2, None)], &options(10, false));
+    let round_trip =
+        plan_route(depot(), vec![visit("A", 1, None), visit("B", 2, None)], &options(10, true));
+
+    let last = &round_trip.stops[1];
+    let back = haversine_distance(&GeoPoint::new(DEPOT.0 + 0.2, DEPOT.1), &depot());
+    let drive_back = Duration::seconds((back / (SPEED_KMH / 3.6)).round() as i64);
+
+    assert_eq!(stop_ids(&one_way), ["A", "B"]);
+    assert_eq!(one_way.end, one_way.stops[1].departure);
+    assert_eq!(round_trip.end, last.departure + drive_back);
+    assert!((round_trip.total_distance - one_way.total_distance - back).abs() < 1e-6);
+}
@@ -0,0 +1,62 @@
+//! Decodes `If-Match` into a [`VersionPrecondition`] the way the update routes extract it
+
+use axum::body::Body;
+use axum::headers::HeaderMapExt;
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::routing::put;
+use axum::{Router, TypedHeader};
+use delivery_backend::query::VersionPrecondition;
+use tower::ServiceExt;
+
+/// Mirrors the extractor of `PUT /customer/update` and echoes the precondition it got
+///
+/// Without a precondition the update goes through, so the route answers `200 OK`.
+fn app() -> Router {
+    Router::new().route(
+        "/customer/update",
+        put(|precondition: Option<TypedHeader<VersionPrecondition>>| async move {
+            let mut headers = HeaderMap::new();
+
+            match precondition {
+                None => (StatusCode::OK, headers),
+                Some(TypedHeader(precondition)) => {
+                    headers.typed_insert(precondition);
+                    (StatusCode::PRECONDITION_FAILED, headers)
+                }
+            }
+        })
+    )
+}
+
+async fn send(if_match: Option<&str>) -> (StatusCode, Option<String>) {
+    let mut request = Request::builder().method("PUT").uri("/customer/update");
+
+    if let Some(if_match) = if_match {
+        request = request.header(header::IF_MATCH, if_match);
+    }
+
+    let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let echoed =
+        response.headers().get(header::IF_MATCH).map(|value| value.to_str().unwrap().to_owned());
+
+    (response.status(), echoed)
+}
+
+#[tokio::test]
+async fn update_without_if_match_succeeds() {
+    assert_eq!(send(None).await, (StatusCode::OK, None));
+}
+
+#[tokio::test]
+async fn if_match_lists_the_accepted_versions() {
+    let (_, precondition) = send(Some("\"v3\", W/\"v4\", \"other\", \"v5\"")).await;
+
+    assert_eq!(precondition.as_deref(), Some("\"v3\", \"v5\""));
+}
+
+#[tokio::test]
+async fn if_match_any_accepts_every_version() {
+    let (_, precondition) = send(Some("*")).await;
+
+    assert_eq!(precondition.as_deref(), Some("*"));
+}